@@ -0,0 +1,175 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use qmm_syntax::{
+    qmm::{Jump, JumpId, JumpParameterCondition, LocationId, LocationType, Quest},
+    text::{formula::Formula, interval::Interval},
+};
+
+/// Problem of a quest found without playing it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditFinding {
+    /// The jump's conditions can't be met with any value the parameters may
+    /// take
+    DeadJump { jump: JumpId, reason: String },
+    /// Every jump from the location that doesn't end the quest is dead, the
+    /// player gets stuck there
+    SoftLock { location: LocationId },
+}
+
+impl Display for AuditFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditFinding::DeadJump { jump, reason } => {
+                write!(f, "Jump {} can never be taken: {reason}", jump.0)
+            }
+            AuditFinding::SoftLock { location } => write!(
+                f,
+                "Location {} can't be left, none of its jumps can be taken",
+                location.0
+            ),
+        }
+    }
+}
+
+/// Values every parameter may take during the game, `[p1]` is at index 0.
+///
+/// The parameter changes are clamped to the parameter's bounds, so only the
+/// starting value may be outside them. The parameters that are never changed
+/// keep their starting value.
+pub fn parameter_bounds(quest: &Quest) -> Vec<Interval> {
+    let changed: BTreeSet<u32> = quest
+        .locations
+        .iter()
+        .flat_map(|location| &location.parameter_changes)
+        .chain(quest.jumps.iter().flat_map(|jump| &jump.parameter_changes))
+        .map(|change| change.parameter_id)
+        .collect();
+    let mut bounds = Vec::with_capacity(quest.parameters.len());
+
+    for (index, parameter) in quest.parameters.iter().enumerate() {
+        // Broken starting values are 0, as in the player
+        let start = Formula::parse(&parameter.starting_value)
+            .ok()
+            .and_then(|formula| formula.expression().ok())
+            .map_or(Interval::point(0.0), |expression| {
+                expression.eval_interval(&bounds).round()
+            });
+
+        bounds.push(if changed.contains(&(index as u32 + 1)) {
+            start.union(&Interval::from(parameter.min_value..=parameter.max_value))
+        } else {
+            start
+        });
+    }
+
+    bounds
+}
+
+/// Checks the jumps with the parameter bounds.
+pub fn audit(quest: &Quest) -> Vec<AuditFinding> {
+    let bounds = parameter_bounds(quest);
+    let findings = quest
+        .jumps
+        .iter()
+        .filter_map(|jump| {
+            dead_jump_reason(jump, &bounds).map(|reason| AuditFinding::DeadJump {
+                jump: jump.id,
+                reason,
+            })
+        })
+        .collect();
+
+    with_soft_locks(quest, findings)
+}
+
+/// Adds the locations all jumps from which are among the dead jumps.
+pub(crate) fn with_soft_locks(quest: &Quest, mut findings: Vec<AuditFinding>) -> Vec<AuditFinding> {
+    let dead: BTreeSet<JumpId> = findings
+        .iter()
+        .filter_map(|finding| match finding {
+            AuditFinding::DeadJump { jump, .. } => Some(*jump),
+            _ => None,
+        })
+        .collect();
+
+    for location in &quest.locations {
+        if !matches!(
+            location.ty,
+            LocationType::Ordinary | LocationType::Starting | LocationType::Empty
+        ) {
+            continue;
+        }
+
+        let mut jumps = quest.jumps_from(location.id).peekable();
+
+        if jumps.peek().is_some() && jumps.all(|jump| dead.contains(&jump.id)) {
+            findings.push(AuditFinding::SoftLock {
+                location: location.id,
+            });
+        }
+    }
+
+    findings
+}
+
+pub(crate) fn dead_jump_reason(jump: &Jump, bounds: &[Interval]) -> Option<String> {
+    if let Some(reason) = jump
+        .parameters_conditions
+        .iter()
+        .find_map(|condition| dead_condition_reason(condition, bounds))
+    {
+        return Some(reason);
+    }
+
+    if jump.formula.is_empty() {
+        return None;
+    }
+
+    // Unparseable formulas are never met
+    let Ok(expression) = jump.formula.expression() else {
+        return Some(format!("the formula `{}` can't be parsed", jump.formula));
+    };
+
+    let value = expression.eval_interval(bounds).round();
+
+    (value == Interval::point(0.0)).then(|| format!("the formula `{}` is always 0", jump.formula))
+}
+
+fn dead_condition_reason(
+    condition: &JumpParameterCondition,
+    bounds: &[Interval],
+) -> Option<String> {
+    let parameter_id = condition.parameter_id;
+    let Some(value) = (parameter_id as usize)
+        .checked_sub(1)
+        .and_then(|index| bounds.get(index))
+    else {
+        return Some(format!("[p{parameter_id}] doesn't exist"));
+    };
+
+    if !value.intersects(&Interval::from(condition.range_start..=condition.range_end)) {
+        return Some(format!(
+            "[p{parameter_id}] stays within {value}, the jump needs {}..{}",
+            condition.range_start, condition.range_end
+        ));
+    }
+
+    let values = &condition.must_equal_values;
+
+    if condition.must_equal
+        && !values.is_empty()
+        && !values.iter().any(|equal| value.contains(*equal as f64))
+    {
+        return Some(format!(
+            "[p{parameter_id}] stays within {value}, the jump needs one of {values:?}"
+        ));
+    }
+
+    if !condition.must_equal && value.min == value.max && values.contains(&(value.min as i32)) {
+        return Some(format!(
+            "[p{parameter_id}] stays within {value}, the jump needs none of {values:?}"
+        ));
+    }
+
+    None
+}
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+
+use qmm_syntax::qmm::{Quest, TextSource};
+
+/// Words and phrases of the content categories, e.g. `profanity` or
+/// `violence`, looked for by [`content_flags`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordLists {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    category: String,
+    entry: String,
+    words: Vec<String>,
+    /// The last word matches the words starting with it
+    prefix: bool,
+}
+
+impl WordLists {
+    /// Adds a word or a phrase to the category. The case and the punctuation
+    /// are ignored, a `*` at the end matches any ending of the last word,
+    /// e.g. `damn*` finds "Damned!".
+    pub fn add(&mut self, category: &str, entry: &str) {
+        let prefix = entry.trim_end().ends_with('*');
+        let words = words(entry);
+
+        if words.is_empty() {
+            return;
+        }
+
+        self.patterns.push(Pattern {
+            category: category.to_string(),
+            entry: entry.trim().to_string(),
+            words,
+            prefix,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+impl Pattern {
+    fn matches(&self, words: &[String]) -> bool {
+        let Some((last, rest)) = self.words.split_last() else {
+            return false;
+        };
+
+        words.len() >= self.words.len()
+            && rest.iter().zip(words).all(|(a, b)| a == b)
+            && if self.prefix {
+                words[rest.len()].starts_with(last.as_str())
+            } else {
+                words[rest.len()] == *last
+            }
+    }
+}
+
+/// Text with a word or a phrase of the lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentFlag {
+    pub source: TextSource,
+    pub category: String,
+    /// Entry of the list that matched
+    pub entry: String,
+    /// Words of the text that matched, in lowercase
+    pub found: String,
+}
+
+impl Display for ContentFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} `{}`", self.source, self.category, self.found)
+    }
+}
+
+/// Lowercase words without the punctuation.
+fn words(text: &str) -> Vec<String> {
+    text.split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Places of the quest's texts that have the words of the lists, in file
+/// order. Every entry is reported once per text.
+pub fn content_flags(quest: &Quest, lists: &WordLists) -> Vec<ContentFlag> {
+    let mut flags = Vec::new();
+
+    if lists.is_empty() {
+        return flags;
+    }
+
+    for text in quest.texts() {
+        let words = words(&text.text);
+
+        for pattern in &lists.patterns {
+            let Some(start) = (0..words.len()).find(|start| pattern.matches(&words[*start..]))
+            else {
+                continue;
+            };
+
+            flags.push(ContentFlag {
+                source: text.source,
+                category: pattern.category.clone(),
+                entry: pattern.entry.clone(),
+                found: words[start..start + pattern.words.len()].join(" "),
+            });
+        }
+    }
+
+    flags
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use qmm_syntax::qmm::{Quest, QuestText, TextSource};
+
+/// Options of [`duplicate_texts`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DuplicateOptions {
+    /// Similarity from 0 to 1 of the texts put into one cluster, 1 only
+    /// groups the texts equal after the normalization
+    pub threshold: f64,
+    /// Shorter normalized texts, like "Next" on many jumps, are skipped
+    pub min_length: usize,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.9,
+            min_length: 20,
+        }
+    }
+}
+
+/// Texts of the locations and the jumps which are the same or almost the
+/// same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateCluster {
+    /// In file order
+    pub texts: Vec<QuestText>,
+    /// All the texts are equal after the normalization
+    pub identical: bool,
+}
+
+/// Lowercase words without the punctuation, separated by single spaces.
+fn normalize(text: &str) -> String {
+    text.split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Character trigrams, the similarity of two texts is the share of the
+/// trigrams they have in common.
+fn trigrams(text: &str) -> BTreeSet<[char; 3]> {
+    let chars: Vec<char> = text.chars().collect();
+
+    chars
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+fn similarity(a: &BTreeSet<[char; 3]>, b: &BTreeSet<[char; 3]>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let common = a.intersection(b).count();
+
+    common as f64 / (a.len() + b.len() - common) as f64
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+
+    index
+}
+
+/// Clusters of the location and jump texts that are similar at least by the
+/// threshold, the largest first.
+pub fn duplicate_texts(quest: &Quest, options: DuplicateOptions) -> Vec<DuplicateCluster> {
+    let texts: Vec<(QuestText, String)> = quest
+        .texts()
+        .into_iter()
+        .filter(|text| {
+            !matches!(
+                text.source,
+                TextSource::TaskText
+                    | TextSource::SuccessText
+                    | TextSource::ParameterName(_)
+                    | TextSource::ParameterRangeLine { .. }
+                    | TextSource::ParameterCriticalText(_)
+            )
+        })
+        .map(|text| {
+            let normalized = normalize(&text.text);
+            (text, normalized)
+        })
+        .filter(|(_, normalized)| normalized.chars().count() >= options.min_length)
+        .collect();
+
+    // The equal texts are merged first, the rest is compared by pairs
+    let mut parents: Vec<usize> = (0..texts.len()).collect();
+    let mut first_equal: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (index, (_, normalized)) in texts.iter().enumerate() {
+        let first = *first_equal.entry(normalized).or_insert(index);
+        parents[index] = first;
+    }
+
+    if options.threshold < 1.0 {
+        let distinct: Vec<(usize, BTreeSet<[char; 3]>)> = first_equal
+            .values()
+            .map(|index| (*index, trigrams(&texts[*index].1)))
+            .collect();
+
+        for (i, (a, a_trigrams)) in distinct.iter().enumerate() {
+            for (b, b_trigrams) in &distinct[i + 1..] {
+                // The share can't reach the threshold if the sizes differ
+                // too much
+                let sizes = (a_trigrams.len().min(b_trigrams.len()) as f64)
+                    / (a_trigrams.len().max(b_trigrams.len()) as f64);
+
+                if sizes < options.threshold
+                    || similarity(a_trigrams, b_trigrams) < options.threshold
+                {
+                    continue;
+                }
+
+                let (a, b) = (find(&mut parents, *a), find(&mut parents, *b));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+    for index in 0..texts.len() {
+        let root = find(&mut parents, index);
+        clusters.entry(root).or_default().push(index);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = clusters
+        .into_values()
+        .filter(|indices| indices.len() > 1)
+        .map(|indices| DuplicateCluster {
+            identical: indices
+                .iter()
+                .all(|index| texts[*index].1 == texts[indices[0]].1),
+            texts: indices
+                .into_iter()
+                .map(|index| texts[index].0.clone())
+                .collect(),
+        })
+        .collect();
+    // Stable, the clusters of the same size stay in file order
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.texts.len()));
+
+    clusters
+}
@@ -0,0 +1,251 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use qmm_syntax::{
+    qmm::{
+        LocationSelectType, LocationType, MaxVisits, Media, ParameterChange, ParameterChangeType,
+        ParameterShowType, ParameterType, Quest, Version,
+    },
+    text::{
+        formatted_text::{FormattedText, TextElementKind},
+        formula::FormulaTokenKind,
+    },
+};
+
+/// Construct of the format or the engine a quest relies on, for telling
+/// which ones occur in the packs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuestFeature {
+    Qmm7,
+    /// A location picks its text by a formula
+    SelectionFormula,
+    /// A location has several texts
+    MultipleTexts,
+    /// The location ends nothing and shows no text, the player moves on
+    EmptyLocation,
+    PercentageChange,
+    FormulaChange,
+    /// A change shows or hides the parameter
+    ParameterVisibility,
+    /// A jump condition lists the values the parameter must (not) equal
+    EqualCondition,
+    /// A jump condition lists the values the parameter must (not) be a
+    /// multiple of
+    ModCondition,
+    JumpFormula,
+    /// A jump is shown even if it can't be taken
+    ShowAlways,
+    /// Jumps with the same text are picked by their priorities
+    JumpPriority,
+    VisitLimit,
+    PassingDays,
+    /// `[1..10]` or `1 to 10` in a formula, a random value or the values
+    /// for `in`
+    Range,
+    FractionalNumber,
+    /// `{[p1] * 2}` in a text
+    TextFormula,
+    /// `<clr>` in a text
+    TextSelection,
+    /// A parameter that fails, wins or kills at its critical value
+    CriticalParameter,
+    MoneyParameter,
+    /// Images, sounds or tracks
+    Media,
+}
+
+impl QuestFeature {
+    pub const ALL: [QuestFeature; 21] = [
+        QuestFeature::Qmm7,
+        QuestFeature::SelectionFormula,
+        QuestFeature::MultipleTexts,
+        QuestFeature::EmptyLocation,
+        QuestFeature::PercentageChange,
+        QuestFeature::FormulaChange,
+        QuestFeature::ParameterVisibility,
+        QuestFeature::EqualCondition,
+        QuestFeature::ModCondition,
+        QuestFeature::JumpFormula,
+        QuestFeature::ShowAlways,
+        QuestFeature::JumpPriority,
+        QuestFeature::VisitLimit,
+        QuestFeature::PassingDays,
+        QuestFeature::Range,
+        QuestFeature::FractionalNumber,
+        QuestFeature::TextFormula,
+        QuestFeature::TextSelection,
+        QuestFeature::CriticalParameter,
+        QuestFeature::MoneyParameter,
+        QuestFeature::Media,
+    ];
+}
+
+impl Display for QuestFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            QuestFeature::Qmm7 => "QMM7 format",
+            QuestFeature::SelectionFormula => "text selection by formula",
+            QuestFeature::MultipleTexts => "multiple location texts",
+            QuestFeature::EmptyLocation => "empty locations",
+            QuestFeature::PercentageChange => "percentage changes",
+            QuestFeature::FormulaChange => "formula changes",
+            QuestFeature::ParameterVisibility => "showing and hiding parameters",
+            QuestFeature::EqualCondition => "equality conditions",
+            QuestFeature::ModCondition => "mod conditions",
+            QuestFeature::JumpFormula => "jump formulas",
+            QuestFeature::ShowAlways => "always shown jumps",
+            QuestFeature::JumpPriority => "jump priorities",
+            QuestFeature::VisitLimit => "visit limits",
+            QuestFeature::PassingDays => "passing days",
+            QuestFeature::Range => "ranges in formulas",
+            QuestFeature::FractionalNumber => "fractional numbers",
+            QuestFeature::TextFormula => "formulas in texts",
+            QuestFeature::TextSelection => "highlighted text",
+            QuestFeature::CriticalParameter => "critical parameters",
+            QuestFeature::MoneyParameter => "money parameters",
+            QuestFeature::Media => "media",
+        })
+    }
+}
+
+/// Features the quest uses.
+pub fn features(quest: &Quest) -> BTreeSet<QuestFeature> {
+    let mut features = BTreeSet::new();
+    let mut changes: Vec<&ParameterChange> = Vec::new();
+    let mut media: Vec<&Media> = Vec::new();
+
+    if quest.header.version == Version::Qmm7 {
+        features.insert(QuestFeature::Qmm7);
+    }
+
+    for parameter in &quest.parameters {
+        if parameter.ty != ParameterType::Ordinary {
+            features.insert(QuestFeature::CriticalParameter);
+        }
+
+        if parameter.is_money {
+            features.insert(QuestFeature::MoneyParameter);
+        }
+
+        if [&parameter.image, &parameter.sound, &parameter.track]
+            .iter()
+            .any(|file| !file.is_empty())
+        {
+            features.insert(QuestFeature::Media);
+        }
+    }
+
+    for location in &quest.locations {
+        if let LocationSelectType::ByFormula(_) = &location.select_type {
+            features.insert(QuestFeature::SelectionFormula);
+        }
+
+        if location.texts.iter().filter(|text| !is_blank(text)).count() > 1 {
+            features.insert(QuestFeature::MultipleTexts);
+        }
+
+        if location.ty == LocationType::Empty {
+            features.insert(QuestFeature::EmptyLocation);
+        }
+
+        if matches!(location.max_visits, MaxVisits::Limit(_)) {
+            features.insert(QuestFeature::VisitLimit);
+        }
+
+        if location.do_pass_day {
+            features.insert(QuestFeature::PassingDays);
+        }
+
+        changes.extend(&location.parameter_changes);
+        media.extend(&location.media);
+    }
+
+    for jump in &quest.jumps {
+        for condition in &jump.parameters_conditions {
+            if !condition.must_equal_values.is_empty() {
+                features.insert(QuestFeature::EqualCondition);
+            }
+
+            if !condition.must_mod_values.is_empty() {
+                features.insert(QuestFeature::ModCondition);
+            }
+        }
+
+        if !jump.formula.is_empty() {
+            features.insert(QuestFeature::JumpFormula);
+        }
+
+        if jump.show_always {
+            features.insert(QuestFeature::ShowAlways);
+        }
+
+        if jump.priority != 1.0 {
+            features.insert(QuestFeature::JumpPriority);
+        }
+
+        if matches!(jump.max_visits, MaxVisits::Limit(_)) {
+            features.insert(QuestFeature::VisitLimit);
+        }
+
+        if jump.do_pass_day {
+            features.insert(QuestFeature::PassingDays);
+        }
+
+        changes.extend(&jump.parameter_changes);
+        media.push(&jump.media);
+    }
+
+    for change in changes {
+        match change.change_type {
+            ParameterChangeType::Percentage => {
+                features.insert(QuestFeature::PercentageChange);
+            }
+            ParameterChangeType::Formula => {
+                features.insert(QuestFeature::FormulaChange);
+            }
+            ParameterChangeType::Value | ParameterChangeType::Sum => (),
+        }
+
+        if change.show_type != ParameterShowType::Nothing {
+            features.insert(QuestFeature::ParameterVisibility);
+        }
+
+        media.push(&change.media);
+    }
+
+    for token in quest.formulas().flat_map(|(_, formula)| &formula.tokens) {
+        match token.kind {
+            FormulaTokenKind::Range { .. } | FormulaTokenKind::ToRange { .. } => {
+                features.insert(QuestFeature::Range);
+            }
+            FormulaTokenKind::Double { .. } => {
+                features.insert(QuestFeature::FractionalNumber);
+            }
+            _ => (),
+        }
+    }
+
+    for element in quest.formatted_texts().flat_map(|(_, text)| &text.elements) {
+        match element.kind {
+            TextElementKind::Formula { .. } => {
+                features.insert(QuestFeature::TextFormula);
+            }
+            TextElementKind::Selection { .. } => {
+                features.insert(QuestFeature::TextSelection);
+            }
+            _ => (),
+        }
+    }
+
+    if media
+        .iter()
+        .any(|media| !(media.image.is_empty() && media.sound.is_empty() && media.track.is_empty()))
+    {
+        features.insert(QuestFeature::Media);
+    }
+
+    features
+}
+
+fn is_blank(text: &FormattedText) -> bool {
+    text.to_string().trim().is_empty()
+}
@@ -0,0 +1,333 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use qmm_syntax::qmm::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz
+    Dot,
+    /// Gephi
+    Gexf,
+    Graphml,
+    /// Mermaid flowchart for Markdown documents
+    Mermaid,
+}
+
+struct Node {
+    id: u32,
+    label: String,
+    ty: &'static str,
+    /// 0 if unlimited
+    max_visits: u32,
+    text_length: usize,
+    /// Fewest jumps from the start
+    distance: Option<usize>,
+}
+
+struct Edge {
+    id: u32,
+    from: u32,
+    to: u32,
+    label: String,
+    priority: f64,
+    conditions: usize,
+}
+
+/// Locations and jumps with the attributes shown by the graph tools.
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(quest: &Quest, distances: &BTreeMap<LocationId, usize>) -> Self {
+        let nodes = quest
+            .locations
+            .iter()
+            .map(|location| Node {
+                id: location.id.0,
+                label: match distances.get(&location.id) {
+                    Some(distance) => format!("L{} ({distance})", location.id.0),
+                    None => format!("L{}", location.id.0),
+                },
+                ty: location_type(&location.ty),
+                max_visits: match location.max_visits {
+                    MaxVisits::Infinite => 0,
+                    MaxVisits::Limit(limit) => limit,
+                },
+                text_length: location
+                    .texts
+                    .iter()
+                    .map(|text| text.to_string().chars().count())
+                    .sum(),
+                distance: distances.get(&location.id).copied(),
+            })
+            .collect();
+        let edges = quest
+            .jumps
+            .iter()
+            .map(|jump| Edge {
+                id: jump.id.0,
+                from: jump.from.0,
+                to: jump.to.0,
+                label: label(&jump.text.to_string()),
+                priority: jump.priority,
+                conditions: jump.parameters_conditions.len()
+                    + usize::from(!jump.formula.is_empty()),
+            })
+            .collect();
+
+        Self { nodes, edges }
+    }
+}
+
+fn location_type(ty: &LocationType) -> &'static str {
+    match ty {
+        LocationType::Ordinary => "ordinary",
+        LocationType::Starting => "starting",
+        LocationType::Empty => "empty",
+        LocationType::Success => "success",
+        LocationType::Fail => "fail",
+        LocationType::Death => "death",
+    }
+}
+
+/// First line of the text, shortened to fit on the edge.
+fn label(text: &str) -> String {
+    const MAX_CHARS: usize = 40;
+
+    let line = text.lines().next().unwrap_or_default().trim();
+
+    if line.chars().count() > MAX_CHARS {
+        format!("{}…", line.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Mermaid has no escapes inside the quoted labels, only entity codes.
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Graph of the locations connected by the jumps, the locations are labeled
+/// with the distances if there are any.
+pub fn graph(
+    quest: &Quest,
+    format: GraphFormat,
+    distances: &BTreeMap<LocationId, usize>,
+) -> String {
+    let graph = Graph::new(quest, distances);
+
+    match format {
+        GraphFormat::Dot => dot(&graph),
+        GraphFormat::Gexf => gexf(&graph),
+        GraphFormat::Graphml => graphml(&graph),
+        GraphFormat::Mermaid => mermaid(&graph),
+    }
+}
+
+fn dot(graph: &Graph) -> String {
+    let mut out = String::from("digraph quest {\n");
+
+    for node in &graph.nodes {
+        let style = match node.ty {
+            "starting" => ", shape=box",
+            "success" => ", style=filled, fillcolor=palegreen",
+            "fail" => ", style=filled, fillcolor=lightsalmon",
+            "death" => ", style=filled, fillcolor=gray",
+            "empty" => ", shape=point",
+            _ => "",
+        };
+
+        writeln!(out, "    L{} [label=\"{}\"{style}];", node.id, node.label).unwrap();
+    }
+
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            "    L{} -> L{} [label=\"{}\"];",
+            edge.from,
+            edge.to,
+            escape_dot(&edge.label)
+        )
+        .unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn gexf(graph: &Graph) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n",
+        "  <graph defaultedgetype=\"directed\">\n",
+        "    <attributes class=\"node\">\n",
+        "      <attribute id=\"type\" title=\"type\" type=\"string\"/>\n",
+        "      <attribute id=\"max_visits\" title=\"max_visits\" type=\"integer\"/>\n",
+        "      <attribute id=\"text_length\" title=\"text_length\" type=\"integer\"/>\n",
+        "      <attribute id=\"distance\" title=\"distance\" type=\"integer\"/>\n",
+        "    </attributes>\n",
+        "    <attributes class=\"edge\">\n",
+        "      <attribute id=\"priority\" title=\"priority\" type=\"double\"/>\n",
+        "      <attribute id=\"conditions\" title=\"conditions\" type=\"integer\"/>\n",
+        "    </attributes>\n",
+        "    <nodes>\n",
+    ));
+
+    for node in &graph.nodes {
+        write!(
+            out,
+            concat!(
+                "      <node id=\"L{}\" label=\"{}\"><attvalues>",
+                "<attvalue for=\"type\" value=\"{}\"/>",
+                "<attvalue for=\"max_visits\" value=\"{}\"/>",
+                "<attvalue for=\"text_length\" value=\"{}\"/>",
+            ),
+            node.id, node.label, node.ty, node.max_visits, node.text_length
+        )
+        .unwrap();
+
+        if let Some(distance) = node.distance {
+            write!(out, "<attvalue for=\"distance\" value=\"{distance}\"/>").unwrap();
+        }
+
+        out.push_str("</attvalues></node>\n");
+    }
+
+    out.push_str("    </nodes>\n    <edges>\n");
+
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            concat!(
+                "      <edge id=\"J{}\" source=\"L{}\" target=\"L{}\" label=\"{}\"><attvalues>",
+                "<attvalue for=\"priority\" value=\"{}\"/>",
+                "<attvalue for=\"conditions\" value=\"{}\"/>",
+                "</attvalues></edge>"
+            ),
+            edge.id,
+            edge.from,
+            edge.to,
+            escape_xml(&edge.label),
+            edge.priority,
+            edge.conditions
+        )
+        .unwrap();
+    }
+
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    out
+}
+
+fn graphml(graph: &Graph) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n",
+        "  <key id=\"max_visits\" for=\"node\" attr.name=\"max_visits\" attr.type=\"int\"/>\n",
+        "  <key id=\"text_length\" for=\"node\" attr.name=\"text_length\" attr.type=\"int\"/>\n",
+        "  <key id=\"distance\" for=\"node\" attr.name=\"distance\" attr.type=\"int\"/>\n",
+        "  <key id=\"label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        "  <key id=\"priority\" for=\"edge\" attr.name=\"priority\" attr.type=\"double\"/>\n",
+        "  <key id=\"conditions\" for=\"edge\" attr.name=\"conditions\" attr.type=\"int\"/>\n",
+        "  <graph id=\"quest\" edgedefault=\"directed\">\n",
+    ));
+
+    for node in &graph.nodes {
+        write!(
+            out,
+            concat!(
+                "    <node id=\"L{}\">",
+                "<data key=\"type\">{}</data>",
+                "<data key=\"max_visits\">{}</data>",
+                "<data key=\"text_length\">{}</data>",
+            ),
+            node.id, node.ty, node.max_visits, node.text_length
+        )
+        .unwrap();
+
+        if let Some(distance) = node.distance {
+            write!(out, "<data key=\"distance\">{distance}</data>").unwrap();
+        }
+
+        out.push_str("</node>\n");
+    }
+
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            concat!(
+                "    <edge id=\"J{}\" source=\"L{}\" target=\"L{}\">",
+                "<data key=\"label\">{}</data>",
+                "<data key=\"priority\">{}</data>",
+                "<data key=\"conditions\">{}</data>",
+                "</edge>"
+            ),
+            edge.id,
+            edge.from,
+            edge.to,
+            escape_xml(&edge.label),
+            edge.priority,
+            edge.conditions
+        )
+        .unwrap();
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn mermaid(graph: &Graph) -> String {
+    let mut out = String::from(concat!(
+        "flowchart TD\n",
+        "    classDef success fill:#9f9\n",
+        "    classDef fail fill:#f96\n",
+        "    classDef death fill:#999\n",
+    ));
+
+    for node in &graph.nodes {
+        let (open, close) = match node.ty {
+            "starting" => ("([", "])"),
+            "success" | "fail" | "death" => ("((", "))"),
+            _ => ("[", "]"),
+        };
+
+        write!(out, "    L{}{open}\"{}\"{close}", node.id, node.label).unwrap();
+
+        if matches!(node.ty, "success" | "fail" | "death") {
+            write!(out, ":::{}", node.ty).unwrap();
+        }
+
+        out.push('\n');
+    }
+
+    for edge in &graph.edges {
+        if edge.label.is_empty() {
+            writeln!(out, "    L{} --> L{}", edge.from, edge.to).unwrap();
+        } else {
+            writeln!(
+                out,
+                "    L{} -->|\"{}\"| L{}",
+                edge.from,
+                escape_mermaid(&edge.label),
+                edge.to
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
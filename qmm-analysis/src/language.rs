@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use qmm_syntax::qmm::{Quest, TextSource};
+
+/// Language of the quest texts, told apart by its most frequent words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Language {
+    English,
+    Russian,
+    Ukrainian,
+    German,
+    French,
+    Spanish,
+    Polish,
+}
+
+impl Language {
+    pub const ALL: [Language; 7] = [
+        Language::English,
+        Language::Russian,
+        Language::Ukrainian,
+        Language::German,
+        Language::French,
+        Language::Spanish,
+        Language::Polish,
+    ];
+
+    /// ISO 639-1 code, like the `language` of the quest metadata.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Russian => "ru",
+            Language::Ukrainian => "uk",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Polish => "pl",
+        }
+    }
+
+    fn common_words(self) -> &'static [&'static str] {
+        match self {
+            Language::English => &[
+                "the", "and", "you", "to", "of", "is", "are", "in", "it", "that", "your", "with",
+                "for", "on", "not", "this", "have", "be", "was", "will", "what", "but", "they",
+                "at", "from",
+            ],
+            Language::Russian => &[
+                "и",
+                "в",
+                "не",
+                "на",
+                "что",
+                "вы",
+                "с",
+                "как",
+                "это",
+                "вас",
+                "по",
+                "но",
+                "а",
+                "к",
+                "у",
+                "за",
+                "из",
+                "же",
+                "то",
+                "все",
+                "так",
+                "было",
+                "его",
+                "только",
+                "уже",
+                "когда",
+                "если",
+                "он",
+                "она",
+            ],
+            Language::Ukrainian => &[
+                "і",
+                "й",
+                "та",
+                "що",
+                "ви",
+                "на",
+                "не",
+                "як",
+                "це",
+                "до",
+                "з",
+                "від",
+                "але",
+                "вас",
+                "був",
+                "була",
+                "які",
+                "щоб",
+                "вже",
+                "тільки",
+                "його",
+                "коли",
+                "якщо",
+                "він",
+                "вона",
+                "їх",
+                "є",
+            ],
+            Language::German => &[
+                "der", "die", "das", "und", "ist", "sie", "nicht", "ein", "eine", "zu", "den",
+                "mit", "auf", "es", "sich", "ich", "dem", "des", "auch", "wird", "noch", "aber",
+                "ihr", "ihre", "wie", "sind",
+            ],
+            Language::French => &[
+                "le", "la", "les", "et", "est", "vous", "de", "des", "un", "une", "que", "qui",
+                "pas", "pour", "dans", "au", "sur", "ne", "avec", "il", "elle", "votre", "ce",
+            ],
+            Language::Spanish => &[
+                "el", "la", "los", "las", "y", "es", "que", "de", "en", "un", "una", "por", "con",
+                "no", "su", "para", "se", "lo", "al", "del", "usted", "pero", "está",
+            ],
+            Language::Polish => &[
+                "i", "w", "nie", "się", "na", "że", "to", "jest", "z", "do", "jak", "ale", "co",
+                "jego", "tak", "już", "po", "od", "za", "czy", "jej", "są", "ty",
+            ],
+        }
+    }
+
+    /// Language with the most common words in the text, `None` if there
+    /// are too few of them or two languages have as many, like in the short
+    /// jump texts.
+    pub fn detect(text: &str) -> Option<Language> {
+        let words: Vec<String> = text
+            .split(|char: char| !char.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let mut scores: Vec<(usize, Language)> = Language::ALL
+            .iter()
+            .map(|language| {
+                let common = language.common_words();
+                let score = words
+                    .iter()
+                    .filter(|word| common.contains(&word.as_str()))
+                    .count();
+
+                (score, *language)
+            })
+            .collect();
+        scores.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        match scores.as_slice() {
+            [(best, language), (second, _), ..] if *best >= 2 && best > second => Some(*language),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Language::English => "English",
+            Language::Russian => "Russian",
+            Language::Ukrainian => "Ukrainian",
+            Language::German => "German",
+            Language::French => "French",
+            Language::Spanish => "Spanish",
+            Language::Polish => "Polish",
+        })
+    }
+}
+
+/// Languages of the quest texts, see [`languages`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageReport {
+    /// Words of the texts in each language
+    pub words: BTreeMap<Language, usize>,
+    /// Language with the most words
+    pub dominant: Option<Language>,
+    /// Texts in another language than the dominant one, in file order
+    pub foreign: Vec<(TextSource, Language)>,
+}
+
+/// Detects the language of every text and finds the ones that differ from
+/// the rest of the quest, like the untranslated leftovers of a translation.
+/// The texts whose language can't be told are skipped.
+pub fn languages(quest: &Quest) -> LanguageReport {
+    let mut report = LanguageReport::default();
+    let texts: Vec<_> = quest
+        .texts()
+        .into_iter()
+        .filter_map(|text| Some((Language::detect(&text.text)?, text)))
+        .collect();
+
+    for (language, text) in &texts {
+        *report.words.entry(*language).or_default() += text.word_count();
+    }
+
+    report.dominant = report
+        .words
+        .iter()
+        .max_by_key(|(_, words)| **words)
+        .map(|(language, _)| *language);
+    report.foreign = texts
+        .into_iter()
+        .filter(|(language, _)| Some(*language) != report.dominant)
+        .map(|(language, text)| (text.source, language))
+        .collect();
+
+    report
+}
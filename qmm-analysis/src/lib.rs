@@ -0,0 +1,244 @@
+//! Checks of quests without playing them: the jumps that can never be
+//! taken, the locations that can't be left, the duplicated texts, the
+//! texts with the words of the content lists, the texts in another language
+//! than the rest, the constructs a quest uses, and
+//! with the features the quest graph export (`graphviz`) and the solver
+//! checks (`smt`).
+//!
+//! Kept apart from `qmm-syntax`, so the parser stays lean for the WASM and
+//! embedded consumers.
+
+mod audit;
+mod content;
+mod duplicates;
+mod features;
+#[cfg(feature = "graphviz")]
+mod graph;
+mod language;
+#[cfg(feature = "smt")]
+mod smt;
+
+pub use audit::{audit, parameter_bounds, AuditFinding};
+pub use content::{content_flags, ContentFlag, WordLists};
+pub use duplicates::{duplicate_texts, DuplicateCluster, DuplicateOptions};
+pub use features::{features, QuestFeature};
+#[cfg(feature = "graphviz")]
+pub use graph::{graph, GraphFormat};
+pub use language::{languages, Language, LanguageReport};
+#[cfg(feature = "smt")]
+pub use smt::{audit_smt, jump_script, SatResult, Z3};
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use qmm_syntax::{
+        qmm::{parse_qmm, JumpParameterCondition, Quest, TextSource, Version},
+        text::formula::Formula,
+    };
+
+    use crate::*;
+
+    pub fn quest() -> Quest {
+        parse_qmm(&fs::read("../qmm-syntax/test.qmm").unwrap()).unwrap()
+    }
+
+    #[test]
+    pub fn audit_dead_jumps() {
+        let mut quest = quest();
+        let bounds = parameter_bounds(&quest);
+        let max = quest.parameters[0].max_value;
+
+        assert_eq!(bounds.len(), quest.parameters.len());
+        assert!(bounds[0].contains(20.0));
+        assert!(audit(&quest).is_empty());
+
+        quest.jumps[0].formula = Formula::parse(&format!("[p1] > {max}")).unwrap();
+        quest.jumps[1].parameters_conditions = vec![JumpParameterCondition {
+            parameter_id: 1,
+            range_start: max + 1,
+            range_end: max + 10,
+            must_equal: false,
+            must_equal_values: Vec::new(),
+            must_mod: false,
+            must_mod_values: Vec::new(),
+        }];
+
+        let findings = audit(&quest);
+        assert_eq!(
+            findings
+                .iter()
+                .filter_map(|finding| match finding {
+                    AuditFinding::DeadJump { jump, .. } => Some(*jump),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec![quest.jumps[0].id, quest.jumps[1].id]
+        );
+        assert_eq!(
+            findings[0].to_string(),
+            format!(
+                "Jump {} can never be taken: the formula `[p1] > {max}` is always 0",
+                quest.jumps[0].id.0
+            )
+        );
+    }
+
+    #[test]
+    pub fn quest_features() {
+        let mut quest = quest();
+        let used = features(&quest);
+
+        assert!(used.contains(&QuestFeature::SelectionFormula));
+        assert!(used.contains(&QuestFeature::ModCondition));
+        assert!(!used.contains(&QuestFeature::Qmm7));
+        assert!(!used.contains(&QuestFeature::FractionalNumber));
+
+        quest.header.version = Version::Qmm7;
+        quest.jumps[0].formula = Formula::parse("[p1] * 1.5 > 3").unwrap();
+
+        let used = features(&quest);
+        assert!(used.contains(&QuestFeature::Qmm7));
+        assert!(used.contains(&QuestFeature::FractionalNumber));
+    }
+
+    #[test]
+    pub fn duplicated_texts() {
+        let mut quest = quest();
+        let text = "You enter the old hangar, the lights are off and it smells of fuel.";
+        let sources = [
+            TextSource::JumpDescription(quest.jumps[0].id),
+            TextSource::JumpDescription(quest.jumps[1].id),
+            TextSource::JumpDescription(quest.jumps[2].id),
+        ];
+
+        assert!(quest.set_text(sources[0], text));
+        assert!(quest.set_text(sources[1], &text.to_uppercase()));
+        assert!(quest.set_text(
+            sources[2],
+            "You enter the old hangar, the lights are on and it smells of fuel."
+        ));
+
+        let find = |options| {
+            duplicate_texts(&quest, options)
+                .into_iter()
+                .find(|cluster| cluster.texts.iter().any(|text| text.source == sources[0]))
+                .unwrap()
+        };
+
+        let identical = find(DuplicateOptions {
+            threshold: 1.0,
+            ..DuplicateOptions::default()
+        });
+        assert!(identical.identical);
+        assert_eq!(
+            identical
+                .texts
+                .iter()
+                .map(|text| text.source)
+                .collect::<Vec<_>>(),
+            sources[..2]
+        );
+
+        let similar = find(DuplicateOptions::default());
+        assert!(!similar.identical);
+        assert_eq!(similar.texts.len(), 3);
+    }
+
+    #[test]
+    pub fn content_words() {
+        let mut quest = quest();
+        let source = TextSource::JumpDescription(quest.jumps[0].id);
+        let mut lists = WordLists::default();
+
+        assert!(content_flags(&quest, &lists).is_empty());
+
+        lists.add("profanity", "damn*");
+        lists.add("violence", "Blood bath");
+        lists.add("violence", "kill");
+        assert!(quest.set_text(source, "Damned! A blood-bath... You've killed them."));
+
+        let flags: Vec<_> = content_flags(&quest, &lists)
+            .into_iter()
+            .filter(|flag| flag.source == source)
+            .collect();
+        assert_eq!(
+            flags
+                .iter()
+                .map(|flag| (flag.category.as_str(), flag.found.as_str()))
+                .collect::<Vec<_>>(),
+            [("profanity", "damned"), ("violence", "blood bath")]
+        );
+        assert_eq!(
+            flags[1].to_string(),
+            format!("{source}: violence `blood bath`")
+        );
+    }
+
+    #[test]
+    pub fn mixed_languages() {
+        let mut quest = quest();
+        let source = TextSource::JumpDescription(quest.jumps[0].id);
+
+        assert_eq!(
+            Language::detect("You open the hatch and the cold air of the station hits you."),
+            Some(Language::English)
+        );
+        assert_eq!(
+            Language::detect("Вы открываете люк, и в лицо вам бьёт холодный воздух."),
+            Some(Language::Russian)
+        );
+        assert_eq!(Language::detect("Далее"), None);
+
+        let report = languages(&quest);
+        assert_eq!(report.dominant, Some(Language::Russian));
+        assert!(report.foreign.is_empty());
+
+        assert!(quest.set_text(
+            source,
+            "You open the hatch and the cold air of the station hits you."
+        ));
+
+        let report = languages(&quest);
+        assert_eq!(report.dominant, Some(Language::Russian));
+        assert_eq!(report.foreign, vec![(source, Language::English)]);
+    }
+
+    #[cfg(feature = "graphviz")]
+    #[test]
+    pub fn graph_formats() {
+        let quest = quest();
+        let distances = quest.distances(quest.locations[0].id);
+        let dot = graph(&quest, GraphFormat::Dot, &distances);
+
+        assert!(dot.starts_with("digraph quest {\n"));
+        assert_eq!(dot.matches(" -> ").count(), quest.jumps.len());
+        assert!(graph(&quest, GraphFormat::Mermaid, &distances).starts_with("flowchart"));
+        assert!(graph(&quest, GraphFormat::Gexf, &Default::default()).starts_with("<?xml"));
+    }
+
+    #[cfg(feature = "smt")]
+    #[test]
+    pub fn smt_jump_script() {
+        let mut quest = quest();
+        let bounds = parameter_bounds(&quest);
+
+        quest.jumps[0].formula = Formula::parse("[p1] mod 2 = 1 and [p1] mod 2 = 0").unwrap();
+        quest.jumps[0].parameters_conditions = vec![JumpParameterCondition {
+            parameter_id: 1,
+            range_start: -5,
+            range_end: 10,
+            must_equal: true,
+            must_equal_values: vec![3, 4],
+            must_mod: false,
+            must_mod_values: Vec::new(),
+        }];
+
+        let script = jump_script(&quest.jumps[0], &bounds, true);
+
+        assert!(script.starts_with("(declare-const p1 Int)\n"));
+        assert!(script.contains("(assert (and (<= (- 5) p1) (<= p1 10) (or (= p1 3) (= p1 4))))\n"));
+        assert!(script.contains("(assert (not (= 2.0 0.0)))\n"));
+        assert!(script.ends_with("(assert (or (>= t7 0.5) (<= t7 (- 0.5))))\n"));
+    }
+}
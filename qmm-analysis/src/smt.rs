@@ -0,0 +1,435 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use qmm_syntax::{
+    qmm::{Jump, JumpParameterCondition, Quest},
+    text::{
+        expression::{BinaryOperator, Expression},
+        formula::ToRangeValue,
+        interval::Interval,
+    },
+};
+
+use crate::{
+    audit::{dead_jump_reason, with_soft_locks},
+    parameter_bounds, AuditFinding,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    Sat,
+    Unsat,
+    /// The solver gave up, e.g. on the timeout or the nonlinear arithmetic
+    Unknown,
+}
+
+/// The `z3` executable, the scripts are passed to it through the standard
+/// input.
+#[derive(Debug, Clone)]
+pub struct Z3 {
+    command: PathBuf,
+    timeout: Duration,
+}
+
+impl Default for Z3 {
+    fn default() -> Self {
+        Self {
+            command: PathBuf::from("z3"),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Z3 {
+    /// Path to the executable, `z3` from the `PATH` by default.
+    pub fn command(mut self, command: impl Into<PathBuf>) -> Self {
+        self.command = command.into();
+        self
+    }
+
+    /// Time given to a single check.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Checks the SMT-LIB script without the `(check-sat)` command.
+    pub fn check(&self, script: &str) -> io::Result<SatResult> {
+        let mut child = Command::new(&self.command)
+            .arg("-in")
+            .arg("-smt2")
+            .arg(format!("-t:{}", self.timeout.as_millis()))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes())?;
+            stdin.write_all(b"(check-sat)\n")?;
+        }
+
+        let output = child.wait_with_output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        match stdout.lines().next().map(str::trim) {
+            Some("sat") => Ok(SatResult::Sat),
+            Some("unsat") => Ok(SatResult::Unsat),
+            Some("unknown") => Ok(SatResult::Unknown),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected solver output: {}", stdout.trim()),
+            )),
+        }
+    }
+}
+
+/// Same as [`crate::audit`], but the jumps the interval analysis can't rule
+/// out are checked by the solver.
+///
+/// `strict_math` follows the player's option: whether division by zero fails
+/// the formula instead of saturating.
+pub fn audit_smt(quest: &Quest, strict_math: bool, solver: &Z3) -> io::Result<Vec<AuditFinding>> {
+    let bounds = parameter_bounds(quest);
+    let mut findings = Vec::new();
+
+    for jump in &quest.jumps {
+        if let Some(reason) = dead_jump_reason(jump, &bounds) {
+            findings.push(AuditFinding::DeadJump {
+                jump: jump.id,
+                reason,
+            });
+            continue;
+        }
+
+        let script = jump_script(jump, &bounds, strict_math);
+
+        if solver.check(&script)? == SatResult::Unsat {
+            findings.push(AuditFinding::DeadJump {
+                jump: jump.id,
+                reason: "its conditions are unsatisfiable".to_string(),
+            });
+        }
+    }
+
+    Ok(with_soft_locks(quest, findings))
+}
+
+/// SMT-LIB script satisfiable if the jump's parameter conditions and
+/// formula can be met with the parameters within the bounds.
+pub fn jump_script(jump: &Jump, bounds: &[Interval], strict_math: bool) -> String {
+    let mut script = Script {
+        strict_math,
+        parameters: bounds.len(),
+        ..Script::default()
+    };
+
+    for (index, bound) in bounds.iter().enumerate() {
+        let parameter = format!("p{}", index + 1);
+
+        script.line(format!("(declare-const {parameter} Int)"));
+
+        if bound.min.is_finite() {
+            script.assert(format!("(<= {} {parameter})", int(bound.min as i32)));
+        }
+
+        if bound.max.is_finite() {
+            script.assert(format!("(<= {parameter} {})", int(bound.max as i32)));
+        }
+    }
+
+    for condition in &jump.parameters_conditions {
+        let condition = script.condition(condition);
+        script.assert(condition);
+    }
+
+    if !jump.formula.is_empty() {
+        match jump.formula.expression() {
+            Ok(expression) => {
+                let value = script.term(&expression);
+                // Rounded to a non-zero integer
+                script.assert(format!("(or (>= {value} 0.5) (<= {value} (- 0.5)))"));
+            }
+            Err(_) => script.assert("false".to_string()),
+        }
+    }
+
+    script.text
+}
+
+/// SMT-LIB translation of the formulas. The values are reals, the subterms
+/// are bound to constants to keep the script linear in the formula size.
+#[derive(Default)]
+struct Script {
+    text: String,
+    strict_math: bool,
+    parameters: usize,
+    constants: usize,
+}
+
+impl Script {
+    fn line(&mut self, line: String) {
+        self.text.push_str(&line);
+        self.text.push('\n');
+    }
+
+    fn assert(&mut self, term: String) {
+        self.line(format!("(assert {term})"));
+    }
+
+    fn constant(&mut self, sort: &str) -> String {
+        self.constants += 1;
+        let name = format!("t{}", self.constants);
+
+        self.line(format!("(declare-const {name} {sort})"));
+        name
+    }
+
+    /// Real constant equal to the term.
+    fn bind(&mut self, term: String) -> String {
+        let name = self.constant("Real");
+
+        self.assert(format!("(= {name} {term})"));
+        name
+    }
+
+    /// Integer parameter, `None` if it doesn't exist and the evaluation fails.
+    fn parameter(&self, index: usize) -> Option<String> {
+        (1..=self.parameters)
+            .contains(&index)
+            .then(|| format!("p{index}"))
+    }
+
+    fn parameter_or_fail(&mut self, index: usize) -> String {
+        self.parameter(index).unwrap_or_else(|| {
+            self.assert("false".to_string());
+            "0".to_string()
+        })
+    }
+
+    fn condition(&mut self, condition: &JumpParameterCondition) -> String {
+        let Some(parameter) = self.parameter(condition.parameter_id as usize) else {
+            return "false".to_string();
+        };
+        let mut terms = vec![
+            format!("(<= {} {parameter})", int(condition.range_start)),
+            format!("(<= {parameter} {})", int(condition.range_end)),
+        ];
+
+        if !condition.must_equal_values.is_empty() {
+            let equal = any(condition
+                .must_equal_values
+                .iter()
+                .map(|value| format!("(= {parameter} {})", int(*value))));
+
+            terms.push(if condition.must_equal {
+                equal
+            } else {
+                format!("(not {equal})")
+            });
+        }
+
+        if !condition.must_mod_values.is_empty() {
+            let is_mod = any(condition
+                .must_mod_values
+                .iter()
+                .filter(|modulo| **modulo != 0)
+                .map(|modulo| format!("(= (mod {parameter} {}) 0)", int(*modulo))));
+
+            terms.push(if condition.must_mod {
+                is_mod
+            } else {
+                format!("(not {is_mod})")
+            });
+        }
+
+        format!("(and {})", terms.join(" "))
+    }
+
+    /// Real term of the expression's value.
+    fn term(&mut self, expression: &Expression) -> String {
+        match expression {
+            Expression::Integer(value) => real(*value as f64),
+            Expression::Double(value) => real(*value),
+            Expression::Parameter(index) => {
+                format!("(to_real {})", self.parameter_or_fail(*index))
+            }
+            Expression::Range(_) | Expression::ToRange { .. } => {
+                // Any of the numbers the ranges may pick
+                let picked = format!("(to_real {})", self.constant("Int"));
+                let ranges = self.ranges(expression, &picked);
+
+                self.assert(ranges);
+                picked
+            }
+            Expression::Negate(operand) => {
+                let operand = self.term(operand);
+
+                format!("(- {operand})")
+            }
+            Expression::Binary {
+                operator: BinaryOperator::In,
+                lhs,
+                rhs,
+            } => {
+                let lhs = self.term(lhs);
+
+                if matches!(**rhs, Expression::Range(_) | Expression::ToRange { .. }) {
+                    let rounded = self.bind(format!(
+                        "(ite (>= {lhs} 0.0) (to_real (to_int (+ {lhs} 0.5))) \
+                         (- (to_real (to_int (+ (- {lhs}) 0.5)))))"
+                    ));
+                    let contains = self.ranges(rhs, &rounded);
+
+                    self.bind(format!("(ite {contains} 1.0 0.0)"))
+                } else {
+                    let rhs = self.term(rhs);
+
+                    self.bind(format!("(ite (= {lhs} {rhs}) 1.0 0.0)"))
+                }
+            }
+            Expression::Binary { operator, lhs, rhs } => {
+                let lhs = self.term(lhs);
+                let rhs = self.term(rhs);
+                let term = self.binary(*operator, &lhs, &rhs);
+
+                self.bind(term)
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, lhs: &str, rhs: &str) -> String {
+        let boolean = |term: String| format!("(ite {term} 1.0 0.0)");
+        let trunc = |term: &str| {
+            format!(
+                "(ite (>= {term} 0.0) (to_real (to_int {term})) (- (to_real (to_int (- {term})))))"
+            )
+        };
+        let divisible = matches!(
+            operator,
+            BinaryOperator::Divide | BinaryOperator::DivideWithRemain | BinaryOperator::Modulo
+        );
+
+        if divisible && self.strict_math {
+            self.assert(format!("(not (= {rhs} 0.0))"));
+        }
+
+        let term = match operator {
+            BinaryOperator::Add => format!("(+ {lhs} {rhs})"),
+            BinaryOperator::Substract => format!("(- {lhs} {rhs})"),
+            BinaryOperator::Multiply => format!("(* {lhs} {rhs})"),
+            BinaryOperator::Divide => format!("(/ {lhs} {rhs})"),
+            BinaryOperator::DivideWithRemain => {
+                let quotient = self.bind(format!("(/ {lhs} {rhs})"));
+
+                trunc(&quotient)
+            }
+            BinaryOperator::Modulo => {
+                let quotient = self.bind(format!("(/ {lhs} {rhs})"));
+
+                format!("(- {lhs} (* {rhs} {}))", trunc(&quotient))
+            }
+            BinaryOperator::And => {
+                boolean(format!("(and (not (= {lhs} 0.0)) (not (= {rhs} 0.0)))"))
+            }
+            BinaryOperator::Or => boolean(format!("(or (not (= {lhs} 0.0)) (not (= {rhs} 0.0)))")),
+            BinaryOperator::Greater => boolean(format!("(> {lhs} {rhs})")),
+            BinaryOperator::GreaterOrEqual => boolean(format!("(>= {lhs} {rhs})")),
+            BinaryOperator::Lesser => boolean(format!("(< {lhs} {rhs})")),
+            BinaryOperator::LesserOrEqual => boolean(format!("(<= {lhs} {rhs})")),
+            BinaryOperator::Equal => boolean(format!("(= {lhs} {rhs})")),
+            BinaryOperator::NotEqual => boolean(format!("(not (= {lhs} {rhs}))")),
+            BinaryOperator::In => unreachable!(),
+        };
+
+        if divisible && !self.strict_math {
+            // Division by zero saturates, the remainder is zero
+            let zero = match operator {
+                BinaryOperator::Modulo => "0.0".to_string(),
+                _ => format!(
+                    "(ite (> {lhs} 0.0) {} (ite (< {lhs} 0.0) {} 0.0))",
+                    real(i32::MAX as f64),
+                    real(i32::MIN as f64)
+                ),
+            };
+
+            return format!("(ite (= {rhs} 0.0) {zero} {term})");
+        }
+
+        term
+    }
+
+    /// Whether the value is within any of the ranges.
+    fn ranges(&mut self, expression: &Expression, value: &str) -> String {
+        let bounds: Vec<(ToRangeValue, ToRangeValue)> = match expression {
+            Expression::Range(ranges) => ranges
+                .iter()
+                .map(|range| (*range.start(), *range.end()))
+                .collect(),
+            Expression::ToRange { start, end } => vec![(*start, *end)],
+            _ => Vec::new(),
+        };
+        let terms: Vec<String> = bounds
+            .into_iter()
+            .map(|(start, end)| {
+                let start = self.bound(start);
+                let end = self.bound(end);
+
+                // The bounds may go in any order
+                format!(
+                    "(or (and (<= {start} {value}) (<= {value} {end})) \
+                     (and (<= {end} {value}) (<= {value} {start})))"
+                )
+            })
+            .collect();
+
+        any(terms.into_iter())
+    }
+
+    fn bound(&mut self, value: ToRangeValue) -> String {
+        match value {
+            ToRangeValue::Integer { value } => real(value as f64),
+            ToRangeValue::Parameter { index } => {
+                format!("(to_real {})", self.parameter_or_fail(index))
+            }
+        }
+    }
+}
+
+fn any(terms: impl Iterator<Item = String>) -> String {
+    let terms: Vec<String> = terms.collect();
+
+    match terms.len() {
+        0 => "false".to_string(),
+        1 => terms[0].clone(),
+        _ => format!("(or {})", terms.join(" ")),
+    }
+}
+
+fn int(value: i32) -> String {
+    if value < 0 {
+        format!("(- {})", value.unsigned_abs())
+    } else {
+        value.to_string()
+    }
+}
+
+/// Decimal literal, SMT-LIB has no negative or exponent literals.
+fn real(value: f64) -> String {
+    let literal = format!("{}", value.abs());
+    let literal = if literal.contains('.') {
+        literal
+    } else {
+        format!("{literal}.0")
+    };
+
+    if value < 0.0 {
+        format!("(- {literal})")
+    } else {
+        literal
+    }
+}
@@ -0,0 +1,78 @@
+//! Replacing files so a crash or a full disk never leaves a half-written
+//! one: the data goes to a temp file next to the target, which is synced and
+//! renamed over it.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// `quest.qmm.bak` for `quest.qmm`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".bak");
+
+    path.with_file_name(name)
+}
+
+/// Hidden file in the same directory, renames across file systems aren't
+/// atomic.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+
+    path.with_file_name(name)
+}
+
+/// Replaces the file with the data, the previous contents are copied to
+/// [`backup_path`] first if `backup`.
+pub fn write(path: &Path, data: &[u8], backup: bool) -> io::Result<()> {
+    let temp_path = temp_path(path);
+    let result = write_synced(&temp_path, data).and_then(|()| {
+        if backup && path.exists() {
+            fs::copy(path, backup_path(path))?;
+        }
+
+        fs::rename(&temp_path, path)
+    });
+
+    if result.is_err() {
+        fs::remove_file(&temp_path).ok();
+    }
+
+    result?;
+    sync_dir(path);
+
+    Ok(())
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Makes the rename durable. Only possible on Unix, and the file is already
+/// replaced if it fails.
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+
+        if let Ok(dir) = File::open(dir) {
+            dir.sync_all().ok();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
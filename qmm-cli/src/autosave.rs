@@ -0,0 +1,76 @@
+use std::{
+    fs, panic,
+    path::PathBuf,
+    sync::{Mutex, Once},
+};
+
+use qmm_player::QuestSnapshot;
+
+use crate::saves::write_json;
+
+/// Steps between the writes of the autosave.
+pub const AUTOSAVE_STEPS: usize = 5;
+
+/// State of the playthrough written by the panic hook.
+static LATEST: Mutex<Option<(PathBuf, QuestSnapshot)>> = Mutex::new(None);
+static PANIC_HOOK: Once = Once::new();
+
+/// Slot in the temp directory with the last playthrough of the quest.
+///
+/// Autosave errors are ignored, they must not interrupt the game.
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    path: PathBuf,
+}
+
+impl Autosave {
+    /// Slot of the quest with the [`crate::saves::fingerprint`].
+    pub fn new(fingerprint: &str) -> Self {
+        PANIC_HOOK.call_once(install_panic_hook);
+
+        Self {
+            path: std::env::temp_dir().join(format!("qmm-cli-{fingerprint}.autosave.json")),
+        }
+    }
+
+    pub fn load(&self) -> Option<QuestSnapshot> {
+        let data = fs::read(&self.path).ok()?;
+
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Remembers the state for the panic hook.
+    pub fn update(&self, snapshot: QuestSnapshot) {
+        if let Ok(mut latest) = LATEST.lock() {
+            *latest = Some((self.path.clone(), snapshot));
+        }
+    }
+
+    pub fn save(&self, snapshot: &QuestSnapshot) {
+        write_json(&self.path, snapshot).ok();
+    }
+
+    /// Removes the slot of the finished playthrough.
+    pub fn clear(&self) {
+        if let Ok(mut latest) = LATEST.lock() {
+            *latest = None;
+        }
+
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+fn install_panic_hook() {
+    let hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        // The lock may be held by the panicking thread
+        if let Ok(latest) = LATEST.try_lock() {
+            if let Some((path, snapshot)) = latest.as_ref() {
+                write_json(path, snapshot).ok();
+            }
+        }
+
+        hook(info);
+    }));
+}
@@ -1,37 +1,50 @@
-use std::{
-    fmt::Debug,
-    io::{self, Stdout},
-    time::Duration,
-};
+use std::{fmt::Debug, path::PathBuf, rc::Rc};
+
+use typed_arena::Arena;
 
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+use qmm_player::{
+    EngineError, PlayerAction, PlayerContext, Progress, QuestError, QuestPlayer,
+    QuestPlayerBuilder, QuestState, StepResult,
+};
+use qmm_syntax::{
+    qmm::{JumpId, Quest},
+    text::formatted_text::FormattedText,
 };
-use qmm_player::{QuestPlayer, QuestState};
-use qmm_syntax::text::formatted_text::{FormattedText, TextElement, TextElementKind};
-use tui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
-    Frame, Terminal,
+
+use crate::{
+    autosave::{Autosave, AUTOSAVE_STEPS},
+    frontend::{FormField, FrontendAction, QuestFrontend, Screen},
+    saves::SaveSlots,
+    speech::Speech,
+    watch::QuestWatcher,
 };
 
 type OptionControlCallback = fn(&OptionControl, &mut CliQuestPlayer);
 
 #[derive(Debug, Clone)]
-enum PlayerState {
+pub enum PlayerState {
     PreStart,
-    InGame { state: QuestState },
+    InGame {
+        state: QuestState,
+    },
+    Finished {
+        state: QuestState,
+        result: StepResult,
+    },
     Exit,
 }
 
+/// Text asked from the user before the next turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextInput {
+    Context,
+    SaveSlot,
+}
+
 #[derive(Clone)]
 pub struct OptionControl {
     pub name: FormattedText,
+    pub jump: Option<JumpId>,
     on_selected: Option<OptionControlCallback>,
 }
 
@@ -39,6 +52,7 @@ impl Debug for OptionControl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OptionControl")
             .field("name", &self.name)
+            .field("jump", &self.jump)
             .finish()
     }
 }
@@ -47,6 +61,7 @@ impl OptionControl {
     pub fn new(name: &str, on_selected: Option<OptionControlCallback>) -> OptionControl {
         Self {
             name: FormattedText::parse(name),
+            jump: None,
             on_selected,
         }
     }
@@ -62,240 +77,557 @@ impl OptionControl {
 
 #[derive(Debug, Clone)]
 pub struct CliQuestPlayer<'q> {
+    /// Starts the quest again when the context is changed before the start
+    builder: QuestPlayerBuilder<'q>,
     player: QuestPlayer<'q>,
+    /// Shown on the intro screen
+    name: String,
     state: PlayerState,
-    selected_option: usize,
     options: Vec<OptionControl>,
+    turn: usize,
+    /// Actions sent to the quest player
+    steps: usize,
+    autosave: Option<Autosave>,
+    /// Player continuing the previous session, offered before the start
+    resumed: Option<QuestPlayer<'q>>,
+    slots: Option<SaveSlots>,
+    /// Slot the game is saved into on exit
+    slot: Option<String>,
+    input: Option<TextInput>,
+    speech: Option<Speech>,
+    /// Turn the text and the options were last spoken at
+    spoken_turn: Option<usize>,
+    /// Quest file loaded into the game again when it changes
+    hot_reload: Option<HotReload<'q>>,
+    /// Shown until the next action
+    notice: Option<String>,
+    /// Error of the last step, becomes the notice
+    engine_error: Option<EngineError>,
 }
 
-fn conv_formatted_text(text: FormattedText) -> Text<'static> {
-    let mut result_text = Text::default();
-    let text_style = Style::default()
-        .fg(Color::LightBlue)
-        .add_modifier(Modifier::BOLD);
-
-    let mut spans = Vec::new();
-    for el in text.elements {
-        match el.kind {
-            TextElementKind::NewLine => {
-                result_text.extend(Text::from(Spans::from(spans)));
-                spans = Vec::new();
-            }
-            TextElementKind::Variable { .. } => spans.push(Span::styled(el.value, text_style)),
-            TextElementKind::Selection { text } => spans.push(Span::styled(text, text_style)),
-            _ => spans.push(Span::raw(el.value)),
-        }
-    }
+/// Quest file watched for the edits.
+#[derive(Clone)]
+struct HotReload<'q> {
+    path: PathBuf,
+    watcher: Rc<QuestWatcher>,
+    /// Owns the edited quests, the players of the game borrow them
+    quests: &'q Arena<Quest>,
+}
 
-    result_text.extend(Text::from(Spans::from(spans)));
-    result_text
+impl Debug for HotReload<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotReload")
+            .field("path", &self.path)
+            .field("quests", &self.quests.len())
+            .finish()
+    }
 }
 
 impl<'q> CliQuestPlayer<'q> {
-    pub fn new(player: QuestPlayer<'q>) -> Self {
-        Self {
-            player,
+    pub fn new(builder: QuestPlayerBuilder<'q>, name: String) -> Result<Self, QuestError> {
+        Ok(Self {
+            player: builder.clone().build()?,
+            builder,
+            name,
             state: PlayerState::PreStart,
-            selected_option: 0,
             options: Vec::new(),
-        }
+            turn: 0,
+            steps: 0,
+            autosave: None,
+            resumed: None,
+            slots: None,
+            slot: None,
+            input: None,
+            speech: None,
+            spoken_turn: None,
+            hot_reload: None,
+            notice: None,
+            engine_error: None,
+        })
+    }
+
+    /// Saves the playthrough and offers to continue the `resumed` one.
+    pub fn autosave(mut self, autosave: Autosave, resumed: Option<QuestPlayer<'q>>) -> Self {
+        self.autosave = Some(autosave);
+        self.resumed = resumed;
+        self
+    }
+
+    /// Allows saving and loading the game, `slot` is continued if it exists
+    /// and saved on exit.
+    pub fn save_slots(mut self, slots: SaveSlots, slot: Option<String>) -> Self {
+        self.slots = Some(slots);
+        self.slot = slot;
+        self
+    }
+
+    /// Reads the text and the options aloud every time they change.
+    pub fn speech(mut self, speech: Speech) -> Self {
+        self.speech = Some(speech);
+        self
+    }
+
+    /// Continues the game in the edited quest every time the file changes,
+    /// the edited quests are kept in `quests`.
+    pub fn hot_reload(
+        mut self,
+        path: PathBuf,
+        watcher: QuestWatcher,
+        quests: &'q Arena<Quest>,
+    ) -> Self {
+        self.hot_reload = Some(HotReload {
+            path,
+            watcher: Rc::new(watcher),
+            quests,
+        });
+        self
     }
 
     pub fn set_options(&mut self, options: Vec<OptionControl>) {
-        self.selected_option = 0;
         self.options = options;
+        self.turn += 1;
     }
 
-    fn on_start_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
-        let state = player.player.state().clone();
-        player.set_options(
-            state
+    fn step(&mut self, action: PlayerAction) {
+        if let StepResult::EngineError(err) = self.player.step(action) {
+            self.engine_error = Some(err);
+        }
+
+        self.steps += 1;
+        self.sync_state();
+        self.save(self.steps.is_multiple_of(AUTOSAVE_STEPS));
+    }
+
+    /// Remembers the state for the crash recovery and writes it to the slot
+    /// if `write`, the slot of the finished quest is removed.
+    fn save(&self, write: bool) {
+        let Some(autosave) = &self.autosave else {
+            return;
+        };
+
+        match &self.state {
+            PlayerState::InGame { .. } => {
+                let Some(snapshot) = self.player.snapshot() else {
+                    return;
+                };
+
+                if write {
+                    autosave.save(&snapshot);
+                }
+
+                autosave.update(snapshot);
+            }
+            PlayerState::Finished { .. } => autosave.clear(),
+            _ => (),
+        }
+    }
+
+    fn sync_state(&mut self) {
+        let state = self.player.state().clone();
+        let options = match state.progress {
+            Progress::Playing => state
                 .jumps
                 .iter()
                 .map(|jump| OptionControl {
                     name: jump.name.clone(),
-                    on_selected: None,
+                    jump: Some(jump.id),
+                    on_selected: Some(Self::on_jump_selected),
                 })
                 .collect(),
-        );
-        player.state = PlayerState::InGame { state };
+            Progress::Description { .. } => vec![OptionControl::new(
+                "Continue",
+                Some(Self::on_continue_selected),
+            )],
+            Progress::Message { .. } => vec![OptionControl::new(
+                "Continue",
+                Some(Self::on_acknowledge_selected),
+            )],
+            Progress::Finished(_) => {
+                let result = self.player.step(PlayerAction::DoNothing);
+
+                self.set_options(vec![OptionControl::new(
+                    "Exit",
+                    Some(Self::on_exit_selected),
+                )]);
+                self.state = PlayerState::Finished { state, result };
+
+                return;
+            }
+        };
+
+        self.set_options(options);
+        self.state = PlayerState::InGame { state };
+    }
+
+    fn on_continue_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        player.step(PlayerAction::Continue);
+    }
+
+    fn on_acknowledge_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        player.step(PlayerAction::Acknowledge);
+    }
+
+    fn on_start_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        player.sync_state();
+    }
+
+    fn on_resume_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        if let Some(resumed) = player.resumed.take() {
+            player.player = resumed;
+        }
+
+        player.sync_state();
+    }
+
+    fn on_jump_selected(option: &OptionControl, player: &mut CliQuestPlayer) {
+        let Some(id) = option.jump else {
+            return;
+        };
+
+        player.step(PlayerAction::Jump(id));
+    }
+
+    fn on_load_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        player.show_slots();
+    }
+
+    fn on_context_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        player.input = Some(TextInput::Context);
     }
 
     fn on_exit_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
-        player.state = PlayerState::Exit;
+        player.exit();
     }
 
-    pub fn run(mut self) {
-        enable_raw_mode().unwrap();
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+    fn on_slot_selected(option: &OptionControl, player: &mut CliQuestPlayer) {
+        player.load_slot(&option.name.to_string());
+    }
+
+    fn on_continue_slot_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        if let Some(slot) = player.slot.clone() {
+            player.load_slot(&slot);
+        }
+    }
 
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend).unwrap();
+    fn on_back_selected(_: &OptionControl, player: &mut CliQuestPlayer) {
+        match player.state {
+            PlayerState::PreStart => player.show_intro(),
+            _ => player.sync_state(),
+        }
+    }
+
+    fn exit(&mut self) {
+        self.save(true);
+
+        if let Some(slot) = self.slot.clone() {
+            self.save_slot(&slot);
+        }
+
+        self.state = PlayerState::Exit;
+    }
 
-        self.set_options(vec![
+    /// Saves the game into the slot, the game can't be saved while a jump
+    /// description or a message is shown.
+    fn save_slot(&mut self, slot: &str) {
+        let (Some(slots), PlayerState::InGame { .. }) = (&self.slots, &self.state) else {
+            return;
+        };
+        let Some(snapshot) = self.player.snapshot() else {
+            return;
+        };
+
+        if slots.save(slot, &snapshot).is_ok() {
+            self.slot = Some(slot.to_string());
+        }
+    }
+
+    fn load_slot(&mut self, slot: &str) {
+        let Some(snapshot) = self.slots.as_ref().and_then(|slots| slots.load(slot)) else {
+            return;
+        };
+        let Ok(player) = self.builder.clone().snapshot(snapshot).build() else {
+            return;
+        };
+
+        self.player = player;
+        self.slot = Some(slot.to_string());
+        self.sync_state();
+    }
+
+    /// Replaces the options with the saved slots.
+    fn show_slots(&mut self) {
+        if !matches!(
+            self.state,
+            PlayerState::PreStart | PlayerState::InGame { .. }
+        ) {
+            return;
+        }
+
+        let Some(slots) = &self.slots else {
+            return;
+        };
+
+        let mut options: Vec<OptionControl> = slots
+            .list()
+            .iter()
+            .map(|slot| OptionControl::new(slot, Some(Self::on_slot_selected)))
+            .collect();
+        options.push(OptionControl::new("Back", Some(Self::on_back_selected)));
+
+        self.set_options(options);
+    }
+
+    fn show_intro(&mut self) {
+        let mut options = vec![
             OptionControl::new("Start", Some(Self::on_start_selected)),
+            OptionControl::new("Change ranger and planets", Some(Self::on_context_selected)),
             OptionControl::new("Exit", Some(Self::on_exit_selected)),
-        ]);
-        self.play(&mut terminal);
-
-        disable_raw_mode().unwrap();
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )
-        .unwrap();
-        terminal.show_cursor().unwrap();
-    }
-
-    fn play(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) {
-        // Flush events
-        while let Ok(true) = event::poll(Duration::from_millis(250)) {
-            event::read().ok();
+        ];
+
+        if self
+            .slots
+            .as_ref()
+            .is_some_and(|slots| !slots.list().is_empty())
+        {
+            options.insert(
+                1,
+                OptionControl::new("Load game", Some(Self::on_load_selected)),
+            );
         }
 
-        loop {
-            if matches!(self.state, PlayerState::Exit) {
-                return;
+        if self.resumed.is_some() {
+            options.insert(
+                0,
+                OptionControl::new("Resume last session", Some(Self::on_resume_selected)),
+            );
+        }
+
+        if let Some(slot) = &self.slot {
+            if self
+                .slots
+                .as_ref()
+                .is_some_and(|slots| slots.load(slot).is_some())
+            {
+                options.insert(
+                    0,
+                    OptionControl::new(
+                        &format!("Continue {slot}"),
+                        Some(Self::on_continue_slot_selected),
+                    ),
+                );
             }
+        }
 
-            terminal.draw(|frame| self.ui(frame)).unwrap();
+        self.set_options(options);
+    }
 
-            if let Event::Key(key) = event::read().unwrap() {
-                match key.code {
-                    KeyCode::Esc | KeyCode::Char('Q') => return,
-                    KeyCode::Up => {
-                        self.selected_option = self.selected_option.saturating_sub(1);
-                    }
-                    KeyCode::Down => {
-                        self.selected_option = self.selected_option.saturating_add(1);
-                    }
-                    KeyCode::Enter => {
-                        if let Some(option) = self.options.get(self.selected_option) {
-                            unsafe {
-                                let player = self as *const CliQuestPlayer as *mut CliQuestPlayer;
-                                option.selected(&mut *player);
-                            }
+    /// Starts the quest and the resumed session again with the context, so
+    /// the texts use it from the beginning.
+    fn set_context(&mut self, context: PlayerContext) {
+        let builder = self.builder.clone().context(context);
+
+        if let Some(snapshot) = self.resumed.as_ref().and_then(|resumed| resumed.snapshot()) {
+            self.resumed = builder.clone().snapshot(snapshot).build().ok();
+        }
+
+        if let Ok(player) = builder.clone().build() {
+            self.player = player;
+            self.builder = builder;
+        }
+    }
+
+    fn read_input(&mut self, frontend: &mut impl QuestFrontend, input: TextInput) {
+        let context = self.player.context().clone();
+        let screen = Screen {
+            player: &self.player,
+            state: &self.state,
+            options: &self.options,
+            name: &self.name,
+            turn: self.turn,
+            steps: self.steps,
+            notice: self.notice.as_deref(),
+        };
+
+        match input {
+            TextInput::Context => {
+                let mut fields = [
+                    FormField::new("Ranger", &context.ranger),
+                    FormField::new("Quest star", &context.from_star),
+                    FormField::new("Quest planet", &context.from_planet),
+                    FormField::new("Target star", &context.to_star),
+                    FormField::new("Target planet", &context.to_planet),
+                    FormField::new("Reward, cr", context.money),
+                ];
+
+                if frontend.read_form(&screen, "Ranger and planets", &mut fields) {
+                    // Empty and invalid values keep the previous ones
+                    let [ranger, from_star, from_planet, to_star, to_planet, money] =
+                        fields.map(|field| field.value.trim().to_string());
+                    let or = |value: String, previous: &String| {
+                        if value.is_empty() {
+                            previous.clone()
+                        } else {
+                            value
                         }
-                    }
-                    _ => (),
+                    };
+
+                    self.set_context(PlayerContext {
+                        ranger: or(ranger, &context.ranger),
+                        from_star: or(from_star, &context.from_star),
+                        from_planet: or(from_planet, &context.from_planet),
+                        to_star: or(to_star, &context.to_star),
+                        to_planet: or(to_planet, &context.to_planet),
+                        money: money.parse().unwrap_or(context.money),
+                        ..context
+                    });
                 }
+
+                self.show_intro();
             }
+            TextInput::SaveSlot => {
+                let slot = self.slot.clone().unwrap_or_else(|| "quicksave".to_string());
 
-            self.selected_option = self
-                .selected_option
-                .clamp(0, self.options.len().wrapping_sub(1));
+                if let Some(slot) = frontend.read_text(&screen, "Save slot", &slot) {
+                    self.save_slot(&slot);
+                }
+            }
         }
     }
 
-    fn ui(&mut self, frame: &mut Frame<CrosstermBackend<Stdout>>) {
-        let player = &self.player;
-        let size = frame.size();
-
-        // Main layout
-        let term_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(0)
-            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
-            .split(size);
-
-        // Right bar layout
-        let right_bar_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(0)
-            .constraints([Constraint::Percentage(90), Constraint::Percentage(10)])
-            .split(term_layout[1]);
-
-        // Params block
-        let params_block = Block::default()
-            .borders(Borders::ALL)
-            .title("Info")
-            .title_alignment(Alignment::Left)
-            .border_type(BorderType::Double);
-
-        frame.render_widget(params_block, right_bar_layout[0]);
-
-        // Help block
-        let help_paragragh = Paragraph::new("ESC/Q - exit").block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Keys")
-                .title_alignment(Alignment::Left)
-                .border_type(BorderType::Double),
-        );
-
-        frame.render_widget(help_paragragh, right_bar_layout[1]);
-
-        let main_block = Block::default()
-            .title("Quest Player")
-            .title_alignment(Alignment::Center)
-            .borders(Borders::ALL)
-            .border_type(BorderType::Double);
-
-        frame.render_widget(main_block, term_layout[0]);
-
-        let main_layout = Layout::default()
-            .margin(1)
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(70), Constraint::Min(30)])
-            .split(term_layout[0]);
+    fn speak(&mut self) {
+        if self.speech.is_none() || self.spoken_turn == Some(self.turn) {
+            return;
+        }
 
-        match &self.state {
-            PlayerState::PreStart => {
-                let task_text = player.task_text().clone();
-                let text_block =
-                    Paragraph::new(conv_formatted_text(task_text)).wrap(Wrap { trim: true });
+        let screen = Screen {
+            player: &self.player,
+            state: &self.state,
+            options: &self.options,
+            name: &self.name,
+            turn: self.turn,
+            steps: self.steps,
+            notice: self.notice.as_deref(),
+        };
+        let mut text = screen.text().to_string();
 
-                frame.render_widget(text_block, main_layout[0]);
-            }
-            PlayerState::InGame { state } => {
-                let location_text_block =
-                    Paragraph::new(conv_formatted_text(state.location.description.clone()))
-                        .wrap(Wrap { trim: true });
+        for (i, option) in self.options.iter().enumerate() {
+            text.push_str(&format!("\n{}. {}", i + 1, option.name));
+        }
+
+        self.spoken_turn = Some(self.turn);
+
+        if let Some(speech) = &mut self.speech {
+            speech.say(&text);
+        }
+    }
+
+    fn poll_reload(&mut self) {
+        let Some(hot_reload) = &self.hot_reload else {
+            return;
+        };
+
+        if hot_reload.watcher.poll().is_empty() {
+            return;
+        }
 
-                frame.render_widget(location_text_block, main_layout[0]);
+        match crate::reload_quest(&hot_reload.path) {
+            Ok(quest) => {
+                let quest = hot_reload.quests.alloc(quest);
+                self.reload(quest)
             }
-            PlayerState::Exit => return,
+            Err(err) => self.notice = Some(format!("Can't reload the quest: {err}")),
         }
+    }
 
-        let items: Vec<ListItem> = self
-            .options
-            .iter()
-            .enumerate()
-            .map(|(idx, option)| {
-                let mut option_name = FormattedText {
-                    elements: vec![TextElement {
-                        kind: TextElementKind::Text,
-                        value: if self.selected_option == idx {
-                            "> ".to_string()
-                        } else {
-                            "  ".to_string()
-                        },
-                    }],
-                };
+    /// Moves the game into the edited quest, the starting screen and the
+    /// endings are kept as they are.
+    fn reload(&mut self, quest: &'q Quest) {
+        self.builder = self.builder.clone().quest(quest);
+        // Sessions of the old quest can't be resumed in the new one safely
+        self.resumed = None;
+
+        let player = match &self.state {
+            PlayerState::InGame { .. } => self.player.reload(quest),
+            PlayerState::PreStart => self.builder.clone().build(),
+            PlayerState::Finished { .. } | PlayerState::Exit => {
+                self.notice = Some("Quest reloaded".to_string());
+                return;
+            }
+        };
 
-                option_name.elements.extend(option.name.elements.clone());
+        match player {
+            Ok(player) => {
+                self.player = player;
+                self.notice = Some("Quest reloaded".to_string());
 
-                let style = if self.selected_option == idx {
-                    Style::default().fg(Color::Yellow)
+                if matches!(self.state, PlayerState::PreStart) {
+                    self.show_intro();
                 } else {
-                    Style::default()
-                };
+                    self.sync_state();
+                }
+            }
+            Err(err) => {
+                self.notice = Some(format!(
+                    "The game can't continue in the edited quest: {err:?}"
+                ))
+            }
+        }
+    }
 
-                let mut text = conv_formatted_text(option_name);
-                text.patch_style(style);
+    pub fn run(mut self, frontend: &mut impl QuestFrontend) {
+        self.show_intro();
+        frontend.flush_input();
 
-                ListItem::new(text)
-            })
-            .collect();
+        loop {
+            if matches!(self.state, PlayerState::Exit) {
+                return;
+            }
+
+            self.poll_reload();
+
+            self.speak();
+
+            let screen = Screen {
+                player: &self.player,
+                state: &self.state,
+                options: &self.options,
+                name: &self.name,
+                turn: self.turn,
+                steps: self.steps,
+                notice: self.notice.as_deref(),
+            };
+
+            frontend.render(&screen);
 
-        let input_block = List::new(items).block(
-            Block::default()
-                .borders(Borders::TOP)
-                .border_type(BorderType::Rounded),
-        );
+            let action = frontend.get_action(&screen);
 
-        frame.render_widget(input_block, main_layout[1]);
+            match action {
+                Some(FrontendAction::Select(index)) => {
+                    if let Some(option) = self.options.get(index).cloned() {
+                        option.selected(&mut self);
+                    }
+                }
+                Some(FrontendAction::Tick) => frontend.tick(&screen),
+                Some(FrontendAction::Save)
+                    if self.slots.is_some() && matches!(self.state, PlayerState::InGame { .. }) =>
+                {
+                    self.input = Some(TextInput::SaveSlot);
+                }
+                Some(FrontendAction::Load) => self.show_slots(),
+                Some(FrontendAction::Exit) => {
+                    self.exit();
+                    return;
+                }
+                Some(FrontendAction::Save) | None => (),
+            }
+
+            if !matches!(action, Some(FrontendAction::Tick) | None) {
+                self.notice = self.engine_error.take().map(|err| err.to_string());
+            }
+
+            if let Some(input) = self.input.take() {
+                self.read_input(frontend, input);
+            }
+        }
     }
 }
@@ -0,0 +1,29 @@
+//! System clipboard, available with the `clipboard` feature.
+
+#[cfg(feature = "clipboard")]
+use std::sync::Mutex;
+
+/// Kept open until the exit, on X11 the copied text is only served while
+/// the clipboard is alive.
+#[cfg(feature = "clipboard")]
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<(), String> {
+    let mut clipboard = CLIPBOARD.lock().unwrap();
+
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new().map_err(|err| err.to_string())?);
+    }
+
+    clipboard
+        .as_mut()
+        .unwrap()
+        .set_text(text)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("Built without the `clipboard` feature".to_string())
+}
@@ -0,0 +1,159 @@
+use qmm_syntax::{
+    qmm::{
+        write_qmm, CompletionCondition, CriticalValue, FormattedRangeLine, Header, Info, Jump,
+        JumpId, JumpParameterCondition, JumpsLimit, Location, LocationId, LocationSelectType,
+        LocationType, MaxVisits, Media, Parameter, ParameterChange, ParameterChangeType,
+        ParameterShowType, ParameterType, PlanetType, PlayerStatus, Quest, Race,
+        StringReplacements, Version,
+    },
+    text::formatted_text::FormattedText,
+};
+
+/// Fuel the ship needs to get home.
+const FUEL_HOME: i32 = 5;
+
+/// Tiny quest played by `play --demo`: one parameter, a choice and both
+/// endings. It's written into the quest format, so the demo is read like
+/// the quest files.
+pub fn quest_data() -> Vec<u8> {
+    write_qmm(&quest())
+}
+
+fn quest() -> Quest {
+    Quest {
+        header: Header {
+            version: Version::Qmm7,
+            giver_race: Race::Human,
+            completion_condition: CompletionCondition::Immediately,
+            quest_planet_type: PlanetType::Populated(Race::Human),
+            player_status: PlayerStatus::all(),
+            player_race: Race::all(),
+            relation_change: 5,
+            default_jumps_limit: JumpsLimit::Infinite,
+            difficult: 10,
+            parameters_count: 1,
+        },
+        parameters: vec![Parameter {
+            min_value: 0,
+            max_value: 10,
+            ty: ParameterType::Ordinary,
+            show_when_zero: true,
+            critical_value: CriticalValue::Min,
+            is_active: true,
+            is_money: false,
+            name: "Fuel".to_string(),
+            formatted_range_lines: vec![FormattedRangeLine {
+                from: 0,
+                to: 10,
+                value: "Fuel: <>".to_string(),
+            }],
+            critical_text: String::new(),
+            image: String::new(),
+            sound: String::new(),
+            track: String::new(),
+            starting_value: "[2]".to_string(),
+        }],
+        string_replacements: StringReplacements {
+            to_star: "Sol".to_string(),
+            to_planet: "Earth".to_string(),
+            from_planet: "Mars".to_string(),
+            from_star: "Sol".to_string(),
+            ranger: "Ranger".to_string(),
+        },
+        info: Info {
+            locations_count: 4,
+            jumps_count: 3,
+            success_text: FormattedText::parse("Thank you, <Ranger>!"),
+            task_text: FormattedText::parse(
+                "Our probe was lost near <ToPlanet>, bring back its data.",
+            ),
+        },
+        locations: vec![
+            location(
+                1,
+                LocationType::Starting,
+                "Your ship drifts next to the probe. The way home takes 5 fuel.",
+                None,
+            ),
+            location(
+                2,
+                LocationType::Ordinary,
+                "The probe's tanks still hold some fuel, you pump it over.",
+                Some(4),
+            ),
+            location(3, LocationType::Success, "You bring the data home.", None),
+            location(4, LocationType::Fail, "The engines die halfway home.", None),
+        ],
+        jumps: vec![
+            jump(1, 1, 2, "Dock with the probe", None),
+            jump(2, 1, 4, "Head home right away", None),
+            jump(3, 2, 3, "Head home", Some(FUEL_HOME)),
+        ],
+    }
+}
+
+/// Location adding `fuel` if given.
+fn location(id: u32, ty: LocationType, text: &str, fuel: Option<i32>) -> Location {
+    Location {
+        do_pass_day: false,
+        x: id as i32 * 64,
+        y: 64,
+        id: LocationId(id),
+        max_visits: MaxVisits::Infinite,
+        ty,
+        parameter_changes: fuel
+            .map(|fuel| ParameterChange {
+                parameter_id: 1,
+                change: fuel,
+                show_type: ParameterShowType::Nothing,
+                change_type: ParameterChangeType::Sum,
+                formula: Default::default(),
+                critical_text: String::new(),
+                media: media(),
+            })
+            .into_iter()
+            .collect(),
+        texts: vec![FormattedText::parse(text)],
+        media: vec![media()],
+        select_type: LocationSelectType::ByOrder,
+    }
+}
+
+/// Jump requiring at least `fuel` if given.
+fn jump(id: u32, from: u32, to: u32, text: &str, fuel: Option<i32>) -> Jump {
+    Jump {
+        priority: 1.0,
+        do_pass_day: false,
+        id: JumpId(id),
+        from: LocationId(from),
+        to: LocationId(to),
+        show_always: false,
+        max_visits: MaxVisits::Infinite,
+        show_order: id,
+        parameters_conditions: fuel
+            .map(|fuel| JumpParameterCondition {
+                parameter_id: 1,
+                range_start: fuel,
+                range_end: 10,
+                must_equal: false,
+                must_equal_values: Vec::new(),
+                must_mod: false,
+                must_mod_values: Vec::new(),
+            })
+            .into_iter()
+            .collect(),
+        parameter_changes: Vec::new(),
+        formula: Default::default(),
+        text: FormattedText::parse(text),
+        description: FormattedText::default(),
+        media: media(),
+    }
+}
+
+fn media() -> Media {
+    Media {
+        image: String::new(),
+        sound: String::new(),
+        track: String::new(),
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt::Debug;
+
+use clap::ValueEnum;
+use qmm_syntax::qmm::Quest;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DumpFormat {
+    /// Rust debug output
+    Debug,
+    Json,
+    Yaml,
+    Ron,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DumpSection {
+    Header,
+    Params,
+    Locations,
+    Jumps,
+}
+
+fn format<T: Serialize + Debug>(value: &T, format: DumpFormat) -> Result<String, String> {
+    match format {
+        DumpFormat::Debug => Ok(format!("{value:#?}")),
+        DumpFormat::Json => serde_json::to_string_pretty(value).map_err(|err| err.to_string()),
+        DumpFormat::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        DumpFormat::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string()),
+    }
+}
+
+/// Serializes the whole quest or only one of its sections.
+pub fn dump(
+    quest: &Quest,
+    dump_format: DumpFormat,
+    section: Option<DumpSection>,
+) -> Result<String, String> {
+    match section {
+        None => format(quest, dump_format),
+        Some(DumpSection::Header) => format(&quest.header, dump_format),
+        Some(DumpSection::Params) => format(&quest.parameters, dump_format),
+        Some(DumpSection::Locations) => format(&quest.locations, dump_format),
+        Some(DumpSection::Jumps) => format(&quest.jumps, dump_format),
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use qmm_player::QuestPlayer;
+use qmm_syntax::{
+    qmm::{Media, ParameterChange, Quest, TextSource},
+    text::{
+        formula::Formula,
+        mojibake::{self, Mojibake},
+        transliteration,
+    },
+};
+use regex::Regex;
+
+/// New values of the parameter's fields, `None` keeps the field.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterEdit {
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub starting: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Changes the parameter `[p{id}]`, returning the changed fields as
+/// `field: old -> new` lines.
+///
+/// The quest is left unchanged if the result isn't valid.
+pub fn edit_parameter(
+    quest: &mut Quest,
+    id: u32,
+    edit: ParameterEdit,
+) -> Result<Vec<String>, String> {
+    let index = (id as usize)
+        .checked_sub(1)
+        .filter(|index| *index < quest.parameters.len())
+        .ok_or_else(|| format!("[p{id}] doesn't exist"))?;
+    let mut edited = quest.clone();
+    let parameter = &mut edited.parameters[index];
+    let mut changes = Vec::new();
+
+    if let Some(min) = edit.min {
+        changes.push(format!("min: {} -> {min}", parameter.min_value));
+        parameter.min_value = min;
+    }
+
+    if let Some(max) = edit.max {
+        changes.push(format!("max: {} -> {max}", parameter.max_value));
+        parameter.max_value = max;
+    }
+
+    if let Some(starting) = edit.starting {
+        Formula::parse(&starting)
+            .map_err(|err| err.to_string())
+            .and_then(|formula| formula.expression().map_err(|err| err.to_string()))
+            .map_err(|err| format!("invalid starting value `{starting}`: {err}"))?;
+        changes.push(format!(
+            "starting: `{}` -> `{starting}`",
+            parameter.starting_value
+        ));
+        parameter.starting_value = starting;
+    }
+
+    if let Some(name) = edit.name {
+        changes.push(format!("name: `{}` -> `{name}`", parameter.name));
+        parameter.name = name;
+    }
+
+    if parameter.min_value > parameter.max_value {
+        return Err(format!(
+            "[p{id}] min {} is greater than max {}",
+            parameter.min_value, parameter.max_value
+        ));
+    }
+
+    QuestPlayer::new(&edited, 0).map_err(|err| format!("the quest can't be started: {err:?}"))?;
+    *quest = edited;
+
+    Ok(changes)
+}
+
+/// Text changed by [`replace_texts`].
+pub struct Replacement {
+    pub source: TextSource,
+    pub before: String,
+    pub after: String,
+}
+
+/// Line of a [`Replacement`] on one side only.
+pub enum LineChange<'a> {
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl Replacement {
+    /// Removed and added lines in the order of the text, the rest is common
+    /// to both sides. The texts are short enough for the quadratic table of
+    /// the longest common lines.
+    pub fn changed_lines(&self) -> Vec<LineChange<'_>> {
+        let before: Vec<&str> = self.before.split('\n').collect();
+        let after: Vec<&str> = self.after.split('\n').collect();
+
+        // Longest common lines of the tails `before[i..]` and `after[j..]`
+        let mut common = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+
+        for i in (0..before.len()).rev() {
+            for j in (0..after.len()).rev() {
+                common[i][j] = if before[i] == after[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let mut changes = Vec::new();
+
+        while i < before.len() || j < after.len() {
+            if i < before.len() && j < after.len() && before[i] == after[j] {
+                i += 1;
+                j += 1;
+            } else if j == after.len() || (i < before.len() && common[i + 1][j] >= common[i][j + 1])
+            {
+                changes.push(LineChange::Removed(before[i].trim_end_matches('\r')));
+                i += 1;
+            } else {
+                changes.push(LineChange::Added(after[j].trim_end_matches('\r')));
+                j += 1;
+            }
+        }
+
+        changes
+    }
+}
+
+/// Replaces the matches of the regex in every text of the quest, `with` may
+/// refer to the groups as `$1` or `${name}`.
+pub fn replace_texts(quest: &mut Quest, regex: &Regex, with: &str) -> Vec<Replacement> {
+    let mut replacements = Vec::new();
+
+    for text in quest.texts() {
+        let after = regex.replace_all(&text.text, with);
+
+        if after == text.text {
+            continue;
+        }
+
+        let after = after.into_owned();
+        quest.set_text(text.source, &after);
+        replacements.push(Replacement {
+            source: text.source,
+            before: text.text,
+            after,
+        });
+    }
+
+    replacements
+}
+
+/// Decodes back the texts garbled by reading them in the wrong encoding,
+/// see [`mojibake::repair`].
+pub fn fix_encoding(quest: &mut Quest) -> Vec<(Replacement, Mojibake)> {
+    let mut repaired = Vec::new();
+
+    for text in quest.texts() {
+        let Some((after, mojibake)) = mojibake::repair(&text.text) else {
+            continue;
+        };
+
+        quest.set_text(text.source, &after);
+        repaired.push((
+            Replacement {
+                source: text.source,
+                before: text.text,
+                after,
+            },
+            mojibake,
+        ));
+    }
+
+    repaired
+}
+
+/// Writes the Cyrillic letters of the texts in Latin ones, see
+/// [`transliteration::transliterate`].
+pub fn transliterate(quest: &mut Quest) -> Vec<Replacement> {
+    let mut replacements = Vec::new();
+
+    for text in quest.texts() {
+        let after = transliteration::transliterate(&text.text);
+
+        if after == text.text {
+            continue;
+        }
+
+        quest.set_text(text.source, &after);
+        replacements.push(Replacement {
+            source: text.source,
+            before: text.text,
+            after,
+        });
+    }
+
+    replacements
+}
+
+/// Result of [`remap_media`].
+#[derive(Debug, Default)]
+pub struct MediaRemap {
+    /// Number of the changed references
+    pub renamed: usize,
+    /// Referred files missing from the map, with the number of references
+    pub unmatched: BTreeMap<String, usize>,
+    /// Names of the map the quest doesn't refer to
+    pub unused: Vec<String>,
+}
+
+/// Renames the images, sounds and tracks of the parameters, the locations,
+/// the jumps and the parameter changes by the `old -> new` map.
+pub fn remap_media(quest: &mut Quest, map: &BTreeMap<String, String>) -> MediaRemap {
+    let mut remap = MediaRemap::default();
+    let mut used = BTreeSet::new();
+
+    for file in media_files(quest) {
+        if file.is_empty() {
+            continue;
+        }
+
+        match map.get_key_value(file.as_str()) {
+            Some((old, new)) => {
+                used.insert(old);
+
+                if file != new {
+                    *file = new.clone();
+                    remap.renamed += 1;
+                }
+            }
+            None => *remap.unmatched.entry(file.clone()).or_default() += 1,
+        }
+    }
+
+    remap.unused = map
+        .keys()
+        .filter(|old| !used.contains(old))
+        .cloned()
+        .collect();
+
+    remap
+}
+
+fn media_files(quest: &mut Quest) -> Vec<&mut String> {
+    fn media(media: &mut Media) -> [&mut String; 3] {
+        [&mut media.image, &mut media.sound, &mut media.track]
+    }
+
+    fn changes(changes: &mut [ParameterChange]) -> impl Iterator<Item = &mut String> {
+        changes
+            .iter_mut()
+            .flat_map(|change| media(&mut change.media))
+    }
+
+    let mut files = Vec::new();
+
+    for parameter in &mut quest.parameters {
+        files.extend([
+            &mut parameter.image,
+            &mut parameter.sound,
+            &mut parameter.track,
+        ]);
+    }
+
+    for location in &mut quest.locations {
+        files.extend(location.media.iter_mut().flat_map(media));
+        files.extend(changes(&mut location.parameter_changes));
+    }
+
+    for jump in &mut quest.jumps {
+        files.extend(media(&mut jump.media));
+        files.extend(changes(&mut jump.parameter_changes));
+    }
+
+    files
+}
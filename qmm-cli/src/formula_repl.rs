@@ -0,0 +1,132 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+    ops::RangeInclusive,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use fastrand::Rng;
+use qmm_syntax::text::{
+    expression::EvalContext,
+    formula::{Formula, FormulaParseOptions},
+};
+
+const HELP: &str = "\
+Enter a formula to evaluate it, e.g. `([p1] + 2) * 3`
+:set <index> <value>  set the value of `[p<index>]`
+:params               print the parameters that were set
+:help                 print this help
+:quit                 exit";
+
+struct ReplContext {
+    parameters: BTreeMap<usize, i32>,
+    rng: Rng,
+}
+
+impl EvalContext for ReplContext {
+    fn parameter(&self, index: usize) -> Option<i32> {
+        Some(self.parameters.get(&index).copied().unwrap_or(0))
+    }
+
+    fn random(&mut self, range: RangeInclusive<i32>) -> i32 {
+        self.rng.i32(range)
+    }
+}
+
+pub fn run() {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    let mut ctx = ReplContext {
+        parameters: BTreeMap::new(),
+        rng: Rng::with_seed(seed),
+    };
+
+    println!("{HELP}");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        let line = line.trim();
+
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => (),
+            [":quit" | ":q"] => break,
+            [":help"] => println!("{HELP}"),
+            [":params"] => {
+                for (index, value) in &ctx.parameters {
+                    println!("[p{index}] = {value}");
+                }
+            }
+            [":set", index, value] => {
+                match (
+                    index.trim_start_matches("[p").trim_end_matches(']').parse(),
+                    value.parse(),
+                ) {
+                    (Ok(index), Ok(value)) if index > 0 => {
+                        ctx.parameters.insert(index, value);
+                    }
+                    _ => println!("Usage: :set <index> <value>"),
+                }
+            }
+            [command, ..] if command.starts_with(':') => {
+                println!("Unknown command `{command}`, see :help")
+            }
+            _ => explain(line, &mut ctx),
+        }
+    }
+}
+
+fn explain(text: &str, ctx: &mut ReplContext) {
+    let options = FormulaParseOptions {
+        lenient_numbers: true,
+        ..Default::default()
+    };
+    let formula = match Formula::parse_with(text, options) {
+        Ok((formula, lints)) => {
+            for lint in lints {
+                println!("warning: {lint}");
+            }
+
+            formula
+        }
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+
+    let expression = match formula.expression() {
+        Ok(expression) => expression,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+
+    match expression.explain(ctx) {
+        Ok((value, steps)) => {
+            for step in steps {
+                println!(
+                    "{}{} = {}",
+                    "  ".repeat(step.depth),
+                    step.expression,
+                    step.value
+                );
+            }
+
+            let rounded = value.round().clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+
+            println!("Result: {rounded}");
+        }
+        Err(err) => println!("{err}"),
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::Display;
+
+use qmm_syntax::{
+    qmm::{JumpId, LocationId, LocationSelectType, ParameterChangeType, Quest},
+    text::{
+        expression::{BinaryOperator, Expression},
+        formula::Formula,
+    },
+};
+
+use crate::lints::Lint;
+
+/// Place of a formula in the quest.
+#[derive(Debug, Clone, Copy)]
+pub enum FormulaSource {
+    StartingValue(u32),
+    LocationChange {
+        location: LocationId,
+        parameter: u32,
+    },
+    LocationSelection(LocationId),
+    JumpCondition(JumpId),
+    JumpChange {
+        jump: JumpId,
+        parameter: u32,
+    },
+}
+
+impl FormulaSource {
+    /// Whether the formula's value is used as a number rather than as a
+    /// condition.
+    fn is_value(&self) -> bool {
+        !matches!(self, FormulaSource::JumpCondition(_))
+    }
+}
+
+impl Display for FormulaSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormulaSource::StartingValue(parameter) => {
+                write!(f, "[p{parameter}] starting value")
+            }
+            FormulaSource::LocationChange {
+                location,
+                parameter,
+            } => write!(f, "location {} [p{parameter}] change", location.0),
+            FormulaSource::LocationSelection(location) => {
+                write!(f, "location {} text selection", location.0)
+            }
+            FormulaSource::JumpCondition(jump) => write!(f, "jump {} condition", jump.0),
+            FormulaSource::JumpChange { jump, parameter } => {
+                write!(f, "jump {} [p{parameter}] change", jump.0)
+            }
+        }
+    }
+}
+
+/// Formula rewritten by [`format_formulas`].
+pub struct Formatted {
+    pub source: FormulaSource,
+    pub before: String,
+    pub after: String,
+}
+
+/// Rewrites every formula the way the expression printer writes it: single
+/// spaces, lowercase keywords, `=` for the comparison and only the needed
+/// parentheses. The formulas that can't be evaluated are left as they are.
+pub fn format_formulas(quest: &mut Quest) -> Vec<Formatted> {
+    let mut formatted = Vec::new();
+    let mut format = |source, text: &mut String| {
+        let Some(after) = Formula::parse(text)
+            .ok()
+            .filter(|formula| !formula.is_empty())
+            .and_then(|formula| formula.expression().ok())
+            .map(|expression| expression.to_string())
+        else {
+            return;
+        };
+
+        if after != *text {
+            formatted.push(Formatted {
+                source,
+                before: std::mem::replace(text, after.clone()),
+                after,
+            });
+        }
+    };
+
+    for (parameter, id) in quest.parameters.iter_mut().zip(1..) {
+        format(
+            FormulaSource::StartingValue(id),
+            &mut parameter.starting_value,
+        );
+    }
+
+    let mut format_formula = |source, formula: &mut Formula| {
+        let mut text = formula.to_string();
+
+        format(source, &mut text);
+
+        if let Ok(parsed) = Formula::parse(&text) {
+            *formula = parsed;
+        }
+    };
+
+    for location in &mut quest.locations {
+        for change in &mut location.parameter_changes {
+            format_formula(
+                FormulaSource::LocationChange {
+                    location: location.id,
+                    parameter: change.parameter_id,
+                },
+                &mut change.formula,
+            );
+        }
+
+        if let LocationSelectType::ByFormula(formula) = &mut location.select_type {
+            format_formula(FormulaSource::LocationSelection(location.id), formula);
+        }
+    }
+
+    for jump in &mut quest.jumps {
+        format_formula(FormulaSource::JumpCondition(jump.id), &mut jump.formula);
+
+        for change in &mut jump.parameter_changes {
+            format_formula(
+                FormulaSource::JumpChange {
+                    jump: jump.id,
+                    parameter: change.parameter_id,
+                },
+                &mut change.formula,
+            );
+        }
+    }
+
+    formatted
+}
+
+/// Suspicious formula found by [`lint_formulas`].
+pub struct FormulaLint {
+    pub lint: Lint,
+    pub source: FormulaSource,
+    pub message: String,
+}
+
+/// Every formula of the quest used by the player with its place, the change
+/// formulas are only used by the formula changes.
+fn formulas(quest: &Quest) -> Vec<(FormulaSource, String)> {
+    let mut formulas = Vec::new();
+
+    for (parameter, id) in quest.parameters.iter().zip(1..) {
+        formulas.push((
+            FormulaSource::StartingValue(id),
+            parameter.starting_value.clone(),
+        ));
+    }
+
+    for location in &quest.locations {
+        for change in &location.parameter_changes {
+            if change.change_type == ParameterChangeType::Formula {
+                formulas.push((
+                    FormulaSource::LocationChange {
+                        location: location.id,
+                        parameter: change.parameter_id,
+                    },
+                    change.formula.to_string(),
+                ));
+            }
+        }
+
+        if let LocationSelectType::ByFormula(formula) = &location.select_type {
+            formulas.push((
+                FormulaSource::LocationSelection(location.id),
+                formula.to_string(),
+            ));
+        }
+    }
+
+    for jump in &quest.jumps {
+        formulas.push((
+            FormulaSource::JumpCondition(jump.id),
+            jump.formula.to_string(),
+        ));
+
+        for change in &jump.parameter_changes {
+            if change.change_type == ParameterChangeType::Formula {
+                formulas.push((
+                    FormulaSource::JumpChange {
+                        jump: jump.id,
+                        parameter: change.parameter_id,
+                    },
+                    change.formula.to_string(),
+                ));
+            }
+        }
+    }
+
+    formulas
+}
+
+fn is_comparison(expression: &Expression) -> Option<BinaryOperator> {
+    match expression {
+        Expression::Binary { operator, .. } if operator.precedence() == 3 => Some(*operator),
+        _ => None,
+    }
+}
+
+/// Comparisons of comparisons, like `[p1] = [p2] = 1`, compare a 0 or 1
+/// result with the next operand.
+fn chained_comparison(expression: &Expression) -> Option<String> {
+    let Expression::Binary { operator, lhs, rhs } = expression else {
+        return None;
+    };
+
+    if operator.precedence() == 3 && is_comparison(lhs).is_some() {
+        return Some(format!(
+            "`{expression}` compares the result of `{lhs}`, which is 0 or 1"
+        ));
+    }
+
+    chained_comparison(lhs).or_else(|| chained_comparison(rhs))
+}
+
+pub fn lint_formulas(quest: &Quest) -> Vec<FormulaLint> {
+    let mut lints = Vec::new();
+
+    for (source, text) in formulas(quest) {
+        let Some(expression) = Formula::parse(&text)
+            .ok()
+            .and_then(|formula| formula.expression().ok())
+        else {
+            continue;
+        };
+
+        if let Some(message) = chained_comparison(&expression) {
+            lints.push(FormulaLint {
+                lint: Lint::ChainedComparison,
+                source,
+                message,
+            });
+        }
+
+        // `[p1] = 5` in a change reads like an assignment, but the parameter
+        // gets the result of the comparison
+        match is_comparison(&expression) {
+            Some(operator) if source.is_value() && operator != BinaryOperator::In => {
+                lints.push(FormulaLint {
+                    lint: Lint::ComparisonValue,
+                    source,
+                    message: format!(
+                        "`{expression}` is a comparison, the value is 0 or 1 rather than the \
+                         right side"
+                    ),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    lints
+}
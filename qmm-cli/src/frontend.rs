@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use qmm_player::{Progress, QuestPlayer, QuestState, StepResult};
+use qmm_syntax::text::formatted_text::{FormattedText, TextElementKind};
+
+use crate::cli_player::{OptionControl, PlayerState};
+
+/// Text without the selection tags.
+pub fn plain_text(text: &FormattedText) -> String {
+    text.elements
+        .iter()
+        .map(|el| match &el.kind {
+            TextElementKind::Selection { text } => plain_text(text),
+            TextElementKind::NewLine => "\n".to_string(),
+            _ => el.value.clone(),
+        })
+        .collect()
+}
+
+/// Everything a frontend needs to draw the game.
+pub struct Screen<'a, 'q> {
+    pub player: &'a QuestPlayer<'q>,
+    pub state: &'a PlayerState,
+    pub options: &'a [OptionControl],
+    /// Quest name, the file name without the extension
+    pub name: &'a str,
+    /// Changes every time the options are replaced
+    pub turn: usize,
+    /// Jumps and continues made since the start
+    pub steps: usize,
+    /// Message about the last action, like a failed hot reload
+    pub notice: Option<&'a str>,
+}
+
+impl Screen<'_, '_> {
+    /// Main text: the task, the location, the jump description or the
+    /// ending.
+    pub fn text(&self) -> FormattedText {
+        match self.state {
+            PlayerState::PreStart => self.intro(),
+            PlayerState::InGame { state } => match &state.progress {
+                Progress::Description { text, .. } | Progress::Message { text, .. } => text.clone(),
+                _ => state.location.description.clone(),
+            },
+            PlayerState::Finished { state, result } => {
+                let mut text = match result {
+                    StepResult::Success {
+                        text, reward, days, ..
+                    } => {
+                        let mut text = text.clone();
+                        text.push_text(&format!("\n\nReward: {reward} cr, days: {days}"));
+
+                        text
+                    }
+                    _ => state.location.description.clone(),
+                };
+
+                text.push_text(&time_summary(state));
+                text
+            }
+            PlayerState::Exit => FormattedText::default(),
+        }
+    }
+
+    /// Quest details and the task shown before the start.
+    fn intro(&self) -> FormattedText {
+        let header = &self.player.quest().header;
+        let races = header
+            .giver_race
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut text = FormattedText::default();
+        text.push_text(&format!(
+            "{}\n\nGiver: {races}\nDifficulty: {}%\n",
+            self.name, header.difficult
+        ));
+
+        if let Some(deadline) = self.player.context().deadline {
+            text.push_text(&format!("Deadline: {deadline} days\n"));
+        }
+
+        text.push_text("\n");
+        text.concat(self.player.task_text().clone())
+    }
+
+    /// Quest state while the game is played or after it's finished.
+    pub fn quest_state(&self) -> Option<&QuestState> {
+        match self.state {
+            PlayerState::InGame { state } | PlayerState::Finished { state, .. } => Some(state),
+            _ => None,
+        }
+    }
+
+    /// Value of the first active money parameter.
+    pub fn money(&self) -> Option<i32> {
+        let state = self.quest_state()?;
+
+        self.player
+            .quest()
+            .parameters
+            .iter()
+            .zip(&state.parameters)
+            .find(|(parameter, _)| parameter.is_active && parameter.is_money)
+            .map(|(_, value)| *value)
+    }
+
+    /// Range lines of the parameters shown to the player.
+    pub fn parameters(&self) -> Vec<String> {
+        match self.state {
+            PlayerState::InGame { .. } => self
+                .player
+                .parameter_texts()
+                .iter()
+                .map(plain_text)
+                .collect(),
+            PlayerState::Finished { state, .. } => self
+                .player
+                .quest()
+                .parameters
+                .iter()
+                .zip(&state.parameters)
+                .filter(|(parameter, _)| parameter.is_active)
+                .filter_map(|(parameter, value)| self.player.parameter_text(parameter, *value))
+                .map(|text| plain_text(&text))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Time of the session and the location the player thought longest in.
+fn time_summary(state: &QuestState) -> String {
+    let total = state.location_time.values().sum();
+    let mut summary = format!("\n\nTime: {}", format_duration(total));
+
+    if let Some((location, time)) = state.location_time.iter().max_by_key(|(_, time)| **time) {
+        summary.push_str(&format!(
+            ", longest at L{}: {}",
+            location.0,
+            format_duration(*time)
+        ));
+    }
+
+    summary
+}
+
+/// `mm:ss` or `h:mm:ss` for the long sessions.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs / 3600 {
+        0 => format!("{:02}:{:02}", secs / 60, secs % 60),
+        hours => format!("{hours}:{:02}:{:02}", secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Text field of a form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormField {
+    pub label: String,
+    pub value: String,
+}
+
+impl FormField {
+    pub fn new(label: &str, value: impl ToString) -> Self {
+        Self {
+            label: label.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontendAction {
+    /// Select the option with the index
+    Select(usize),
+    /// No input during the tick, the screen is rendered again
+    Tick,
+    /// Save the game into a slot
+    Save,
+    /// Show the saved slots
+    Load,
+    Exit,
+}
+
+/// User interface driven by [`crate::cli_player::CliQuestPlayer`].
+pub trait QuestFrontend {
+    /// Drops the input made before the game has started.
+    fn flush_input(&mut self) {}
+
+    fn render(&mut self, screen: &Screen);
+
+    /// Called on every [`FrontendAction::Tick`] before the screen is rendered
+    /// again, for animations and background work.
+    fn tick(&mut self, _screen: &Screen) {}
+
+    /// Waits for the user input, `None` if the screen just needs to be
+    /// rendered again. Frontends which can't wait forever return
+    /// [`FrontendAction::Tick`] when there was no input for a while.
+    fn get_action(&mut self, screen: &Screen) -> Option<FrontendAction>;
+
+    /// Asks for a line of text with the current `value`, `None` if it's
+    /// cancelled or left empty.
+    fn read_text(&mut self, screen: &Screen, prompt: &str, value: &str) -> Option<String>;
+
+    /// Lets the user edit the fields, `false` if the form is cancelled. The
+    /// fields are asked one by one by default.
+    fn read_form(&mut self, screen: &Screen, _title: &str, fields: &mut [FormField]) -> bool {
+        for field in fields {
+            if let Some(value) = self.read_text(screen, &field.label, &field.value) {
+                field.value = value;
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,894 @@
+//! Browser of a quest's locations, jumps and parameters, which can also edit
+//! the quest and save it back.
+
+use std::{
+    env, fs,
+    io::Stdout,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crossterm::event::{self, Event, KeyCode};
+use qmm_syntax::{
+    qmm::{parse_qmm, write_qmm, LocationId, LocationSelectType, Quest, TextSource},
+    text::{formatted_text::FormattedText, formula::Formula},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap,
+    },
+    Frame, Terminal,
+};
+
+use crate::{
+    atomic_write, clipboard,
+    edit::{self, ParameterEdit},
+    frontend::plain_text,
+    markdown,
+    meta::{QuestMeta, QuestObject},
+    tui_frontend::{restore_terminal, take_terminal},
+    widgets::Form,
+};
+
+/// Distance between a new location and the selected one in the editor.
+const NEW_LOCATION_OFFSET: i32 = 30;
+/// Starts the text sections of the file opened in the external editor.
+const SECTION_MARK: &str = "#### ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Locations,
+    Jumps,
+    Parameters,
+}
+
+impl Pane {
+    const ALL: [Pane; 3] = [Pane::Locations, Pane::Jumps, Pane::Parameters];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|pane| *pane == self).unwrap()
+    }
+
+    fn of(object: QuestObject) -> Self {
+        match object {
+            QuestObject::Location(_) => Pane::Locations,
+            QuestObject::Jump(_) => Pane::Jumps,
+            QuestObject::Parameter(_) => Pane::Parameters,
+        }
+    }
+}
+
+/// Whether the letters of the query appear in the text in the same order,
+/// ignoring the case.
+fn fuzzy_match(text: &str, query: &str) -> bool {
+    let mut chars = text.chars().flat_map(char::to_lowercase);
+
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|query| chars.any(|ch| ch == query))
+}
+
+/// Single line of the text for the lists.
+fn summary(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Objects of the panes with their list labels.
+fn entries(quest: &Quest) -> [Vec<(QuestObject, String)>; 3] {
+    let locations = quest
+        .locations
+        .iter()
+        .map(|location| {
+            let text = location
+                .texts
+                .first()
+                .map(|text| summary(&text.to_string()))
+                .unwrap_or_default();
+
+            (
+                QuestObject::Location(location.id),
+                format!("L{} {:?}: {text}", location.id.0, location.ty),
+            )
+        })
+        .collect();
+    let jumps = quest
+        .jumps
+        .iter()
+        .map(|jump| {
+            (
+                QuestObject::Jump(jump.id),
+                format!(
+                    "J{} L{} -> L{}: {}",
+                    jump.id.0,
+                    jump.from.0,
+                    jump.to.0,
+                    summary(&jump.text.to_string())
+                ),
+            )
+        })
+        .collect();
+    let parameters = quest
+        .parameters
+        .iter()
+        .zip(1..)
+        .map(|(parameter, id)| {
+            (
+                QuestObject::Parameter(id),
+                format!("p{id} {}", summary(&parameter.name)),
+            )
+        })
+        .collect();
+
+    [locations, jumps, parameters]
+}
+
+/// Empty formulas are allowed, they remove the jump conditions and the text
+/// selections.
+fn check_formula(text: &str) -> Result<(), String> {
+    let formula = Formula::parse(text).map_err(|err| err.to_string())?;
+
+    if !formula.is_empty() {
+        formula.expression().map_err(|err| err.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Location id typed as `5` or `L5`.
+fn parse_location(text: &str) -> Option<LocationId> {
+    text.trim()
+        .trim_start_matches(['L', 'l'])
+        .parse()
+        .ok()
+        .map(LocationId)
+}
+
+/// Opens the text in `$VISUAL` or `$EDITOR`, returning the saved text.
+fn edit_externally(text: &str) -> Result<String, String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut args = editor.split_whitespace();
+    let program = args.next().ok_or("The editor is empty")?;
+    let path = env::temp_dir().join(format!("qmm-inspect-{}.txt", process::id()));
+
+    fs::write(&path, text).map_err(|err| err.to_string())?;
+
+    let status = process::Command::new(program)
+        .args(args)
+        .arg(&path)
+        .status()
+        .map_err(|err| format!("Can't start `{editor}`: {err}"));
+    let edited = fs::read_to_string(&path).map_err(|err| err.to_string());
+
+    fs::remove_file(&path).ok();
+
+    if !status?.success() {
+        return Err(format!("`{editor}` failed, the texts are unchanged"));
+    }
+
+    edited
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Formula(QuestObject),
+    /// Target of a new jump from the location
+    JumpTarget(LocationId),
+}
+
+/// Single line input drawn over the inspector.
+struct Prompt {
+    kind: PromptKind,
+    value: String,
+}
+
+impl Prompt {
+    fn title(&self) -> String {
+        match self.kind {
+            PromptKind::Formula(QuestObject::Location(id)) => {
+                format!("L{} text selection", id.0)
+            }
+            PromptKind::Formula(QuestObject::Jump(id)) => format!("J{} condition", id.0),
+            PromptKind::Formula(QuestObject::Parameter(id)) => format!("p{id} starting value"),
+            PromptKind::JumpTarget(from) => format!("Jump from L{} to", from.0),
+        }
+    }
+
+    /// Checked while typing, the quest is only changed by valid values.
+    fn validate(&self, quest: &Quest) -> Result<(), String> {
+        match self.kind {
+            PromptKind::Formula(_) => check_formula(&self.value),
+            PromptKind::JumpTarget(_) => {
+                let id = parse_location(&self.value).ok_or("Expected a location id")?;
+
+                match quest.location(id) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("L{} doesn't exist", id.0)),
+                }
+            }
+        }
+    }
+}
+
+/// What the inspector does after a key.
+enum Action {
+    Continue,
+    Quit,
+    /// Open the object's texts in the external editor
+    EditTexts(QuestObject),
+}
+
+/// Terminal inspector, takes over the terminal until dropped.
+pub struct Inspector<'a> {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    view: View<'a>,
+}
+
+struct View<'a> {
+    quest: Quest,
+    meta: &'a QuestMeta,
+    /// Where the edited quest is saved, `None` if it can't be edited
+    path: Option<PathBuf>,
+    /// Whether there are unsaved changes
+    modified: bool,
+    /// Whether the quest was saved before, the first save keeps the
+    /// original as a backup
+    saved: bool,
+    entries: [Vec<(QuestObject, String)>; 3],
+    pane: Pane,
+    /// Selected object of every pane
+    selected: [Option<QuestObject>; 3],
+    search: String,
+    /// Whether the keys are typed into the search
+    searching: bool,
+    prompt: Option<Prompt>,
+    /// Key to press again to delete or to quit with unsaved changes
+    confirm: Option<char>,
+    /// Result of the last action
+    status: String,
+    /// Objects the jumps were followed from
+    history: Vec<QuestObject>,
+    scroll: u16,
+}
+
+impl<'a> Inspector<'a> {
+    /// The quest can be edited and saved to `path` if it's given.
+    pub fn new(quest: Quest, meta: &'a QuestMeta, path: Option<&Path>) -> Self {
+        let entries = entries(&quest);
+        let selected = [0, 1, 2].map(|index| entries[index].first().map(|(object, _)| *object));
+
+        Self {
+            terminal: take_terminal(),
+            view: View {
+                quest,
+                meta,
+                path: path.map(Path::to_path_buf),
+                modified: false,
+                saved: false,
+                entries,
+                pane: Pane::Locations,
+                selected,
+                search: String::new(),
+                searching: false,
+                prompt: None,
+                confirm: None,
+                status: String::new(),
+                history: Vec::new(),
+                scroll: 0,
+            },
+        }
+    }
+
+    /// Shows the quest until quit.
+    pub fn run(&mut self) {
+        loop {
+            let view = &self.view;
+            self.terminal.draw(|frame| view.ui(frame)).unwrap();
+
+            let Event::Key(key) = event::read().unwrap() else {
+                continue;
+            };
+
+            match self.view.handle_key(key.code) {
+                Action::Continue => (),
+                Action::Quit => return,
+                Action::EditTexts(object) => self.edit_texts(object),
+            }
+        }
+    }
+
+    /// Gives the terminal to the external editor with every text of the
+    /// object, each one after a line naming it.
+    fn edit_texts(&mut self, object: QuestObject) {
+        let texts: Vec<_> = self
+            .view
+            .quest
+            .texts()
+            .into_iter()
+            .filter(|text| QuestObject::of_text(text.source) == Some(object))
+            .collect();
+        let mut file = String::new();
+
+        for text in &texts {
+            file.push_str(&format!(
+                "{SECTION_MARK}{}\n{}\n",
+                text.source,
+                text.text.replace("\r\n", "\n")
+            ));
+        }
+
+        restore_terminal();
+        let edited = edit_externally(&file);
+        self.terminal = take_terminal();
+
+        let edited = match edited {
+            Ok(edited) => edited,
+            Err(err) => {
+                self.view.status = err;
+                return;
+            }
+        };
+        let sections: Vec<&str> = edited
+            .split(SECTION_MARK)
+            .skip(1)
+            .map(|section| {
+                let (_, text) = section.split_once('\n').unwrap_or((section, ""));
+
+                text.trim_end_matches('\n')
+            })
+            .collect();
+
+        if sections.len() != texts.len() {
+            self.view.status = format!(
+                "Expected {} texts, found {}, the texts are unchanged",
+                texts.len(),
+                sections.len()
+            );
+            return;
+        }
+
+        let mut changed = 0;
+
+        for (text, edited) in texts.iter().zip(sections) {
+            if edited == text.text.replace("\r\n", "\n") {
+                continue;
+            }
+
+            // The line breaks are written back the way the quest had them
+            let edited = if text.text.contains("\r\n") {
+                edited.replace('\n', "\r\n")
+            } else {
+                edited.to_string()
+            };
+
+            self.view.quest.set_text(text.source, &edited);
+            changed += 1;
+        }
+
+        if changed > 0 {
+            self.view.changed();
+        }
+
+        self.view.status = format!("{changed} texts changed");
+    }
+}
+
+impl View<'_> {
+    fn handle_key(&mut self, code: KeyCode) -> Action {
+        let confirm = self.confirm.take();
+        self.status.clear();
+
+        if self.prompt.is_some() {
+            self.handle_prompt_key(code);
+            return Action::Continue;
+        }
+
+        if self.searching {
+            match code {
+                KeyCode::Char(ch) => self.search.push(ch),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search.clear();
+                }
+                _ => (),
+            }
+
+            self.select_visible();
+            return Action::Continue;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc if !self.search.is_empty() => {
+                self.search.clear();
+                self.select_visible();
+            }
+            KeyCode::Char('q') | KeyCode::Esc if !self.modified || confirm == Some('q') => {
+                return Action::Quit
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.confirm = Some('q');
+                self.status = "Unsaved changes, press q again to quit".to_string();
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Tab => self.switch(Pane::ALL[(self.pane.index() + 1) % 3]),
+            KeyCode::BackTab => self.switch(Pane::ALL[(self.pane.index() + 2) % 3]),
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::Enter => self.follow(None),
+            KeyCode::Char(ch @ '1'..='9') => self.follow(ch.to_digit(10)),
+            KeyCode::Backspace => {
+                if let Some(object) = self.history.pop() {
+                    self.open(object);
+                }
+            }
+            KeyCode::Char('c') => self.copy(),
+            KeyCode::Char(ch) if self.path.is_some() => return self.handle_edit_key(ch, confirm),
+            _ => (),
+        }
+
+        Action::Continue
+    }
+
+    fn handle_edit_key(&mut self, key: char, confirm: Option<char>) -> Action {
+        let selected = self.selected();
+
+        match (key, selected) {
+            ('a', _) => {
+                let (x, y) = match selected {
+                    Some(QuestObject::Location(id)) => self
+                        .quest
+                        .location(id)
+                        .map(|location| (location.x + NEW_LOCATION_OFFSET, location.y))
+                        .unwrap_or_default(),
+                    _ => (0, 0),
+                };
+                let id = self.quest.add_location(x, y);
+
+                self.changed();
+                self.open(QuestObject::Location(id));
+                self.status = format!("L{} added", id.0);
+            }
+            ('j', Some(QuestObject::Location(from))) => {
+                self.prompt = Some(Prompt {
+                    kind: PromptKind::JumpTarget(from),
+                    value: String::new(),
+                });
+            }
+            ('d', Some(object)) if confirm == Some('d') => {
+                let removed = match object {
+                    QuestObject::Location(id) => self.quest.remove_location(id).is_some(),
+                    QuestObject::Jump(id) => self.quest.remove_jump(id).is_some(),
+                    QuestObject::Parameter(_) => false,
+                };
+
+                if removed {
+                    self.history.retain(|visited| *visited != object);
+                    self.changed();
+                    self.status = format!("{object} deleted");
+                }
+            }
+            ('d', Some(object @ (QuestObject::Location(_) | QuestObject::Jump(_)))) => {
+                self.confirm = Some('d');
+                self.status = format!("Press d again to delete {object}");
+            }
+            ('e', Some(object)) => return Action::EditTexts(object),
+            ('f', Some(object)) => {
+                let quest = &self.quest;
+                let value = match object {
+                    QuestObject::Location(id) => {
+                        match quest.location(id).map(|location| &location.select_type) {
+                            Some(LocationSelectType::ByFormula(formula)) => formula.to_string(),
+                            _ => String::new(),
+                        }
+                    }
+                    QuestObject::Jump(id) => quest
+                        .jump(id)
+                        .map(|jump| jump.formula.to_string())
+                        .unwrap_or_default(),
+                    QuestObject::Parameter(id) => quest
+                        .parameters
+                        .get(id as usize - 1)
+                        .map(|parameter| parameter.starting_value.clone())
+                        .unwrap_or_default(),
+                };
+
+                self.prompt = Some(Prompt {
+                    kind: PromptKind::Formula(object),
+                    value,
+                });
+            }
+            ('s', _) => self.save(),
+            _ => (),
+        }
+
+        Action::Continue
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+
+        match code {
+            KeyCode::Char(ch) => prompt.value.push(ch),
+            KeyCode::Backspace => {
+                prompt.value.pop();
+            }
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter if prompt.validate(&self.quest).is_ok() => {
+                let prompt = self.prompt.take().unwrap();
+
+                if let Err(err) = self.apply(prompt) {
+                    self.status = err;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn apply(&mut self, prompt: Prompt) -> Result<(), String> {
+        let value = prompt.value.trim();
+
+        match prompt.kind {
+            PromptKind::JumpTarget(from) => {
+                let to = parse_location(value).ok_or("Expected a location id")?;
+                let id = self
+                    .quest
+                    .add_jump(from, to)
+                    .ok_or("The locations don't exist")?;
+
+                self.history.push(QuestObject::Location(from));
+                self.changed();
+                self.open(QuestObject::Jump(id));
+                self.status = format!("J{} added", id.0);
+            }
+            PromptKind::Formula(QuestObject::Location(id)) => {
+                let location = self
+                    .quest
+                    .locations
+                    .iter_mut()
+                    .find(|location| location.id == id)
+                    .ok_or("The location doesn't exist")?;
+
+                location.select_type = match Formula::parse(value) {
+                    Ok(formula) if !formula.is_empty() => LocationSelectType::ByFormula(formula),
+                    _ => LocationSelectType::ByOrder,
+                };
+                self.changed();
+            }
+            PromptKind::Formula(QuestObject::Jump(id)) => {
+                self.quest
+                    .jumps
+                    .iter_mut()
+                    .find(|jump| jump.id == id)
+                    .ok_or("The jump doesn't exist")?
+                    .set_formula(value)
+                    .map_err(|err| err.to_string())?;
+                self.changed();
+            }
+            PromptKind::Formula(QuestObject::Parameter(id)) => {
+                let edit = ParameterEdit {
+                    starting: Some(value.to_string()),
+                    ..ParameterEdit::default()
+                };
+
+                edit::edit_parameter(&mut self.quest, id, edit)?;
+                self.changed();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the texts of the selected location, the text of the jump or
+    /// the name of the parameter without the tags.
+    fn copy(&mut self) {
+        let Some(object) = self.selected() else {
+            return;
+        };
+        let texts: Vec<_> = self
+            .quest
+            .texts()
+            .into_iter()
+            .filter(|text| match (text.source, object) {
+                (TextSource::LocationText { location, .. }, QuestObject::Location(id)) => {
+                    location == id
+                }
+                (TextSource::JumpText(jump), QuestObject::Jump(id)) => jump == id,
+                (TextSource::ParameterName(parameter), QuestObject::Parameter(id)) => {
+                    parameter == id
+                }
+                _ => false,
+            })
+            .map(|text| plain_text(&FormattedText::parse(&text.text)))
+            .collect();
+
+        self.status = match clipboard::copy(&texts.join("\n\n")) {
+            Ok(()) => format!("{object} text copied"),
+            Err(err) => err,
+        };
+    }
+
+    /// Writes the quest back, unless it can't be read again.
+    fn save(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let data = write_qmm(&self.quest);
+
+        if let Err(err) = parse_qmm(&data) {
+            self.status = format!("The edited quest can't be read back: {err}");
+            return;
+        }
+
+        self.status = match atomic_write::write(path, &data, !self.saved) {
+            Ok(()) => {
+                self.modified = false;
+                self.saved = true;
+                format!("Saved to {}", path.display())
+            }
+            Err(err) => format!("Can't save: {err}"),
+        };
+    }
+
+    /// Updates the lists after the quest was edited.
+    fn changed(&mut self) {
+        self.modified = true;
+        self.entries = entries(&self.quest);
+
+        for (selected, entries) in self.selected.iter_mut().zip(&self.entries) {
+            if !entries.iter().any(|(object, _)| Some(*object) == *selected) {
+                *selected = entries.first().map(|(object, _)| *object);
+            }
+        }
+    }
+
+    /// Objects of the current pane matching the search.
+    fn visible(&self) -> Vec<&(QuestObject, String)> {
+        self.entries[self.pane.index()]
+            .iter()
+            .filter(|(_, label)| fuzzy_match(label, &self.search))
+            .collect()
+    }
+
+    fn selected(&self) -> Option<QuestObject> {
+        self.selected[self.pane.index()]
+    }
+
+    fn select(&mut self, object: Option<QuestObject>) {
+        if object != self.selected() {
+            self.scroll = 0;
+        }
+
+        self.selected[self.pane.index()] = object;
+    }
+
+    /// Keeps the selection among the search results.
+    fn select_visible(&mut self) {
+        let visible = self.visible();
+
+        if visible
+            .iter()
+            .any(|(object, _)| Some(*object) == self.selected())
+        {
+            return;
+        }
+
+        let first = visible.first().map(|(object, _)| *object);
+        self.select(first);
+    }
+
+    fn switch(&mut self, pane: Pane) {
+        self.pane = pane;
+        self.scroll = 0;
+        self.select_visible();
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let visible = self.visible();
+
+        if visible.is_empty() {
+            return;
+        }
+
+        let current = visible
+            .iter()
+            .position(|(object, _)| Some(*object) == self.selected())
+            .unwrap_or(0);
+        let index = current.saturating_add_signed(offset).min(visible.len() - 1);
+        let object = visible[index].0;
+
+        self.select(Some(object));
+    }
+
+    /// Selects the object in its pane, clearing the search that may hide it.
+    fn open(&mut self, object: QuestObject) {
+        self.search.clear();
+        self.pane = Pane::of(object);
+        self.select(Some(object));
+    }
+
+    /// Opens the target of the selected jump, or the `nth` jump of the
+    /// selected location.
+    fn follow(&mut self, nth: Option<u32>) {
+        let Some(current) = self.selected() else {
+            return;
+        };
+        let target = match (current, nth) {
+            (QuestObject::Jump(id), None) => self
+                .quest
+                .jump(id)
+                .map(|jump| QuestObject::Location(jump.to)),
+            (QuestObject::Location(id), Some(nth)) => self
+                .quest
+                .jumps_from(id)
+                .nth(nth as usize - 1)
+                .map(|jump| QuestObject::Jump(jump.id)),
+            _ => None,
+        };
+
+        if let Some(target) = target {
+            self.history.push(current);
+            self.open(target);
+        }
+    }
+
+    fn details(&self, object: QuestObject) -> String {
+        let quest = &self.quest;
+        let mut md = match object {
+            QuestObject::Location(id) => quest
+                .location(id)
+                .map(|location| markdown::location(quest, location)),
+            QuestObject::Jump(id) => quest.jump(id).map(|jump| markdown::jump(quest, jump)),
+            QuestObject::Parameter(id) => quest
+                .parameters
+                .get(id as usize - 1)
+                .map(|parameter| markdown::parameter(id, parameter)),
+        }
+        .unwrap_or_default();
+
+        if let Some(notes) = self.meta.notes.get(&object) {
+            md.push_str(&markdown::notes(notes));
+        }
+
+        md
+    }
+
+    fn help(&self) -> &'static str {
+        if self.searching {
+            "Type to search, Enter - keep, Esc - clear"
+        } else if self.path.is_some() {
+            "Tab - pane, / - search, Enter/1-9 - follow, Backspace - back, c - copy, \
+             a - add location, j - add jump, d - delete, e - edit texts, f - edit formula, s - save, q - quit"
+        } else {
+            "Tab - pane, / - search, Enter - jump target, 1-9 - location's jump, \
+             Backspace - back, PgUp/PgDn - scroll, c - copy text, q - quit"
+        }
+    }
+
+    fn ui(&self, frame: &mut Frame<CrosstermBackend<Stdout>>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[1]);
+        let block = |title: String| {
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(title)
+        };
+        let highlight = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+
+        let titles = ["Locations", "Jumps", "Parameters"]
+            .into_iter()
+            .map(Spans::from)
+            .collect();
+        let title = match &self.path {
+            None => "Quest".to_string(),
+            Some(path) if self.modified => format!("{} *", path.display()),
+            Some(path) => path.display().to_string(),
+        };
+        let tabs = Tabs::new(titles)
+            .block(block(title))
+            .select(self.pane.index())
+            .highlight_style(highlight);
+        frame.render_widget(tabs, rows[0]);
+
+        let visible = self.visible();
+        let mut state = ListState::default();
+        state.select(
+            visible
+                .iter()
+                .position(|(object, _)| Some(*object) == self.selected()),
+        );
+        let items: Vec<_> = visible
+            .iter()
+            .map(|(_, label)| ListItem::new(label.as_str()))
+            .collect();
+        let title = if self.search.is_empty() && !self.searching {
+            format!(
+                "{} of {}",
+                visible.len(),
+                self.entries[self.pane.index()].len()
+            )
+        } else {
+            format!("/{}", self.search)
+        };
+        let list = List::new(items)
+            .block(block(title))
+            .highlight_style(highlight)
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, columns[0], &mut state);
+
+        let details = self
+            .selected()
+            .map(|object| self.details(object))
+            .unwrap_or_default();
+        let paragraph = Paragraph::new(details)
+            .block(block("Details".to_string()))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(paragraph, columns[1]);
+
+        let (footer, color) = if self.status.is_empty() {
+            (self.help(), Color::DarkGray)
+        } else {
+            (self.status.as_str(), Color::Yellow)
+        };
+        frame.render_widget(
+            Paragraph::new(footer).style(Style::default().fg(color)),
+            rows[2],
+        );
+
+        // The formula is checked on every key, so the typos are seen before
+        // they're applied
+        if let Some(prompt) = &self.prompt {
+            let check = match prompt.validate(&self.quest) {
+                Ok(()) => Span::styled("ok", Style::default().fg(Color::Green)),
+                Err(err) => Span::styled(err, Style::default().fg(Color::Red)),
+            };
+            let lines = vec![
+                Spans::from(format!("{}_", prompt.value)),
+                Spans::from(check),
+            ];
+            let area = Form::area(frame.size(), lines.len());
+
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines).block(block(format!(
+                    "{} (Enter - apply, ESC - cancel)",
+                    prompt.title()
+                ))),
+                area,
+            );
+        }
+    }
+}
+
+impl Drop for Inspector<'_> {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
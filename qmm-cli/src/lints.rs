@@ -0,0 +1,216 @@
+//! Diagnostics of the `lint` command with configurable levels, so the packs
+//! with known-quirky but playable quests can still pass the checks.
+
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use clap::ValueEnum;
+use qmm_analysis::{AuditFinding, WordLists};
+use qmm_syntax::qmm::Quest;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    formulas::{self, FormulaSource},
+    meta::{QuestMeta, QuestObject},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Lint {
+    /// A jump's conditions can't be met
+    DeadJump,
+    /// None of a location's jumps can be taken
+    SoftLock,
+    /// A comparison of a comparison, like `[p1] = [p2] = 1`
+    ChainedComparison,
+    /// A change or a starting value that is a comparison
+    ComparisonValue,
+    /// A text has a word of the content word lists
+    Content,
+}
+
+impl Lint {
+    pub fn name(self) -> String {
+        self.to_possible_value().unwrap().get_name().to_string()
+    }
+
+    fn default_level(self) -> Level {
+        match self {
+            Lint::SoftLock => Level::Deny,
+            Lint::DeadJump | Lint::ChainedComparison | Lint::ComparisonValue | Lint::Content => {
+                Level::Warn
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Allow,
+    Warn,
+    /// The lint fails the check
+    Deny,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// Problem found by [`lint`].
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub level: Level,
+    /// Key of the suppressions, `None` for the texts of the whole quest
+    pub object: Option<QuestObject>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            Level::Allow => "allowed",
+            Level::Warn => "warning",
+            Level::Deny => "error",
+        };
+
+        match self.object {
+            Some(object) => write!(f, "{object}: ")?,
+            None => f.write_str("quest: ")?,
+        }
+
+        write!(f, "{level}[{}]: {}", self.lint.name(), self.message)
+    }
+}
+
+/// Levels of the lints: the defaults overridden by the quest's metadata and
+/// then by the command line.
+pub struct LintLevels {
+    levels: BTreeMap<Lint, Level>,
+    /// Lints allowed for single objects
+    allowed: BTreeMap<QuestObject, Vec<Lint>>,
+}
+
+impl LintLevels {
+    /// Levels of the metadata, returning the unknown lint names too.
+    pub fn new(meta: &QuestMeta) -> (Self, Vec<String>) {
+        let mut unknown = Vec::new();
+        let mut parse = |name: &String| {
+            let lint = Lint::from_str(name, false).ok();
+
+            if lint.is_none() {
+                unknown.push(name.clone());
+            }
+
+            lint
+        };
+
+        let levels = meta
+            .lints
+            .iter()
+            .filter_map(|(name, level)| Some((parse(name)?, *level)))
+            .collect();
+        let allowed = meta
+            .allow
+            .iter()
+            .map(|(object, names)| (*object, names.iter().filter_map(&mut parse).collect()))
+            .collect();
+
+        (Self { levels, allowed }, unknown)
+    }
+
+    pub fn set(&mut self, lints: &[Lint], level: Level) {
+        for lint in lints {
+            self.levels.insert(*lint, level);
+        }
+    }
+
+    fn level(&self, lint: Lint, object: Option<QuestObject>) -> Level {
+        if object
+            .and_then(|object| self.allowed.get(&object))
+            .is_some_and(|lints| lints.contains(&lint))
+        {
+            return Level::Allow;
+        }
+
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+}
+
+fn object(source: FormulaSource) -> QuestObject {
+    match source {
+        FormulaSource::StartingValue(parameter) => QuestObject::Parameter(parameter),
+        FormulaSource::LocationChange { location, .. }
+        | FormulaSource::LocationSelection(location) => QuestObject::Location(location),
+        FormulaSource::JumpCondition(jump) | FormulaSource::JumpChange { jump, .. } => {
+            QuestObject::Jump(jump)
+        }
+    }
+}
+
+/// Word lists of a TOML file with an array of words for every category, e.g.
+/// `profanity = ["damn*", "hell"]`, see [`WordLists::add`].
+pub fn load_word_lists(path: &Path) -> Result<WordLists, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let categories: BTreeMap<String, Vec<String>> =
+        toml::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+    let mut lists = WordLists::default();
+
+    for (category, entries) in &categories {
+        for entry in entries {
+            lists.add(category, entry);
+        }
+    }
+
+    Ok(lists)
+}
+
+/// Audit findings, formula lints and content flags of the quest, the
+/// allowed ones are left out.
+pub fn lint(quest: &Quest, levels: &LintLevels, words: &WordLists) -> Vec<Diagnostic> {
+    let findings = qmm_analysis::audit(quest)
+        .into_iter()
+        .map(|finding| match &finding {
+            AuditFinding::DeadJump { jump, .. } => (
+                Lint::DeadJump,
+                Some(QuestObject::Jump(*jump)),
+                finding.to_string(),
+            ),
+            AuditFinding::SoftLock { location } => (
+                Lint::SoftLock,
+                Some(QuestObject::Location(*location)),
+                finding.to_string(),
+            ),
+        });
+    let formula_lints = formulas::lint_formulas(quest).into_iter().map(|lint| {
+        (
+            lint.lint,
+            Some(object(lint.source)),
+            format!("{}: {}", lint.source, lint.message),
+        )
+    });
+    let content_flags = qmm_analysis::content_flags(quest, words)
+        .into_iter()
+        .map(|flag| {
+            (
+                Lint::Content,
+                QuestObject::of_text(flag.source),
+                flag.to_string(),
+            )
+        });
+
+    findings
+        .chain(formula_lints)
+        .chain(content_flags)
+        .map(|(lint, object, message)| Diagnostic {
+            level: levels.level(lint, object),
+            lint,
+            object,
+            message,
+        })
+        .filter(|diagnostic| diagnostic.level != Level::Allow)
+        .collect()
+}
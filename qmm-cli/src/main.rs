@@ -1,63 +1,1934 @@
+mod atomic_write;
+mod autosave;
 mod cli_player;
+mod clipboard;
+mod demo;
+mod dump;
+mod edit;
+mod formula_repl;
+mod formulas;
+mod frontend;
+mod inspector;
+mod lints;
+mod manifest;
+mod markdown;
+mod meta;
+mod plain_frontend;
+mod quest_data;
+mod quest_patch;
+mod saves;
+mod script;
+mod speech;
+mod tui_frontend;
+mod watch;
+mod widgets;
 
 use std::{
-    fs::File,
-    io::{Read, Write},
+    borrow::Cow,
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 
-use clap::{Parser, Subcommand};
-use qmm_player::QuestPlayer;
-use qmm_syntax::qmm::{parse_qmm, Quest};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use qmm_analysis::{DuplicateOptions, QuestFeature, WordLists};
+use qmm_player::{PlayerContext, QuestDate, QuestError, QuestPlayer, TextCoverage};
+use qmm_syntax::qmm::{
+    default_codecs, describe_format, pack, parse_qmm, parse_qmm_located, patch_qmm,
+    unwrap_container, write_qmm, Compression, JumpId, LocationId, LocationType, Quest,
+    QuestSection, SectionFormat, TextSource,
+};
+use qmm_syntax::text::formatted_text::{TextElement, TextElementKind};
+use regex::Regex;
+use typed_arena::Arena;
 
-use crate::cli_player::CliQuestPlayer;
+use crate::{
+    autosave::Autosave,
+    cli_player::CliQuestPlayer,
+    dump::{DumpFormat, DumpSection},
+    edit::{LineChange, ParameterEdit},
+    inspector::Inspector,
+    lints::{Level, Lint, LintLevels},
+    meta::{QuestMeta, QuestObject},
+    plain_frontend::PlainFrontend,
+    quest_data::QuestData,
+    quest_patch::QuestPatch,
+    saves::{fingerprint, SaveSlots},
+    script::Script,
+    speech::Speech,
+    tui_frontend::TuiFrontend,
+    watch::QuestWatcher,
+};
 
 #[derive(Debug, Clone, Parser)]
 struct Cli {
-    /// Path to a quest file (.qmm)
-    pub quest: PathBuf,
-
     #[command(subcommand)]
     pub command: Command,
+    /// How to print the errors
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Human)]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    Human,
+    /// One JSON object per error, for editors and CI
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PackCompression {
+    Gzip,
+    Zstd,
+}
+
+impl From<PackCompression> for Compression {
+    fn from(value: PackCompression) -> Self {
+        match value {
+            PackCompression::Gzip => Compression::Gzip,
+            PackCompression::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz
+    Dot,
+    /// Gephi
+    Gexf,
+    Graphml,
+    /// Mermaid flowchart for Markdown documents
+    Mermaid,
+}
+
+impl From<GraphFormat> for qmm_analysis::GraphFormat {
+    fn from(value: GraphFormat) -> Self {
+        match value {
+            GraphFormat::Dot => qmm_analysis::GraphFormat::Dot,
+            GraphFormat::Gexf => qmm_analysis::GraphFormat::Gexf,
+            GraphFormat::Graphml => qmm_analysis::GraphFormat::Graphml,
+            GraphFormat::Mermaid => qmm_analysis::GraphFormat::Mermaid,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Subcommand)]
 enum Command {
     Dump {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
         /// Dump path
         path: PathBuf,
+        #[arg(long, value_enum, default_value_t = DumpFormat::Debug)]
+        format: DumpFormat,
+        /// Dump only a part of the quest
+        #[arg(long, value_enum)]
+        section: Option<DumpSection>,
+    },
+    Play {
+        /// Path to a quest file (.qmm)
+        #[arg(required_unless_present = "demo")]
+        quest: Option<PathBuf>,
+        /// Play the demo quest built into the program
+        #[arg(long, conflicts_with = "quest")]
+        demo: bool,
+        /// Print texts and read choices line by line instead of using the TUI
+        #[arg(long)]
+        plain: bool,
+        /// Days given to complete the quest, shown in the status bar
+        #[arg(long)]
+        deadline: Option<u32>,
+        /// Save slot to continue, the game is saved into it on exit
+        #[arg(long)]
+        save: Option<String>,
+        /// Text-to-speech command reading the texts and the choices aloud,
+        /// `{}` is replaced with the text, e.g. `espeak-ng {}`, otherwise the
+        /// text is written to its input
+        #[arg(long)]
+        speak: Option<String>,
+        /// Continue the game in the edited quest whenever the file changes
+        #[arg(long, conflicts_with = "demo")]
+        watch: bool,
+    },
+    /// Print word counts of every quest text and of the languages the texts
+    /// are in, with the texts in another language than most of the quest
+    Words {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+    },
+    /// Print the clusters of the same or almost the same location and jump
+    /// texts
+    Duplicates {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Similarity from 0 to 1 of the clustered texts, 1 only clusters
+        /// the texts that differ in case, spaces and punctuation
+        #[arg(long, default_value_t = DuplicateOptions::default().threshold)]
+        threshold: f64,
+        /// Skip the texts shorter than this many characters
+        #[arg(long, default_value_t = DuplicateOptions::default().min_length)]
+        min_length: usize,
+    },
+    /// Print texts that are identical in the quest and its translation
+    Progress {
+        /// Path to the original quest file (.qmm)
+        quest: PathBuf,
+        /// Path to the translated quest file (.qmm)
+        translated: PathBuf,
+    },
+    /// Evaluate formulas step by step
+    Formula,
+    /// Compress a quest into a bundle which is opened like a plain quest
+    Pack {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Bundle path
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = PackCompression::Gzip)]
+        compression: PackCompression,
+    },
+    /// Write the hashes of the files in a quest directory
+    PackManifest {
+        /// Quest directory
+        dir: PathBuf,
+        /// Manifest path, `manifest.sha256` in the directory by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Check the files in a quest directory against its manifest
+    Verify {
+        /// Quest directory
+        dir: PathBuf,
+        /// Manifest path, `manifest.sha256` in the directory by default
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+    },
+    /// Print a location or a jump in Markdown
+    #[command(group(ArgGroup::new("object").required(true).args(["location", "jump"])))]
+    Show {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Location id
+        #[arg(long)]
+        location: Option<u32>,
+        /// Jump id
+        #[arg(long)]
+        jump: Option<u32>,
+    },
+    /// Write the graph of the locations and the jumps
+    Graph {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Graph path
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+        /// Only the locations around this one
+        #[arg(long)]
+        around: Option<u32>,
+        /// Jumps from the `--around` location to include
+        #[arg(long, default_value_t = 2, requires = "around")]
+        depth: usize,
+        /// Label the locations with the fewest jumps from the start
+        #[arg(long)]
+        distances: bool,
+        /// Take the jump conditions into account in the distances
+        #[arg(long, requires = "distances")]
+        conditions: bool,
+    },
+    /// Print the fewest jumps from the start to every ending
+    Paths {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Play the quest to take the jump conditions into account
+        #[arg(long)]
+        conditions: bool,
+    },
+    /// Print every text in the order of a playthrough as Markdown, with the
+    /// choices, for proofreading
+    ReadingOrder {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Follow the jumps ignoring their conditions instead of playing the
+        /// quest, faster for the large ones
+        #[arg(long)]
+        no_conditions: bool,
+    },
+    /// Play the quest with the actions of a walkthrough script
+    Run {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Path to a script with one action per line
+        script: PathBuf,
+        /// Write the parameter values after every step, as JSON if the path
+        /// ends with `.json` and as CSV otherwise
+        #[arg(long)]
+        trace_params: Option<PathBuf>,
+    },
+    /// Play a walkthrough script twice with the same seed and report the
+    /// first step where the states differ
+    Determinism {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Path to a script with one action per line
+        script: PathBuf,
+    },
+    /// Report the location texts and the text formula values the
+    /// walkthrough scripts never show
+    Coverage {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Paths to the scripts, their coverage is merged
+        #[arg(required = true)]
+        scripts: Vec<PathBuf>,
+    },
+    /// Change the quest without opening an editor
+    Edit {
+        #[command(subcommand)]
+        command: EditCommand,
+    },
+    /// Replace the matches of a regular expression in every quest text
+    Replace {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        #[arg(long)]
+        regex: String,
+        /// Replacement, `$1` and `${name}` insert the groups
+        #[arg(long = "with")]
+        replacement: String,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Repair the texts garbled by a conversion in the wrong encoding, like
+    /// `Ïðèâåò` for `Привет`
+    FixEncoding {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Write the Cyrillic letters of the texts in Latin ones, like `Shchit`
+    /// for `Щит`
+    Transliterate {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Rename the images, sounds and tracks the quest refers to, e.g. after
+    /// reorganizing an asset pack
+    #[command(group(ArgGroup::new("names").required(true).multiple(true).args(["map", "rename"])))]
+    RemapMedia {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// TOML file of `"old name" = "new name"` pairs
+        #[arg(long)]
+        map: Option<PathBuf>,
+        /// Rename a single file, `old=new`
+        #[arg(long, value_parser = parse_rename)]
+        rename: Vec<(String, String)>,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Write the changes between two versions of a quest as a JSON patch
+    MakePatch {
+        /// Path to the original quest file (.qmm)
+        original: PathBuf,
+        /// Path to the edited quest file (.qmm)
+        edited: PathBuf,
+        /// Write the patch to this path instead of printing it
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Apply a JSON patch made by `make-patch`, nothing is written if any of
+    /// its changes doesn't fit the quest
+    ApplyPatch {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Path to the patch
+        #[arg(value_name = "PATCH")]
+        patch_file: PathBuf,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Rewrite the formulas in one style and report the suspicious ones
+    FmtFormulas {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        #[command(flatten)]
+        output: EditOutput,
+    },
+    /// Read or change the quest details kept next to the quest file
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommand,
+    },
+    /// Report jumps that can never be taken and texts in another language
+    /// than most of the quest
+    Audit {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Check the jumps the interval analysis can't rule out with the
+        /// `z3` executable
+        #[cfg(feature = "z3")]
+        #[arg(long)]
+        smt: bool,
+    },
+    /// Report the problems of the quest with their levels, exiting with an
+    /// error if any of them is denied
+    ///
+    /// The levels and the lints allowed for single objects are read from the
+    /// `lints` and `allow` tables of the quest's metadata, the flags override
+    /// them.
+    Lint {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// May be repeated
+        #[arg(long, short = 'A', value_enum)]
+        allow: Vec<Lint>,
+        #[arg(long, short = 'W', value_enum)]
+        warn: Vec<Lint>,
+        #[arg(long, short = 'D', value_enum)]
+        deny: Vec<Lint>,
+        /// TOML file with the word lists of the `content` lint, an array of
+        /// words or phrases for every category, e.g. `profanity = ["damn*"]`
+        #[arg(long, value_name = "FILE")]
+        words: Option<PathBuf>,
+    },
+    /// Lint the quest, or every quest in the directory, again on every change
+    ///
+    /// The metadata sidecars are watched too.
+    Watch {
+        /// Quest file (.qmm) or a directory of them
+        path: PathBuf,
+        /// Lints to allow, like the `lint` flags
+        #[arg(long, short = 'A', value_enum)]
+        allow: Vec<Lint>,
+        #[arg(long, short = 'W', value_enum)]
+        warn: Vec<Lint>,
+        #[arg(long, short = 'D', value_enum)]
+        deny: Vec<Lint>,
+        /// Word lists of the `content` lint, like for `lint`
+        #[arg(long, value_name = "FILE")]
+        words: Option<PathBuf>,
+    },
+    /// Browse the locations, the jumps and the parameters in the terminal
+    Inspect {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Allow adding, deleting and changing the objects and saving the
+        /// quest back, the first save keeps the original as `<quest>.bak`
+        #[arg(long)]
+        edit: bool,
+    },
+    /// Count the quests of a directory using each feature of the format and
+    /// the engine
+    Features {
+        /// Directory searched for quests (.qmm), subdirectories included
+        dir: PathBuf,
+    },
+    /// Print the layout of the quest files as Markdown tables, recorded by
+    /// parsing a quest. The fields of the lists empty in it are left out
+    DescribeFormat {
+        /// Path to a quest file (.qmm), the demo quest by default
+        quest: Option<PathBuf>,
     },
-    Play,
 }
 
-fn dump(quest: Quest, path: &Path) {
-    let mut f = File::create(path).unwrap();
-    f.write_all(format!("{quest:#?}").as_bytes()).unwrap();
+#[derive(Debug, Clone, Subcommand)]
+enum EditCommand {
+    /// Change a parameter's bounds, starting value or name
+    Param {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Parameter number, 1 for `[p1]`
+        id: u32,
+        #[arg(long, allow_hyphen_values = true)]
+        min: Option<i32>,
+        #[arg(long, allow_hyphen_values = true)]
+        max: Option<i32>,
+        /// Starting value formula, e.g. `[10]`
+        #[arg(long)]
+        starting: Option<String>,
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        output: EditOutput,
+    },
 }
 
-fn play(quest: Quest) {
-    let quest_player = QuestPlayer::new(&quest, 1).unwrap();
-    let cli_player = CliQuestPlayer::new(quest_player);
-    cli_player.run();
+/// Where the edited quest goes.
+#[derive(Debug, Clone, clap::Args)]
+struct EditOutput {
+    /// Write the quest to this path instead of replacing it, the bundles are
+    /// written unpacked
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Print the changes without writing them
+    #[arg(long)]
+    dry_run: bool,
+    /// Keep the replaced file as `<quest>.bak`
+    #[arg(long)]
+    backup: bool,
+    /// Only rewrite the changed objects, the rest of the file is copied
+    /// byte for byte
+    #[arg(long)]
+    patch: bool,
 }
 
-fn main() {
-    let args = Cli::parse();
+#[derive(Debug, Clone, Subcommand)]
+enum MetaCommand {
+    /// Print the quest details
+    Show {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+    },
+    /// Change the given details, the others are kept
+    Set {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Name shown instead of the file name
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        author: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        /// Language code, e.g. `ru`
+        #[arg(long)]
+        language: Option<String>,
+        /// Replaces the tags, may be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Stamp a new version of the quest, the changes go to the changelog
+    Release {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        /// Version given by the author, e.g. `1.2`
+        version: String,
+        /// What changed since the previous version
+        #[arg(long)]
+        changes: Option<String>,
+    },
+    /// Attach a note to a location (`L5`), a jump (`J12`) or a parameter
+    /// (`p3`), the notes are shown by `show`
+    Note {
+        /// Path to a quest file (.qmm)
+        quest: PathBuf,
+        object: QuestObject,
+        #[arg(required_unless_present = "clear")]
+        text: Option<String>,
+        /// Remove the object's notes
+        #[arg(long, conflicts_with = "text")]
+        clear: bool,
+    },
+}
+
+fn dump(
+    quest: Quest,
+    path: &Path,
+    format: DumpFormat,
+    section: Option<DumpSection>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let data = dump::dump(&quest, format, section).map_err(|err| {
+        fail(
+            error_format,
+            None,
+            "Serialize",
+            &format!("Can't serialize the quest: {err}"),
+        )
+    })?;
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(data.as_bytes()))
+        .map_err(|err| fail(error_format, Some(path), "Io", &err.to_string()))
+}
+
+/// How the game is shown and saved.
+struct PlayOptions {
+    plain: bool,
+    deadline: Option<u32>,
+    /// Save slot to continue and to save into on exit
+    slot: Option<String>,
+    speak: Option<String>,
+    /// Quest file to reload when it changes
+    hot_reload: Option<PathBuf>,
+}
+
+fn play(
+    quest: Quest,
+    name: String,
+    fingerprint: &str,
+    options: PlayOptions,
+    path: &Path,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let PlayOptions {
+        plain,
+        deadline,
+        slot,
+        speak,
+        hot_reload,
+    } = options;
+    // Edited quests of the hot reload, dropped with the game
+    let reloaded = Arena::new();
+    let context = PlayerContext {
+        start_date: Some(QuestDate::new(3300, 3, 15)),
+        deadline,
+        ..Default::default()
+    };
+    let builder = QuestPlayer::builder(&quest).seed(1).context(context);
+    let autosave = Autosave::new(fingerprint);
+    // Saves of an edited quest may not fit it anymore
+    let resumed = autosave
+        .load()
+        .and_then(|snapshot| builder.clone().snapshot(snapshot).build().ok());
+    let mut cli_player = CliQuestPlayer::new(builder, name)
+        .map_err(|err| {
+            let message = match err {
+                QuestError::NoStartingLocation => "The quest has no starting location",
+                QuestError::InvalidSnapshot => "The save doesn't fit the quest",
+            };
+
+            fail(error_format, Some(path), &format!("{err:?}"), message)
+        })?
+        .autosave(autosave, resumed)
+        .save_slots(SaveSlots::new(fingerprint), slot);
+
+    if let Some(speech) = speak.as_deref().and_then(Speech::new) {
+        cli_player = cli_player.speech(speech);
+    }
+
+    if let Some(path) = hot_reload {
+        match QuestWatcher::new(&path) {
+            Ok(watcher) => cli_player = cli_player.hot_reload(path, watcher, &reloaded),
+            Err(err) => eprintln!("Can't watch {}: {err}", path.display()),
+        }
+    }
+
+    if plain {
+        cli_player.run(&mut PlainFrontend::new());
+    } else {
+        cli_player.run(&mut TuiFrontend::new());
+    }
+
+    Ok(())
+}
+
+fn words(quest: Quest) {
+    let texts = quest.texts();
+    let mut total = 0;
+
+    for text in &texts {
+        let count = text.word_count();
+        total += count;
+
+        println!("{}: {count}", text.source);
+    }
+
+    println!("Total: {total} words in {} texts", texts.len());
+
+    let languages = qmm_analysis::languages(&quest);
+
+    for (language, words) in &languages.words {
+        println!("{language} ({}): {words} words", language.code());
+    }
+
+    for (source, language) in &languages.foreign {
+        println!("{source}: in {language}");
+    }
+}
+
+fn duplicates(quest: Quest, options: DuplicateOptions) {
+    let clusters = qmm_analysis::duplicate_texts(&quest, options);
+    let mut words = 0;
+
+    for cluster in &clusters {
+        let kind = if cluster.identical {
+            "identical"
+        } else {
+            "similar"
+        };
+
+        println!("{} {kind} texts:", cluster.texts.len());
+
+        for text in &cluster.texts {
+            let line = text.text.lines().find(|line| !line.trim().is_empty());
+            let preview: String = line.unwrap_or_default().chars().take(60).collect();
+
+            println!("  {}: {preview}", text.source);
+        }
+
+        // The first one has to be translated anyway
+        words += cluster.texts[1..]
+            .iter()
+            .map(|text| text.word_count())
+            .sum::<usize>();
+    }
+
+    println!(
+        "{} clusters, {words} words repeated in the other texts of the clusters",
+        clusters.len()
+    );
+}
+
+fn progress(original: Quest, translated: Quest) {
+    let translated: BTreeMap<_, _> = translated
+        .texts()
+        .into_iter()
+        .map(|text| (text.source, text.text))
+        .collect();
+    let texts = original.texts();
+    let mut total = 0;
+    let mut untranslated = 0;
+    let mut untranslated_texts = 0;
+
+    for text in &texts {
+        let count = text.word_count();
+        total += count;
+
+        let status = match translated.get(&text.source) {
+            Some(translation) if *translation == text.text => "untranslated",
+            Some(_) => continue,
+            None => "missing",
+        };
+
+        untranslated += count;
+        untranslated_texts += 1;
+
+        println!("{}: {count} words, {status}", text.source);
+    }
+
+    let percent = if total == 0 {
+        100.0
+    } else {
+        (total - untranslated) as f64 * 100.0 / total as f64
+    };
+
+    println!(
+        "Translated {} of {total} words ({percent:.1}%), {untranslated_texts} of {} texts left",
+        total - untranslated,
+        texts.len()
+    );
+}
+
+fn show(
+    path: &Path,
+    quest: Quest,
+    meta: QuestMeta,
+    location: Option<u32>,
+    jump: Option<u32>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let notes = |object| {
+        meta.notes
+            .get(&object)
+            .map(|notes| markdown::notes(notes))
+            .unwrap_or_default()
+    };
+
+    if let Some(id) = location {
+        match quest.location(LocationId(id)) {
+            Some(location) => print!(
+                "{}{}",
+                markdown::location(&quest, location),
+                notes(QuestObject::Location(location.id))
+            ),
+            None => {
+                let message = format!("Location {id} not found");
+                return Err(fail(error_format, Some(path), "NotFound", &message));
+            }
+        }
+    }
+
+    if let Some(id) = jump {
+        match quest.jump(JumpId(id)) {
+            Some(jump) => print!(
+                "{}{}",
+                markdown::jump(&quest, jump),
+                notes(QuestObject::Jump(jump.id))
+            ),
+            None => {
+                let message = format!("Jump {id} not found");
+                return Err(fail(error_format, Some(path), "NotFound", &message));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// States the search for the paths with the jump conditions may visit.
+const MAX_SEARCH_STATES: usize = 100_000;
+
+/// Fewest jumps from the start to the locations.
+fn location_distances(quest: &Quest, conditions: bool) -> BTreeMap<LocationId, usize> {
+    if conditions {
+        return match QuestPlayer::new(quest, 1) {
+            Ok(player) => qmm_player::shortest_paths(&player, MAX_SEARCH_STATES),
+            Err(_) => BTreeMap::new(),
+        };
+    }
+
+    quest
+        .locations
+        .iter()
+        .find(|location| location.ty == LocationType::Starting)
+        .map(|location| quest.distances(location.id))
+        .unwrap_or_default()
+}
+
+fn paths(quest: Quest, conditions: bool) {
+    let distances = location_distances(&quest, conditions);
+
+    for location in &quest.locations {
+        if !matches!(
+            location.ty,
+            LocationType::Success | LocationType::Fail | LocationType::Death
+        ) {
+            continue;
+        }
+
+        match distances.get(&location.id) {
+            Some(jumps) => println!("{:?} {}: {jumps} jumps", location.ty, location.id.0),
+            // The search gives up on the large quests
+            None if conditions => println!("{:?} {}: not found", location.ty, location.id.0),
+            None => println!("{:?} {}: unreachable", location.ty, location.id.0),
+        }
+    }
+}
+
+fn run(
+    quest: Quest,
+    script_path: &Path,
+    trace_params: Option<&Path>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let script = load_script(script_path, error_format)?;
+    let trace = script
+        .run(&quest)
+        .map_err(|err| fail(error_format, Some(script_path), "Script", &err))?;
+
+    println!("{} steps played", trace.len() - 1);
+
+    if let Some(path) = trace_params {
+        write_file(
+            path,
+            script::format_trace(&quest, &trace, path),
+            error_format,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn load_script(path: &Path, error_format: ErrorFormat) -> Result<Script, Failed> {
+    fs::read_to_string(path)
+        .map_err(|err| fail(error_format, Some(path), "Io", &err.to_string()))
+        .and_then(|text| {
+            Script::parse(&text).map_err(|err| fail(error_format, Some(path), "Script", &err))
+        })
+}
+
+/// Fails if the replays differ, so the checks can be scripted.
+fn determinism(quest: Quest, path: &Path, error_format: ErrorFormat) -> Result<(), Failed> {
+    let result = load_script(path, error_format)?
+        .check_determinism(&quest)
+        .map_err(|err| fail(error_format, Some(path), "Script", &err))?;
+
+    match result {
+        None => {
+            println!("The replays are identical");
+            Ok(())
+        }
+        Some((step, parts)) => {
+            println!("Step {step}: {} differ", parts.join(", "));
+            Err(Failed)
+        }
+    }
+}
+
+/// Fails after the report if some of the scripts can't be played.
+fn coverage(quest: Quest, scripts: &[PathBuf], error_format: ErrorFormat) -> Result<(), Failed> {
+    let mut coverage = TextCoverage::default();
+    let mut result = Ok(());
+
+    for path in scripts {
+        let script = load_script(path, error_format).and_then(|script| {
+            script
+                .coverage(&quest)
+                .map_err(|err| fail(error_format, Some(path), "Script", &err))
+        });
+
+        match script {
+            Ok(script) => coverage.merge(&script),
+            Err(failed) => result = Err(failed),
+        }
+    }
+
+    let mut texts = (0, 0);
+
+    println!("Location texts:");
+
+    for location in &quest.locations {
+        let variants: Vec<usize> = (0..location.texts.len())
+            .filter(|index| !location.texts[*index].to_string().trim().is_empty())
+            .collect();
+
+        if variants.len() < 2 {
+            continue;
+        }
+
+        let shown = coverage.shown_texts(location.id);
+        let missed: Vec<String> = variants
+            .iter()
+            .filter(|index| !shown.contains(index))
+            .map(|index| (index + 1).to_string())
+            .collect();
+
+        texts.0 += variants.len() - missed.len();
+        texts.1 += variants.len();
+
+        if !missed.is_empty() {
+            println!(
+                "  location {}: texts {} never shown",
+                location.id.0,
+                missed.join(", ")
+            );
+        }
+    }
+
+    println!(
+        "{} of {} texts of the multi-text locations shown",
+        texts.0, texts.1
+    );
+
+    let mut formulas = (0, 0);
+
+    println!("Text formulas:");
+
+    // The task and success texts aren't counted
+    let texts = quest
+        .formatted_texts()
+        .filter(|(source, _)| !matches!(source, TextSource::TaskText | TextSource::SuccessText));
+
+    for (source, text) in texts {
+        for formula in text_formulas(&text.elements) {
+            formulas.1 += 1;
+
+            match coverage.text_formulas.get(&(source, formula.to_string())) {
+                Some(values) => {
+                    formulas.0 += 1;
+
+                    let values: Vec<&str> = values.keys().map(String::as_str).collect();
+                    println!("  {source}: {{{formula}}} = {}", values.join(", "));
+                }
+                None => println!("  {source}: {{{formula}}} never shown"),
+            }
+        }
+    }
+
+    println!("{} of {} text formulas shown", formulas.0, formulas.1);
+
+    result
+}
+
+/// `{...}` formulas of the text, the highlighted ones included.
+fn text_formulas(elements: &[TextElement]) -> Vec<&str> {
+    elements
+        .iter()
+        .flat_map(|el| match &el.kind {
+            TextElementKind::Selection { text } => text_formulas(&text.elements),
+            TextElementKind::Formula { text } => vec![text.as_str()],
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+fn edit(command: EditCommand, error_format: ErrorFormat) -> Result<(), Failed> {
+    match command {
+        EditCommand::Param {
+            quest: path,
+            id,
+            min,
+            max,
+            starting,
+            name,
+            output,
+        } => {
+            let mut quest = load_quest(&path, error_format)?;
+            let edit = ParameterEdit {
+                min,
+                max,
+                starting,
+                name,
+            };
+
+            let changes = edit::edit_parameter(&mut quest, id, edit)
+                .map_err(|err| fail(error_format, Some(&path), "InvalidEdit", &err.to_string()))?;
+
+            for change in changes {
+                println!("[p{id}] {change}");
+            }
+
+            write_edited(&quest, &path, output, error_format)
+        }
+    }
+}
+
+fn replace(
+    path: &Path,
+    regex: &str,
+    replacement: &str,
+    output: EditOutput,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let regex = Regex::new(regex)
+        .map_err(|err| fail(error_format, None, "InvalidRegex", &err.to_string()))?;
+    let mut quest = load_quest(path, error_format)?;
+    let replacements = edit::replace_texts(&mut quest, &regex, replacement);
+
+    for replacement in &replacements {
+        println!("{}:", replacement.source);
+        print_changed_lines(replacement);
+    }
+
+    println!("{} texts changed", replacements.len());
+
+    if replacements.is_empty() {
+        return Ok(());
+    }
+
+    write_edited(&quest, path, output, error_format)
+}
+
+/// Only the changed lines, the texts may be long.
+fn print_changed_lines(replacement: &edit::Replacement) {
+    for change in replacement.changed_lines() {
+        match change {
+            LineChange::Removed(line) => println!("  - {line}"),
+            LineChange::Added(line) => println!("  + {line}"),
+        }
+    }
+}
+
+fn fix_encoding(path: &Path, output: EditOutput, error_format: ErrorFormat) -> Result<(), Failed> {
+    let mut quest = load_quest(path, error_format)?;
+    let repaired = edit::fix_encoding(&mut quest);
+
+    for (replacement, mojibake) in &repaired {
+        println!("{} ({mojibake}):", replacement.source);
+        print_changed_lines(replacement);
+    }
+
+    println!("{} texts repaired", repaired.len());
+
+    if repaired.is_empty() {
+        return Ok(());
+    }
+
+    write_edited(&quest, path, output, error_format)
+}
+
+fn transliterate(path: &Path, output: EditOutput, error_format: ErrorFormat) -> Result<(), Failed> {
+    let mut quest = load_quest(path, error_format)?;
+    let replacements = edit::transliterate(&mut quest);
+
+    for replacement in &replacements {
+        println!("{}:", replacement.source);
+        print_changed_lines(replacement);
+    }
+
+    println!("{} texts transliterated", replacements.len());
+
+    if replacements.is_empty() {
+        return Ok(());
+    }
+
+    write_edited(&quest, path, output, error_format)
+}
+
+fn parse_rename(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .ok_or_else(|| format!("expected `old=new`, got `{value}`"))
+}
 
-    let mut quest_file = File::open(args.quest).unwrap();
-    let mut quest_data = Vec::new();
-    quest_file.read_to_end(&mut quest_data).unwrap();
+fn remap_media(
+    path: &Path,
+    map_path: Option<&Path>,
+    renames: Vec<(String, String)>,
+    output: EditOutput,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let mut map = BTreeMap::new();
 
-    let quest = match parse_qmm(&quest_data) {
-        Ok(quest) => quest,
+    if let Some(map_path) = map_path {
+        map = fs::read_to_string(map_path)
+            .map_err(|err| fail(error_format, Some(map_path), "Io", &err.to_string()))
+            .and_then(|text| {
+                toml::from_str(&text).map_err(|err| {
+                    fail(error_format, Some(map_path), "InvalidMap", &err.to_string())
+                })
+            })?;
+    }
+
+    map.extend(renames);
+
+    let mut quest = load_quest(path, error_format)?;
+    let remap = edit::remap_media(&mut quest, &map);
+
+    for (file, references) in &remap.unmatched {
+        println!("unmatched: {file} ({references} references)");
+    }
+
+    for file in &remap.unused {
+        println!("unused: {file}");
+    }
+
+    println!("{} references renamed", remap.renamed);
+
+    if remap.renamed == 0 {
+        return Ok(());
+    }
+
+    write_edited(&quest, path, output, error_format)
+}
+
+fn make_patch(
+    original: &Path,
+    edited: &Path,
+    output: Option<&Path>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let (original, edited) = (
+        load_quest(original, error_format),
+        load_quest(edited, error_format),
+    );
+    let (original, edited) = (original?, edited?);
+
+    let patch = quest_patch::make_patch(&original, &edited)
+        .map_err(|err| fail(error_format, None, "Patch", &err.to_string()))?;
+    let json = serde_json::to_string_pretty(&patch).unwrap();
+
+    match output {
+        Some(path) => {
+            write_file(path, json, error_format)?;
+            println!("{} changes", patch.changes.len());
+        }
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
+
+fn apply_patch(
+    path: &Path,
+    patch_path: &Path,
+    output: EditOutput,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let patch: QuestPatch = fs::read(patch_path)
+        .map_err(|err| fail(error_format, Some(patch_path), "Io", &err.to_string()))
+        .and_then(|data| {
+            serde_json::from_slice(&data).map_err(|err| {
+                fail(
+                    error_format,
+                    Some(patch_path),
+                    "InvalidPatch",
+                    &err.to_string(),
+                )
+            })
+        })?;
+
+    let mut quest = load_quest(path, error_format)?;
+
+    quest_patch::apply_patch(&mut quest, &patch)
+        .map_err(|err| fail(error_format, Some(patch_path), "Patch", &err.to_string()))?;
+
+    println!("{} changes applied", patch.changes.len());
+    write_edited(&quest, path, output, error_format)
+}
+
+fn fmt_formulas(path: &Path, output: EditOutput, error_format: ErrorFormat) -> Result<(), Failed> {
+    let mut quest = load_quest(path, error_format)?;
+
+    for lint in formulas::lint_formulas(&quest) {
+        println!("{}: warning: {}", lint.source, lint.message);
+    }
+
+    let formatted = formulas::format_formulas(&mut quest);
+
+    for formula in &formatted {
+        println!(
+            "{}: `{}` -> `{}`",
+            formula.source, formula.before, formula.after
+        );
+    }
+
+    println!("{} formulas formatted", formatted.len());
+
+    if formatted.is_empty() {
+        return Ok(());
+    }
+
+    write_edited(&quest, path, output, error_format)
+}
+
+fn write_edited(
+    quest: &Quest,
+    path: &Path,
+    output: EditOutput,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    if output.dry_run {
+        return Ok(());
+    }
+
+    let data = if output.patch {
+        let original = read_quest(path, error_format)?;
+
+        patch_qmm(&original, quest).map_err(|err| {
+            let message = format!("The original quest can't be patched: {err}");
+            fail(error_format, Some(path), "Patch", &message)
+        })?
+    } else {
+        write_qmm(quest)
+    };
+
+    // Never replace a quest with one which can't be opened
+    if let Err(err) = parse_qmm(&data) {
+        let message = format!("The edited quest can't be read back: {err}");
+        return Err(fail(error_format, Some(path), err.kind(), &message));
+    }
+
+    let target = output.output.as_deref().unwrap_or(path);
+
+    atomic_write::write(target, &data, output.backup)
+        .map_err(|err| fail(error_format, Some(target), "Io", &err.to_string()))
+}
+
+fn print_format(sections: &[SectionFormat]) {
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        println!("## {}\n", section.name);
+        println!("| Field | Type | Size |");
+        println!("|---|---|---|");
+
+        for field in &section.fields {
+            let size = match field.ty.size() {
+                Some(size) => size.to_string(),
+                None => "4, or 8 + 2 per UTF-16 unit".to_string(),
+            };
+
+            println!("| `{}` | {} | {size} |", field.name, field.ty);
+        }
+    }
+}
+
+fn meta(command: MetaCommand, error_format: ErrorFormat) -> Result<(), Failed> {
+    let load = |quest: &Path| {
+        QuestMeta::load(quest).map_err(|err| fail(error_format, None, "Meta", &err.to_string()))
+    };
+    let save = |meta: QuestMeta, quest: &Path| {
+        meta.save(quest)
+            .map_err(|err| fail(error_format, None, "Meta", &err.to_string()))
+    };
+
+    match command {
+        MetaCommand::Show { quest } => {
+            print!("{}", load(&quest)?);
+            Ok(())
+        }
+        MetaCommand::Set {
+            quest,
+            name,
+            author,
+            description,
+            language,
+            tags,
+        } => {
+            let mut meta = load(&quest)?;
+
+            meta.name = name.or(meta.name);
+            meta.author = author.or(meta.author);
+            meta.description = description.or(meta.description);
+            meta.language = language.or(meta.language);
+
+            if !tags.is_empty() {
+                meta.tags = tags;
+            }
+
+            save(meta, &quest)
+        }
+        MetaCommand::Release {
+            quest,
+            version,
+            changes,
+        } => {
+            let mut meta = load(&quest)?;
+
+            meta.release(version, changes);
+
+            save(meta, &quest)
+        }
+        MetaCommand::Note {
+            quest,
+            object,
+            text,
+            clear,
+        } => {
+            let mut meta = load(&quest)?;
+
+            if clear {
+                meta.notes.remove(&object);
+            }
+
+            if let Some(text) = text {
+                meta.notes.entry(object).or_default().push(text);
+            }
+
+            save(meta, &quest)
+        }
+    }
+}
+
+fn audit(quest: Quest) -> Result<(), Failed> {
+    print_findings(&quest, &qmm_analysis::audit(&quest));
+    Ok(())
+}
+
+#[cfg(feature = "z3")]
+fn audit_smt(quest: Quest, error_format: ErrorFormat) -> Result<(), Failed> {
+    let strict_math = qmm_player::PlayerOptions::default().strict_math;
+    let findings = qmm_analysis::audit_smt(&quest, strict_math, &Default::default())
+        .map_err(|err| fail(error_format, None, "Smt", &format!("Can't run z3: {err}")))?;
+
+    print_findings(&quest, &findings);
+    Ok(())
+}
+
+/// Word lists of the file, empty without one.
+fn word_lists(path: Option<&Path>, error_format: ErrorFormat) -> Result<WordLists, Failed> {
+    let Some(path) = path else {
+        return Ok(WordLists::default());
+    };
+
+    lints::load_word_lists(path)
+        .map_err(|err| fail(error_format, Some(path), "WordLists", &err.to_string()))
+}
+
+/// Fails if the quest has errors, so the checks can be scripted.
+fn lint(
+    quest: &Path,
+    levels: [(Vec<Lint>, Level); 3],
+    words: Option<&Path>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let words = word_lists(words, error_format)?;
+
+    match lint_quest(quest, &levels, &words, error_format)? {
+        0 => Ok(()),
+        _ => Err(Failed),
+    }
+}
+
+/// Prints the diagnostics of the quest, returning the number of errors.
+fn lint_quest(
+    quest: &Path,
+    levels: &[(Vec<Lint>, Level); 3],
+    words: &WordLists,
+    error_format: ErrorFormat,
+) -> Result<usize, Failed> {
+    let meta =
+        QuestMeta::load(quest).map_err(|err| fail(error_format, None, "Meta", &err.to_string()))?;
+    let quest = load_quest(quest, error_format)?;
+    let (mut lint_levels, unknown) = LintLevels::new(&meta);
+
+    for name in unknown {
+        eprintln!("warning: unknown lint `{name}` in the metadata");
+    }
+
+    for (lints, level) in levels {
+        lint_levels.set(lints, *level);
+    }
+
+    let diagnostics = lints::lint(&quest, &lint_levels, words);
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level == Level::Deny)
+        .count();
+
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+
+    println!("{errors} errors, {} warnings", diagnostics.len() - errors);
+
+    Ok(errors)
+}
+
+fn watch(
+    path: &Path,
+    levels: [(Vec<Lint>, Level); 3],
+    words: Option<&Path>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let words = word_lists(words, error_format)?;
+    let watcher = QuestWatcher::new(path).map_err(|err| {
+        let message = format!("Can't watch: {err}");
+        fail(error_format, Some(path), "Io", &message)
+    })?;
+    // The failures are reported, the watch goes on
+    let check = |quest: &Path| {
+        println!("==> {}", quest.display());
+        let _ = lint_quest(quest, &levels, &words, error_format);
+    };
+
+    if path.is_dir() {
+        watch::quests(path).iter().for_each(|quest| check(quest));
+    } else {
+        check(path);
+    }
+
+    while let Some(changed) = watcher.wait() {
+        for quest in changed {
+            // Deleted quests have nothing to check
+            if quest.exists() {
+                check(&quest);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn features(dir: &Path, error_format: ErrorFormat) {
+    let mut counts: BTreeMap<QuestFeature, usize> = BTreeMap::new();
+    let mut quests = 0;
+    let mut broken = 0;
+
+    for path in watch::quests(dir) {
+        let Ok(quest) = load_quest(&path, error_format) else {
+            broken += 1;
+            continue;
+        };
+
+        quests += 1;
+
+        for feature in qmm_analysis::features(&quest) {
+            *counts.entry(feature).or_default() += 1;
+        }
+    }
+
+    println!("{quests} quests read, {broken} unreadable");
+
+    if quests == 0 {
+        return;
+    }
+
+    let mut counts: Vec<_> = QuestFeature::ALL
+        .iter()
+        .map(|feature| (*feature, counts.get(feature).copied().unwrap_or_default()))
+        .collect();
+    // The most used first, the stable sort keeps the rest in order
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    for (feature, count) in counts {
+        println!("{count:>6} {:>4}%  {feature}", count * 100 / quests);
+    }
+}
+
+/// Prints the findings and the texts in another language than the rest.
+fn print_findings(quest: &Quest, findings: &[qmm_analysis::AuditFinding]) {
+    let languages = qmm_analysis::languages(quest);
+
+    for finding in findings {
+        println!("{finding}");
+    }
+
+    if let Some(dominant) = languages.dominant {
+        for (source, language) in &languages.foreign {
+            println!("{source}: in {language}, the rest of the quest is in {dominant}");
+        }
+    }
+
+    println!(
+        "{} problems found",
+        findings.len() + languages.foreign.len()
+    );
+}
+
+/// Failure of a command, already reported to the user.
+#[derive(Debug, Clone, Copy)]
+struct Failed;
+
+/// Prints the error to stderr, in JSON for the editors and CI if asked.
+fn print_error(
+    error_format: ErrorFormat,
+    path: Option<&Path>,
+    kind: &str,
+    message: &str,
+    location: Option<(QuestSection, u64)>,
+) {
+    match error_format {
+        ErrorFormat::Human => match (path, location) {
+            (Some(path), Some((section, offset))) => eprintln!(
+                "{}: {message} (in {section} at byte {offset})",
+                path.display()
+            ),
+            (Some(path), None) => eprintln!("{}: {message}", path.display()),
+            (None, _) => eprintln!("{message}"),
+        },
+        ErrorFormat::Json => {
+            let error = serde_json::json!({
+                "file": path,
+                "kind": kind,
+                "section": location.map(|(section, _)| section.to_string()),
+                "offset": location.map(|(_, offset)| offset),
+                "message": message,
+            });
+
+            eprintln!("{error}");
+        }
+    }
+}
+
+/// Reports the error which isn't tied to a place in a quest.
+fn fail(error_format: ErrorFormat, path: Option<&Path>, kind: &str, message: &str) -> Failed {
+    print_error(error_format, path, kind, message, None);
+    Failed
+}
+
+fn write_file(
+    path: &Path,
+    data: impl AsRef<[u8]>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    fs::write(path, data).map_err(|err| fail(error_format, Some(path), "Io", &err.to_string()))
+}
+
+fn demo_quest() -> QuestData {
+    QuestData::Owned(demo::quest_data())
+}
+
+/// Quest data without the containers. Plain quests aren't copied out of the
+/// file mapping.
+fn read_quest(path: &Path, error_format: ErrorFormat) -> Result<QuestData, Failed> {
+    let quest_data = QuestData::read(path)
+        .map_err(|err| fail(error_format, Some(path), "Io", &err.to_string()))?;
+
+    // Quest packs may wrap the files into containers
+    let unwrapped = match unwrap_container(&quest_data, &default_codecs()) {
+        Ok(Cow::Borrowed(_)) => None,
+        Ok(Cow::Owned(data)) => Some(data),
+        Err(err) => {
+            return Err(fail(
+                error_format,
+                Some(path),
+                "Container",
+                &err.to_string(),
+            ));
+        }
+    };
+
+    Ok(unwrapped.map_or(quest_data, QuestData::Owned))
+}
+
+fn parse_quest(path: &Path, quest_data: &[u8], error_format: ErrorFormat) -> Result<Quest, Failed> {
+    parse_qmm_located(quest_data).map_err(|err| {
+        print_error(
+            error_format,
+            Some(path),
+            err.error.kind(),
+            &err.error.to_string(),
+            Some((err.section, err.offset)),
+        );
+        Failed
+    })
+}
+
+/// Edited quest for the hot reload.
+fn reload_quest(path: &Path) -> Result<Quest, String> {
+    let data = QuestData::read(path).map_err(|err| err.to_string())?;
+    let data = unwrap_container(&data, &default_codecs()).map_err(|err| err.to_string())?;
+
+    parse_qmm(&data).map_err(|err| err.to_string())
+}
+
+fn load_quest(path: &Path, error_format: ErrorFormat) -> Result<Quest, Failed> {
+    parse_quest(path, &read_quest(path, error_format)?, error_format)
+}
+
+fn pack_quest(
+    path: &Path,
+    output: &Path,
+    compression: PackCompression,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let quest_data = read_quest(path, error_format)?;
+
+    // Don't bundle files which can't be opened anyway
+    parse_quest(path, &quest_data, error_format)?;
+
+    let bundle = pack(&quest_data, compression.into()).map_err(|err| {
+        let message = format!("Can't pack the quest: {err}");
+        fail(error_format, Some(path), "Pack", &message)
+    })?;
+
+    write_file(output, &bundle, error_format)?;
+    println!(
+        "Packed {} bytes into {} bytes",
+        quest_data.len(),
+        bundle.len()
+    );
+
+    Ok(())
+}
+
+fn pack_manifest(
+    dir: &Path,
+    output: Option<PathBuf>,
+    error_format: ErrorFormat,
+) -> Result<(), Failed> {
+    let output = output.unwrap_or_else(|| dir.join(manifest::MANIFEST_NAME));
+    let manifest = manifest::create(dir)
+        .map_err(|err| fail(error_format, Some(dir), "Io", &err.to_string()))?;
+
+    write_file(&output, &manifest, error_format)?;
+    println!("{} files hashed", manifest.lines().count());
+
+    Ok(())
+}
+
+/// Fails if the files don't match, so the checks can be scripted.
+fn verify(dir: &Path, manifest: Option<PathBuf>, error_format: ErrorFormat) -> Result<(), Failed> {
+    let path = manifest.unwrap_or_else(|| dir.join(manifest::MANIFEST_NAME));
+    let text = fs::read_to_string(&path)
+        .map_err(|err| fail(error_format, Some(&path), "Io", &err.to_string()))?;
+    let problems = manifest::verify(dir, &text)
+        .map_err(|err| fail(error_format, Some(dir), "Manifest", &err.to_string()))?;
+
+    if problems.is_empty() {
+        println!("All files match the manifest");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    Err(Failed)
+}
+
+/// Writes logs to the file from the `QMM_LOG` environment variable, the
+/// terminal is occupied by the player.
+#[cfg(feature = "tracing")]
+fn init_tracing() {
+    let Some(path) = std::env::var_os("QMM_LOG") else {
+        return;
+    };
+
+    // Playing without the logs beats not playing at all
+    let file = match File::create(&path) {
+        Ok(file) => file,
         Err(err) => {
-            println!("Got error: {err}\n{err:#?}");
+            eprintln!(
+                "Can't write the log to {}: {err}",
+                Path::new(&path).display()
+            );
             return;
         }
     };
 
-    match args.command {
-        Command::Dump { path } => dump(quest, &path),
-        Command::Play => play(quest),
+    tracing_subscriber::fmt()
+        .with_max_level(tracing_subscriber::filter::LevelFilter::TRACE)
+        .with_ansi(false)
+        .with_writer(std::sync::Mutex::new(file))
+        .init();
+}
+
+fn main() -> ExitCode {
+    #[cfg(feature = "tracing")]
+    init_tracing();
+
+    let args = Cli::parse();
+
+    match run_command(args.command, args.error_format) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failed) => ExitCode::FAILURE,
+    }
+}
+
+fn run_command(command: Command, error_format: ErrorFormat) -> Result<(), Failed> {
+    let load_quest = |path: &Path| load_quest(path, error_format);
+
+    match command {
+        Command::Dump {
+            quest,
+            path,
+            format,
+            section,
+        } => dump(load_quest(&quest)?, &path, format, section, error_format),
+        Command::Play {
+            quest: path,
+            demo,
+            plain,
+            deadline,
+            save,
+            speak,
+            watch,
+        } => {
+            let (path, quest_data) = match path {
+                Some(path) => {
+                    let quest_data = read_quest(&path, error_format)?;
+                    (path, quest_data)
+                }
+                None => (PathBuf::from("demo.qmm"), demo_quest()),
+            };
+            let quest = parse_quest(&path, &quest_data, error_format)?;
+
+            let name = if demo {
+                "Demo".to_string()
+            } else {
+                // Broken sidecar files don't prevent playing
+                QuestMeta::load(&path)
+                    .ok()
+                    .and_then(|meta| meta.name)
+                    .or_else(|| {
+                        path.file_stem()
+                            .map(|name| name.to_string_lossy().into_owned())
+                    })
+                    .unwrap_or_default()
+            };
+            let fingerprint = fingerprint(&quest_data);
+
+            let options = PlayOptions {
+                plain,
+                deadline,
+                slot: save,
+                speak,
+                hot_reload: watch.then(|| path.clone()),
+            };
+
+            play(quest, name, &fingerprint, options, &path, error_format)
+        }
+        Command::Words { quest } => {
+            words(load_quest(&quest)?);
+            Ok(())
+        }
+        Command::Duplicates {
+            quest,
+            threshold,
+            min_length,
+        } => {
+            let options = DuplicateOptions {
+                threshold,
+                min_length,
+            };
+
+            duplicates(load_quest(&quest)?, options);
+            Ok(())
+        }
+        Command::Progress { quest, translated } => {
+            let (quest, translated) = (load_quest(&quest), load_quest(&translated));
+
+            progress(quest?, translated?);
+            Ok(())
+        }
+        Command::Pack {
+            quest,
+            path,
+            compression,
+        } => pack_quest(&quest, &path, compression, error_format),
+        Command::PackManifest { dir, output } => pack_manifest(&dir, output, error_format),
+        Command::Verify { dir, manifest } => verify(&dir, manifest, error_format),
+        Command::Formula => {
+            formula_repl::run();
+            Ok(())
+        }
+        Command::Show {
+            quest,
+            location,
+            jump,
+        } => {
+            // Broken sidecar files don't prevent showing the quest
+            let meta = QuestMeta::load(&quest).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                QuestMeta::default()
+            });
+
+            show(
+                &quest,
+                load_quest(&quest)?,
+                meta,
+                location,
+                jump,
+                error_format,
+            )
+        }
+        Command::Graph {
+            quest,
+            path,
+            format,
+            around,
+            depth,
+            distances,
+            conditions,
+        } => {
+            let mut quest = load_quest(&quest)?;
+            let distances = if distances {
+                location_distances(&quest, conditions)
+            } else {
+                BTreeMap::new()
+            };
+
+            if let Some(id) = around {
+                quest = quest.neighborhood(LocationId(id), depth);
+            }
+
+            let graph = qmm_analysis::graph(&quest, format.into(), &distances);
+            write_file(&path, graph, error_format)
+        }
+        Command::Run {
+            quest,
+            script,
+            trace_params,
+        } => run(
+            load_quest(&quest)?,
+            &script,
+            trace_params.as_deref(),
+            error_format,
+        ),
+        Command::Determinism { quest, script } => {
+            determinism(load_quest(&quest)?, &script, error_format)
+        }
+        Command::Coverage { quest, scripts } => {
+            coverage(load_quest(&quest)?, &scripts, error_format)
+        }
+        Command::Edit { command } => edit(command, error_format),
+        Command::Replace {
+            quest,
+            regex,
+            replacement,
+            output,
+        } => replace(&quest, &regex, &replacement, output, error_format),
+        Command::FixEncoding { quest, output } => fix_encoding(&quest, output, error_format),
+        Command::Transliterate { quest, output } => transliterate(&quest, output, error_format),
+        Command::RemapMedia {
+            quest,
+            map,
+            rename,
+            output,
+        } => remap_media(&quest, map.as_deref(), rename, output, error_format),
+        Command::MakePatch {
+            original,
+            edited,
+            output,
+        } => make_patch(&original, &edited, output.as_deref(), error_format),
+        Command::ApplyPatch {
+            quest,
+            patch_file,
+            output,
+        } => apply_patch(&quest, &patch_file, output, error_format),
+        Command::FmtFormulas { quest, output } => fmt_formulas(&quest, output, error_format),
+        Command::Meta { command } => meta(command, error_format),
+        Command::ReadingOrder {
+            quest,
+            no_conditions,
+        } => {
+            let quest = load_quest(&quest)?;
+            let reached = location_distances(&quest, !no_conditions);
+
+            print!("{}", markdown::reading_order(&quest, &reached));
+            Ok(())
+        }
+        Command::Paths { quest, conditions } => {
+            paths(load_quest(&quest)?, conditions);
+            Ok(())
+        }
+        Command::Audit {
+            quest,
+            #[cfg(feature = "z3")]
+            smt,
+        } => {
+            let quest = load_quest(&quest)?;
+
+            #[cfg(feature = "z3")]
+            if smt {
+                return audit_smt(quest, error_format);
+            }
+
+            audit(quest)
+        }
+        Command::Lint {
+            quest,
+            allow,
+            warn,
+            deny,
+            words,
+        } => lint(
+            &quest,
+            [
+                (allow, Level::Allow),
+                (warn, Level::Warn),
+                (deny, Level::Deny),
+            ],
+            words.as_deref(),
+            error_format,
+        ),
+        Command::Watch {
+            path,
+            allow,
+            warn,
+            deny,
+            words,
+        } => watch(
+            &path,
+            [
+                (allow, Level::Allow),
+                (warn, Level::Warn),
+                (deny, Level::Deny),
+            ],
+            words.as_deref(),
+            error_format,
+        ),
+        Command::Inspect { quest: path, edit } => {
+            let meta = QuestMeta::load(&path).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                QuestMeta::default()
+            });
+            let quest = load_quest(&path)?;
+
+            Inspector::new(quest, &meta, edit.then_some(path.as_path())).run();
+            Ok(())
+        }
+        Command::Features { dir } => {
+            features(&dir, error_format);
+            Ok(())
+        }
+        Command::DescribeFormat { quest: path } => {
+            let (path, quest_data) = match path {
+                Some(path) => {
+                    let quest_data = read_quest(&path, error_format)?;
+                    (path, quest_data)
+                }
+                None => (PathBuf::from("demo.qmm"), demo_quest()),
+            };
+            let sections = describe_format(&quest_data)
+                .map_err(|err| fail(error_format, Some(&path), err.kind(), &err.to_string()))?;
+
+            print_format(&sections);
+            Ok(())
+        }
     }
 }
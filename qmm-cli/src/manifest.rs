@@ -0,0 +1,116 @@
+//! Hashes of the files of a quest directory, in the `sha256sum` format so
+//! the manifest can be checked without this tool too.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write},
+    fs, io,
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+/// Manifest file in the root of the directory, not listed in itself.
+pub const MANIFEST_NAME: &str = "manifest.sha256";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The file's contents differ from the hashed ones
+    Modified(String),
+    Missing(String),
+    /// The file was added after the manifest was made
+    Unlisted(String),
+    /// The manifest line can't be read
+    InvalidLine(usize),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Modified(path) => write!(f, "{path}: modified"),
+            Problem::Missing(path) => write!(f, "{path}: missing"),
+            Problem::Unlisted(path) => write!(f, "{path}: not in the manifest"),
+            Problem::InvalidLine(number) => write!(f, "manifest line {number}: invalid"),
+        }
+    }
+}
+
+/// Hashes of the files by their paths relative to the directory, separated
+/// by `/` on every platform.
+fn hash_files(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(current) = dirs.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(dir)
+                .unwrap()
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if relative == MANIFEST_NAME {
+                continue;
+            }
+
+            let hash = Sha256::digest(fs::read(&path)?);
+            let mut hex = String::with_capacity(64);
+
+            for byte in hash {
+                write!(hex, "{byte:02x}").unwrap();
+            }
+
+            hashes.insert(relative, hex);
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// Manifest of every file in the directory.
+pub fn create(dir: &Path) -> io::Result<String> {
+    let mut manifest = String::new();
+
+    for (path, hash) in hash_files(dir)? {
+        writeln!(manifest, "{hash}  {path}").unwrap();
+    }
+
+    Ok(manifest)
+}
+
+/// Compares the files in the directory with the manifest.
+pub fn verify(dir: &Path, manifest: &str) -> io::Result<Vec<Problem>> {
+    let mut hashes = hash_files(dir)?;
+    let mut problems = Vec::new();
+
+    for (number, line) in manifest.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // `sha256sum` marks the binary mode with `*` before the path
+        let Some((hash, path)) = line.split_once("  ").or_else(|| line.split_once(" *")) else {
+            problems.push(Problem::InvalidLine(number + 1));
+            continue;
+        };
+
+        match hashes.remove(path) {
+            Some(actual) if actual.eq_ignore_ascii_case(hash) => (),
+            Some(_) => problems.push(Problem::Modified(path.to_string())),
+            None => problems.push(Problem::Missing(path.to_string())),
+        }
+    }
+
+    problems.extend(hashes.into_keys().map(Problem::Unlisted));
+
+    Ok(problems)
+}
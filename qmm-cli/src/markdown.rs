@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use qmm_syntax::qmm::*;
+
+pub fn location(quest: &Quest, location: &Location) -> String {
+    let mut md = String::new();
+
+    writeln!(md, "# Location {}\n", location.id.0).unwrap();
+    writeln!(md, "- Type: {:?}", location.ty).unwrap();
+    writeln!(md, "- Max visits: {}", max_visits(&location.max_visits)).unwrap();
+    writeln!(md, "- Passes a day: {}", yes_no(location.do_pass_day)).unwrap();
+
+    match &location.select_type {
+        LocationSelectType::ByOrder => writeln!(md, "- Text selection: by order").unwrap(),
+        LocationSelectType::ByFormula(formula) => {
+            writeln!(md, "- Text selection: `{formula}`").unwrap()
+        }
+    }
+
+    md.push_str("\n## Texts\n");
+
+    for (index, text) in location.texts.iter().enumerate() {
+        writeln!(md, "\n### Text {}\n", index + 1).unwrap();
+
+        if !text.elements.is_empty() {
+            writeln!(md, "{}", quote(&text.clone().normalize().to_string())).unwrap();
+        }
+
+        if let Some(media) = location.media.get(index) {
+            write_media(&mut md, media);
+        }
+    }
+
+    write_parameter_changes(&mut md, quest, &location.parameter_changes);
+
+    md.push_str("\n## Jumps\n\n");
+
+    for jump in quest.jumps_from(location.id) {
+        writeln!(
+            md,
+            "- Jump {} to location {}: {}",
+            jump.id.0,
+            jump.to.0,
+            inline_text(&jump.text.to_string())
+        )
+        .unwrap();
+    }
+
+    md
+}
+
+/// `id` is one-based, like in `[p1]`.
+pub fn parameter(id: u32, parameter: &Parameter) -> String {
+    let mut md = String::new();
+
+    writeln!(md, "# Parameter {id}: {}\n", parameter.name).unwrap();
+    writeln!(md, "- Type: {:?}", parameter.ty).unwrap();
+    writeln!(
+        md,
+        "- Range: {}..{}",
+        parameter.min_value, parameter.max_value
+    )
+    .unwrap();
+    writeln!(md, "- Starting value: `{}`", parameter.starting_value).unwrap();
+    writeln!(md, "- Critical value: {:?}", parameter.critical_value).unwrap();
+    writeln!(md, "- Active: {}", yes_no(parameter.is_active)).unwrap();
+    writeln!(md, "- Money: {}", yes_no(parameter.is_money)).unwrap();
+    writeln!(
+        md,
+        "- Shown when zero: {}",
+        yes_no(parameter.show_when_zero)
+    )
+    .unwrap();
+
+    if !parameter.formatted_range_lines.is_empty() {
+        md.push_str("\n## Range lines\n\n");
+
+        for line in &parameter.formatted_range_lines {
+            writeln!(
+                md,
+                "- {}..{}: {}",
+                line.from,
+                line.to,
+                inline_text(&line.value)
+            )
+            .unwrap();
+        }
+    }
+
+    if !parameter.critical_text.is_empty() {
+        md.push_str("\n## Critical text\n\n");
+        writeln!(md, "{}", quote(&parameter.critical_text)).unwrap();
+    }
+
+    write_media(
+        &mut md,
+        &Media {
+            image: parameter.image.clone(),
+            sound: parameter.sound.clone(),
+            track: parameter.track.clone(),
+        },
+    );
+
+    md
+}
+
+/// Notes of an object from the quest's metadata.
+pub fn notes(notes: &[String]) -> String {
+    let mut md = String::from("\n## Notes\n\n");
+
+    for note in notes {
+        writeln!(md, "- {}", inline_text(note)).unwrap();
+    }
+
+    md
+}
+
+pub fn jump(quest: &Quest, jump: &Jump) -> String {
+    let mut md = String::new();
+
+    writeln!(md, "# Jump {}\n", jump.id.0).unwrap();
+
+    if !jump.text.elements.is_empty() {
+        writeln!(md, "{}\n", quote(&jump.text.to_string())).unwrap();
+    }
+
+    writeln!(
+        md,
+        "- From location {} to location {}",
+        jump.from.0, jump.to.0
+    )
+    .unwrap();
+    writeln!(md, "- Priority: {}", jump.priority).unwrap();
+    writeln!(md, "- Show order: {}", jump.show_order).unwrap();
+    writeln!(md, "- Show always: {}", yes_no(jump.show_always)).unwrap();
+    writeln!(md, "- Max passes: {}", max_visits(&jump.max_visits)).unwrap();
+    writeln!(md, "- Passes a day: {}", yes_no(jump.do_pass_day)).unwrap();
+
+    if !jump.formula.is_empty() {
+        writeln!(md, "- Formula: `{}`", jump.formula).unwrap();
+    }
+
+    if !jump.parameters_conditions.is_empty() {
+        md.push_str("\n## Conditions\n\n");
+
+        for condition in &jump.parameters_conditions {
+            write!(
+                md,
+                "- {} in {}..{}",
+                parameter_name(quest, condition.parameter_id),
+                condition.range_start,
+                condition.range_end
+            )
+            .unwrap();
+
+            if !condition.must_equal_values.is_empty() {
+                let not = if condition.must_equal { "" } else { "not " };
+                write!(md, ", {not}equal to {}", join(&condition.must_equal_values)).unwrap();
+            }
+
+            if !condition.must_mod_values.is_empty() {
+                let not = if condition.must_mod { "" } else { "not " };
+                write!(
+                    md,
+                    ", {not}divisible by {}",
+                    join(&condition.must_mod_values)
+                )
+                .unwrap();
+            }
+
+            md.push('\n');
+        }
+    }
+
+    if !jump.description.elements.is_empty() {
+        md.push_str("\n## Description\n\n");
+        writeln!(md, "{}", quote(&jump.description.to_string())).unwrap();
+    }
+
+    write_media(&mut md, &jump.media);
+    write_parameter_changes(&mut md, quest, &jump.parameter_changes);
+
+    md
+}
+
+/// Every text of the quest in the order of a playthrough, for proofreading.
+///
+/// The locations are read depth-first from the start, a choice's branch up
+/// to its end before the next choice. The jumps to the locations in
+/// `reached`, the ones the player got to when playing the quest, are
+/// followed first, then the rest of the graph and the unreachable
+/// locations.
+pub fn reading_order(quest: &Quest, reached: &BTreeMap<LocationId, usize>) -> String {
+    let mut md = String::from("# Reading order\n");
+
+    for id in reading_locations(quest, reached) {
+        let Some(location) = quest.location(id) else {
+            continue;
+        };
+
+        writeln!(md, "\n## Location {}\n", id.0).unwrap();
+
+        if !reached.contains_key(&id) {
+            md.push_str("_Not reached when playing._\n\n");
+        }
+
+        let texts: Vec<_> = location
+            .texts
+            .iter()
+            .map(|text| text.to_string())
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+
+        for (index, text) in texts.iter().enumerate() {
+            if texts.len() > 1 {
+                writeln!(md, "### Text {}\n", index + 1).unwrap();
+            }
+
+            writeln!(md, "{}\n", quote(text)).unwrap();
+        }
+
+        for change in &location.parameter_changes {
+            if !change.critical_text.is_empty() {
+                writeln!(
+                    md,
+                    "Critical text of {}:\n\n{}\n",
+                    parameter_name(quest, change.parameter_id),
+                    quote(&change.critical_text)
+                )
+                .unwrap();
+            }
+        }
+
+        let jumps = sorted_jumps(quest, id);
+
+        if jumps.is_empty() {
+            writeln!(md, "_The quest ends: {:?}._", location.ty).unwrap();
+            continue;
+        }
+
+        for jump in jumps {
+            let text = inline_text(&jump.text.to_string());
+            let text = if text.is_empty() {
+                "_taken automatically_".to_string()
+            } else {
+                text
+            };
+
+            write!(md, "- {text} → [location {0}](#location-{0})", jump.to.0).unwrap();
+
+            if !jump.formula.is_empty() {
+                write!(md, ", if `{}`", jump.formula).unwrap();
+            }
+
+            if !jump.parameters_conditions.is_empty() {
+                write!(
+                    md,
+                    ", {} parameter conditions",
+                    jump.parameters_conditions.len()
+                )
+                .unwrap();
+            }
+
+            md.push('\n');
+
+            let description = jump.description.to_string();
+            let critical_texts = jump
+                .parameter_changes
+                .iter()
+                .map(|change| &change.critical_text)
+                .filter(|text| !text.is_empty());
+
+            for text in [&description].into_iter().chain(critical_texts) {
+                if !text.trim().is_empty() {
+                    writeln!(md, "\n{}\n", indent(&quote(text))).unwrap();
+                }
+            }
+        }
+    }
+
+    md
+}
+
+fn sorted_jumps(quest: &Quest, location: LocationId) -> Vec<&Jump> {
+    let mut jumps: Vec<&Jump> = quest.jumps_from(location).collect();
+    jumps.sort_by_key(|jump| jump.show_order);
+
+    jumps
+}
+
+fn reading_locations(quest: &Quest, reached: &BTreeMap<LocationId, usize>) -> Vec<LocationId> {
+    let mut order = Vec::new();
+    let mut visited = BTreeSet::new();
+    let start = quest
+        .locations
+        .iter()
+        .find(|location| location.ty == LocationType::Starting)
+        .map(|location| location.id);
+
+    for played in [true, false] {
+        let mut stack: Vec<LocationId> = start.into_iter().collect();
+
+        while let Some(id) = stack.pop() {
+            if (played && !reached.contains_key(&id)) || !visited.insert(id) {
+                continue;
+            }
+
+            order.push(id);
+            // Reversed, so the first choice is read first
+            stack.extend(sorted_jumps(quest, id).iter().rev().map(|jump| jump.to));
+        }
+    }
+
+    for location in &quest.locations {
+        if visited.insert(location.id) {
+            order.push(location.id);
+        }
+    }
+
+    order
+}
+
+fn write_parameter_changes(md: &mut String, quest: &Quest, changes: &[ParameterChange]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    md.push_str("\n## Parameter changes\n\n");
+
+    for change in changes {
+        let mut details = Vec::new();
+
+        match change.change_type {
+            ParameterChangeType::Value => details.push(format!("set to {}", change.change)),
+            ParameterChangeType::Sum if change.change == 0 => (),
+            ParameterChangeType::Sum => details.push(format!("{:+}", change.change)),
+            ParameterChangeType::Percentage => details.push(format!("{:+}%", change.change)),
+            ParameterChangeType::Formula => details.push(format!("`{}`", change.formula)),
+        }
+
+        match change.show_type {
+            ParameterShowType::Nothing => (),
+            ParameterShowType::Show => details.push("show".to_string()),
+            ParameterShowType::Hide => details.push("hide".to_string()),
+        }
+
+        if !change.critical_text.is_empty() {
+            details.push(format!(
+                "critical text: {}",
+                inline_text(&change.critical_text)
+            ));
+        }
+
+        if details.is_empty() {
+            continue;
+        }
+
+        writeln!(
+            md,
+            "- {}: {}",
+            parameter_name(quest, change.parameter_id),
+            details.join(", ")
+        )
+        .unwrap();
+    }
+}
+
+fn write_media(md: &mut String, media: &Media) {
+    for (name, value) in [
+        ("Image", &media.image),
+        ("Sound", &media.sound),
+        ("Track", &media.track),
+    ] {
+        if !value.is_empty() {
+            writeln!(md, "\n{name}: `{value}`").unwrap();
+        }
+    }
+}
+
+fn parameter_name(quest: &Quest, id: u32) -> String {
+    match (id as usize)
+        .checked_sub(1)
+        .and_then(|index| quest.parameters.get(index))
+    {
+        Some(parameter) => format!("`[p{id}]` {}", parameter.name),
+        None => format!("`[p{id}]`"),
+    }
+}
+
+fn max_visits(limit: &MaxVisits) -> String {
+    match limit {
+        MaxVisits::Infinite => "infinite".to_string(),
+        MaxVisits::Limit(limit) => limit.to_string(),
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn join(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {}", line.trim_end()).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn inline_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
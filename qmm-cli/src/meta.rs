@@ -0,0 +1,210 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use qmm_syntax::qmm::{JumpId, LocationId, TextSource};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{atomic_write, lints::Level};
+
+/// Quest details the binary format has no place for, kept in the
+/// `<quest>.toml` file next to the quest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuestMeta {
+    /// Shown instead of the file name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Language code, e.g. `ru`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Revision of the quest given by the author, e.g. `1.2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Changes of the revisions, the latest last
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changelog: Vec<Revision>,
+    /// Levels of the `lint` command's lints by their names
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub lints: BTreeMap<String, Level>,
+    /// Lints allowed for single objects, e.g. `J12 = ["dead-jump"]`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub allow: BTreeMap<QuestObject, Vec<String>>,
+    /// Review comments and TODOs of the objects, they don't get into the
+    /// quest
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<QuestObject, Vec<String>>,
+}
+
+/// Entry of the changelog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    pub version: String,
+    pub changes: String,
+}
+
+/// Location, jump or parameter the metadata refers to, written as `L5`, `J12`
+/// or `p3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuestObject {
+    Location(LocationId),
+    Jump(JumpId),
+    /// One-based, like `[p3]`
+    Parameter(u32),
+}
+
+impl QuestObject {
+    /// Object the text belongs to, `None` for the texts of the whole quest.
+    pub fn of_text(source: TextSource) -> Option<Self> {
+        match source {
+            TextSource::TaskText | TextSource::SuccessText => None,
+            TextSource::ParameterName(parameter)
+            | TextSource::ParameterRangeLine { parameter, .. }
+            | TextSource::ParameterCriticalText(parameter) => {
+                Some(QuestObject::Parameter(parameter))
+            }
+            TextSource::LocationText { location, .. }
+            | TextSource::LocationCriticalText { location, .. } => {
+                Some(QuestObject::Location(location))
+            }
+            TextSource::JumpText(jump)
+            | TextSource::JumpDescription(jump)
+            | TextSource::JumpCriticalText { jump, .. } => Some(QuestObject::Jump(jump)),
+        }
+    }
+}
+
+impl Display for QuestObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestObject::Location(id) => write!(f, "L{}", id.0),
+            QuestObject::Jump(id) => write!(f, "J{}", id.0),
+            QuestObject::Parameter(id) => write!(f, "p{id}"),
+        }
+    }
+}
+
+impl FromStr for QuestObject {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("`{text}` isn't an object like `L5`, `J12` or `p3`");
+        let (kind, id) = text.split_at(text.chars().next().map_or(0, char::len_utf8));
+        let id = id.parse().map_err(|_| error())?;
+
+        match kind {
+            "L" => Ok(QuestObject::Location(LocationId(id))),
+            "J" => Ok(QuestObject::Jump(JumpId(id))),
+            "p" => Ok(QuestObject::Parameter(id)),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl Serialize for QuestObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for QuestObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// `quest.qmm.toml` for `quest.qmm`.
+pub fn sidecar_path(quest: &Path) -> PathBuf {
+    let mut name = quest.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".toml");
+
+    quest.with_file_name(name)
+}
+
+impl QuestMeta {
+    /// Sets the version and adds its changes to the changelog.
+    pub fn release(&mut self, version: String, changes: Option<String>) {
+        if let Some(changes) = changes {
+            self.changelog.push(Revision {
+                version: version.clone(),
+                changes,
+            });
+        }
+
+        self.version = Some(version);
+    }
+
+    /// Details of the quest, empty if there is no sidecar file.
+    pub fn load(quest: &Path) -> Result<Self, String> {
+        let path = sidecar_path(quest);
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|err| format!("{}: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("{}: {err}", path.display())),
+        }
+    }
+
+    pub fn save(&self, quest: &Path) -> Result<(), String> {
+        let path = sidecar_path(quest);
+        let text = toml::to_string_pretty(self).map_err(|err| err.to_string())?;
+
+        atomic_write::write(&path, text.as_bytes(), false)
+            .map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
+
+impl Display for QuestMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("Name", &self.name),
+            ("Author", &self.author),
+            ("Description", &self.description),
+            ("Language", &self.language),
+            ("Version", &self.version),
+        ];
+
+        for (title, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{title}: {value}")?;
+            }
+        }
+
+        if !self.tags.is_empty() {
+            writeln!(f, "Tags: {}", self.tags.join(", "))?;
+        }
+
+        for revision in &self.changelog {
+            writeln!(f, "Changes in {}: {}", revision.version, revision.changes)?;
+        }
+
+        for (lint, level) in &self.lints {
+            writeln!(f, "Lint: {lint} = {level}")?;
+        }
+
+        for (object, lints) in &self.allow {
+            writeln!(f, "Allowed for {object}: {}", lints.join(", "))?;
+        }
+
+        for (object, notes) in &self.notes {
+            for note in notes {
+                writeln!(f, "Note on {object}: {note}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
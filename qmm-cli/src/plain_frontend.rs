@@ -0,0 +1,91 @@
+use std::io::{self, BufRead, Write};
+
+use crate::frontend::{plain_text, FrontendAction, QuestFrontend, Screen};
+
+/// Line mode frontend: prints the texts and numbered options to stdout and
+/// reads the option numbers from stdin.
+pub struct PlainFrontend {
+    turn: usize,
+}
+
+impl PlainFrontend {
+    pub fn new() -> Self {
+        Self { turn: 0 }
+    }
+}
+
+impl QuestFrontend for PlainFrontend {
+    fn render(&mut self, screen: &Screen) {
+        let mut stdout = io::stdout().lock();
+
+        if screen.turn != self.turn {
+            self.turn = screen.turn;
+
+            if let Some(notice) = screen.notice {
+                writeln!(stdout, "\n[{notice}]").unwrap();
+            }
+
+            writeln!(stdout, "\n{}\n", plain_text(&screen.text().normalize())).unwrap();
+
+            let parameters = screen.parameters();
+
+            if !parameters.is_empty() {
+                for parameter in parameters {
+                    writeln!(stdout, "  {parameter}").unwrap();
+                }
+
+                writeln!(stdout).unwrap();
+            }
+
+            for (index, option) in screen.options.iter().enumerate() {
+                writeln!(stdout, "{}. {}", index + 1, plain_text(&option.name)).unwrap();
+            }
+        }
+
+        write!(stdout, "> ").unwrap();
+        stdout.flush().unwrap();
+    }
+
+    fn read_text(&mut self, _screen: &Screen, prompt: &str, value: &str) -> Option<String> {
+        print!("{prompt} [{value}]: ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).ok()?;
+
+        let line = line.trim();
+        (!line.is_empty()).then(|| line.to_string())
+    }
+
+    fn get_action(&mut self, screen: &Screen) -> Option<FrontendAction> {
+        let mut line = String::new();
+
+        if io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 {
+            return Some(FrontendAction::Exit);
+        }
+
+        let line = line.trim();
+
+        if line.eq_ignore_ascii_case("q") {
+            return Some(FrontendAction::Exit);
+        }
+
+        if line.eq_ignore_ascii_case("s") {
+            return Some(FrontendAction::Save);
+        }
+
+        if line.eq_ignore_ascii_case("l") {
+            return Some(FrontendAction::Load);
+        }
+
+        match line.parse::<usize>() {
+            Ok(number) if (1..=screen.options.len()).contains(&number) => {
+                Some(FrontendAction::Select(number - 1))
+            }
+            _ => {
+                println!("Enter a number from 1 to {}", screen.options.len());
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::{io, ops::Deref, path::Path};
+
+/// Contents of a quest file, mapped into memory with the `mmap` feature.
+pub enum QuestData {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl QuestData {
+    #[cfg(not(feature = "mmap"))]
+    pub fn read(path: &Path) -> io::Result<Self> {
+        std::fs::read(path).map(QuestData::Owned)
+    }
+
+    #[cfg(feature = "mmap")]
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // Mapping empty files fails on some platforms
+        if file.metadata()?.len() == 0 {
+            return Ok(QuestData::Owned(Vec::new()));
+        }
+
+        // SAFETY: quest files aren't expected to change while the CLI runs,
+        // the mapping is only read and dropped before the command exits.
+        unsafe { memmap2::Mmap::map(&file) }.map(QuestData::Mapped)
+    }
+}
+
+impl Deref for QuestData {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            QuestData::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            QuestData::Mapped(data) => data,
+        }
+    }
+}
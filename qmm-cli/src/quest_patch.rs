@@ -0,0 +1,449 @@
+//! Changes between two versions of a quest as JSON, so contributors can send
+//! small patches to review instead of the binary quest.
+//!
+//! Texts and formulas are addressed by their [`TextSource`] and
+//! [`FormulaSource`] and keep the old value, a patch made against another
+//! version of the text is rejected. The other changes add, replace or remove
+//! whole parameters, locations and jumps. The header and the string
+//! replacements aren't patched.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use qmm_syntax::{
+    qmm::{
+        FormulaSource, Jump, JumpId, Location, LocationId, LocationSelectType, Parameter,
+        ParameterChange, ParameterChangeType, Quest, TextSource,
+    },
+    text::formula::Formula,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Change {
+    SetText {
+        path: TextSource,
+        old: String,
+        new: String,
+    },
+    SetFormula {
+        path: FormulaSource,
+        old: String,
+        new: String,
+    },
+    /// Parameters can only be added at the end, the formulas and the
+    /// conditions refer to them by index
+    AddParameter {
+        parameter: Parameter,
+    },
+    ReplaceParameter {
+        id: u32,
+        parameter: Parameter,
+    },
+    AddLocation {
+        location: Location,
+    },
+    ReplaceLocation {
+        location: Location,
+    },
+    /// Removes the jumps leading out of the location and into it too
+    RemoveLocation {
+        id: LocationId,
+    },
+    AddJump {
+        jump: Jump,
+    },
+    ReplaceJump {
+        jump: Jump,
+    },
+    RemoveJump {
+        id: JumpId,
+    },
+}
+
+/// Changes applied in order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuestPatch {
+    pub changes: Vec<Change>,
+}
+
+/// Objects in both versions whose texts and formulas are patched one by one.
+#[derive(Default)]
+struct Kept {
+    parameters: BTreeSet<u32>,
+    locations: BTreeSet<LocationId>,
+    jumps: BTreeSet<JumpId>,
+}
+
+impl Kept {
+    fn has_text(&self, source: TextSource) -> bool {
+        match source {
+            TextSource::TaskText | TextSource::SuccessText => true,
+            TextSource::ParameterName(id)
+            | TextSource::ParameterRangeLine { parameter: id, .. }
+            | TextSource::ParameterCriticalText(id) => self.parameters.contains(&id),
+            TextSource::LocationText { location, .. }
+            | TextSource::LocationCriticalText { location, .. } => {
+                self.locations.contains(&location)
+            }
+            TextSource::JumpText(jump)
+            | TextSource::JumpDescription(jump)
+            | TextSource::JumpCriticalText { jump, .. } => self.jumps.contains(&jump),
+        }
+    }
+
+    fn has_formula(&self, source: FormulaSource) -> bool {
+        match source {
+            FormulaSource::LocationSelection(location)
+            | FormulaSource::LocationChange { location, .. } => self.locations.contains(&location),
+            FormulaSource::JumpFormula(jump) | FormulaSource::JumpChange { jump, .. } => {
+                self.jumps.contains(&jump)
+            }
+        }
+    }
+}
+
+/// Changes turning the original quest into the edited one.
+pub fn make_patch(original: &Quest, edited: &Quest) -> Result<QuestPatch, String> {
+    if edited.parameters.len() < original.parameters.len() {
+        return Err("removing parameters can't be patched".to_string());
+    }
+
+    let mut changes = Vec::new();
+    let mut kept = Kept::default();
+
+    for (id, (old, new)) in (1..).zip(original.parameters.iter().zip(&edited.parameters)) {
+        if same_parameter(old, new) {
+            kept.parameters.insert(id);
+        } else {
+            changes.push(Change::ReplaceParameter {
+                id,
+                parameter: new.clone(),
+            });
+        }
+    }
+
+    for parameter in &edited.parameters[original.parameters.len()..] {
+        changes.push(Change::AddParameter {
+            parameter: parameter.clone(),
+        });
+    }
+
+    for jump in &original.jumps {
+        if edited.jump(jump.id).is_none() {
+            changes.push(Change::RemoveJump { id: jump.id });
+        }
+    }
+
+    for location in &original.locations {
+        if edited.location(location.id).is_none() {
+            changes.push(Change::RemoveLocation { id: location.id });
+        }
+    }
+
+    for location in &edited.locations {
+        match original.location(location.id) {
+            None => changes.push(Change::AddLocation {
+                location: location.clone(),
+            }),
+            Some(old) if same_location(old, location) => {
+                kept.locations.insert(location.id);
+            }
+            Some(_) => changes.push(Change::ReplaceLocation {
+                location: location.clone(),
+            }),
+        }
+    }
+
+    for jump in &edited.jumps {
+        match original.jump(jump.id) {
+            None => changes.push(Change::AddJump { jump: jump.clone() }),
+            Some(old) if same_jump(old, jump) => {
+                kept.jumps.insert(jump.id);
+            }
+            Some(_) => changes.push(Change::ReplaceJump { jump: jump.clone() }),
+        }
+    }
+
+    let old_texts = texts(original);
+    let new_texts = texts(edited);
+    let sources: BTreeSet<TextSource> = old_texts.keys().chain(new_texts.keys()).copied().collect();
+
+    for source in sources.into_iter().filter(|source| kept.has_text(*source)) {
+        let old = old_texts.get(&source).cloned().unwrap_or_default();
+        let new = new_texts.get(&source).cloned().unwrap_or_default();
+
+        if old != new {
+            changes.push(Change::SetText {
+                path: source,
+                old,
+                new,
+            });
+        }
+    }
+
+    let old_formulas = formulas(original);
+
+    for (source, new) in formulas(edited) {
+        let Some(old) = old_formulas.get(&source) else {
+            continue;
+        };
+
+        if *old != new && kept.has_formula(source) {
+            changes.push(Change::SetFormula {
+                path: source,
+                old: old.clone(),
+                new,
+            });
+        }
+    }
+
+    Ok(QuestPatch { changes })
+}
+
+fn texts(quest: &Quest) -> BTreeMap<TextSource, String> {
+    quest
+        .texts()
+        .into_iter()
+        .map(|text| (text.source, text.text))
+        .collect()
+}
+
+fn formulas(quest: &Quest) -> BTreeMap<FormulaSource, String> {
+    quest
+        .formulas()
+        .map(|(source, formula)| (source, formula.to_string()))
+        .collect()
+}
+
+/// The critical texts and the formulas of the changes are addressed by the
+/// parameter, so it must be changed once.
+fn copy_change_texts(old: &mut [ParameterChange], new: &[ParameterChange]) -> bool {
+    let parameters: BTreeSet<u32> = new.iter().map(|change| change.parameter_id).collect();
+
+    if old.len() != new.len() || parameters.len() != new.len() {
+        return false;
+    }
+
+    for (old, new) in old.iter_mut().zip(new) {
+        old.critical_text.clone_from(&new.critical_text);
+
+        if old.change_type == ParameterChangeType::Formula
+            && new.change_type == ParameterChangeType::Formula
+        {
+            old.formula.clone_from(&new.formula);
+        }
+    }
+
+    true
+}
+
+/// Whether the parameters only differ by their texts.
+fn same_parameter(old: &Parameter, new: &Parameter) -> bool {
+    if old.formatted_range_lines.len() != new.formatted_range_lines.len() {
+        return false;
+    }
+
+    let mut old = old.clone();
+    old.name.clone_from(&new.name);
+    old.critical_text.clone_from(&new.critical_text);
+
+    for (old, new) in old
+        .formatted_range_lines
+        .iter_mut()
+        .zip(&new.formatted_range_lines)
+    {
+        old.value.clone_from(&new.value);
+    }
+
+    old == *new
+}
+
+/// Whether the locations only differ by their texts and formulas.
+fn same_location(old: &Location, new: &Location) -> bool {
+    if old.texts.len() != new.texts.len() {
+        return false;
+    }
+
+    let mut old = old.clone();
+    old.texts.clone_from(&new.texts);
+
+    if let (LocationSelectType::ByFormula(old), LocationSelectType::ByFormula(new)) =
+        (&mut old.select_type, &new.select_type)
+    {
+        old.clone_from(new);
+    }
+
+    copy_change_texts(&mut old.parameter_changes, &new.parameter_changes) && old == *new
+}
+
+/// Whether the jumps only differ by their texts and formulas.
+fn same_jump(old: &Jump, new: &Jump) -> bool {
+    let mut old = old.clone();
+    old.text.clone_from(&new.text);
+    old.description.clone_from(&new.description);
+    old.formula.clone_from(&new.formula);
+
+    copy_change_texts(&mut old.parameter_changes, &new.parameter_changes) && old == *new
+}
+
+/// Applies the changes in order, stops at the first one that doesn't fit the
+/// quest and leaves the ones before it applied.
+pub fn apply_patch(quest: &mut Quest, patch: &QuestPatch) -> Result<(), String> {
+    for (index, change) in patch.changes.iter().enumerate() {
+        apply(quest, change).map_err(|err| format!("change {}: {err}", index + 1))?;
+    }
+
+    Ok(())
+}
+
+fn apply(quest: &mut Quest, change: &Change) -> Result<(), String> {
+    match change {
+        Change::SetText { path, old, new } => {
+            let current = quest
+                .texts()
+                .into_iter()
+                .find(|text| text.source == *path)
+                .map(|text| text.text)
+                .unwrap_or_default();
+
+            if current != *old {
+                return Err(format!("{path} was changed to `{current}`"));
+            }
+
+            if !quest.set_text(*path, new) {
+                return Err(format!("no {path}"));
+            }
+        }
+        Change::SetFormula { path, old, new } => {
+            let formula = formula_mut(quest, *path).ok_or_else(|| format!("no {path}"))?;
+            let current = formula.to_string();
+
+            if current != *old {
+                return Err(format!("{path} was changed to `{current}`"));
+            }
+
+            *formula = Formula::parse(new).map_err(|err| format!("{path}: {err}"))?;
+        }
+        Change::AddParameter { parameter } => {
+            quest.parameters.push(parameter.clone());
+            quest.header.parameters_count = quest.parameters.len();
+        }
+        Change::ReplaceParameter { id, parameter } => {
+            let target = (*id as usize)
+                .checked_sub(1)
+                .and_then(|index| quest.parameters.get_mut(index))
+                .ok_or_else(|| format!("no parameter {id}"))?;
+
+            *target = parameter.clone();
+        }
+        Change::AddLocation { location } => {
+            if quest.location(location.id).is_some() {
+                return Err(format!("location {} already exists", location.id.0));
+            }
+
+            quest.locations.push(location.clone());
+            quest.info.locations_count = quest.locations.len() as u32;
+        }
+        Change::ReplaceLocation { location } => {
+            let target = quest
+                .locations
+                .iter_mut()
+                .find(|target| target.id == location.id)
+                .ok_or_else(|| format!("no location {}", location.id.0))?;
+
+            *target = location.clone();
+        }
+        Change::RemoveLocation { id } => {
+            quest
+                .remove_location(*id)
+                .ok_or_else(|| format!("no location {}", id.0))?;
+        }
+        Change::AddJump { jump } => {
+            if quest.jump(jump.id).is_some() {
+                return Err(format!("jump {} already exists", jump.id.0));
+            }
+
+            check_jump_locations(quest, jump)?;
+            quest.jumps.push(jump.clone());
+            quest.info.jumps_count = quest.jumps.len() as u32;
+        }
+        Change::ReplaceJump { jump } => {
+            check_jump_locations(quest, jump)?;
+
+            let target = quest
+                .jumps
+                .iter_mut()
+                .find(|target| target.id == jump.id)
+                .ok_or_else(|| format!("no jump {}", jump.id.0))?;
+
+            *target = jump.clone();
+        }
+        Change::RemoveJump { id } => {
+            quest
+                .remove_jump(*id)
+                .ok_or_else(|| format!("no jump {}", id.0))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_jump_locations(quest: &Quest, jump: &Jump) -> Result<(), String> {
+    for location in [jump.from, jump.to] {
+        if quest.location(location).is_none() {
+            return Err(format!(
+                "jump {} leads to no location {}",
+                jump.id.0, location.0
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The formula at the place, the ones of the changes are found by the first
+/// formula change of the parameter.
+fn formula_mut(quest: &mut Quest, source: FormulaSource) -> Option<&mut Formula> {
+    let is_formula = |change: &&mut ParameterChange, parameter| {
+        change.parameter_id == parameter && change.change_type == ParameterChangeType::Formula
+    };
+
+    match source {
+        FormulaSource::LocationSelection(id) => {
+            let location = quest
+                .locations
+                .iter_mut()
+                .find(|location| location.id == id)?;
+
+            match &mut location.select_type {
+                LocationSelectType::ByFormula(formula) => Some(formula),
+                LocationSelectType::ByOrder => None,
+            }
+        }
+        FormulaSource::LocationChange {
+            location,
+            parameter,
+        } => quest
+            .locations
+            .iter_mut()
+            .find(|target| target.id == location)?
+            .parameter_changes
+            .iter_mut()
+            .find(|change| is_formula(change, parameter))
+            .map(|change| &mut change.formula),
+        FormulaSource::JumpFormula(id) => quest
+            .jumps
+            .iter_mut()
+            .find(|jump| jump.id == id)
+            .map(|jump| &mut jump.formula),
+        FormulaSource::JumpChange { jump, parameter } => quest
+            .jumps
+            .iter_mut()
+            .find(|target| target.id == jump)?
+            .parameter_changes
+            .iter_mut()
+            .find(|change| is_formula(change, parameter))
+            .map(|change| &mut change.formula),
+    }
+}
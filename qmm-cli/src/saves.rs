@@ -0,0 +1,99 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use qmm_player::QuestSnapshot;
+
+use crate::atomic_write;
+
+/// Identifies the quest by its contents, so the saves of different quests
+/// and versions of the quest with the same name don't mix.
+pub fn fingerprint(data: &[u8]) -> String {
+    // FNV-1a, stable between the builds unlike the std hashers
+    let hash = data.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{hash:016x}")
+}
+
+/// Directory for the application data of the current user.
+fn data_dir() -> PathBuf {
+    let var = |name| std::env::var_os(name).map(PathBuf::from);
+
+    var("XDG_DATA_HOME")
+        .or_else(|| var("APPDATA"))
+        .or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("qmm-cli")
+}
+
+/// Named save slots of a quest.
+#[derive(Debug, Clone)]
+pub struct SaveSlots {
+    dir: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(fingerprint: &str) -> Self {
+        Self {
+            dir: data_dir().join("saves").join(fingerprint),
+        }
+    }
+
+    fn path(&self, slot: &str) -> PathBuf {
+        // Keep the slot inside the directory whatever the user has typed
+        let name: String = slot
+            .chars()
+            .map(|ch| {
+                if ch.is_alphanumeric() || matches!(ch, '-' | '_' | ' ') {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.dir.join(format!("{name}.json"))
+    }
+
+    /// Names of the saved slots, sorted.
+    pub fn list(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut slots: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+
+                (path.extension()? == "json")
+                    .then(|| path.file_stem()?.to_str().map(str::to_string))
+                    .flatten()
+            })
+            .collect();
+
+        slots.sort();
+        slots
+    }
+
+    pub fn load(&self, slot: &str) -> Option<QuestSnapshot> {
+        let data = fs::read(self.path(slot)).ok()?;
+
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub fn save(&self, slot: &str, snapshot: &QuestSnapshot) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        write_json(&self.path(slot), snapshot)
+    }
+}
+
+/// Writes the snapshot through a temp file, a crash in the middle of the
+/// write keeps the previous one.
+pub fn write_json(path: &Path, snapshot: &QuestSnapshot) -> io::Result<()> {
+    let data = serde_json::to_vec(snapshot)?;
+
+    atomic_write::write(path, &data, false)
+}
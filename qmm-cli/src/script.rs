@@ -0,0 +1,280 @@
+//! Scripted playthroughs in the format of the player's walkthrough tests.
+//!
+//! One command per line, `#` starts a comment: `seed <number>`,
+//! `jump <id>`, `choose <text>`, `continue`, `acknowledge` and
+//! `ending <success|fail|death>`. The `quest` lines are ignored, the quest is
+//! given on the command line.
+
+use std::{fmt::Write, path::Path};
+
+use qmm_player::{Ending, PlayerAction, QuestPlayer, StepResult, TextCoverage};
+use qmm_syntax::qmm::{JumpId, LocationId, Quest};
+
+enum Command {
+    Jump(JumpId),
+    Choose(String),
+    Continue,
+    Acknowledge,
+}
+
+pub struct Script {
+    seed: u64,
+    commands: Vec<(usize, Command)>,
+    ending: Option<Ending>,
+}
+
+/// Parameter values after a step of the script, the step 0 is the start.
+pub struct TracePoint {
+    step: usize,
+    location: LocationId,
+    days: u32,
+    parameters: Vec<i32>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut seed = 0;
+        let mut commands = Vec::new();
+        let mut ending = None;
+
+        for (number, line) in text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if ending.is_some() {
+                return Err(format!("line {number}: command after the ending"));
+            }
+
+            let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+            let argument = argument.trim();
+
+            match name {
+                "quest" => {}
+                "seed" => {
+                    seed = argument
+                        .parse()
+                        .map_err(|_| format!("line {number}: invalid seed `{argument}`"))?
+                }
+                "jump" => {
+                    let id = argument
+                        .parse()
+                        .map_err(|_| format!("line {number}: invalid jump id `{argument}`"))?;
+
+                    commands.push((number, Command::Jump(JumpId(id))));
+                }
+                "choose" => commands.push((number, Command::Choose(argument.to_string()))),
+                "continue" => commands.push((number, Command::Continue)),
+                "acknowledge" => commands.push((number, Command::Acknowledge)),
+                "ending" => {
+                    ending = Some(match argument {
+                        "success" => Ending::Success,
+                        "fail" => Ending::Fail,
+                        "death" => Ending::Death,
+                        _ => return Err(format!("line {number}: unknown ending `{argument}`")),
+                    })
+                }
+                _ => return Err(format!("line {number}: unknown command `{name}`")),
+            }
+        }
+
+        Ok(Self {
+            seed,
+            commands,
+            ending,
+        })
+    }
+
+    /// Plays the script, returning the parameter values after every step.
+    ///
+    /// Fails on the actions the player rejects and on an ending other than
+    /// the expected one, the trace up to the failure is lost.
+    pub fn run(&self, quest: &Quest) -> Result<Vec<TracePoint>, String> {
+        let player = QuestPlayer::new(quest, self.seed)
+            .map_err(|err| format!("can't start the quest: {err:?}"))?;
+
+        let mut trace = Vec::new();
+
+        self.play(player, |step, player| {
+            trace.push(TracePoint::new(step, player))
+        })?;
+
+        Ok(trace)
+    }
+
+    /// Texts shown while playing the script, it fails like [`Self::run`].
+    pub fn coverage(&self, quest: &Quest) -> Result<TextCoverage, String> {
+        let player = QuestPlayer::builder(quest)
+            .seed(self.seed)
+            .track_coverage()
+            .build()
+            .map_err(|err| format!("can't start the quest: {err:?}"))?;
+
+        self.play(player, |_, _| ())
+            .map(|player| player.coverage().cloned().unwrap_or_default())
+    }
+
+    /// Plays the script twice and compares the states after every step, the
+    /// first step where they differ is returned with the differing parts.
+    pub fn check_determinism(
+        &self,
+        quest: &Quest,
+    ) -> Result<Option<(usize, Vec<&'static str>)>, String> {
+        let start = || {
+            QuestPlayer::new(quest, self.seed)
+                .map_err(|err| format!("can't start the quest: {err:?}"))
+        };
+        let mut first = Vec::new();
+        self.play(start()?, |_, player| first.push(player.clone()))?;
+
+        let mut divergence = None;
+        self.play(start()?, |step, player| {
+            let differences = first[step].differences(player);
+
+            if divergence.is_none() && !differences.is_empty() {
+                divergence = Some((step, differences));
+            }
+        })?;
+
+        Ok(divergence)
+    }
+
+    /// Calls `on_step` with the starting state and the state after every
+    /// action, returns the player at the end.
+    fn play<'q>(
+        &self,
+        mut player: QuestPlayer<'q>,
+        mut on_step: impl FnMut(usize, &QuestPlayer<'q>),
+    ) -> Result<QuestPlayer<'q>, String> {
+        on_step(0, &player);
+
+        for (step, (number, command)) in self.commands.iter().enumerate() {
+            let action = match command {
+                Command::Jump(id) => PlayerAction::Jump(*id),
+                Command::Choose(text) => {
+                    let jump = player
+                        .state()
+                        .jumps
+                        .iter()
+                        .find(|jump| jump.available && jump.name.to_string() == *text)
+                        .ok_or_else(|| format!("line {number}: no available jump `{text}`"))?;
+
+                    PlayerAction::Jump(jump.id)
+                }
+                Command::Continue => PlayerAction::Continue,
+                Command::Acknowledge => PlayerAction::Acknowledge,
+            };
+
+            if player.step(action) == StepResult::InvalidAction {
+                return Err(format!(
+                    "line {number}: invalid action at location {}",
+                    player.state().location.id.0
+                ));
+            }
+
+            on_step(step + 1, &player);
+        }
+
+        let Some(expected) = self.ending else {
+            return Ok(player);
+        };
+
+        let ending = match player.step(PlayerAction::DoNothing) {
+            StepResult::Success { .. } => Ending::Success,
+            StepResult::Fail => Ending::Fail,
+            StepResult::Death => Ending::Death,
+            result => return Err(format!("quest isn't finished: {result:?}")),
+        };
+
+        if ending != expected {
+            return Err(format!("expected {expected:?} ending, got {ending:?}"));
+        }
+
+        Ok(player)
+    }
+}
+
+impl TracePoint {
+    fn new(step: usize, player: &QuestPlayer) -> Self {
+        let state = player.state();
+
+        Self {
+            step,
+            location: state.location.id,
+            days: state.days_passed,
+            parameters: state.parameters.clone(),
+        }
+    }
+}
+
+/// Column names of the parameters, `p{n}` for the unnamed ones.
+fn parameter_names(quest: &Quest) -> Vec<String> {
+    quest
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| match parameter.name.trim() {
+            "" => format!("p{}", i + 1),
+            name => name.to_string(),
+        })
+        .collect()
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Parameter values per step, as JSON if the path ends with `.json` and as
+/// CSV otherwise.
+pub fn format_trace(quest: &Quest, trace: &[TracePoint], path: &Path) -> String {
+    let names = parameter_names(quest);
+
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        // The names may repeat, so the values are listed in their order
+        let steps: Vec<serde_json::Value> = trace
+            .iter()
+            .map(|point| {
+                serde_json::json!({
+                    "step": point.step,
+                    "location": point.location.0,
+                    "days": point.days,
+                    "values": point.parameters,
+                })
+            })
+            .collect();
+        let trace = serde_json::json!({ "parameters": names, "steps": steps });
+
+        return serde_json::to_string_pretty(&trace).unwrap();
+    }
+
+    let mut out = String::from("step,location,days");
+
+    for name in &names {
+        write!(out, ",{}", escape_csv(name)).unwrap();
+    }
+
+    out.push('\n');
+
+    for point in trace {
+        write!(out, "{},{},{}", point.step, point.location.0, point.days).unwrap();
+
+        for value in &point.parameters {
+            write!(out, ",{value}").unwrap();
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
@@ -0,0 +1,85 @@
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+/// External text-to-speech command reading the new texts aloud.
+///
+/// The template is split by whitespace, the `{}` arguments are replaced with
+/// the text, otherwise the text is written to the command's input. Speech
+/// errors are ignored, they must not interrupt the game.
+#[derive(Debug)]
+pub struct Speech {
+    program: String,
+    args: Vec<String>,
+    /// Text being spoken, cut short by the next one
+    child: Option<Child>,
+}
+
+impl Speech {
+    /// `None` for an empty template.
+    pub fn new(template: &str) -> Option<Self> {
+        let mut words = template.split_whitespace().map(str::to_string);
+
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+            child: None,
+        })
+    }
+
+    pub fn say(&mut self, text: &str) {
+        self.stop();
+
+        let placeholder = self.args.iter().any(|arg| arg.contains("{}"));
+        let mut command = Command::new(&self.program);
+        command
+            .args(self.args.iter().map(|arg| arg.replace("{}", text)))
+            .stdin(if placeholder {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let Ok(mut child) = command.spawn() else {
+            return;
+        };
+
+        // Long texts don't fit into the pipe, the engine reads them while
+        // speaking
+        if let Some(mut stdin) = child.stdin.take() {
+            let text = text.to_string();
+
+            thread::spawn(move || stdin.write_all(text.as_bytes()));
+        }
+
+        self.child = Some(child);
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Clone for Speech {
+    /// The copy doesn't own the text being spoken.
+    fn clone(&self) -> Self {
+        Self {
+            program: self.program.clone(),
+            args: self.args.clone(),
+            child: None,
+        }
+    }
+}
+
+impl Drop for Speech {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
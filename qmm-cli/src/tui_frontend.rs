@@ -0,0 +1,540 @@
+use std::{
+    io::{self, Stdout},
+    panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::Show,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use qmm_player::{Condition, QuestState};
+use qmm_syntax::qmm::{Location, LocationType, Quest};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    symbols::Marker,
+    text::{Span, Spans},
+    widgets::{
+        canvas::{Canvas, Line as CanvasLine},
+        Block, BorderType, Borders, Paragraph, Wrap,
+    },
+    Frame, Terminal,
+};
+
+use crate::{
+    cli_player::PlayerState,
+    clipboard,
+    frontend::{format_duration, plain_text, FormField, FrontendAction, QuestFrontend, Screen},
+    widgets::{Form, OptionList, OptionListState, ParamPanel, TextView, TextViewState},
+};
+
+/// Smallest terminal size the game can be drawn in.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 16;
+/// Terminals narrower than this show the side bar below the game.
+const WIDE_WIDTH: u16 = 80;
+/// How long to wait for the input before redrawing the screen.
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// Terminal frontend, takes over the terminal until dropped.
+pub struct TuiFrontend {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    view: View,
+}
+
+struct View {
+    text: TextViewState,
+    options: OptionListState,
+    turn: usize,
+    /// Show why the selected jump is (un)available instead of the parameters
+    debug: bool,
+    /// Show the map of the visited locations instead of the text
+    map: bool,
+    /// Result of the last key, shown in the status line until the next one
+    notice: String,
+}
+
+/// Draws the visited locations at their editor positions, connected by the
+/// passed jumps.
+fn map(frame: &mut Frame<CrosstermBackend<Stdout>>, area: Rect, quest: &Quest, state: &QuestState) {
+    // Bounds of the whole quest keep the map still while it's explored
+    let bounds = |coordinate: fn(&Location) -> i32| {
+        let min = quest.locations.iter().map(coordinate).min().unwrap_or(0) as f64;
+        let max = quest.locations.iter().map(coordinate).max().unwrap_or(0) as f64;
+
+        [min - 1.0, max + 1.0]
+    };
+    // The editor's y grows downwards, the canvas' one grows upwards
+    let position = |location: &Location| (location.x as f64, -location.y as f64);
+    let [y_min, y_max] = bounds(|location| location.y);
+
+    let canvas = Canvas::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Map: @ - you, $ - success, x - fail")
+                .border_type(BorderType::Rounded),
+        )
+        .marker(Marker::Braille)
+        .x_bounds(bounds(|location| location.x))
+        .y_bounds([-y_max, -y_min])
+        .paint(|ctx| {
+            for id in state.jump_passes.keys() {
+                let Some(jump) = quest.jump(*id) else {
+                    continue;
+                };
+                let (Some(from), Some(to)) = (quest.location(jump.from), quest.location(jump.to))
+                else {
+                    continue;
+                };
+                let ((x1, y1), (x2, y2)) = (position(from), position(to));
+
+                ctx.draw(&CanvasLine {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    color: Color::DarkGray,
+                });
+            }
+
+            ctx.layer();
+
+            for id in state.location_visits.keys() {
+                let Some(location) = quest.location(*id) else {
+                    continue;
+                };
+                let (mark, color) = match location.ty {
+                    _ if *id == state.location.id => ("@", Color::Yellow),
+                    LocationType::Success => ("$", Color::Green),
+                    LocationType::Fail | LocationType::Death => ("x", Color::Red),
+                    _ => ("o", Color::Gray),
+                };
+                let (x, y) = position(location);
+
+                ctx.print(x, y, Span::styled(mark, Style::default().fg(color)));
+            }
+        });
+
+    frame.render_widget(canvas, area);
+}
+
+/// Whether the terminal is taken over by a [`TuiFrontend`].
+static TERMINAL_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Gives the terminal back in the state it was before the game.
+pub fn restore_terminal() {
+    if !TERMINAL_TAKEN.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    disable_raw_mode().ok();
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        Show
+    )
+    .ok();
+}
+
+/// Leaves the alternate screen before the panic message is printed, so it
+/// isn't wiped together with the game and the shell isn't left in the raw
+/// mode.
+fn install_panic_hook() {
+    static PANIC_HOOK: Once = Once::new();
+
+    PANIC_HOOK.call_once(|| {
+        let hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            hook(info);
+        }));
+    });
+}
+
+/// Switches the terminal to the alternate screen and the raw mode until
+/// [`restore_terminal`] is called.
+pub fn take_terminal() -> Terminal<CrosstermBackend<Stdout>> {
+    install_panic_hook();
+    TERMINAL_TAKEN.store(true, Ordering::SeqCst);
+    enable_raw_mode().unwrap();
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+
+    Terminal::new(CrosstermBackend::new(stdout)).unwrap()
+}
+
+impl TuiFrontend {
+    pub fn new() -> Self {
+        Self {
+            terminal: take_terminal(),
+            view: View {
+                text: TextViewState::default(),
+                options: OptionListState::default(),
+                turn: 0,
+                debug: false,
+                map: false,
+                notice: String::new(),
+            },
+        }
+    }
+}
+
+impl View {
+    fn debug_lines(&self, screen: &Screen) -> Vec<Spans<'static>> {
+        let Some(id) = screen
+            .options
+            .get(self.options.selected())
+            .and_then(|option| option.jump)
+        else {
+            return Vec::new();
+        };
+
+        let parameter = |id: u32| match (id as usize)
+            .checked_sub(1)
+            .and_then(|index| screen.player.quest().parameters.get(index))
+        {
+            Some(parameter) => format!("[p{id}] {}", parameter.name),
+            None => format!("[p{id}]"),
+        };
+        let join = |values: &[i32]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut lines = vec![Spans::from(format!("Jump {}", id.0))];
+
+        for result in screen.player.explain_jump(id) {
+            let description = match &result.condition {
+                Condition::Range {
+                    parameter_id,
+                    start,
+                    end,
+                } => format!("{} in {start}..{end}", parameter(*parameter_id)),
+                Condition::Equal {
+                    parameter_id,
+                    values,
+                    must_equal,
+                } => format!(
+                    "{} {} {}",
+                    parameter(*parameter_id),
+                    if *must_equal { "=" } else { "<>" },
+                    join(values)
+                ),
+                Condition::Modulo {
+                    parameter_id,
+                    values,
+                    must_mod,
+                } => format!(
+                    "{} {}divisible by {}",
+                    parameter(*parameter_id),
+                    if *must_mod { "" } else { "not " },
+                    join(values)
+                ),
+                Condition::Formula(formula) => formula.clone(),
+            };
+            let value = result
+                .value
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| "error".to_string());
+            let (mark, color) = if result.passed {
+                ("+", Color::Green)
+            } else {
+                ("-", Color::Red)
+            };
+
+            lines.push(Spans::from(vec![
+                Span::styled(format!("{mark} "), Style::default().fg(color)),
+                Span::raw(format!("{description} ({value})")),
+            ]));
+        }
+
+        lines
+    }
+
+    fn status_line(&self, screen: &Screen) -> Spans<'static> {
+        let mut items = vec![screen.name.to_string()];
+
+        if let Some(state) = screen.quest_state() {
+            items.push(format!("Day {}", state.days_passed + 1));
+
+            if let Some(deadline) = screen.player.context().deadline {
+                let left = deadline as i64 - state.days_passed as i64;
+                items.push(format!("Days left: {left}"));
+            }
+
+            if let Some(money) = screen.money() {
+                items.push(format!("Money: {money}"));
+            }
+
+            items.push(format!("Steps: {}", screen.steps));
+            items.push(format!(
+                "Time: {}",
+                format_duration(screen.player.session_time())
+            ));
+        }
+
+        if let Some(notice) = screen.notice {
+            items.push(notice.to_string());
+        }
+
+        if !self.notice.is_empty() {
+            items.push(self.notice.clone());
+        }
+
+        Spans::from(Span::styled(
+            format!(" {}", items.join(" | ")),
+            Style::default().fg(Color::Black).bg(Color::Gray),
+        ))
+    }
+
+    fn ui(&mut self, frame: &mut Frame<CrosstermBackend<Stdout>>, screen: &Screen) {
+        let size = frame.size();
+
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            let message = Paragraph::new(vec![
+                Spans::from("The terminal is too small."),
+                Spans::from(format!(
+                    "Enlarge it to at least {MIN_WIDTH}x{MIN_HEIGHT} (now {}x{}).",
+                    size.width, size.height
+                )),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+            frame.render_widget(message, size);
+            return;
+        }
+
+        // Status bar
+        let status_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(0)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(size);
+        let size = status_layout[0];
+
+        frame.render_widget(
+            Paragraph::new(self.status_line(screen)).style(Style::default().bg(Color::Gray)),
+            status_layout[1],
+        );
+
+        // Main layout, the side bar goes below the game on narrow terminals
+        let term_layout = if size.width < WIDE_WIDTH {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .margin(0)
+                .constraints([Constraint::Min(MIN_HEIGHT - 9), Constraint::Length(8)])
+                .split(size)
+        } else {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .margin(0)
+                .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .split(size)
+        };
+
+        // Right bar layout
+        let right_bar_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(0)
+            .constraints([Constraint::Min(3), Constraint::Length(4)])
+            .split(term_layout[1]);
+
+        // Params block
+        let params_lines: Vec<Spans> = match screen.state {
+            PlayerState::InGame { .. } if self.debug => self.debug_lines(screen),
+            _ => screen.parameters().into_iter().map(Spans::from).collect(),
+        };
+        let params_title = if self.debug { "Debug" } else { "Info" };
+
+        frame.render_widget(
+            ParamPanel::new(params_title, params_lines),
+            right_bar_layout[0],
+        );
+
+        // Help block
+        let help_paragragh =
+            Paragraph::new("ESC/Q - exit, D - debug, M - map, S - save, L - load, C - copy text")
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Keys")
+                        .title_alignment(Alignment::Left)
+                        .border_type(BorderType::Double),
+                );
+
+        frame.render_widget(help_paragragh, right_bar_layout[1]);
+
+        let main_block = Block::default()
+            .title("Quest Player")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double);
+
+        frame.render_widget(main_block, term_layout[0]);
+
+        // The options take the rows they need (plus the border), but at most
+        // half of the main block
+        let options_height = (screen.options.len() as u16 + 1).min(term_layout[0].height / 2);
+        let main_layout = Layout::default()
+            .margin(1)
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(options_height)])
+            .split(term_layout[0]);
+
+        if matches!(screen.state, PlayerState::Exit) {
+            return;
+        }
+
+        match screen.quest_state() {
+            Some(state) if self.map => map(frame, main_layout[0], screen.player.quest(), state),
+            _ => frame.render_stateful_widget(
+                TextView::new(screen.text()),
+                main_layout[0],
+                &mut self.text,
+            ),
+        }
+
+        frame.render_stateful_widget(
+            OptionList::new(screen.options),
+            main_layout[1],
+            &mut self.options,
+        );
+    }
+}
+
+impl Drop for TuiFrontend {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+impl QuestFrontend for TuiFrontend {
+    fn flush_input(&mut self) {
+        while let Ok(true) = event::poll(Duration::from_millis(250)) {
+            event::read().ok();
+        }
+    }
+
+    fn render(&mut self, screen: &Screen) {
+        let view = &mut self.view;
+
+        if screen.turn != view.turn {
+            view.turn = screen.turn;
+            view.options.reset();
+        }
+
+        view.options.set_len(screen.options.len());
+
+        self.terminal.draw(|frame| view.ui(frame, screen)).unwrap();
+    }
+
+    fn read_text(&mut self, screen: &Screen, prompt: &str, value: &str) -> Option<String> {
+        let mut fields = [FormField::new(prompt, value)];
+
+        if !self.read_form(screen, prompt, &mut fields) {
+            return None;
+        }
+
+        let [field] = fields;
+        let value = field.value.trim();
+
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn read_form(&mut self, screen: &Screen, title: &str, fields: &mut [FormField]) -> bool {
+        let view = &mut self.view;
+        let mut selected = 0;
+
+        loop {
+            self.terminal
+                .draw(|frame| {
+                    view.ui(frame, screen);
+
+                    let area = Form::area(frame.size(), fields.len());
+                    frame.render_widget(Form::new(title, fields, selected), area);
+                })
+                .unwrap();
+
+            if !event::poll(TICK_RATE).unwrap() {
+                continue;
+            }
+
+            let Event::Key(key) = event::read().unwrap() else {
+                continue;
+            };
+
+            match key.code {
+                KeyCode::Up | KeyCode::BackTab => {
+                    selected = selected.checked_sub(1).unwrap_or(fields.len() - 1);
+                }
+                KeyCode::Down | KeyCode::Tab => selected = (selected + 1) % fields.len(),
+                KeyCode::Char(ch) => fields[selected].value.push(ch),
+                KeyCode::Backspace => {
+                    fields[selected].value.pop();
+                }
+                KeyCode::Enter => return true,
+                KeyCode::Esc => return false,
+                _ => (),
+            }
+        }
+    }
+
+    fn get_action(&mut self, screen: &Screen) -> Option<FrontendAction> {
+        if !event::poll(TICK_RATE).unwrap() {
+            return Some(FrontendAction::Tick);
+        }
+
+        let key = match event::read().unwrap() {
+            Event::Key(key) => key,
+            Event::Resize(..) => {
+                self.terminal.autoresize().unwrap();
+                return None;
+            }
+            _ => return None,
+        };
+
+        let view = &mut self.view;
+        view.options.set_len(screen.options.len());
+        view.notice.clear();
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('Q') => return Some(FrontendAction::Exit),
+            KeyCode::Char('D') | KeyCode::Char('d') => view.debug = !view.debug,
+            KeyCode::Char('M') | KeyCode::Char('m') => view.map = !view.map,
+            KeyCode::Char('S') | KeyCode::Char('s') => return Some(FrontendAction::Save),
+            KeyCode::Char('L') | KeyCode::Char('l') => return Some(FrontendAction::Load),
+            KeyCode::Char('C') | KeyCode::Char('c') => {
+                view.notice = match clipboard::copy(&plain_text(&screen.text())) {
+                    Ok(()) => "Text copied".to_string(),
+                    Err(err) => err,
+                };
+            }
+            KeyCode::Up => view.options.previous(),
+            KeyCode::Down => view.options.next(),
+            KeyCode::Home => view.options.first(),
+            KeyCode::End => view.options.last_option(),
+            KeyCode::PageUp => view.options.page_up(),
+            KeyCode::PageDown => view.options.page_down(),
+            KeyCode::Enter => return Some(FrontendAction::Select(view.options.selected())),
+            _ => (),
+        }
+
+        None
+    }
+}
@@ -0,0 +1,150 @@
+//! Notifications about the changed quests of a file or a directory.
+
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Editors write a file in several steps, the changes made this close to
+/// each other are reported once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+fn is_quest(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("qmm"))
+}
+
+/// The quest a changed file belongs to, the metadata sidecars included.
+fn quest_of(path: &Path) -> Option<PathBuf> {
+    if is_quest(path) {
+        return Some(path.to_path_buf());
+    }
+
+    let quest = path.to_str()?.strip_suffix(".toml")?;
+
+    is_quest(Path::new(quest)).then(|| PathBuf::from(quest))
+}
+
+/// Quests in the directory and its subdirectories, sorted by path.
+pub fn quests(dir: &Path) -> Vec<PathBuf> {
+    let mut quests = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(current) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else if is_quest(&path) {
+                quests.push(path);
+            }
+        }
+    }
+
+    quests.sort();
+    quests
+}
+
+/// Watches a quest file or a directory of quests until dropped.
+#[derive(Debug)]
+pub struct QuestWatcher {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// The only quest reported when a file is watched
+    file: Option<PathBuf>,
+}
+
+impl QuestWatcher {
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = recommended_watcher(sender)?;
+        let file = path.is_file().then(|| path.to_path_buf());
+
+        // Editors often replace the file instead of writing into it, so the
+        // directory of a watched file is watched too
+        match &file {
+            Some(file) => {
+                let dir = file
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            }
+            None => watcher.watch(path, RecursiveMode::Recursive)?,
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            file,
+        })
+    }
+
+    fn changed_quests(&self, event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+        let Ok(event) = event else {
+            return;
+        };
+
+        if event.kind.is_access() {
+            return;
+        }
+
+        for quest in event.paths.iter().filter_map(|path| quest_of(path)) {
+            match &self.file {
+                Some(file) if !same_file(file, &quest) => (),
+                Some(file) => {
+                    changed.insert(file.clone());
+                }
+                None => {
+                    changed.insert(quest);
+                }
+            }
+        }
+    }
+
+    /// Waits for the next changes, `None` once the watcher stops.
+    pub fn wait(&self) -> Option<BTreeSet<PathBuf>> {
+        let mut changed = BTreeSet::new();
+
+        while changed.is_empty() {
+            let event = self.events.recv().ok()?;
+            self.changed_quests(event, &mut changed);
+
+            while let Ok(event) = self.events.recv_timeout(DEBOUNCE) {
+                self.changed_quests(event, &mut changed);
+            }
+        }
+
+        Some(changed)
+    }
+
+    /// Changes made since the last call, without waiting.
+    pub fn poll(&self) -> BTreeSet<PathBuf> {
+        let mut changed = BTreeSet::new();
+
+        while let Ok(event) = self.events.try_recv() {
+            self.changed_quests(event, &mut changed);
+        }
+
+        changed
+    }
+}
+
+/// The watcher reports absolute paths, the user may give relative ones.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.file_name() == b.file_name(),
+    }
+}
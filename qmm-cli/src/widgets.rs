@@ -0,0 +1,261 @@
+use qmm_syntax::text::{
+    formatted_text::FormattedText,
+    terminal::{to_ratatui_text, TextStyles},
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget, Wrap,
+    },
+};
+
+use crate::{cli_player::OptionControl, frontend::FormField};
+
+fn conv_formatted_text(text: FormattedText) -> Text<'static> {
+    to_ratatui_text(&text.normalize(), &TextStyles::default())
+}
+
+/// Main text of the game.
+pub struct TextView {
+    text: FormattedText,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextViewState {
+    /// Lines scrolled from the top
+    pub scroll: u16,
+}
+
+impl TextView {
+    pub fn new(text: FormattedText) -> Self {
+        Self { text }
+    }
+}
+
+impl StatefulWidget for TextView {
+    type State = TextViewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        Paragraph::new(conv_formatted_text(self.text))
+            .wrap(Wrap { trim: true })
+            .scroll((state.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+/// Options of the current turn, the selected one is highlighted.
+pub struct OptionList<'a> {
+    options: &'a [OptionControl],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OptionListState {
+    selected: usize,
+    len: usize,
+    /// Options that fit on the screen, used for paging
+    rows: usize,
+    /// Scroll position of the options
+    list: ListState,
+}
+
+impl<'a> OptionList<'a> {
+    pub fn new(options: &'a [OptionControl]) -> Self {
+        Self { options }
+    }
+}
+
+impl OptionListState {
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the first option of the new turn.
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+
+    /// Keeps the selection within the options.
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.selected = self.selected.min(self.last());
+    }
+
+    fn last(&self) -> usize {
+        self.len.saturating_sub(1)
+    }
+
+    /// Selects the previous option, the last one after the first.
+    pub fn previous(&mut self) {
+        self.selected = self.selected.checked_sub(1).unwrap_or(self.last());
+    }
+
+    /// Selects the next option, the first one after the last.
+    pub fn next(&mut self) {
+        self.selected = if self.selected < self.last() {
+            self.selected + 1
+        } else {
+            0
+        };
+    }
+
+    pub fn first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn last_option(&mut self) {
+        self.selected = self.last();
+    }
+
+    pub fn page_up(&mut self) {
+        self.selected = self.selected.saturating_sub(self.rows.max(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.selected = (self.selected + self.rows.max(1)).min(self.last());
+    }
+}
+
+impl StatefulWidget for OptionList<'_> {
+    type State = OptionListState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let items: Vec<ListItem> = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(idx, option)| {
+                let selected = state.selected == idx;
+                let prefix = if selected { "> " } else { "  " };
+                let option_name = FormattedText::from(prefix).concat(option.name.clone());
+
+                let style = if selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+
+                let mut text = conv_formatted_text(option_name);
+                text.patch_style(style);
+
+                ListItem::new(text)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::TOP)
+                .border_type(BorderType::Rounded),
+        );
+
+        state.rows = (area.height as usize).saturating_sub(1).max(1);
+        state.list.select(Some(state.selected));
+
+        StatefulWidget::render(list, area, buf, &mut state.list);
+    }
+}
+
+/// Side panel with the parameters or the debug info.
+pub struct ParamPanel<'a> {
+    title: &'a str,
+    lines: Vec<Spans<'a>>,
+}
+
+impl<'a> ParamPanel<'a> {
+    pub fn new(title: &'a str, lines: Vec<Spans<'a>>) -> Self {
+        Self { title, lines }
+    }
+}
+
+impl Widget for ParamPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.title)
+            .title_alignment(Alignment::Left)
+            .border_type(BorderType::Double);
+
+        Paragraph::new(self.lines)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
+/// Fields edited by the user, drawn over the game.
+pub struct Form<'a> {
+    title: &'a str,
+    fields: &'a [FormField],
+    selected: usize,
+}
+
+impl<'a> Form<'a> {
+    pub fn new(title: &'a str, fields: &'a [FormField], selected: usize) -> Self {
+        Self {
+            title,
+            fields,
+            selected,
+        }
+    }
+
+    /// Area in the middle of the screen.
+    pub fn area(screen: Rect, fields: usize) -> Rect {
+        let width = screen.width.min(60);
+        let height = screen.height.min(fields as u16 + 2);
+
+        Rect::new(
+            screen.x + (screen.width - width) / 2,
+            screen.y + (screen.height - height) / 2,
+            width,
+            height,
+        )
+    }
+}
+
+impl Widget for Form<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{} (Enter - save, ESC - cancel)", self.title))
+            .border_type(BorderType::Rounded);
+        let label_width = self
+            .fields
+            .iter()
+            .map(|field| field.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        // The end of the long values stays visible
+        let value_width = (area.width as usize).saturating_sub(label_width + 5);
+
+        let lines: Vec<Spans> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                let selected = idx == self.selected;
+                let skip = field.value.chars().count().saturating_sub(value_width);
+                let value: String = field.value.chars().skip(skip).collect();
+                let style = if selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+
+                Spans::from(Span::styled(
+                    format!(
+                        "{:label_width$}: {value}{}",
+                        field.label,
+                        if selected { "_" } else { "" }
+                    ),
+                    style,
+                ))
+            })
+            .collect();
+
+        Clear.render(area, buf);
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
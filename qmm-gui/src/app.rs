@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use eframe::egui::{
+    self, text::LayoutJob, Color32, ColorImage, FontId, RichText, TextFormat, TextureHandle,
+};
+use qmm_player::{PlayerAction, Progress, QuestPlayer, StepResult};
+use qmm_syntax::{
+    qmm::Quest,
+    text::formatted_text::{FormattedText, TextElement, TextElementKind},
+};
+
+const SAVE_SLOTS: usize = 3;
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "bmp"];
+
+enum Action {
+    Start,
+    NewGame,
+    Step(PlayerAction),
+    Save(usize),
+    Load(usize),
+}
+
+pub struct QuestApp<'q> {
+    quest: &'q Quest,
+    player: QuestPlayer<'q>,
+    started: bool,
+    assets: Option<PathBuf>,
+    /// Loaded images by their names, `None` if the image wasn't found
+    textures: HashMap<String, Option<TextureHandle>>,
+    slots: [Option<(QuestPlayer<'q>, bool)>; SAVE_SLOTS],
+}
+
+fn new_player(quest: &Quest) -> QuestPlayer<'_> {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+
+    QuestPlayer::new(quest, seed).unwrap()
+}
+
+fn layout_job(text: &FormattedText, color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    append_elements(&mut job, &text.clone().normalize().elements, color);
+
+    job
+}
+
+fn append_elements(job: &mut LayoutJob, elements: &[TextElement], color: Color32) {
+    let format = |color| TextFormat {
+        font_id: FontId::proportional(16.0),
+        color,
+        ..Default::default()
+    };
+
+    for el in elements {
+        match &el.kind {
+            TextElementKind::NewLine => job.append("\n", 0.0, format(color)),
+            TextElementKind::Selection { text } => {
+                append_elements(job, &text.elements, Color32::LIGHT_BLUE)
+            }
+            TextElementKind::Variable { .. } => {
+                job.append(&el.value, 0.0, format(Color32::LIGHT_BLUE))
+            }
+            _ => job.append(&el.value, 0.0, format(color)),
+        }
+    }
+}
+
+impl<'q> QuestApp<'q> {
+    pub fn new(quest: &'q Quest, assets: Option<PathBuf>) -> Self {
+        Self {
+            quest,
+            player: new_player(quest),
+            started: false,
+            assets,
+            textures: HashMap::new(),
+            slots: Default::default(),
+        }
+    }
+
+    fn texture(&mut self, ctx: &egui::Context, name: &str) -> Option<TextureHandle> {
+        let assets = self.assets.as_ref()?;
+
+        self.textures
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let image = IMAGE_EXTENSIONS
+                    .iter()
+                    .find_map(|extension| {
+                        image::open(assets.join(name).with_extension(extension)).ok()
+                    })?
+                    .to_rgba8();
+                let size = [image.width() as usize, image.height() as usize];
+                let image = ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+
+                Some(ctx.load_texture(name, image, Default::default()))
+            })
+            .clone()
+    }
+
+    fn location_image(&self) -> Option<String> {
+        self.quest
+            .location(self.player.state().location.id)?
+            .media
+            .iter()
+            .find(|media| !media.image.is_empty())
+            .map(|media| media.image.clone())
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Start => self.started = true,
+            Action::NewGame => {
+                self.player = new_player(self.quest);
+                self.started = false;
+            }
+            Action::Step(action) => {
+                self.player.step(action);
+            }
+            Action::Save(slot) => self.slots[slot] = Some((self.player.clone(), self.started)),
+            Action::Load(slot) => {
+                if let Some((player, started)) = &self.slots[slot] {
+                    self.player = player.clone();
+                    self.started = *started;
+                }
+            }
+        }
+    }
+
+    fn menu(&self, ui: &mut egui::Ui, actions: &mut Vec<Action>) {
+        ui.horizontal(|ui| {
+            if ui.button("New game").clicked() {
+                actions.push(Action::NewGame);
+            }
+
+            ui.separator();
+
+            for slot in 0..SAVE_SLOTS {
+                if ui.button(format!("Save {}", slot + 1)).clicked() {
+                    actions.push(Action::Save(slot));
+                }
+
+                if ui
+                    .add_enabled(
+                        self.slots[slot].is_some(),
+                        egui::Button::new(format!("Load {}", slot + 1)),
+                    )
+                    .clicked()
+                {
+                    actions.push(Action::Load(slot));
+                }
+            }
+        });
+    }
+
+    fn parameters(&self, ui: &mut egui::Ui) {
+        ui.heading("Parameters");
+
+        if !self.started {
+            return;
+        }
+
+        let color = ui.visuals().text_color();
+
+        if let Progress::Finished(_) = self.player.state().progress {
+            for text in self
+                .quest
+                .parameters
+                .iter()
+                .zip(&self.player.state().parameters)
+                .filter(|(parameter, _)| parameter.is_active)
+                .filter_map(|(parameter, value)| self.player.parameter_text(parameter, *value))
+            {
+                ui.label(layout_job(&text, color));
+            }
+        } else {
+            for text in self.player.parameter_texts() {
+                ui.label(layout_job(&text, color));
+            }
+        }
+    }
+
+    fn game(&mut self, ui: &mut egui::Ui, actions: &mut Vec<Action>) {
+        let color = ui.visuals().text_color();
+
+        if !self.started {
+            ui.label(layout_job(self.player.task_text(), color));
+            ui.separator();
+
+            if ui.button("Start").clicked() {
+                actions.push(Action::Start);
+            }
+
+            return;
+        }
+
+        if let Some(name) = self.location_image() {
+            if let Some(texture) = self.texture(ui.ctx(), &name) {
+                ui.add(egui::Image::from_texture(&texture).max_height(300.0));
+            }
+        }
+
+        let state = self.player.state().clone();
+
+        match &state.progress {
+            Progress::Playing => {
+                ui.label(layout_job(&state.location.description, color));
+                ui.separator();
+
+                for jump in &state.jumps {
+                    let button = egui::Button::new(layout_job(&jump.name, color));
+
+                    if ui.add_enabled(jump.available, button).clicked() {
+                        actions.push(Action::Step(PlayerAction::Jump(jump.id)));
+                    }
+                }
+            }
+            Progress::Description { text, .. } => {
+                ui.label(layout_job(text, color));
+                ui.separator();
+
+                if ui.button("Continue").clicked() {
+                    actions.push(Action::Step(PlayerAction::Continue));
+                }
+            }
+            Progress::Message { text, .. } => {
+                ui.label(layout_job(text, color));
+                ui.separator();
+
+                if ui.button("Continue").clicked() {
+                    actions.push(Action::Step(PlayerAction::Acknowledge));
+                }
+            }
+            Progress::Finished(_) => {
+                match self.player.step(PlayerAction::DoNothing) {
+                    StepResult::Success { text, reward, days } => {
+                        ui.label(layout_job(&text, color));
+                        ui.label(
+                            RichText::new(format!("Reward: {reward} cr, days: {days}")).strong(),
+                        );
+                    }
+                    _ => {
+                        ui.label(layout_job(&state.location.description, color));
+                    }
+                }
+
+                ui.separator();
+
+                if ui.button("New game").clicked() {
+                    actions.push(Action::NewGame);
+                }
+            }
+        }
+    }
+}
+
+impl eframe::App for QuestApp<'_> {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut actions = Vec::new();
+
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| self.menu(ui, &mut actions));
+        egui::SidePanel::right("parameters")
+            .min_width(200.0)
+            .show(ctx, |ui| self.parameters(ui));
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.game(ui, &mut actions));
+        });
+
+        for action in actions {
+            self.apply(action);
+        }
+    }
+}
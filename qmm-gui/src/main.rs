@@ -0,0 +1,43 @@
+mod app;
+
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use qmm_syntax::qmm::{default_codecs, parse_qmm, unwrap_container};
+
+use crate::app::QuestApp;
+
+#[derive(Debug, Clone, Parser)]
+struct Cli {
+    /// Path to a quest file (.qmm)
+    pub quest: PathBuf,
+    /// Directory with the quest images
+    #[arg(long)]
+    pub assets: Option<PathBuf>,
+}
+
+fn main() -> eframe::Result {
+    let args = Cli::parse();
+
+    let quest_data = fs::read(&args.quest).unwrap();
+    let quest_data = match unwrap_container(&quest_data, &default_codecs()) {
+        Ok(data) => data,
+        Err(err) => {
+            println!("Got error: {err}");
+            return Ok(());
+        }
+    };
+    let quest = match parse_qmm(&quest_data) {
+        Ok(quest) => quest,
+        Err(err) => {
+            println!("Got error: {err}\n{err:#?}");
+            return Ok(());
+        }
+    };
+
+    eframe::run_native(
+        "Quest Player",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(QuestApp::new(&quest, args.assets)))),
+    )
+}
@@ -0,0 +1,156 @@
+//! Plays many sessions of one quest at once with random actions, like a
+//! server hosting them, and reports the throughput and the memory.
+//!
+//! `cargo run --release --example stress -- <quest.qmm> [sessions] [steps]
+//! [threads]`, the bundled test quest by default. The sessions borrow the
+//! quest shared by the threads, every thread steps its sessions in turns and
+//! a finished session starts over with another seed.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    env, fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+use fastrand::Rng;
+use qmm_player::{PlayerAction, Progress, QuestPlayer};
+use qmm_syntax::qmm::{parse_qmm, Quest};
+
+/// Counts the allocated bytes.
+struct Counter;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+struct Session<'q> {
+    player: QuestPlayer<'q>,
+    rng: Rng,
+}
+
+impl<'q> Session<'q> {
+    fn new(quest: &'q Quest, seed: u64) -> Self {
+        Self {
+            player: QuestPlayer::new(quest, seed).expect("the quest can't be started"),
+            rng: Rng::with_seed(seed),
+        }
+    }
+
+    /// Takes a random available jump, `false` once the quest is over.
+    fn step(&mut self) -> bool {
+        let state = self.player.state();
+        let action = match &state.progress {
+            Progress::Playing => {
+                let jumps: Vec<_> = state.jumps.iter().filter(|jump| jump.available).collect();
+
+                if jumps.is_empty() {
+                    return false;
+                }
+
+                PlayerAction::Jump(jumps[self.rng.usize(..jumps.len())].id)
+            }
+            Progress::Description { .. } => PlayerAction::Continue,
+            Progress::Message { .. } => PlayerAction::Acknowledge,
+            Progress::Finished(_) => return false,
+        };
+
+        self.player.step(action);
+
+        true
+    }
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| "../qmm-syntax/test.qmm".to_string());
+    let mut number = |default| {
+        args.next()
+            .map_or(default, |arg| arg.parse().expect("not a number"))
+    };
+    let sessions = number(10_000);
+    let steps = number(100);
+    let threads = number(thread::available_parallelism().map_or(4, |threads| threads.get()));
+
+    let data = fs::read(&path).unwrap_or_else(|err| panic!("{path}: {err}"));
+    let quest = Arc::new(parse_qmm(&data).expect("the quest can't be read"));
+    drop(data);
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    // The threads get their sessions ready to measure them at once
+    let groups: Vec<Vec<Session>> = (0..threads)
+        .map(|thread| {
+            (thread..sessions)
+                .step_by(threads)
+                .map(|seed| Session::new(&quest, seed as u64))
+                .collect()
+        })
+        .collect();
+    let sessions_memory = ALLOCATED.load(Ordering::Relaxed) - baseline;
+    let restarts = AtomicUsize::new(0);
+    let started = Instant::now();
+
+    thread::scope(|scope| {
+        for (thread, mut group) in groups.into_iter().enumerate() {
+            let (quest, restarts) = (&quest, &restarts);
+
+            scope.spawn(move || {
+                let mut next_seed = (sessions + thread) as u64;
+
+                for _ in 0..steps {
+                    for session in &mut group {
+                        if !session.step() {
+                            *session = Session::new(quest, next_seed);
+                            next_seed += threads as u64;
+                            restarts.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let elapsed = started.elapsed();
+    let total_steps = sessions * steps;
+
+    println!("{sessions} sessions, {steps} steps each, {threads} threads");
+    println!(
+        "{total_steps} steps in {:.2?}, {:.0} steps/s, {} sessions finished and restarted",
+        elapsed,
+        total_steps as f64 / elapsed.as_secs_f64(),
+        restarts.load(Ordering::Relaxed)
+    );
+    println!(
+        "quest {:.1} MiB, sessions {:.1} MiB ({:.1} KiB each), peak {:.1} MiB",
+        mib(baseline),
+        mib(sessions_memory),
+        sessions_memory as f64 / sessions.max(1) as f64 / 1024.0,
+        mib(PEAK.load(Ordering::Relaxed))
+    );
+}
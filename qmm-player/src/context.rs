@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+const MONTHS: [&str; 12] = [
+    "Января",
+    "Февраля",
+    "Марта",
+    "Апреля",
+    "Мая",
+    "Июня",
+    "Июля",
+    "Августа",
+    "Сентября",
+    "Октября",
+    "Ноября",
+    "Декабря",
+];
+
+/// Date in the in-game calendar, shown like `15 Марта 3300`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QuestDate {
+    pub year: i32,
+    /// From 1 to 12
+    pub month: u8,
+    /// From 1 to 31
+    pub day: u8,
+}
+
+impl QuestDate {
+    pub fn new(year: i32, month: u8, day: u8) -> Self {
+        Self { year, month, day }
+    }
+
+    /// Date the number of days later.
+    pub fn add_days(self, days: u32) -> Self {
+        Self::from_days(self.to_days() + days as i64)
+    }
+
+    /// Days since 1970-01-01 in the proleptic Gregorian calendar.
+    fn to_days(self) -> i64 {
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146097 + day_of_era - 719468
+    }
+
+    fn from_days(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+}
+
+impl Display for QuestDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let month = MONTHS
+            .get((self.month as usize).wrapping_sub(1))
+            .copied()
+            .unwrap_or("?");
+
+        write!(f, "{} {month} {}", self.day, self.year)
+    }
+}
+
+/// Grouping of the digits in `<Money>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoneyFormat {
+    /// `10000`, like the original game
+    #[default]
+    Plain,
+    /// `10 000` with a no-break space, the four-digit numbers aren't grouped
+    Russian,
+    /// `10,000`
+    English,
+}
+
+impl MoneyFormat {
+    pub fn format(self, value: u32) -> String {
+        let digits = value.to_string();
+        let (separator, min_len) = match self {
+            MoneyFormat::Plain => return digits,
+            MoneyFormat::Russian => ('\u{a0}', 5),
+            MoneyFormat::English => (',', 4),
+        };
+
+        if digits.len() < min_len {
+            return digits;
+        }
+
+        let mut text = String::with_capacity(digits.len() * 2);
+
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                text.push(separator);
+            }
+
+            text.push(digit);
+        }
+
+        text
+    }
+}
+
+/// Values substituted for the `<Ranger>`, `<ToStar>`, `<Money>` and other
+/// text variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerContext {
+    pub ranger: String,
+    pub to_star: String,
+    pub to_planet: String,
+    pub from_star: String,
+    pub from_planet: String,
+    /// `<Date>` if there is no start date
+    pub date: String,
+    /// `<Day>` if there is no deadline
+    pub day: String,
+    /// Reward for completing the quest
+    pub money: u32,
+    pub money_format: MoneyFormat,
+    /// Day the quest was taken, `<Date>` shows the deadline date counted from
+    /// it
+    pub start_date: Option<QuestDate>,
+    /// Days given to complete the quest, `<Day>` shows how many of them are
+    /// left
+    pub deadline: Option<u32>,
+}
+
+impl Default for PlayerContext {
+    fn default() -> Self {
+        Self {
+            ranger: "Греф".to_string(),
+            to_star: "Процион".to_string(),
+            to_planet: "Боннасис".to_string(),
+            from_star: "Солнечная".to_string(),
+            from_planet: "Земля".to_string(),
+            date: "15 Марта 3300".to_string(),
+            day: "15 Марта".to_string(),
+            money: 10000,
+            money_format: MoneyFormat::default(),
+            start_date: None,
+            deadline: None,
+        }
+    }
+}
+
+impl PlayerContext {
+    pub(crate) fn variables(&self) -> BTreeMap<String, String> {
+        let mut map = BTreeMap::new();
+
+        map.insert("<ToStar>".to_string(), self.to_star.clone());
+        map.insert("<ToPlanet>".to_string(), self.to_planet.clone());
+        map.insert("<FromStar>".to_string(), self.from_star.clone());
+        map.insert("<FromPlanet>".to_string(), self.from_planet.clone());
+        map.insert("<Ranger>".to_string(), self.ranger.clone());
+        map.insert("<Date>".to_string(), self.deadline_date());
+        map.insert("<Day>".to_string(), self.day.clone());
+        map.insert("<Money>".to_string(), self.money_format.format(self.money));
+
+        map
+    }
+
+    fn deadline_date(&self) -> String {
+        match (self.start_date, self.deadline) {
+            (Some(start), deadline) => start.add_days(deadline.unwrap_or(0)).to_string(),
+            (None, _) => self.date.clone(),
+        }
+    }
+
+    /// `<Day>` after the days passed in the quest.
+    pub(crate) fn days_left(&self, days_passed: u32) -> Option<String> {
+        self.deadline
+            .map(|deadline| deadline.saturating_sub(days_passed).to_string())
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use qmm_syntax::{
+    qmm::{LocationId, TextSource},
+    text::formatted_text::{FormattedText, TextElement, TextElementKind},
+};
+
+/// Texts the player has shown, collected when enabled by
+/// [`crate::QuestPlayerBuilder::track_coverage`] to find the content no
+/// playthrough has reached.
+///
+/// Only the location texts, the jump names and the jump descriptions are
+/// counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextCoverage {
+    /// How many times the location showed its text at the index
+    pub location_texts: BTreeMap<(LocationId, usize), u32>,
+    /// Values the `{...}` formulas of the texts evaluated to and how many
+    /// times each was shown, by the formula text
+    pub text_formulas: BTreeMap<(TextSource, String), BTreeMap<String, u32>>,
+}
+
+impl TextCoverage {
+    /// Adds the counts of another session.
+    pub fn merge(&mut self, other: &TextCoverage) {
+        for (key, count) in &other.location_texts {
+            *self.location_texts.entry(*key).or_default() += count;
+        }
+
+        for (key, values) in &other.text_formulas {
+            let shown = self.text_formulas.entry(key.clone()).or_default();
+
+            for (value, count) in values {
+                *shown.entry(value.clone()).or_default() += count;
+            }
+        }
+    }
+
+    /// Indices of the texts shown in the location.
+    pub fn shown_texts(&self, location: LocationId) -> BTreeSet<usize> {
+        self.location_texts
+            .range((location, 0)..=(location, usize::MAX))
+            .map(|((_, index), _)| *index)
+            .collect()
+    }
+
+    pub(crate) fn record_location_text(&mut self, location: LocationId, index: usize) {
+        *self.location_texts.entry((location, index)).or_default() += 1;
+    }
+
+    /// Records the values of the formulas in the formatted text.
+    pub(crate) fn record_text(&mut self, source: TextSource, text: &FormattedText) {
+        self.record_elements(source, &text.elements);
+    }
+
+    fn record_elements(&mut self, source: TextSource, elements: &[TextElement]) {
+        for el in elements {
+            match &el.kind {
+                TextElementKind::Selection { text } => self.record_elements(source, &text.elements),
+                TextElementKind::Formula { text } => {
+                    *self
+                        .text_formulas
+                        .entry((source, text.clone()))
+                        .or_default()
+                        .entry(el.value.clone())
+                        .or_default() += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+
+use qmm_syntax::qmm::{JumpId, LocationId, Quest};
+
+use crate::{
+    Ending, PlayerAction, Progress, QuestError, QuestPlayer, QuestPlayerBuilder, StepResult,
+};
+
+/// Rewards given by [`QuestEnv::step`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvRewards {
+    pub success: f32,
+    pub fail: f32,
+    pub death: f32,
+    /// Given for every step that doesn't end the quest
+    pub step: f32,
+}
+
+impl Default for EnvRewards {
+    fn default() -> Self {
+        Self {
+            success: 1.0,
+            fail: -1.0,
+            death: -1.0,
+            step: 0.0,
+        }
+    }
+}
+
+/// State of the quest as numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    /// Parameter values, then the one-hot of the location in the order of
+    /// [`Quest::locations`], then 1 if a jump description or a message is
+    /// shown
+    pub features: Vec<f32>,
+    /// Actions that can be taken, see [`QuestEnv::step`]
+    pub action_mask: Vec<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvStep {
+    pub observation: Observation,
+    pub reward: f32,
+    /// The quest has ended or the player stopped it with an
+    /// [`crate::EngineError`], [`QuestEnv::reset`] starts it again
+    pub done: bool,
+    pub result: StepResult,
+}
+
+/// Quest as a reinforcement learning environment with a fixed action space:
+/// action 0 proceeds from the jump description or the message, action `n`
+/// takes the `n`-th jump of [`Quest::jumps`].
+#[derive(Debug, Clone)]
+pub struct QuestEnv<'q> {
+    builder: QuestPlayerBuilder<'q>,
+    player: QuestPlayer<'q>,
+    rewards: EnvRewards,
+    locations: BTreeMap<LocationId, usize>,
+    jumps: BTreeMap<JumpId, usize>,
+}
+
+impl<'q> QuestEnv<'q> {
+    pub fn new(builder: QuestPlayerBuilder<'q>) -> Result<Self, QuestError> {
+        let player = builder.clone().build()?;
+        let quest = player.quest();
+        let locations = quest
+            .locations
+            .iter()
+            .enumerate()
+            .map(|(idx, location)| (location.id, idx))
+            .collect();
+        let jumps = quest
+            .jumps
+            .iter()
+            .enumerate()
+            .map(|(idx, jump)| (jump.id, idx + 1))
+            .collect();
+
+        Ok(Self {
+            builder,
+            player,
+            rewards: EnvRewards::default(),
+            locations,
+            jumps,
+        })
+    }
+
+    pub fn rewards(mut self, rewards: EnvRewards) -> Self {
+        self.rewards = rewards;
+        self
+    }
+
+    pub fn player(&self) -> &QuestPlayer<'q> {
+        &self.player
+    }
+
+    pub fn quest(&self) -> &'q Quest {
+        self.player.quest
+    }
+
+    /// Length of [`Observation::features`].
+    pub fn observation_len(&self) -> usize {
+        self.quest().parameters.len() + self.locations.len() + 1
+    }
+
+    /// Length of [`Observation::action_mask`].
+    pub fn action_count(&self) -> usize {
+        self.jumps.len() + 1
+    }
+
+    /// Starts the quest again with another seed.
+    pub fn reset(&mut self, seed: u64) -> Result<Observation, QuestError> {
+        self.player = self.builder.clone().seed(seed).build()?;
+
+        Ok(self.observation())
+    }
+
+    /// Takes the action, the masked out ones return
+    /// [`StepResult::InvalidAction`] and change nothing. The ending is only
+    /// rewarded by the step into it, the steps after it give nothing.
+    pub fn step(&mut self, action: usize) -> EnvStep {
+        let finished = matches!(self.player.state.progress, Progress::Finished(_));
+        let result = match self.player_action(action) {
+            Some(action) => self.player.step(action),
+            None => StepResult::InvalidAction,
+        };
+        let (reward, done) = match self.player.state.progress {
+            Progress::Finished(_) if finished => (0.0, true),
+            Progress::Finished(Ending::Success) => (self.rewards.success, true),
+            Progress::Finished(Ending::Fail) => (self.rewards.fail, true),
+            Progress::Finished(Ending::Death) => (self.rewards.death, true),
+            _ => (
+                self.rewards.step,
+                matches!(result, StepResult::EngineError(_)),
+            ),
+        };
+
+        EnvStep {
+            observation: self.observation(),
+            reward,
+            done,
+            result,
+        }
+    }
+
+    fn player_action(&self, action: usize) -> Option<PlayerAction> {
+        match (&self.player.state.progress, action) {
+            (Progress::Description { .. }, 0) => Some(PlayerAction::Continue),
+            (Progress::Message { .. }, 0) => Some(PlayerAction::Acknowledge),
+            (Progress::Playing, action) if action > 0 => self
+                .player
+                .state
+                .jumps
+                .iter()
+                .find(|jump| jump.available && self.jumps.get(&jump.id) == Some(&action))
+                .map(|jump| PlayerAction::Jump(jump.id)),
+            _ => None,
+        }
+    }
+
+    pub fn observation(&self) -> Observation {
+        let state = &self.player.state;
+        let mut features = Vec::with_capacity(self.observation_len());
+
+        features.extend(state.parameters.iter().map(|value| *value as f32));
+
+        let location = features.len() + self.locations[&state.location.id];
+        features.resize(features.len() + self.locations.len(), 0.0);
+        features[location] = 1.0;
+
+        let pending = matches!(
+            state.progress,
+            Progress::Description { .. } | Progress::Message { .. }
+        );
+        features.push(if pending { 1.0 } else { 0.0 });
+
+        let mut action_mask = vec![false; self.action_count()];
+
+        match state.progress {
+            Progress::Description { .. } | Progress::Message { .. } => action_mask[0] = true,
+            Progress::Playing => {
+                for jump in state.jumps.iter().filter(|jump| jump.available) {
+                    action_mask[self.jumps[&jump.id]] = true;
+                }
+            }
+            Progress::Finished(_) => (),
+        }
+
+        Observation {
+            features,
+            action_mask,
+        }
+    }
+}
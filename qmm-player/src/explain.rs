@@ -0,0 +1,105 @@
+use qmm_syntax::qmm::JumpParameterCondition;
+
+/// Single requirement a jump must meet to be available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// `[p{parameter_id}]` is within `start..=end`
+    Range {
+        parameter_id: u32,
+        start: i32,
+        end: i32,
+    },
+    /// `[p{parameter_id}]` is (or isn't, if `must_equal` is false) one of the values
+    Equal {
+        parameter_id: u32,
+        values: Vec<i32>,
+        must_equal: bool,
+    },
+    /// `[p{parameter_id}]` is (or isn't, if `must_mod` is false) divisible by
+    /// one of the values
+    Modulo {
+        parameter_id: u32,
+        values: Vec<i32>,
+        must_mod: bool,
+    },
+    /// The jump's formula isn't zero
+    Formula(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionResult {
+    pub condition: Condition,
+    /// Value of the parameter or the formula, `None` if it can't be computed
+    pub value: Option<i32>,
+    pub passed: bool,
+}
+
+pub(crate) fn in_range(condition: &JumpParameterCondition, value: i32) -> bool {
+    (condition.range_start..=condition.range_end).contains(&value)
+}
+
+/// `None` if the condition has no values to compare with.
+pub(crate) fn equal_met(condition: &JumpParameterCondition, value: i32) -> Option<bool> {
+    if condition.must_equal_values.is_empty() {
+        return None;
+    }
+
+    Some(condition.must_equal_values.contains(&value) == condition.must_equal)
+}
+
+/// `None` if the condition has no divisors.
+pub(crate) fn mod_met(condition: &JumpParameterCondition, value: i32) -> Option<bool> {
+    if condition.must_mod_values.is_empty() {
+        return None;
+    }
+
+    let is_mod = condition
+        .must_mod_values
+        .iter()
+        .any(|modulo| *modulo != 0 && value % modulo == 0);
+
+    Some(is_mod == condition.must_mod)
+}
+
+/// Results of the range, equality and divisibility checks of the condition.
+pub(crate) fn explain_condition(
+    condition: &JumpParameterCondition,
+    value: Option<i32>,
+) -> Vec<ConditionResult> {
+    let parameter_id = condition.parameter_id;
+    let mut results = vec![ConditionResult {
+        condition: Condition::Range {
+            parameter_id,
+            start: condition.range_start,
+            end: condition.range_end,
+        },
+        value,
+        passed: value.is_some_and(|value| in_range(condition, value)),
+    }];
+
+    if !condition.must_equal_values.is_empty() {
+        results.push(ConditionResult {
+            condition: Condition::Equal {
+                parameter_id,
+                values: condition.must_equal_values.clone(),
+                must_equal: condition.must_equal,
+            },
+            value,
+            passed: value.and_then(|value| equal_met(condition, value)) == Some(true),
+        });
+    }
+
+    if !condition.must_mod_values.is_empty() {
+        results.push(ConditionResult {
+            condition: Condition::Modulo {
+                parameter_id,
+                values: condition.must_mod_values.clone(),
+                must_mod: condition.must_mod,
+            },
+            value,
+            passed: value.and_then(|value| mod_met(condition, value)) == Some(true),
+        });
+    }
+
+    results
+}
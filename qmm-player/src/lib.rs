@@ -1,27 +1,145 @@
-use std::collections::BTreeMap;
+/// Emits a `tracing` event if the `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+mod context;
+mod coverage;
+mod env;
+mod explain;
+mod options;
+mod search;
+mod snapshot;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use fastrand::Rng;
 use qmm_syntax::{
     qmm::*,
-    text::formatted_text::{FormattedText, TextElementKind},
+    text::{
+        expression::{EvalContext, EvalError, Expression},
+        formatted_text::{FormattedText, TextElement, TextElementKind},
+        formula::Formula,
+    },
 };
 
+pub use context::{MoneyFormat, PlayerContext, QuestDate};
+pub use coverage::TextCoverage;
+pub use env::{EnvRewards, EnvStep, Observation, QuestEnv};
+pub use explain::{Condition, ConditionResult};
+pub use options::{PlayerOptions, TgeVersion};
+pub use search::shortest_paths;
+pub use snapshot::QuestSnapshot;
+
+/// The player and the types of its steps, with [`qmm_syntax::prelude`].
+///
+/// Everything reachable from the prelude is covered by semver.
+pub mod prelude {
+    pub use qmm_syntax::prelude::*;
+
+    pub use crate::{
+        Ending, EngineError, MoneyFormat, PlayerAction, PlayerContext, PlayerOptions, Progress,
+        QuestError, QuestPlayer, QuestPlayerBuilder, QuestSnapshot, QuestState, StepResult,
+        TextCoverage,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PlayerAction {
     DoNothing,
+    Jump(JumpId),
+    /// Dismiss the message shown by [`StepResult::Message`]
+    Acknowledge,
+    /// Proceed from the jump description shown by [`StepResult::Description`]
+    Continue,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Ending {
+    Success,
+    Fail,
+    Death,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum StepResult {
     InProgress,
+    InvalidAction,
+    /// Text that must be acknowledged before the quest continues
+    Message(FormattedText),
+    /// Jump description shown before arriving at the target location
+    Description(FormattedText),
+    Success {
+        text: FormattedText,
+        reward: u32,
+        days: u32,
+    },
+    Fail,
+    Death,
+    /// The player stopped a broken quest or a formula of the step failed on
+    /// a limit. The step is undone, the state is kept as it was before it
+    EngineError(EngineError),
 }
 
-#[derive(Debug, Clone)]
+/// Limit of [`PlayerOptions`] a quest ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EngineError {
+    /// [`PlayerOptions::max_empty_hops`] jumps without text were taken in a
+    /// row
+    Loop,
+    /// The session took [`PlayerOptions::max_steps`] actions
+    StepLimit,
+    /// A formula of the step took more than
+    /// [`PlayerOptions::max_formula_operations`] or picked from a range larger
+    /// than [`PlayerOptions::max_range_size`], it failed like an invalid one
+    FormulaLimit(EvalError),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Loop => f.write_str("The quest loops through jumps without text"),
+            EngineError::StepLimit => f.write_str("The step limit of the session is reached"),
+            EngineError::FormulaLimit(err) => {
+                f.write_fmt(format_args!("A formula ran into a limit: {err}"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    Playing,
+    /// Jump description shown before entering the target location
+    Description {
+        text: FormattedText,
+        target: LocationId,
+    },
+    /// Critical parameter text shown before the quest ends
+    Message {
+        text: FormattedText,
+        ending: Ending,
+    },
+    Finished(Ending),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocationState {
     pub id: LocationId,
     pub description: FormattedText,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JumpState {
     pub id: JumpId,
     pub name: FormattedText,
@@ -32,11 +150,127 @@ pub struct JumpState {
 pub struct QuestState {
     pub location: LocationState,
     pub jumps: Vec<JumpState>,
+    /// Values of the quest parameters, `[p1]` is at index 0
+    pub parameters: Vec<i32>,
+    /// Whether the parameter is shown or hidden by parameter changes
+    pub parameters_shown: Vec<bool>,
+    pub location_visits: BTreeMap<LocationId, u32>,
+    /// Index of the text shown last in the locations with texts selected by
+    /// order
+    pub location_texts: BTreeMap<LocationId, usize>,
+    pub jump_passes: BTreeMap<JumpId, u32>,
+    pub days_passed: u32,
+    /// Time spent in the locations since the player was created, counted
+    /// when the next action is taken
+    pub location_time: BTreeMap<LocationId, Duration>,
+    pub progress: Progress,
+}
+
+impl QuestState {
+    /// Hash of the location, the parameter values and the visit counters,
+    /// equal for the states the quest continues the same way from. Stable
+    /// between the runs, so it can key transposition tables of the search
+    /// tools.
+    pub fn hash_key(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+
+        self.location.id.hash(&mut hasher);
+        match &self.progress {
+            Progress::Playing => 0u8.hash(&mut hasher),
+            Progress::Description { target, .. } => (1u8, target).hash(&mut hasher),
+            Progress::Message { ending, .. } => (2u8, *ending as u8).hash(&mut hasher),
+            Progress::Finished(ending) => (3u8, *ending as u8).hash(&mut hasher),
+        }
+        self.parameters.hash(&mut hasher);
+        self.location_visits.hash(&mut hasher);
+        self.jump_passes.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
+
+/// FNV-1a, the std hashers are randomly seeded or not stable between the
+/// builds.
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ParameterDelta {
+    pub parameter_id: u32,
+    pub from: i32,
+    pub to: i32,
+}
+
+/// Outcome of a jump computed without changing the player's state.
+///
+/// Jumps with random formulas show only one of the possible outcomes.
+#[derive(Debug, Clone)]
+pub struct JumpPreview {
+    pub jump: JumpId,
+    pub target: LocationId,
+    pub description: FormattedText,
+    /// Text of the location the player ends up in, after the jumps without
+    /// text it follows
+    pub location_text: FormattedText,
+    pub parameters: Vec<ParameterDelta>,
+    pub days: u32,
+    pub result: StepResult,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum QuestError {
     NoStartingLocation,
+    /// The snapshot's location doesn't exist or its parameters don't match
+    /// the quest
+    InvalidSnapshot,
+}
+
+/// Result of a jump's conditions and formula, valid while the parameters it
+/// depends on keep their values.
+#[derive(Debug, Clone)]
+struct CachedAvailability {
+    dependencies: Vec<(usize, i32)>,
+    available: bool,
+}
+
+/// Jump formula parsed once when the player is built.
+#[derive(Debug)]
+struct JumpFormula {
+    /// `None` if the formula doesn't parse, the jump is unavailable then
+    expression: Option<Expression>,
+    /// The formula picks random values, so its result can't be reused
+    is_random: bool,
+    /// One-based indices of the parameters the formula references
+    parameters: BTreeSet<usize>,
+}
+
+impl JumpFormula {
+    fn new(formula: &Formula) -> Self {
+        let expression = formula.expression().ok();
+
+        Self {
+            is_random: expression.as_ref().is_some_and(Expression::is_random),
+            expression,
+            parameters: formula.parameters(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,83 +278,630 @@ pub struct QuestPlayer<'q> {
     quest: &'q Quest,
     state: QuestState,
     task_text: FormattedText,
+    success_text: FormattedText,
+    context: PlayerContext,
+    options: PlayerOptions,
     variables: BTreeMap<String, String>,
-    rng: Rng,
+    rng: PlayerRng,
+    availability_cache: BTreeMap<JumpId, CachedAvailability>,
+    /// Formulas of the quest's jumps that have one
+    jump_formulas: Arc<BTreeMap<JumpId, JumpFormula>>,
+    started_at: Instant,
+    /// When the time of the current location was counted last
+    counted_at: Instant,
+    coverage: Option<TextCoverage>,
+    /// Actions taken, limited by [`PlayerOptions::max_steps`]
+    steps: usize,
+    /// Formula work of the step
+    budget: FormulaBudget,
 }
 
-impl<'q> QuestPlayer<'q> {
-    pub fn new(quest: &'q Quest, seed: u64) -> Result<Self, QuestError> {
+/// Configures and creates a [`QuestPlayer`].
+#[derive(Debug, Clone)]
+pub struct QuestPlayerBuilder<'q> {
+    quest: &'q Quest,
+    seed: u64,
+    context: PlayerContext,
+    options: PlayerOptions,
+    snapshot: Option<QuestSnapshot>,
+    track_coverage: bool,
+}
+
+/// Generator whose clones continue with the same numbers. `Rng::clone`
+/// derives a new sequence, and a cloned player would roll differently from
+/// the original one.
+#[derive(Debug)]
+struct PlayerRng(Rng);
+
+impl Clone for PlayerRng {
+    fn clone(&self) -> Self {
+        Self(Rng::with_seed(self.0.get_seed()))
+    }
+}
+
+/// Formula work of a step, limited by
+/// [`PlayerOptions::max_formula_operations`] and
+/// [`PlayerOptions::max_range_size`].
+#[derive(Debug, Clone, Copy, Default)]
+struct FormulaBudget {
+    operations: usize,
+    /// First limit a formula ran into
+    exceeded: Option<EvalError>,
+}
+
+struct Evaluator<'a> {
+    parameters: &'a [i32],
+    rng: &'a mut Rng,
+    budget: &'a mut FormulaBudget,
+    options: &'a PlayerOptions,
+}
+
+impl EvalContext for Evaluator<'_> {
+    fn parameter(&self, index: usize) -> Option<i32> {
+        self.parameters.get(index.checked_sub(1)?).copied()
+    }
+
+    fn random(&mut self, range: RangeInclusive<i32>) -> i32 {
+        self.rng.i32(range)
+    }
+
+    fn strict_math(&self) -> bool {
+        self.options.strict_math
+    }
+
+    fn count_operation(&mut self) -> bool {
+        self.budget.operations += 1;
+
+        self.options
+            .max_formula_operations
+            .is_none_or(|max| self.budget.operations <= max)
+    }
+
+    fn max_range_size(&self) -> Option<u64> {
+        self.options.max_range_size
+    }
+}
+
+/// Values of the starting formulas, the ones which can't be evaluated are 0.
+fn starting_parameters(quest: &Quest, rng: &mut Rng, options: &PlayerOptions) -> Vec<i32> {
+    let mut parameters = Vec::with_capacity(quest.parameters.len());
+    let mut budget = FormulaBudget::default();
+
+    for parameter in &quest.parameters {
+        let value = Formula::parse_with(&parameter.starting_value, options.formula_options)
+            .ok()
+            .and_then(|(formula, _)| eval_formula(&formula, &parameters, rng, &mut budget, options))
+            .unwrap_or(0);
+
+        parameters.push(value);
+    }
+
+    parameters
+}
+
+impl<'q> QuestPlayerBuilder<'q> {
+    /// The same settings for another quest, e.g. an edited version of it.
+    pub fn quest<'n>(self, quest: &'n Quest) -> QuestPlayerBuilder<'n> {
+        QuestPlayerBuilder {
+            quest,
+            seed: self.seed,
+            context: self.context,
+            options: self.options,
+            snapshot: self.snapshot,
+            track_coverage: self.track_coverage,
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn context(mut self, context: PlayerContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn options(mut self, options: PlayerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Continues the quest from the snapshot instead of starting it.
+    pub fn snapshot(mut self, snapshot: QuestSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Collects the [`TextCoverage`] of the session.
+    pub fn track_coverage(mut self) -> Self {
+        self.track_coverage = true;
+        self
+    }
+
+    pub fn build(self) -> Result<QuestPlayer<'q>, QuestError> {
+        let quest = self.quest;
         let starting_location = quest
             .locations
             .iter()
             .find(|loc| matches!(loc.ty, LocationType::Starting))
             .ok_or(QuestError::NoStartingLocation)?;
 
-        let variables = default_variables();
-        let mut jumps = Vec::new();
-
-        for jump in &quest.jumps {
-            if jump.from != starting_location.id {
-                continue;
-            }
-
-            jumps.push(JumpState {
-                id: jump.id,
-                name: jump.text.clone(),
-                available: true,
-            })
-        }
+        let variables = self.context.variables();
+        let mut rng = Rng::with_seed(self.seed);
+        let parameters = starting_parameters(quest, &mut rng, &self.options);
 
         let state = QuestState {
             location: LocationState {
                 id: starting_location.id,
-                description: Self::replace_formatted_text(
-                    &variables,
-                    starting_location.texts.first().cloned().unwrap(),
-                ),
+                description: FormattedText::default(),
             },
-            jumps,
+            jumps: Vec::new(),
+            parameters_shown: vec![true; parameters.len()],
+            parameters,
+            location_visits: BTreeMap::new(),
+            location_texts: BTreeMap::new(),
+            jump_passes: BTreeMap::new(),
+            days_passed: 0,
+            location_time: BTreeMap::new(),
+            progress: Progress::Playing,
         };
+        let now = Instant::now();
 
-        let task_text = Self::replace_formatted_text(&variables, quest.info.task_text.clone());
-        let rng = Rng::with_seed(seed);
-
-        Ok(Self {
+        let mut player = QuestPlayer {
             quest,
             state,
-            task_text,
-            rng,
+            task_text: FormattedText::default(),
+            success_text: FormattedText::default(),
+            context: self.context,
+            options: self.options,
+            rng: PlayerRng(rng),
             variables,
-        })
+            availability_cache: BTreeMap::new(),
+            jump_formulas: Arc::new(
+                quest
+                    .jumps
+                    .iter()
+                    .filter(|jump| !jump.formula.is_empty())
+                    .map(|jump| (jump.id, JumpFormula::new(&jump.formula)))
+                    .collect(),
+            ),
+            started_at: now,
+            counted_at: now,
+            coverage: self.track_coverage.then(TextCoverage::default),
+            steps: 0,
+            budget: FormulaBudget::default(),
+        };
+
+        player.task_text = player.format_text(&quest.info.task_text);
+
+        if let Some(snapshot) = self.snapshot {
+            player.restore(snapshot)?;
+
+            return Ok(player);
+        }
+
+        let result = player.enter_location(starting_location);
+        player.follow_empty_jumps(result);
+
+        Ok(player)
+    }
+}
+
+impl<'q> QuestPlayer<'q> {
+    pub fn new(quest: &'q Quest, seed: u64) -> Result<Self, QuestError> {
+        Self::with_context(quest, seed, PlayerContext::default())
     }
 
-    fn replace_formatted_text(
-        variables: &BTreeMap<String, String>,
-        mut text: FormattedText,
-    ) -> FormattedText {
-        for el in &mut text.elements {
-            if !matches!(el.kind, TextElementKind::Variable { .. }) {
-                continue;
-            }
+    pub fn with_context(
+        quest: &'q Quest,
+        seed: u64,
+        context: PlayerContext,
+    ) -> Result<Self, QuestError> {
+        Self::builder(quest).seed(seed).context(context).build()
+    }
 
-            if let Some(value) = variables.get(&el.value) {
-                el.value = value.to_string();
+    pub fn builder(quest: &'q Quest) -> QuestPlayerBuilder<'q> {
+        QuestPlayerBuilder {
+            quest,
+            seed: 0,
+            context: PlayerContext::default(),
+            options: PlayerOptions::default(),
+            snapshot: None,
+            track_coverage: false,
+        }
+    }
+
+    /// Puts the player into the snapshot's location without applying its
+    /// parameter changes again.
+    fn restore(&mut self, snapshot: QuestSnapshot) -> Result<(), QuestError> {
+        let quest = self.quest;
+        let location = quest
+            .location(snapshot.location)
+            .ok_or(QuestError::InvalidSnapshot)?;
+
+        if snapshot.parameters.len() != quest.parameters.len() {
+            return Err(QuestError::InvalidSnapshot);
+        }
+
+        let parameters_shown = match snapshot.parameters_shown.len() {
+            0 => vec![true; quest.parameters.len()],
+            len if len == quest.parameters.len() => snapshot.parameters_shown,
+            _ => return Err(QuestError::InvalidSnapshot),
+        };
+
+        self.state.parameters = snapshot.parameters;
+        self.state.parameters_shown = parameters_shown;
+        self.state.location_visits = snapshot.location_visits;
+        self.state.location_texts = snapshot.location_texts;
+        self.state.jump_passes = snapshot.jump_passes;
+        self.state.days_passed = snapshot.days_passed;
+
+        let text = self.location_text(location);
+        self.state.location = LocationState {
+            id: location.id,
+            description: text,
+        };
+
+        match location.ty {
+            LocationType::Success => {
+                self.finish(Ending::Success);
             }
+            LocationType::Fail => {
+                self.finish(Ending::Fail);
+            }
+            LocationType::Death => {
+                self.finish(Ending::Death);
+            }
+            _ => self.refresh_jumps(),
+        }
+
+        Ok(())
+    }
+
+    /// Texts shown so far, if enabled by
+    /// [`QuestPlayerBuilder::track_coverage`].
+    pub fn coverage(&self) -> Option<&TextCoverage> {
+        self.coverage.as_ref()
+    }
+
+    /// [`Self::format_text`] of a text counted by the coverage.
+    fn format_shown_text(&mut self, source: TextSource, text: &FormattedText) -> FormattedText {
+        let text = self.format_text(text);
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_text(source, &text);
         }
 
         text
     }
 
+    /// Substitutes variables, parameters and formulas into the text.
+    fn format_text(&mut self, text: &FormattedText) -> FormattedText {
+        let mut rng = self.rng_copy();
+        let mut budget = self.budget;
+        let text = self.format_text_with(text, &mut rng, &mut budget);
+        self.rng = PlayerRng(rng);
+        self.budget = budget;
+
+        text
+    }
+
+    /// Generator in the same state.
+    fn rng_copy(&self) -> Rng {
+        self.rng.clone().0
+    }
+
+    fn format_text_with(
+        &self,
+        text: &FormattedText,
+        rng: &mut Rng,
+        budget: &mut FormulaBudget,
+    ) -> FormattedText {
+        let mut text = text.clone();
+        self.format_elements(&mut text.elements, rng, budget);
+
+        text
+    }
+
+    fn format_elements(
+        &self,
+        elements: &mut [TextElement],
+        rng: &mut Rng,
+        budget: &mut FormulaBudget,
+    ) {
+        for el in elements {
+            let value = match &mut el.kind {
+                TextElementKind::Selection { text } => {
+                    self.format_elements(&mut text.elements, rng, budget);
+
+                    Some(format!("<clr>{text}<clrEnd>"))
+                }
+                TextElementKind::Variable { .. } if el.value == "<Day>" => self
+                    .context
+                    .days_left(self.state.days_passed)
+                    .or_else(|| self.variables.get(&el.value).cloned()),
+                TextElementKind::Variable { .. } => self.variables.get(&el.value).cloned(),
+                TextElementKind::Parameter { index } => index
+                    .checked_sub(1)
+                    .and_then(|index| self.state.parameters.get(index))
+                    .map(|value| value.to_string()),
+                TextElementKind::Formula { text } => {
+                    Formula::parse_with(text, self.options.formula_options)
+                        .ok()
+                        .and_then(|(formula, _)| {
+                            eval_formula(
+                                &formula,
+                                &self.state.parameters,
+                                rng,
+                                budget,
+                                &self.options,
+                            )
+                        })
+                        .map(|value| value.to_string())
+                }
+                _ => None,
+            };
+
+            if let Some(value) = value {
+                el.value = value;
+            }
+        }
+    }
+
+    pub fn context(&self) -> &PlayerContext {
+        &self.context
+    }
+
+    pub fn options(&self) -> &PlayerOptions {
+        &self.options
+    }
+
+    /// Parameters that should be displayed to the player with their values.
+    ///
+    /// A parameter is displayed if it's active, wasn't hidden by a parameter
+    /// change and either isn't zero or is shown when zero.
+    pub fn visible_parameters(&self) -> impl Iterator<Item = (&Parameter, i32)> {
+        self.quest
+            .parameters
+            .iter()
+            .zip(&self.state.parameters)
+            .zip(&self.state.parameters_shown)
+            .filter(|((parameter, value), shown)| {
+                parameter.is_active && **shown && (**value != 0 || parameter.show_when_zero)
+            })
+            .map(|((parameter, value), _)| (parameter, *value))
+    }
+
+    /// Range line of the parameter for the value with its formulas and
+    /// variables substituted, `None` if no line matches the value.
+    ///
+    /// The formulas are evaluated with a copy of the random generator, so
+    /// showing the parameters doesn't change the game.
+    pub fn parameter_text(&self, parameter: &Parameter, value: i32) -> Option<FormattedText> {
+        let line = parameter.display_for(value)?;
+
+        Some(self.format_text_with(
+            &FormattedText::parse(&line),
+            &mut self.rng_copy(),
+            &mut self.budget.clone(),
+        ))
+    }
+
+    /// Texts of the [visible parameters](Self::visible_parameters) that
+    /// have a range line for their values.
+    pub fn parameter_texts(&self) -> Vec<FormattedText> {
+        self.visible_parameters()
+            .filter_map(|(parameter, value)| self.parameter_text(parameter, value))
+            .collect()
+    }
+
     pub fn task_text(&self) -> &FormattedText {
         &self.task_text
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), ret)
+    )]
     pub fn step(&mut self, action: PlayerAction) -> StepResult {
-        match action {
-            PlayerAction::DoNothing => StepResult::InProgress,
+        self.count_time();
+        self.budget = FormulaBudget::default();
+
+        if action != PlayerAction::DoNothing
+            && self.options.max_steps.is_some_and(|max| self.steps >= max)
+        {
+            return StepResult::EngineError(EngineError::StepLimit);
+        }
+
+        // Restored if the step fails, the quest may have taken any number of
+        // jumps by then
+        let before = self.clone();
+        let result = match action {
+            PlayerAction::DoNothing => return self.result(),
+            PlayerAction::Jump(id) => {
+                let result = self.jump(id);
+                self.follow_empty_jumps(result)
+            }
+            PlayerAction::Acknowledge => self.acknowledge(),
+            PlayerAction::Continue => {
+                let result = self.continue_jump();
+                self.follow_empty_jumps(result)
+            }
+        };
+
+        if result == StepResult::InvalidAction {
+            return result;
+        }
+
+        let result = match self.budget.exceeded {
+            Some(err) if !matches!(result, StepResult::EngineError(_)) => {
+                StepResult::EngineError(EngineError::FormulaLimit(err))
+            }
+            _ => result,
+        };
+
+        if matches!(result, StepResult::EngineError(_)) {
+            *self = before;
+        }
+
+        // The undone steps are counted too, so retrying them runs into the
+        // limit
+        self.steps += 1;
+
+        result
+    }
+
+    /// Actions taken since the start, the invalid ones aren't counted.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Parts of the state that differ from the other player's, for finding
+    /// where two replays with the same seed and actions diverged. The time
+    /// spent in the locations comes from the clock and isn't compared.
+    pub fn differences(&self, other: &QuestPlayer) -> Vec<&'static str> {
+        let (a, b) = (&self.state, &other.state);
+        let parts = [
+            ("location", a.location == b.location),
+            ("jumps", a.jumps == b.jumps),
+            ("parameters", a.parameters == b.parameters),
+            ("parameters shown", a.parameters_shown == b.parameters_shown),
+            ("location visits", a.location_visits == b.location_visits),
+            ("location texts", a.location_texts == b.location_texts),
+            ("jump passes", a.jump_passes == b.jump_passes),
+            ("days passed", a.days_passed == b.days_passed),
+            ("progress", a.progress == b.progress),
+            (
+                "random generator",
+                self.rng.0.get_seed() == other.rng.0.get_seed(),
+            ),
+        ];
+
+        parts
+            .into_iter()
+            .filter(|(_, equal)| !equal)
+            .map(|(part, _)| part)
+            .collect()
+    }
+
+    /// Time since the player was created.
+    pub fn session_time(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Adds the time since the last action to the current location.
+    fn count_time(&mut self) {
+        let now = Instant::now();
+
+        *self
+            .state
+            .location_time
+            .entry(self.state.location.id)
+            .or_default() += now - self.counted_at;
+        self.counted_at = now;
+    }
+
+    /// Leaves locations without text through their only jump without text,
+    /// as TGE 5 does.
+    fn follow_empty_jumps(&mut self, mut result: StepResult) -> StepResult {
+        if self.options.tge_behavior != TgeVersion::Tge5 {
+            return result;
+        }
+
+        let mut hops = 0;
+
+        while result == StepResult::InProgress {
+            let Some(id) = self.empty_jump() else {
+                break;
+            };
+
+            if hops == self.options.max_empty_hops {
+                trace_event!(
+                    warn,
+                    location = self.state.location.id.0,
+                    "empty jumps loop"
+                );
+
+                return StepResult::EngineError(EngineError::Loop);
+            }
+
+            result = self.jump(id);
+            hops += 1;
+        }
+
+        result
+    }
+
+    /// The only jump out of a location without text, if it has no text
+    /// either.
+    fn empty_jump(&self) -> Option<JumpId> {
+        if !self.state.location.description.elements.is_empty() {
+            return None;
+        }
+
+        let [jump] = self.state.jumps.as_slice() else {
+            return None;
+        };
+
+        (jump.available && jump.name.elements.is_empty()).then_some(jump.id)
+    }
+
+    fn result(&self) -> StepResult {
+        match &self.state.progress {
+            Progress::Playing => StepResult::InProgress,
+            Progress::Description { text, .. } => StepResult::Description(text.clone()),
+            Progress::Message { text, .. } => StepResult::Message(text.clone()),
+            Progress::Finished(ending) => self.ending_result(*ending),
         }
     }
 
+    fn ending_result(&self, ending: Ending) -> StepResult {
+        match ending {
+            Ending::Success => StepResult::Success {
+                text: self.success_text.clone(),
+                reward: self.context.money,
+                days: self.state.days_passed,
+            },
+            Ending::Fail => StepResult::Fail,
+            Ending::Death => StepResult::Death,
+        }
+    }
+
+    fn finish(&mut self, ending: Ending) -> StepResult {
+        if ending == Ending::Success {
+            self.success_text = self.format_text(&self.quest.info.success_text);
+        }
+
+        trace_event!(debug, ending = ?ending, "quest finished");
+
+        self.state.progress = Progress::Finished(ending);
+        self.state.jumps.clear();
+
+        self.ending_result(ending)
+    }
+
+    fn acknowledge(&mut self) -> StepResult {
+        let Progress::Message { ending, .. } = self.state.progress else {
+            return StepResult::InvalidAction;
+        };
+
+        self.finish(ending)
+    }
+
+    fn continue_jump(&mut self) -> StepResult {
+        let Progress::Description { target, .. } = self.state.progress else {
+            return StepResult::InvalidAction;
+        };
+
+        let Some(location) = self.quest.location(target) else {
+            return StepResult::InvalidAction;
+        };
+
+        self.state.progress = Progress::Playing;
+        self.enter_location(location)
+    }
+
     pub fn state(&self) -> &QuestState {
         &self.state
     }
@@ -128,19 +909,1901 @@ impl<'q> QuestPlayer<'q> {
     pub fn quest(&self) -> &Quest {
         self.quest
     }
-}
 
-fn default_variables() -> BTreeMap<String, String> {
-    let mut map = BTreeMap::new();
+    /// State to save, `None` while a jump description or a message is shown
+    /// since it can't be resumed from.
+    pub fn snapshot(&self) -> Option<QuestSnapshot> {
+        if !matches!(
+            self.state.progress,
+            Progress::Playing | Progress::Finished(_)
+        ) {
+            return None;
+        }
+
+        Some(QuestSnapshot {
+            location: self.state.location.id,
+            parameters: self.state.parameters.clone(),
+            parameters_shown: self.state.parameters_shown.clone(),
+            location_visits: self.state.location_visits.clone(),
+            location_texts: self.state.location_texts.clone(),
+            jump_passes: self.state.jump_passes.clone(),
+            days_passed: self.state.days_passed,
+        })
+    }
+
+    /// Player of an edited version of the quest continuing from the same
+    /// spot: the location is found by its id and the parameters by their
+    /// indices.
+    ///
+    /// The values are clamped into the new ranges and the added parameters
+    /// get their starting values. The visits of the removed locations and
+    /// jumps are forgotten. A shown jump description continues in its target
+    /// location and a shown critical message is dropped.
+    pub fn reload<'n>(&self, quest: &'n Quest) -> Result<QuestPlayer<'n>, QuestError> {
+        let state = &self.state;
+        let location = match &state.progress {
+            Progress::Description { target, .. } => *target,
+            _ => state.location.id,
+        };
+        let mut rng = self.rng_copy();
+        let parameters = starting_parameters(quest, &mut rng, &self.options)
+            .into_iter()
+            .zip(&quest.parameters)
+            .enumerate()
+            .map(|(index, (starting, parameter))| {
+                state.parameters.get(index).map_or(starting, |value| {
+                    (*value).clamp(parameter.min_value, parameter.max_value)
+                })
+            })
+            .collect();
+        let parameters_shown = (0..quest.parameters.len())
+            .map(|index| state.parameters_shown.get(index).copied().unwrap_or(true))
+            .collect();
+        let has_location = |id: &LocationId| quest.location(*id).is_some();
+        let snapshot = QuestSnapshot {
+            location,
+            parameters,
+            parameters_shown,
+            location_visits: state
+                .location_visits
+                .iter()
+                .filter(|(id, _)| has_location(id))
+                .map(|(id, visits)| (*id, *visits))
+                .collect(),
+            location_texts: state
+                .location_texts
+                .iter()
+                .filter(|(id, _)| has_location(id))
+                .map(|(id, index)| (*id, *index))
+                .collect(),
+            jump_passes: state
+                .jump_passes
+                .iter()
+                .filter(|(id, _)| quest.jump(**id).is_some())
+                .map(|(id, passes)| (*id, *passes))
+                .collect(),
+            days_passed: state.days_passed,
+        };
+        let mut player = QuestPlayer::builder(quest)
+            .seed(rng.get_seed())
+            .context(self.context.clone())
+            .options(self.options.clone())
+            .snapshot(snapshot)
+            .build()?;
+
+        player.state.location_time = state
+            .location_time
+            .iter()
+            .filter(|(id, _)| has_location(id))
+            .map(|(id, time)| (*id, *time))
+            .collect();
+        player.started_at = self.started_at;
+        player.coverage.clone_from(&self.coverage);
+        player.steps = self.steps;
 
-    map.insert("<ToStar>".to_string(), "Процион".to_string());
-    map.insert("<ToPlanet>".to_string(), "Боннасис".to_string());
-    map.insert("<FromStar>".to_string(), "Солнечная".to_string());
-    map.insert("<FromPlanet>".to_string(), "Земля".to_string());
-    map.insert("<Ranger>".to_string(), "Греф".to_string());
-    map.insert("<Date>".to_string(), "15 Марта 3300".to_string());
-    map.insert("<Day>".to_string(), "15 Марта".to_string());
-    map.insert("<Money>".to_string(), "10000".to_string());
+        Ok(player)
+    }
 
-    map
+    /// Takes the jump in a copy of the player, continuing past its
+    /// description and following the jumps without text like [`Self::step`].
+    pub fn preview_jump(&self, id: JumpId) -> Option<JumpPreview> {
+        let mut player = self.clone();
+        player.budget = FormulaBudget::default();
+
+        let mut result = player.jump(id);
+        let mut description = FormattedText::default();
+
+        if result == StepResult::InvalidAction {
+            return None;
+        }
+
+        // Formatted with the values before the target's parameter changes
+        if let StepResult::Description(text) = &result {
+            description = text.clone();
+            result = player.continue_jump();
+        }
+
+        let result = player.follow_empty_jumps(result);
+        let jump = self.quest.jump(id)?;
+        let parameters = self
+            .state
+            .parameters
+            .iter()
+            .zip(&player.state.parameters)
+            .enumerate()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(index, (from, to))| ParameterDelta {
+                parameter_id: index as u32 + 1,
+                from: *from,
+                to: *to,
+            })
+            .collect();
+
+        Some(JumpPreview {
+            jump: id,
+            target: jump.to,
+            description,
+            location_text: player.state.location.description,
+            parameters,
+            days: player.state.days_passed - self.state.days_passed,
+            result,
+        })
+    }
+
+    fn jump(&mut self, id: JumpId) -> StepResult {
+        let quest = self.quest;
+
+        if self.state.progress != Progress::Playing {
+            return StepResult::InvalidAction;
+        }
+
+        if !self
+            .state
+            .jumps
+            .iter()
+            .any(|jump| jump.id == id && jump.available)
+        {
+            return StepResult::InvalidAction;
+        }
+
+        let Some(jump) = quest.jump(id) else {
+            return StepResult::InvalidAction;
+        };
+
+        let Some(location) = quest.location(jump.to) else {
+            return StepResult::InvalidAction;
+        };
+
+        trace_event!(debug, jump = id.0, target = jump.to.0, "taking jump");
+
+        *self.state.jump_passes.entry(id).or_default() += 1;
+
+        if jump.do_pass_day {
+            self.state.days_passed += 1;
+        }
+
+        self.apply_parameter_changes(&jump.parameter_changes);
+
+        if let Some(result) = self.check_critical_parameters(&jump.parameter_changes) {
+            return result;
+        }
+
+        if !jump.description.elements.is_empty() {
+            let text = self.format_shown_text(TextSource::JumpDescription(id), &jump.description);
+            trace_event!(debug, jump = id.0, "showing jump description");
+
+            self.state.progress = Progress::Description {
+                text: text.clone(),
+                target: location.id,
+            };
+            self.state.jumps.clear();
+
+            return StepResult::Description(text);
+        }
+
+        self.enter_location(location)
+    }
+
+    fn enter_location(&mut self, location: &Location) -> StepResult {
+        trace_event!(debug, location = location.id.0, ty = ?location.ty, "entering location");
+
+        *self.state.location_visits.entry(location.id).or_default() += 1;
+
+        if location.do_pass_day {
+            self.state.days_passed += 1;
+        }
+
+        self.apply_parameter_changes(&location.parameter_changes);
+        self.advance_location_text(location);
+
+        self.state.location = LocationState {
+            id: location.id,
+            description: self.location_text(location),
+        };
+
+        if let Some(result) = self.check_critical_parameters(&location.parameter_changes) {
+            return result;
+        }
+
+        let ending = match location.ty {
+            LocationType::Success => Ending::Success,
+            LocationType::Fail => Ending::Fail,
+            LocationType::Death => Ending::Death,
+            _ => {
+                self.refresh_jumps();
+
+                return StepResult::InProgress;
+            }
+        };
+
+        self.finish(ending)
+    }
+
+    /// Moves to the next non-empty text of the location with texts selected
+    /// by order, wrapping around after the last one.
+    fn advance_location_text(&mut self, location: &Location) {
+        if !matches!(location.select_type, LocationSelectType::ByOrder) || location.texts.is_empty()
+        {
+            return;
+        }
+
+        let count = location.texts.len();
+        let start = self
+            .state
+            .location_texts
+            .get(&location.id)
+            .map_or(0, |index| index + 1);
+        let next = (start..start + count)
+            .map(|index| index % count)
+            .find(|&index| !location.texts[index].elements.is_empty())
+            .unwrap_or(0);
+
+        self.state.location_texts.insert(location.id, next);
+    }
+
+    /// Formatted text of the location chosen by its select type.
+    fn location_text(&mut self, location: &Location) -> FormattedText {
+        let text_index = match &location.select_type {
+            LocationSelectType::ByOrder => self
+                .state
+                .location_texts
+                .get(&location.id)
+                .copied()
+                .unwrap_or(0),
+            LocationSelectType::ByFormula(formula) => self
+                .eval(formula)
+                .map(|index| (index.max(1) - 1) as usize)
+                .unwrap_or(0),
+        };
+
+        let text_index = if text_index < location.texts.len() {
+            text_index
+        } else {
+            0
+        };
+        let text = location.texts.get(text_index).cloned().unwrap_or_default();
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_location_text(location.id, text_index);
+        }
+
+        self.format_shown_text(
+            TextSource::LocationText {
+                location: location.id,
+                index: text_index + 1,
+            },
+            &text,
+        )
+    }
+
+    /// Interrupts the quest with the critical text of the first non-ordinary
+    /// parameter that reached its critical value after the changes.
+    fn check_critical_parameters(&mut self, changes: &[ParameterChange]) -> Option<StepResult> {
+        for change in changes {
+            let Some(index) = (change.parameter_id as usize).checked_sub(1) else {
+                continue;
+            };
+
+            let Some(parameter) = self.quest.parameters.get(index) else {
+                continue;
+            };
+
+            let value = self.state.parameters[index];
+
+            let ending = match parameter.ty {
+                ParameterType::Ordinary => continue,
+                ParameterType::Fail => Ending::Fail,
+                ParameterType::Win => Ending::Success,
+                ParameterType::Death => Ending::Death,
+            };
+
+            let reached = match parameter.critical_value {
+                CriticalValue::Min => value <= parameter.min_value,
+                CriticalValue::Max => value >= parameter.max_value,
+            };
+
+            if !reached {
+                continue;
+            }
+
+            let critical_text = if change.critical_text.is_empty() {
+                &parameter.critical_text
+            } else {
+                &change.critical_text
+            };
+            let text = self.format_text(&FormattedText::parse(critical_text));
+            trace_event!(
+                debug,
+                parameter = change.parameter_id,
+                value,
+                ending = ?ending,
+                "critical parameter value reached"
+            );
+
+            self.state.progress = Progress::Message {
+                text: text.clone(),
+                ending,
+            };
+            self.state.jumps.clear();
+
+            return Some(StepResult::Message(text));
+        }
+
+        None
+    }
+
+    fn apply_parameter_changes(&mut self, changes: &[ParameterChange]) {
+        let old_parameters = self.state.parameters.clone();
+
+        for change in changes {
+            let Some(index) = (change.parameter_id as usize).checked_sub(1) else {
+                continue;
+            };
+
+            let Some(parameter) = self.quest.parameters.get(index) else {
+                continue;
+            };
+
+            match change.show_type {
+                ParameterShowType::Nothing => (),
+                ParameterShowType::Show => self.state.parameters_shown[index] = true,
+                ParameterShowType::Hide => self.state.parameters_shown[index] = false,
+            }
+
+            let current = old_parameters[index];
+            let value = match change.change_type {
+                ParameterChangeType::Value => Some(change.change),
+                ParameterChangeType::Sum => Some(current.saturating_add(change.change)),
+                ParameterChangeType::Percentage => {
+                    let delta = (current as f64 * change.change as f64 / 100.0).round();
+                    Some((current as f64 + delta).clamp(i32::MIN as f64, i32::MAX as f64) as i32)
+                }
+                ParameterChangeType::Formula => eval_formula(
+                    &change.formula,
+                    &old_parameters,
+                    &mut self.rng.0,
+                    &mut self.budget,
+                    &self.options,
+                ),
+            };
+
+            if let Some(value) = value {
+                let value = value.clamp(parameter.min_value, parameter.max_value);
+                trace_event!(
+                    trace,
+                    parameter = change.parameter_id,
+                    from = current,
+                    to = value,
+                    "parameter changed"
+                );
+
+                self.state.parameters[index] = value;
+            }
+        }
+    }
+
+    fn refresh_jumps(&mut self) {
+        let quest = self.quest;
+        let mut candidates = Vec::new();
+
+        for jump in quest.jumps_from(self.state.location.id) {
+            if Self::limit_reached(&jump.max_visits, self.state.jump_passes.get(&jump.id)) {
+                continue;
+            }
+
+            if let Some(target) = quest.location(jump.to) {
+                if Self::limit_reached(
+                    &target.max_visits,
+                    self.state.location_visits.get(&target.id),
+                ) {
+                    continue;
+                }
+            }
+
+            let available = self.is_jump_available(jump);
+
+            if !available && !jump.show_always {
+                continue;
+            }
+
+            candidates.push((jump, available));
+        }
+
+        let mut jumps: Vec<(&Jump, bool)> = Vec::with_capacity(candidates.len());
+
+        for (jump, available) in &candidates {
+            if jumps.iter().any(|(other, _)| other.text == jump.text) {
+                continue;
+            }
+
+            let same_text: Vec<&Jump> = candidates
+                .iter()
+                .filter(|(other, other_available)| *other_available && other.text == jump.text)
+                .map(|(other, _)| *other)
+                .collect();
+
+            match same_text.len() {
+                0 => jumps.push((jump, *available)),
+                1 => jumps.push((same_text[0], true)),
+                _ => jumps.push((self.select_by_priority(&same_text), true)),
+            }
+        }
+
+        jumps.sort_by_key(|(jump, _)| jump.show_order);
+
+        self.state.jumps = Vec::with_capacity(jumps.len());
+
+        for (jump, available) in jumps {
+            let name = self.format_shown_text(TextSource::JumpText(jump.id), &jump.text);
+
+            self.state.jumps.push(JumpState {
+                id: jump.id,
+                name,
+                available,
+            });
+        }
+    }
+
+    fn select_by_priority<'j>(&mut self, jumps: &[&'j Jump]) -> &'j Jump {
+        let max_priority = jumps
+            .iter()
+            .map(|jump| jump.priority)
+            .fold(f64::MIN, f64::max);
+        let jumps: Vec<&Jump> = jumps
+            .iter()
+            .copied()
+            .filter(|jump| jump.priority * 100.0 >= max_priority)
+            .collect();
+        let sum: f64 = jumps.iter().map(|jump| jump.priority).sum();
+        let mut pick = self.rng.0.f64() * sum;
+
+        for jump in &jumps {
+            if pick < jump.priority {
+                return jump;
+            }
+
+            pick -= jump.priority;
+        }
+
+        jumps[jumps.len() - 1]
+    }
+
+    fn limit_reached(limit: &MaxVisits, count: Option<&u32>) -> bool {
+        match limit {
+            MaxVisits::Infinite => false,
+            MaxVisits::Limit(limit) => count.copied().unwrap_or(0) >= *limit,
+        }
+    }
+
+    /// Checks the jump's parameter conditions and formula, reusing the
+    /// previous result if none of the referenced parameters changed since.
+    /// The results of the formulas that ran into a limit aren't reused, they
+    /// may succeed in the next step.
+    ///
+    /// The jump must be one of the quest's, its formula is the one parsed
+    /// when the player was built.
+    fn is_jump_available(&mut self, jump: &Jump) -> bool {
+        if let Some(cached) = self.availability_cache.get(&jump.id) {
+            if cached
+                .dependencies
+                .iter()
+                .all(|(index, value)| self.state.parameters.get(index - 1) == Some(value))
+            {
+                return cached.available;
+            }
+        }
+
+        let formula = self.jump_formulas.get(&jump.id);
+
+        // The condition's value is rounded like any formula result, so unlike
+        // `and` and `or` the player treats `0.4` as false
+        let available = Self::conditions_met(&jump.parameters_conditions, &self.state.parameters)
+            && formula.is_none_or(|formula| {
+                formula
+                    .expression
+                    .as_ref()
+                    .and_then(|expression| {
+                        eval_expression(
+                            expression,
+                            &self.state.parameters,
+                            &mut self.rng.0,
+                            &mut self.budget,
+                            &self.options,
+                        )
+                    })
+                    .unwrap_or(0)
+                    != 0
+            });
+
+        let is_random = formula.is_some_and(|formula| formula.is_random);
+
+        if !is_random && self.budget.exceeded.is_none() {
+            let mut dependencies = formula
+                .map(|formula| formula.parameters.clone())
+                .unwrap_or_default();
+
+            dependencies.extend(
+                jump.parameters_conditions
+                    .iter()
+                    .map(|condition| condition.parameter_id as usize),
+            );
+
+            let dependencies = dependencies
+                .into_iter()
+                .filter_map(|index| {
+                    let value = *self.state.parameters.get(index.checked_sub(1)?)?;
+
+                    Some((index, value))
+                })
+                .collect();
+
+            self.availability_cache.insert(
+                jump.id,
+                CachedAvailability {
+                    dependencies,
+                    available,
+                },
+            );
+        }
+
+        available
+    }
+
+    fn conditions_met(conditions: &[JumpParameterCondition], parameters: &[i32]) -> bool {
+        conditions.iter().all(|condition| {
+            let Some(value) = (condition.parameter_id as usize)
+                .checked_sub(1)
+                .and_then(|index| parameters.get(index))
+                .copied()
+            else {
+                return false;
+            };
+
+            explain::in_range(condition, value)
+                && explain::equal_met(condition, value) != Some(false)
+                && explain::mod_met(condition, value) != Some(false)
+        })
+    }
+
+    /// Reports every parameter condition and the formula of the jump with
+    /// the current values, explaining why the jump is (un)available.
+    ///
+    /// Random formulas are evaluated without affecting the player.
+    pub fn explain_jump(&self, id: JumpId) -> Vec<ConditionResult> {
+        let Some(jump) = self.quest.jump(id) else {
+            return Vec::new();
+        };
+
+        let mut results: Vec<ConditionResult> = jump
+            .parameters_conditions
+            .iter()
+            .flat_map(|condition| {
+                let value = (condition.parameter_id as usize)
+                    .checked_sub(1)
+                    .and_then(|index| self.state.parameters.get(index))
+                    .copied();
+
+                explain::explain_condition(condition, value)
+            })
+            .collect();
+
+        if !jump.formula.is_empty() {
+            let value = eval_formula(
+                &jump.formula,
+                &self.state.parameters,
+                &mut self.rng_copy(),
+                &mut self.budget.clone(),
+                &self.options,
+            );
+
+            results.push(ConditionResult {
+                condition: Condition::Formula(jump.formula.to_string()),
+                value,
+                passed: value.unwrap_or(0) != 0,
+            });
+        }
+
+        results
+    }
+
+    fn eval(&mut self, formula: &Formula) -> Option<i32> {
+        eval_formula(
+            formula,
+            &self.state.parameters,
+            &mut self.rng.0,
+            &mut self.budget,
+            &self.options,
+        )
+    }
+}
+
+fn eval_formula(
+    formula: &Formula,
+    parameters: &[i32],
+    rng: &mut Rng,
+    budget: &mut FormulaBudget,
+    options: &PlayerOptions,
+) -> Option<i32> {
+    eval_expression(
+        &formula.expression().ok()?,
+        parameters,
+        rng,
+        budget,
+        options,
+    )
+}
+
+fn eval_expression(
+    expression: &Expression,
+    parameters: &[i32],
+    rng: &mut Rng,
+    budget: &mut FormulaBudget,
+    options: &PlayerOptions,
+) -> Option<i32> {
+    let value = expression.eval(&mut Evaluator {
+        parameters,
+        rng,
+        budget,
+        options,
+    });
+    trace_event!(trace, formula = %expression, result = ?value, "formula evaluated");
+
+    if let Err(err @ (EvalError::OperationLimit | EvalError::RangeTooLarge(_))) = value {
+        trace_event!(warn, formula = %expression, error = %err, "formula ran into a limit");
+        budget.exceeded.get_or_insert(err);
+    }
+
+    let value = value.ok()?;
+
+    Some(value.round().clamp(i32::MIN as f64, i32::MAX as f64) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, time::Duration};
+
+    use qmm_syntax::{
+        qmm::{
+            parse_qmm, JumpId, JumpParameterCondition, LocationId, LocationSelectType,
+            LocationType, MaxVisits, ParameterChange, ParameterChangeType, ParameterShowType,
+            Quest, TextSource,
+        },
+        text::{
+            expression::EvalError,
+            formatted_text::{FormattedText, TextElementKind},
+            formula::{Formula, FormulaParseOptions},
+        },
+    };
+
+    use crate::{
+        Condition, ConditionResult, Ending, EngineError, FormulaBudget, MoneyFormat, PlayerAction,
+        PlayerContext, PlayerOptions, Progress, QuestDate, QuestEnv, QuestError, QuestPlayer,
+        QuestSnapshot, StepResult, TgeVersion,
+    };
+
+    pub fn quest() -> Quest {
+        parse_qmm(&fs::read("../qmm-syntax/test.qmm").unwrap()).unwrap()
+    }
+
+    /// [`quest`] with the jump 2 looping back into the starting location 1,
+    /// which can be visited and left any number of times without changing
+    /// the parameters.
+    pub fn looping_quest() -> Quest {
+        let mut quest = quest();
+
+        let location = quest
+            .locations
+            .iter_mut()
+            .find(|location| location.id == LocationId(1))
+            .unwrap();
+        location.parameter_changes.clear();
+        location.max_visits = MaxVisits::Infinite;
+
+        let jump = quest.jumps.iter_mut().find(|j| j.id == JumpId(2)).unwrap();
+        jump.to = LocationId(1);
+        jump.max_visits = MaxVisits::Infinite;
+        jump.description = FormattedText::default();
+        jump.parameter_changes.clear();
+
+        quest
+    }
+
+    #[test]
+    pub fn starting_state() {
+        let quest = quest();
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        let state = player.state();
+
+        assert_eq!(state.location.id, LocationId(1));
+        assert_eq!(state.parameters.len(), quest.parameters.len());
+        assert_eq!(state.parameters[0], 20);
+        assert!(state.jumps.iter().any(|jump| jump.id == JumpId(2)));
+    }
+
+    #[test]
+    pub fn unavailable_jump() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(u32::MAX))),
+            StepResult::InvalidAction
+        );
+        assert_eq!(player.state().location.id, LocationId(1));
+    }
+
+    #[test]
+    pub fn preview_matches_step() {
+        let mut quest = quest();
+        let target = quest.jump(JumpId(2)).unwrap().to;
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+        let location_index = quest
+            .locations
+            .iter()
+            .position(|location| location.id == target)
+            .unwrap();
+
+        quest.jumps[jump_index].description = FormattedText::parse("Здоровье: [p1]");
+
+        // The target changes the parameter shown by the description and is
+        // left through its only jump without text
+        let change = ParameterChange {
+            change_type: ParameterChangeType::Value,
+            change: 5,
+            ..quest.locations[0].parameter_changes[0].clone()
+        };
+        let location = &mut quest.locations[location_index];
+        location.texts = vec![FormattedText::default()];
+        location.parameter_changes = vec![change];
+
+        let exit = quest.jumps_from(target).next().unwrap().id;
+        quest
+            .jumps
+            .retain(|jump| jump.from != target || jump.id == exit);
+
+        let exit_index = quest.jumps.iter().position(|j| j.id == exit).unwrap();
+        quest.jumps[exit_index].text = FormattedText::default();
+        quest.jumps[exit_index].description = FormattedText::default();
+        quest.jumps[exit_index].parameters_conditions.clear();
+        quest.jumps[exit_index].formula = Formula::default();
+
+        let mut player = QuestPlayer::builder(&quest)
+            .seed(1)
+            .options(PlayerOptions::sr2())
+            .build()
+            .unwrap();
+        let before = player.state().clone();
+        let preview = player.preview_jump(JumpId(2)).unwrap();
+
+        let StepResult::Description(description) = player.step(PlayerAction::Jump(JumpId(2)))
+        else {
+            panic!("the jump has a description");
+        };
+        let result = player.step(PlayerAction::Continue);
+        let state = player.state();
+
+        assert_eq!(preview.description, description);
+        assert_eq!(
+            preview.description.elements[1].value,
+            before.parameters[0].to_string()
+        );
+        assert_eq!(preview.result, result);
+        assert_ne!(state.location.id, target);
+        assert_eq!(preview.location_text, state.location.description);
+        assert_eq!(preview.days, state.days_passed - before.days_passed);
+
+        for delta in &preview.parameters {
+            assert_eq!(state.parameters[delta.parameter_id as usize - 1], delta.to);
+        }
+    }
+
+    #[test]
+    pub fn preview_does_not_commit() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+        let before = player.state().clone();
+        let preview = player.preview_jump(JumpId(2)).unwrap();
+
+        assert_eq!(preview.target, LocationId(2));
+        assert_eq!(player.state().location.id, before.location.id);
+        assert_eq!(player.state().parameters, before.parameters);
+        assert_eq!(player.state().jump_passes, before.jump_passes);
+        assert!(player.preview_jump(JumpId(u32::MAX)).is_none());
+
+        player.step(PlayerAction::Jump(JumpId(2)));
+
+        assert_eq!(player.state().location.id, preview.target);
+        assert_eq!(player.state().location.description, preview.location_text);
+        assert_eq!(player.state().days_passed, preview.days);
+
+        for delta in &preview.parameters {
+            assert_eq!(
+                before.parameters[delta.parameter_id as usize - 1],
+                delta.from
+            );
+            assert_eq!(
+                player.state().parameters[delta.parameter_id as usize - 1],
+                delta.to
+            );
+        }
+    }
+
+    #[test]
+    pub fn critical_parameter_interrupts() {
+        let mut quest = quest();
+        let death_change = quest.locations[0].parameter_changes[0].clone();
+        let jump = quest.jump(JumpId(2)).unwrap().clone();
+        let jump_index = quest.jumps.iter().position(|j| j.id == jump.id).unwrap();
+
+        // `[p1]` is the "Death" health parameter with the critical minimum of 0
+        quest.jumps[jump_index].parameter_changes = vec![ParameterChange {
+            change_type: ParameterChangeType::Formula,
+            formula: Formula::parse("0").unwrap(),
+            critical_text: "Критично".to_string(),
+            ..death_change
+        }];
+
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+        let result = player.step(PlayerAction::Jump(JumpId(2)));
+
+        assert_eq!(
+            result,
+            StepResult::Message(FormattedText::parse("Критично"))
+        );
+        assert_eq!(player.state().location.id, LocationId(1));
+        assert!(player.state().jumps.is_empty());
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::InvalidAction
+        );
+        assert_eq!(player.step(PlayerAction::Acknowledge), StepResult::Death);
+        assert_eq!(player.state().progress, Progress::Finished(Ending::Death));
+        assert_eq!(player.step(PlayerAction::DoNothing), StepResult::Death);
+        assert_eq!(
+            player.step(PlayerAction::Acknowledge),
+            StepResult::InvalidAction
+        );
+    }
+
+    #[test]
+    pub fn location_texts_by_order() {
+        let mut quest = looping_quest();
+        let location = quest
+            .locations
+            .iter_mut()
+            .find(|location| location.id == LocationId(1))
+            .unwrap();
+        location.texts = ["Первый", "", "Второй", "Третий"]
+            .map(FormattedText::parse)
+            .to_vec();
+        location.select_type = LocationSelectType::ByOrder;
+
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+        let mut texts = vec![player.state().location.description.clone()];
+
+        for _ in 0..3 {
+            assert_eq!(
+                player.step(PlayerAction::Jump(JumpId(2))),
+                StepResult::InProgress
+            );
+            texts.push(player.state().location.description.clone());
+        }
+
+        // The empty text is skipped and the texts start over after the last one
+        assert_eq!(
+            texts,
+            ["Первый", "Второй", "Третий", "Первый"].map(FormattedText::parse)
+        );
+        assert_eq!(player.state().location_texts[&LocationId(1)], 0);
+
+        player.step(PlayerAction::Jump(JumpId(2)));
+
+        let resumed = QuestPlayer::builder(&quest)
+            .snapshot(player.snapshot().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            resumed.state().location.description,
+            FormattedText::parse("Второй")
+        );
+    }
+
+    #[test]
+    pub fn text_coverage() {
+        let mut quest = looping_quest();
+        let location = quest
+            .locations
+            .iter_mut()
+            .find(|location| location.id == LocationId(1))
+            .unwrap();
+        location.texts = ["Первый {1}", "Второй", "Третий"]
+            .map(FormattedText::parse)
+            .to_vec();
+        location.select_type = LocationSelectType::ByOrder;
+
+        let mut player = QuestPlayer::builder(&quest)
+            .seed(1)
+            .track_coverage()
+            .build()
+            .unwrap();
+        player.step(PlayerAction::Jump(JumpId(2)));
+        player.step(PlayerAction::Jump(JumpId(2)));
+        player.step(PlayerAction::Jump(JumpId(2)));
+
+        let coverage = player.coverage().unwrap();
+        assert_eq!(coverage.shown_texts(LocationId(1)), [0, 1, 2].into());
+        assert_eq!(coverage.location_texts[&(LocationId(1), 0)], 2);
+
+        let source = TextSource::LocationText {
+            location: LocationId(1),
+            index: 1,
+        };
+        assert_eq!(
+            coverage.text_formulas[&(source, "1".to_string())],
+            [("1".to_string(), 2)].into()
+        );
+
+        let mut merged = coverage.clone();
+        merged.merge(coverage);
+        assert_eq!(merged.location_texts[&(LocationId(1), 0)], 4);
+
+        assert!(QuestPlayer::new(&quest, 1).unwrap().coverage().is_none());
+    }
+
+    #[test]
+    pub fn selection_variables() {
+        let mut quest = quest();
+        let location_index = quest
+            .locations
+            .iter()
+            .position(|location| location.id == LocationId(1))
+            .unwrap();
+
+        quest.locations[location_index].texts =
+            vec![FormattedText::parse("<clr><Ranger>, {2*2}<clrEnd>")];
+
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        let description = &player.state().location.description;
+
+        assert_eq!(description.to_string(), "<clr>Греф, 4<clrEnd>");
+
+        let TextElementKind::Selection { text } = &description.elements[0].kind else {
+            panic!("expected selection, got {description:?}");
+        };
+        assert_eq!(text.to_string(), "Греф, 4");
+    }
+
+    #[test]
+    pub fn quest_dates() {
+        let date = QuestDate::new(3300, 3, 15);
+
+        assert_eq!(date.to_string(), "15 Марта 3300");
+        assert_eq!(date.add_days(0), date);
+        assert_eq!(date.add_days(17), QuestDate::new(3300, 4, 1));
+        assert_eq!(date.add_days(365), QuestDate::new(3301, 3, 15));
+        // 3300 isn't a leap year
+        assert_eq!(
+            QuestDate::new(3300, 2, 28).add_days(1),
+            QuestDate::new(3300, 3, 1)
+        );
+        assert_eq!(
+            QuestDate::new(3304, 2, 28).add_days(1),
+            QuestDate::new(3304, 2, 29)
+        );
+        assert_eq!(
+            QuestDate::new(3300, 12, 31).add_days(1).to_string(),
+            "1 Января 3301"
+        );
+    }
+
+    #[test]
+    pub fn deadline_variables() {
+        let mut quest = looping_quest();
+        let location = quest
+            .locations
+            .iter_mut()
+            .find(|location| location.id == LocationId(1))
+            .unwrap();
+        location.texts = vec![FormattedText::parse("<Date>, осталось <Day>")];
+        location.do_pass_day = true;
+
+        let jump = quest.jumps.iter_mut().find(|j| j.id == JumpId(2)).unwrap();
+        jump.do_pass_day = false;
+
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        assert_eq!(
+            player.state().location.description.to_string(),
+            "15 Марта 3300, осталось 15 Марта"
+        );
+
+        let context = PlayerContext {
+            start_date: Some(QuestDate::new(3300, 3, 15)),
+            deadline: Some(2),
+            ..Default::default()
+        };
+        let mut player = QuestPlayer::with_context(&quest, 1, context).unwrap();
+        let mut texts = vec![player.state().location.description.to_string()];
+
+        for _ in 0..2 {
+            player.step(PlayerAction::Jump(JumpId(2)));
+            texts.push(player.state().location.description.to_string());
+        }
+
+        assert_eq!(
+            texts,
+            [
+                "17 Марта 3300, осталось 1",
+                "17 Марта 3300, осталось 0",
+                "17 Марта 3300, осталось 0",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn jump_description() {
+        let mut quest = quest();
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+
+        quest.jumps[jump_index].description = FormattedText::parse("Описание");
+
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::Description(FormattedText::parse("Описание"))
+        );
+        assert_eq!(player.state().location.id, LocationId(1));
+        assert_eq!(player.snapshot(), None);
+        assert_eq!(
+            player.step(PlayerAction::Acknowledge),
+            StepResult::InvalidAction
+        );
+        assert_eq!(player.step(PlayerAction::Continue), StepResult::InProgress);
+        assert_eq!(player.state().location.id, LocationId(2));
+        assert_eq!(player.state().progress, Progress::Playing);
+        assert_eq!(
+            player.step(PlayerAction::Continue),
+            StepResult::InvalidAction
+        );
+    }
+
+    #[test]
+    pub fn success_ending() {
+        let mut quest = quest();
+        let location_index = quest
+            .locations
+            .iter()
+            .position(|loc| loc.id == LocationId(2))
+            .unwrap();
+
+        quest.locations[location_index].ty = LocationType::Success;
+
+        let context = PlayerContext {
+            ranger: "Тестер".to_string(),
+            money: 5000,
+            ..Default::default()
+        };
+        let mut player = QuestPlayer::with_context(&quest, 1, context).unwrap();
+        let result = player.step(PlayerAction::Jump(JumpId(2)));
+
+        let StepResult::Success { text, reward, days } = result else {
+            panic!("expected success, got {result:?}");
+        };
+
+        let text = text.to_string();
+
+        assert!(text.starts_with("Поздравляем вас, Тестер!"));
+        assert!(text.contains("Эта сумма в 5000 cr"));
+        assert_eq!(reward, 5000);
+        assert_eq!(days, player.state().days_passed);
+        assert_eq!(player.state().progress, Progress::Finished(Ending::Success));
+        assert!(player.state().jumps.is_empty());
+    }
+
+    #[test]
+    pub fn money_formats() {
+        for (format, value, text) in [
+            (MoneyFormat::Plain, 1234567, "1234567"),
+            (MoneyFormat::English, 1234567, "1,234,567"),
+            (MoneyFormat::English, 1000, "1,000"),
+            (MoneyFormat::English, 999, "999"),
+            (MoneyFormat::Russian, 5000, "5000"),
+            (MoneyFormat::Russian, 10000, "10\u{a0}000"),
+            (MoneyFormat::Russian, 0, "0"),
+        ] {
+            assert_eq!(format.format(value), text);
+        }
+    }
+
+    #[test]
+    pub fn money_variable() {
+        let mut quest = quest();
+        let context = PlayerContext {
+            money: 150000,
+            money_format: MoneyFormat::Russian,
+            ..Default::default()
+        };
+        let money = "150\u{a0}000";
+        let location = quest
+            .locations
+            .iter()
+            .find(|location| {
+                location
+                    .texts
+                    .iter()
+                    .any(|text| text.to_string().contains("<Money>"))
+            })
+            .unwrap()
+            .clone();
+        let mut player = QuestPlayer::with_context(&quest, 1, context.clone()).unwrap();
+
+        assert!(player.task_text().to_string().contains(money));
+        assert!(location
+            .texts
+            .iter()
+            .filter(|text| text.to_string().contains("<Money>"))
+            .all(|text| player.format_text(text).to_string().contains(money)));
+
+        let index = quest
+            .locations
+            .iter()
+            .position(|loc| loc.id == LocationId(2))
+            .unwrap();
+        quest.locations[index].ty = LocationType::Success;
+
+        let mut player = QuestPlayer::with_context(&quest, 1, context).unwrap();
+        let StepResult::Success { text, reward, .. } = player.step(PlayerAction::Jump(JumpId(2)))
+        else {
+            panic!("expected success");
+        };
+
+        assert!(text.to_string().contains(money));
+        assert_eq!(reward, 150000);
+    }
+
+    #[test]
+    pub fn parameter_visibility() {
+        let mut quest = quest();
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+        let change = quest.locations[0].parameter_changes[0].clone();
+
+        quest.parameters[0].show_when_zero = false;
+        quest.jumps[jump_index].parameter_changes = vec![ParameterChange {
+            show_type: ParameterShowType::Show,
+            ..change
+        }];
+
+        let visible = |player: &QuestPlayer| {
+            player
+                .visible_parameters()
+                .map(|(parameter, _)| parameter.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        // The starting location hides `[p1]`
+        assert!(!player.state().parameters_shown[0]);
+        assert!(!visible(&player).contains(&quest.parameters[0].name));
+
+        player.step(PlayerAction::Jump(JumpId(2)));
+
+        assert!(player.state().parameters_shown[0]);
+        assert!(visible(&player).contains(&quest.parameters[0].name));
+
+        player.state.parameters[0] = 0;
+
+        assert!(!visible(&player).contains(&quest.parameters[0].name));
+        assert!(visible(&player).iter().all(|name| quest
+            .parameters
+            .iter()
+            .any(|p| p.is_active && &p.name == name)));
+    }
+
+    #[test]
+    pub fn parameter_texts() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+        let parameter = &quest.parameters[0];
+
+        player.state.parameters[0] = 4;
+
+        assert_eq!(
+            player.parameter_text(parameter, 4).unwrap().to_string(),
+            "Ваше здоровье: 20%"
+        );
+        assert_eq!(
+            player.parameter_text(parameter, 0).unwrap().to_string(),
+            "Вы мертвы"
+        );
+        assert_eq!(player.parameter_text(parameter, 21), None);
+
+        let seed = player.rng.0.get_seed();
+        player.parameter_texts();
+
+        assert_eq!(player.rng.0.get_seed(), seed);
+    }
+
+    fn apply_change(change_type: ParameterChangeType, change: i32, formula: &str) -> i32 {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        // `[p1]` is limited to `0..=20`
+        player.state.parameters[0] = 10;
+        player.apply_parameter_changes(&[ParameterChange {
+            change,
+            change_type,
+            formula: Formula::parse(formula).unwrap(),
+            ..quest.locations[0].parameter_changes[0].clone()
+        }]);
+
+        player.state.parameters[0]
+    }
+
+    #[test]
+    pub fn value_change() {
+        assert_eq!(apply_change(ParameterChangeType::Value, 15, ""), 15);
+        assert_eq!(apply_change(ParameterChangeType::Value, 100, ""), 20);
+        assert_eq!(apply_change(ParameterChangeType::Value, -5, ""), 0);
+    }
+
+    #[test]
+    pub fn sum_change() {
+        assert_eq!(apply_change(ParameterChangeType::Sum, 3, ""), 13);
+        assert_eq!(apply_change(ParameterChangeType::Sum, -4, ""), 6);
+        assert_eq!(apply_change(ParameterChangeType::Sum, 50, ""), 20);
+        assert_eq!(apply_change(ParameterChangeType::Sum, -50, ""), 0);
+    }
+
+    #[test]
+    pub fn percentage_change() {
+        assert_eq!(apply_change(ParameterChangeType::Percentage, 50, ""), 15);
+        assert_eq!(apply_change(ParameterChangeType::Percentage, -25, ""), 7);
+        assert_eq!(apply_change(ParameterChangeType::Percentage, 15, ""), 12);
+        assert_eq!(apply_change(ParameterChangeType::Percentage, 500, ""), 20);
+        assert_eq!(apply_change(ParameterChangeType::Percentage, -200, ""), 0);
+    }
+
+    #[test]
+    pub fn formula_change() {
+        assert_eq!(apply_change(ParameterChangeType::Formula, 0, "[p1]*3/4"), 8);
+        assert_eq!(apply_change(ParameterChangeType::Formula, 0, "[p1]*5"), 20);
+        assert_eq!(apply_change(ParameterChangeType::Formula, 0, "(2+3)"), 5);
+    }
+
+    #[test]
+    pub fn empty_location_hops() {
+        let mut quest = quest();
+        let start = quest.locations[0].id;
+        let target = quest.jump(JumpId(2)).unwrap().to;
+
+        quest.locations[0].texts = vec![FormattedText::default()];
+        quest.locations[0].parameter_changes.clear();
+        quest
+            .jumps
+            .retain(|jump| jump.from != start || jump.id == JumpId(2));
+
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+        quest.jumps[jump_index].text = FormattedText::default();
+        quest.jumps[jump_index].description = FormattedText::default();
+
+        let player = |tge_behavior| {
+            QuestPlayer::builder(&quest)
+                .seed(1)
+                .options(PlayerOptions {
+                    tge_behavior,
+                    ..PlayerOptions::sr2()
+                })
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(player(TgeVersion::Tge4).state().location.id, start);
+        assert_eq!(player(TgeVersion::Tge5).state().location.id, target);
+    }
+
+    #[test]
+    pub fn empty_jumps_loop() {
+        let mut quest = quest();
+        let start = quest.locations[0].id;
+
+        quest.locations[0].texts = vec![FormattedText::default()];
+        quest.locations[0].parameter_changes.clear();
+        quest.locations[0].max_visits = MaxVisits::Infinite;
+        quest
+            .jumps
+            .retain(|jump| jump.from != start || jump.id == JumpId(2));
+
+        // The only jump leads back into the location
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+        let jump = &mut quest.jumps[jump_index];
+        jump.to = start;
+        jump.text = FormattedText::default();
+        jump.description = FormattedText::default();
+        jump.parameter_changes.clear();
+        jump.parameters_conditions.clear();
+        jump.max_visits = MaxVisits::Infinite;
+
+        let mut player = QuestPlayer::builder(&quest)
+            .seed(1)
+            .options(PlayerOptions::modern())
+            .build()
+            .unwrap();
+        let before = player.clone();
+
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::EngineError(EngineError::Loop)
+        );
+        // The jumps taken before the watchdog stopped the step are undone
+        assert_eq!(player.differences(&before), Vec::<&str>::new());
+        assert_eq!(player.state().location.id, start);
+        assert_eq!(player.state().jump_passes, before.state().jump_passes);
+        assert_eq!(player.steps(), 1);
+    }
+
+    #[test]
+    pub fn step_limit() {
+        let quest = quest();
+        let mut player = QuestPlayer::builder(&quest)
+            .seed(1)
+            .options(PlayerOptions {
+                max_steps: Some(1),
+                ..PlayerOptions::sr2()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(100_000))),
+            StepResult::InvalidAction
+        );
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::InProgress
+        );
+        assert_eq!(player.steps(), 1);
+
+        let jump = player.state().jumps[0].id;
+        assert_eq!(
+            player.step(PlayerAction::Jump(jump)),
+            StepResult::EngineError(EngineError::StepLimit)
+        );
+        assert_eq!(player.step(PlayerAction::DoNothing), StepResult::InProgress);
+    }
+
+    #[test]
+    pub fn strict_math() {
+        let mut quest = quest();
+        quest.parameters[0].starting_value = "10/0".to_string();
+
+        let player = |options| {
+            QuestPlayer::builder(&quest)
+                .options(options)
+                .build()
+                .unwrap()
+                .state()
+                .parameters[0]
+        };
+
+        // Saturated, then clamped to `0..=20` by the starting location's change
+        assert_eq!(player(PlayerOptions::sr2()), 20);
+        assert_eq!(player(PlayerOptions::modern()), 0);
+    }
+
+    #[test]
+    pub fn formula_limits() {
+        let mut quest = quest();
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+
+        let available = |quest: &Quest, options| {
+            QuestPlayer::builder(quest)
+                .seed(1)
+                .options(options)
+                .build()
+                .unwrap()
+                .state()
+                .jumps
+                .iter()
+                .any(|jump| jump.id == JumpId(2) && jump.available)
+        };
+        let limited = |max_formula_operations, max_range_size| PlayerOptions {
+            max_formula_operations,
+            max_range_size,
+            ..PlayerOptions::sr2()
+        };
+
+        quest.jumps[jump_index].formula = Formula::parse("[1..100] > 0").unwrap();
+        assert!(available(&quest, PlayerOptions::sr2()));
+        assert!(available(&quest, limited(None, Some(100))));
+        assert!(!available(&quest, limited(None, Some(99))));
+
+        // The open ranges checked with `in` span the whole `i32`
+        quest.jumps[jump_index].formula = Formula::parse("[p1] in [5..]").unwrap();
+        assert!(available(&quest, PlayerOptions::modern()));
+
+        let sum = ["[p1]"; 50].join(" + ");
+        quest.jumps[jump_index].formula = Formula::parse(&format!("{sum} >= 0")).unwrap();
+        assert!(available(&quest, PlayerOptions::modern()));
+        assert!(!available(&quest, limited(Some(50), None)));
+    }
+
+    #[test]
+    pub fn lenient_text_formulas() {
+        let mut quest = quest();
+        quest.locations[0].texts = vec![FormattedText::parse("{[p1] * 1,5}")];
+
+        let text = |formula_options| {
+            let player = QuestPlayer::builder(&quest)
+                .seed(1)
+                .options(PlayerOptions {
+                    formula_options,
+                    ..PlayerOptions::sr2()
+                })
+                .build()
+                .unwrap();
+
+            player.state().location.description.elements[0]
+                .value
+                .clone()
+        };
+
+        assert_ne!(text(FormulaParseOptions::default()), "30");
+        assert_eq!(
+            text(FormulaParseOptions {
+                lenient_numbers: true,
+                ..Default::default()
+            }),
+            "30"
+        );
+    }
+
+    #[test]
+    pub fn formula_limit_errors() {
+        let mut quest = quest();
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+        let sum = ["[p1]"; 50].join(" + ");
+
+        quest.jumps[jump_index].formula = Formula::parse(&format!("{sum} >= 0")).unwrap();
+
+        let mut player = QuestPlayer::builder(&quest)
+            .options(PlayerOptions {
+                max_formula_operations: Some(50),
+                ..PlayerOptions::sr2()
+            })
+            .build()
+            .unwrap();
+        let jump = quest.jump(JumpId(2)).unwrap();
+
+        assert!(!player.is_jump_available(jump));
+
+        // The failure isn't reused for the same parameter values
+        player.options.max_formula_operations = None;
+        player.budget = FormulaBudget::default();
+        assert!(player.is_jump_available(jump));
+
+        let mut quest = quest.clone();
+        quest.jumps[jump_index].formula = Formula::default();
+        quest.jumps[jump_index].parameter_changes = vec![ParameterChange {
+            change_type: ParameterChangeType::Formula,
+            formula: Formula::parse(&sum).unwrap(),
+            ..quest.locations[0].parameter_changes[0].clone()
+        }];
+
+        let mut player = QuestPlayer::builder(&quest)
+            .options(PlayerOptions {
+                max_formula_operations: Some(50),
+                ..PlayerOptions::sr2()
+            })
+            .build()
+            .unwrap();
+        let before = player.clone();
+
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::EngineError(EngineError::FormulaLimit(EvalError::OperationLimit))
+        );
+        // The jump is undone
+        assert_eq!(player.differences(&before), Vec::<&str>::new());
+        assert_eq!(player.state().location.id, before.state().location.id);
+        assert_eq!(player.state().parameters, before.state().parameters);
+
+        // Without the limit the same step goes through
+        player.options.max_formula_operations = None;
+        assert_eq!(
+            player.step(PlayerAction::Jump(JumpId(2))),
+            StepResult::InProgress
+        );
+        assert_ne!(player.state().location.id, before.state().location.id);
+    }
+
+    #[test]
+    pub fn explain_jump() {
+        let mut quest = quest();
+        let jump_index = quest.jumps.iter().position(|j| j.id == JumpId(2)).unwrap();
+
+        quest.jumps[jump_index].formula = Formula::parse("[p1] > 100").unwrap();
+        quest.jumps[jump_index].parameters_conditions = vec![JumpParameterCondition {
+            parameter_id: 1,
+            range_start: 0,
+            range_end: 20,
+            must_equal: false,
+            must_equal_values: vec![20],
+            must_mod: true,
+            must_mod_values: vec![2],
+        }];
+
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        let value = player.state().parameters[0];
+        let results = player.explain_jump(JumpId(2));
+
+        assert_eq!(value, 20);
+        assert_eq!(
+            results,
+            vec![
+                ConditionResult {
+                    condition: Condition::Range {
+                        parameter_id: 1,
+                        start: 0,
+                        end: 20
+                    },
+                    value: Some(20),
+                    passed: true,
+                },
+                ConditionResult {
+                    condition: Condition::Equal {
+                        parameter_id: 1,
+                        values: vec![20],
+                        must_equal: false
+                    },
+                    value: Some(20),
+                    passed: false,
+                },
+                ConditionResult {
+                    condition: Condition::Modulo {
+                        parameter_id: 1,
+                        values: vec![2],
+                        must_mod: true
+                    },
+                    value: Some(20),
+                    passed: true,
+                },
+                ConditionResult {
+                    condition: Condition::Formula("[p1] > 100".to_string()),
+                    value: Some(0),
+                    passed: false,
+                },
+            ]
+        );
+        assert!(!player
+            .state()
+            .jumps
+            .iter()
+            .any(|jump| jump.id == JumpId(2) && jump.available));
+        assert!(player.explain_jump(JumpId(u32::MAX)).is_empty());
+    }
+
+    #[test]
+    pub fn resume_snapshot() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        if let StepResult::Description(_) = player.step(PlayerAction::Jump(JumpId(2))) {
+            player.step(PlayerAction::Continue);
+        }
+
+        let state = player.state();
+        let snapshot = QuestSnapshot {
+            location: state.location.id,
+            parameters: state.parameters.clone(),
+            parameters_shown: state.parameters_shown.clone(),
+            location_visits: state.location_visits.clone(),
+            location_texts: state.location_texts.clone(),
+            jump_passes: state.jump_passes.clone(),
+            days_passed: state.days_passed,
+        };
+        let resumed = QuestPlayer::builder(&quest)
+            .snapshot(snapshot.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(player.snapshot().as_ref(), Some(&snapshot));
+        assert_eq!(resumed.snapshot().as_ref(), Some(&snapshot));
+
+        assert_eq!(resumed.state().location.id, state.location.id);
+        assert_eq!(
+            resumed.state().location.description,
+            state.location.description
+        );
+        assert_eq!(resumed.state().parameters, state.parameters);
+        assert_eq!(resumed.state().location_visits, state.location_visits);
+        assert_eq!(
+            resumed
+                .state()
+                .jumps
+                .iter()
+                .map(|jump| jump.id)
+                .collect::<Vec<_>>(),
+            state.jumps.iter().map(|jump| jump.id).collect::<Vec<_>>()
+        );
+
+        let invalid = QuestSnapshot {
+            location: LocationId(u32::MAX),
+            ..snapshot.clone()
+        };
+        assert_eq!(
+            QuestPlayer::builder(&quest).snapshot(invalid).build().err(),
+            Some(QuestError::InvalidSnapshot)
+        );
+
+        let invalid = QuestSnapshot {
+            parameters: vec![0],
+            ..snapshot
+        };
+        assert_eq!(
+            QuestPlayer::builder(&quest).snapshot(invalid).build().err(),
+            Some(QuestError::InvalidSnapshot)
+        );
+    }
+
+    #[test]
+    pub fn reload_edited_quest() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+
+        if let StepResult::Description(_) = player.step(PlayerAction::Jump(JumpId(2))) {
+            player.step(PlayerAction::Continue);
+        }
+
+        let state = player.state().clone();
+        let mut edited = quest.clone();
+        edited.locations[0].texts = vec![FormattedText::parse("Edited")];
+        edited.parameters[0].max_value = state.parameters[0] - 1;
+        edited.parameters.push(edited.parameters[1].clone());
+        edited.parameters.last_mut().unwrap().starting_value = "7".to_string();
+
+        let reloaded = player.reload(&edited).unwrap();
+        let parameters = &reloaded.state().parameters;
+
+        assert_eq!(reloaded.state().location.id, state.location.id);
+        assert_eq!(parameters[0], state.parameters[0] - 1);
+        assert_eq!(parameters[1..state.parameters.len()], state.parameters[1..]);
+        assert_eq!(parameters.last(), Some(&7));
+        assert_eq!(reloaded.state().jump_passes, state.jump_passes);
+
+        edited
+            .locations
+            .retain(|location| location.id != state.location.id);
+        assert_eq!(
+            player.reload(&edited).err(),
+            Some(QuestError::InvalidSnapshot)
+        );
+    }
+
+    #[test]
+    pub fn location_time() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 1).unwrap();
+        let start = player.state().location.id;
+
+        assert!(player.state().location_time.is_empty());
+
+        std::thread::sleep(Duration::from_millis(5));
+        if let StepResult::Description(_) = player.step(PlayerAction::Jump(JumpId(2))) {
+            player.step(PlayerAction::Continue);
+        }
+
+        let location_time = &player.state().location_time;
+        assert!(location_time[&start] >= Duration::from_millis(5));
+        assert!(player.session_time() >= location_time.values().sum());
+    }
+
+    #[test]
+    pub fn shortest_paths() {
+        let quest = quest();
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        let distances = crate::shortest_paths(&player, 5_000);
+        let unconditional = quest.distances(LocationId(1));
+
+        assert_eq!(distances[&LocationId(1)], 0);
+        assert!(distances.len() > 1);
+
+        for (location, distance) in &distances {
+            assert!(*distance >= unconditional[location]);
+        }
+
+        assert_eq!(crate::shortest_paths(&player, 1).len(), 1);
+    }
+
+    #[test]
+    pub fn state_hash_key() {
+        let quest = quest();
+        let player = QuestPlayer::new(&quest, 1).unwrap();
+        let key = player.state().hash_key();
+
+        assert_eq!(QuestPlayer::new(&quest, 1).unwrap().state().hash_key(), key);
+
+        let mut moved = player.clone();
+        moved.step(PlayerAction::Jump(JumpId(2)));
+        assert_ne!(moved.state().hash_key(), key);
+
+        // Doesn't depend on the text and the time
+        let mut state = player.state().clone();
+        state.location.description = FormattedText::from("Другой текст");
+        state
+            .location_time
+            .insert(LocationId(1), Duration::from_secs(1));
+        assert_eq!(state.hash_key(), key);
+
+        state.parameters[0] += 1;
+        assert_ne!(state.hash_key(), key);
+    }
+
+    #[test]
+    pub fn deterministic_replays() {
+        let quest = quest();
+        let play = |seed| {
+            let mut player = QuestPlayer::new(&quest, seed).unwrap();
+            let rng = fastrand::Rng::with_seed(seed);
+            let mut players = vec![player.clone()];
+
+            for _ in 0..200 {
+                let jumps: Vec<JumpId> = player
+                    .state()
+                    .jumps
+                    .iter()
+                    .filter(|jump| jump.available)
+                    .map(|jump| jump.id)
+                    .collect();
+                let action = match &player.state().progress {
+                    Progress::Playing if jumps.is_empty() => break,
+                    Progress::Playing => PlayerAction::Jump(jumps[rng.usize(..jumps.len())]),
+                    Progress::Description { .. } => PlayerAction::Continue,
+                    Progress::Message { .. } => PlayerAction::Acknowledge,
+                    Progress::Finished(_) => break,
+                };
+
+                player.step(action);
+                players.push(player.clone());
+            }
+
+            players
+        };
+
+        let first = play(7);
+        let second = play(7);
+
+        assert!(first.len() > 1);
+        assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.differences(b), Vec::<&str>::new());
+        }
+
+        // A clone rolls the same numbers as the original
+        let player = QuestPlayer::new(&quest, 7).unwrap();
+        let clone = player.clone();
+        assert_eq!(player.rng.0.f64(), clone.rng.0.f64());
+
+        let other = QuestPlayer::new(&quest, 8).unwrap();
+        assert!(first[0].differences(&other).contains(&"random generator"));
+    }
+
+    #[test]
+    pub fn env_episode() {
+        let quest = quest();
+        let mut env = QuestEnv::new(QuestPlayer::builder(&quest)).unwrap();
+        let rng = fastrand::Rng::with_seed(3);
+
+        for seed in 0..10 {
+            let mut observation = env.reset(seed).unwrap();
+
+            for _ in 0..1000 {
+                assert_eq!(observation.features.len(), env.observation_len());
+                assert_eq!(observation.action_mask.len(), env.action_count());
+                assert_eq!(
+                    observation.features[quest.parameters.len()..]
+                        .iter()
+                        .filter(|value| **value == 1.0)
+                        .count(),
+                    if observation.action_mask[0] { 2 } else { 1 }
+                );
+
+                let actions: Vec<usize> = (0..env.action_count())
+                    .filter(|action| observation.action_mask[*action])
+                    .collect();
+
+                if actions.is_empty() {
+                    break;
+                }
+
+                let masked =
+                    (0..env.action_count()).find(|action| !observation.action_mask[*action]);
+                if let Some(masked) = masked {
+                    assert_eq!(env.step(masked).result, StepResult::InvalidAction);
+                }
+
+                let step = env.step(actions[rng.usize(..actions.len())]);
+                assert_ne!(step.result, StepResult::InvalidAction);
+                observation = step.observation;
+
+                if step.done {
+                    assert!(matches!(
+                        env.player().state().progress,
+                        Progress::Finished(_)
+                    ));
+                    assert_ne!(step.reward, 0.0);
+                    assert!(observation.action_mask.iter().all(|available| !available));
+
+                    // The ending is rewarded once
+                    let after = env.step(actions[0]);
+                    assert_eq!(after.result, StepResult::InvalidAction);
+                    assert_eq!(after.reward, 0.0);
+                    assert!(after.done);
+                    break;
+                }
+
+                assert_eq!(step.reward, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    pub fn cached_availability_matches_fresh() {
+        let quest = quest();
+        let mut player = QuestPlayer::new(&quest, 7).unwrap();
+
+        for step in 0..50 {
+            for jump in quest.jumps_from(player.state().location.id) {
+                let mut fresh = player.clone();
+                fresh.availability_cache = BTreeMap::new();
+
+                assert_eq!(
+                    player.is_jump_available(jump),
+                    fresh.is_jump_available(jump)
+                );
+            }
+
+            let available: Vec<JumpId> = player
+                .state()
+                .jumps
+                .iter()
+                .filter(|jump| jump.available)
+                .map(|jump| jump.id)
+                .collect();
+
+            let Some(id) = available.get(step % available.len().max(1)) else {
+                break;
+            };
+
+            let mut result = player.step(PlayerAction::Jump(*id));
+
+            if matches!(result, StepResult::Description(_)) {
+                result = player.step(PlayerAction::Continue);
+            }
+
+            if result != StepResult::InProgress {
+                break;
+            }
+        }
+    }
 }
@@ -0,0 +1,71 @@
+use qmm_syntax::text::formula::FormulaParseOptions;
+
+/// Version of the quest engine (TGE) whose behavior the player follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TgeVersion {
+    /// Locations without text are shown to the player like any other
+    Tge4,
+    /// Locations without text are passed through their only jump if it has
+    /// no text either
+    Tge5,
+}
+
+/// Compatibility settings of [`crate::QuestPlayer`].
+///
+/// [`PlayerOptions::sr2`] follows the original game as closely as possible,
+/// [`PlayerOptions::modern`] treats division by zero as an error, gives up on
+/// jump loops sooner and limits the work of the formulas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerOptions {
+    pub tge_behavior: TgeVersion,
+    /// Division by zero fails the formula instead of saturating
+    pub strict_math: bool,
+    /// Limit of jumps without text taken automatically in a row, protects
+    /// from quests that loop through such jumps
+    pub max_empty_hops: usize,
+    /// Actions the session may take, the ones after return
+    /// [`crate::EngineError::StepLimit`]. Unlimited if `None`
+    pub max_steps: Option<usize>,
+    /// Formula operations a step may take, the formulas evaluated after the
+    /// limit fail like the invalid ones. Unlimited if `None`
+    pub max_formula_operations: Option<usize>,
+    /// Largest number of values a formula may pick a random one from, the
+    /// formulas picking from more fail. Unlimited if `None`
+    pub max_range_size: Option<u64>,
+    /// Options of the formulas in the texts and the starting values of the
+    /// parameters, parse the quest with
+    /// [`qmm_syntax::qmm::parse_qmm_with`] for the rest
+    pub formula_options: FormulaParseOptions,
+}
+
+impl PlayerOptions {
+    pub fn sr2() -> Self {
+        Self {
+            tge_behavior: TgeVersion::Tge5,
+            strict_math: false,
+            max_empty_hops: 100,
+            max_steps: None,
+            max_formula_operations: None,
+            max_range_size: None,
+            formula_options: FormulaParseOptions::default(),
+        }
+    }
+
+    pub fn modern() -> Self {
+        Self {
+            tge_behavior: TgeVersion::Tge5,
+            strict_math: true,
+            max_empty_hops: 10,
+            max_steps: None,
+            max_formula_operations: Some(100_000),
+            max_range_size: Some(10_000_000),
+            formula_options: FormulaParseOptions::default(),
+        }
+    }
+}
+
+impl Default for PlayerOptions {
+    fn default() -> Self {
+        Self::sr2()
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use qmm_syntax::qmm::LocationId;
+
+use crate::{PlayerAction, Progress, QuestPlayer};
+
+/// Fewest jumps to every location from the player's state, playing the
+/// quest with its conditions and parameter changes.
+///
+/// States are told apart by [`crate::QuestState::hash_key`], the search
+/// stops after visiting `max_states` of them, so the distances of the large
+/// quests may be longer than the real ones or missing.
+pub fn shortest_paths(player: &QuestPlayer, max_states: usize) -> BTreeMap<LocationId, usize> {
+    let passes = |player: &QuestPlayer| -> usize {
+        player
+            .state
+            .jump_passes
+            .values()
+            .map(|passes| *passes as usize)
+            .sum()
+    };
+    let start = passes(player);
+    let mut distances = BTreeMap::new();
+    let mut visited = BTreeSet::new();
+    // Players by the jumps taken, the jumps without text are passed
+    // automatically and the steps may take more than one jump
+    let mut queue = BTreeMap::from([(0, VecDeque::from([player.clone()]))]);
+
+    while let Some(mut entry) = queue.first_entry() {
+        let jumps = *entry.key();
+        let Some(player) = entry.get_mut().pop_front() else {
+            entry.remove();
+            continue;
+        };
+
+        if visited.len() >= max_states {
+            break;
+        }
+
+        if !visited.insert(player.state.hash_key()) {
+            continue;
+        }
+
+        distances.entry(player.state.location.id).or_insert(jumps);
+
+        let actions: Vec<PlayerAction> = match player.state.progress {
+            Progress::Playing => player
+                .state
+                .jumps
+                .iter()
+                .filter(|jump| jump.available)
+                .map(|jump| PlayerAction::Jump(jump.id))
+                .collect(),
+            Progress::Description { .. } => vec![PlayerAction::Continue],
+            Progress::Message { .. } => vec![PlayerAction::Acknowledge],
+            Progress::Finished(_) => Vec::new(),
+        };
+
+        for action in actions {
+            let mut next = player.clone();
+            next.step(action);
+
+            queue
+                .entry(passes(&next) - start)
+                .or_default()
+                .push_back(next);
+        }
+    }
+
+    distances
+}
@@ -0,0 +1,22 @@
+use std::collections::BTreeMap;
+
+use qmm_syntax::qmm::{JumpId, LocationId};
+
+/// Position in a quest between the player's choices, enough to continue the
+/// quest with [`crate::QuestPlayerBuilder::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuestSnapshot {
+    pub location: LocationId,
+    /// Values of the quest parameters, `[p1]` is at index 0
+    pub parameters: Vec<i32>,
+    /// Whether the parameter is shown or hidden, all of them are shown if
+    /// empty
+    pub parameters_shown: Vec<bool>,
+    pub location_visits: BTreeMap<LocationId, u32>,
+    /// Index of the text shown last in the locations with texts selected by
+    /// order
+    pub location_texts: BTreeMap<LocationId, usize>,
+    pub jump_passes: BTreeMap<JumpId, u32>,
+    pub days_passed: u32,
+}
@@ -0,0 +1,188 @@
+//! Replays quest walkthroughs from `tests/walkthroughs` and checks that they
+//! reach the expected ending.
+//!
+//! Every walkthrough is a text file with one command per line, `#` starts a
+//! comment:
+//!
+//! - `quest <file>` - quest file, looked up in the `QMM_QUESTS_DIR` directory
+//!   or next to the bundled test quest
+//! - `seed <number>` - seed of the player, `0` by default
+//! - `jump <id>` - take the jump with the id
+//! - `choose <text>` - take the available jump with the text
+//! - `continue` - proceed from a jump description
+//! - `acknowledge` - dismiss a critical parameter message
+//! - `ending <success|fail|death>` - expected ending, must be the last command
+//!
+//! Walkthroughs of quests that aren't found are skipped, so the suite is
+//! ignored by default. Run it with `cargo test -- --ignored`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use qmm_player::{Ending, PlayerAction, QuestPlayer, StepResult};
+use qmm_syntax::qmm::{parse_qmm, JumpId};
+
+enum Command {
+    Jump(JumpId),
+    Choose(String),
+    Continue,
+    Acknowledge,
+}
+
+struct Walkthrough {
+    quest: String,
+    seed: u64,
+    commands: Vec<(usize, Command)>,
+    ending: Ending,
+}
+
+impl Walkthrough {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut quest = None;
+        let mut seed = 0;
+        let mut commands = Vec::new();
+        let mut ending = None;
+
+        for (number, line) in text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if ending.is_some() {
+                return Err(format!("line {number}: command after the ending"));
+            }
+
+            let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+            let argument = argument.trim();
+
+            match name {
+                "quest" => quest = Some(argument.to_string()),
+                "seed" => {
+                    seed = argument
+                        .parse()
+                        .map_err(|_| format!("line {number}: invalid seed `{argument}`"))?
+                }
+                "jump" => {
+                    let id = argument
+                        .parse()
+                        .map_err(|_| format!("line {number}: invalid jump id `{argument}`"))?;
+
+                    commands.push((number, Command::Jump(JumpId(id))));
+                }
+                "choose" => commands.push((number, Command::Choose(argument.to_string()))),
+                "continue" => commands.push((number, Command::Continue)),
+                "acknowledge" => commands.push((number, Command::Acknowledge)),
+                "ending" => {
+                    ending = Some(match argument {
+                        "success" => Ending::Success,
+                        "fail" => Ending::Fail,
+                        "death" => Ending::Death,
+                        _ => return Err(format!("line {number}: unknown ending `{argument}`")),
+                    })
+                }
+                _ => return Err(format!("line {number}: unknown command `{name}`")),
+            }
+        }
+
+        Ok(Self {
+            quest: quest.ok_or("missing `quest` command")?,
+            seed,
+            commands,
+            ending: ending.ok_or("missing `ending` command")?,
+        })
+    }
+}
+
+fn quests_dir() -> PathBuf {
+    env::var_os("QMM_QUESTS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../qmm-syntax"))
+}
+
+fn replay(walkthrough: &Walkthrough, quest: &[u8]) -> Result<(), String> {
+    let quest = parse_qmm(quest).map_err(|err| format!("invalid quest: {err:?}"))?;
+    let mut player = QuestPlayer::new(&quest, walkthrough.seed)
+        .map_err(|err| format!("can't start the quest: {err:?}"))?;
+
+    for (number, command) in &walkthrough.commands {
+        let action = match command {
+            Command::Jump(id) => PlayerAction::Jump(*id),
+            Command::Choose(text) => {
+                let jump = player
+                    .state()
+                    .jumps
+                    .iter()
+                    .find(|jump| jump.available && jump.name.to_string() == *text)
+                    .ok_or_else(|| format!("line {number}: no available jump `{text}`"))?;
+
+                PlayerAction::Jump(jump.id)
+            }
+            Command::Continue => PlayerAction::Continue,
+            Command::Acknowledge => PlayerAction::Acknowledge,
+        };
+
+        if player.step(action) == StepResult::InvalidAction {
+            return Err(format!(
+                "line {number}: invalid action at location {}",
+                player.state().location.id.0
+            ));
+        }
+    }
+
+    let ending = match player.step(PlayerAction::DoNothing) {
+        StepResult::Success { .. } => Ending::Success,
+        StepResult::Fail => Ending::Fail,
+        StepResult::Death => Ending::Death,
+        result => return Err(format!("quest isn't finished: {result:?}")),
+    };
+
+    if ending != walkthrough.ending {
+        return Err(format!(
+            "expected {:?} ending, got {ending:?}",
+            walkthrough.ending
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+pub fn walkthroughs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/walkthroughs");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let walkthrough = match Walkthrough::parse(&fs::read_to_string(&path).unwrap()) {
+            Ok(walkthrough) => walkthrough,
+            Err(err) => {
+                failures.push(format!("{name}: {err}"));
+                continue;
+            }
+        };
+
+        let Ok(quest) = fs::read(quests_dir().join(&walkthrough.quest)) else {
+            eprintln!("{name}: skipped, quest `{}` not found", walkthrough.quest);
+            continue;
+        };
+
+        if let Err(err) = replay(&walkthrough, &quest) {
+            failures.push(format!("{name}: {err}"));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
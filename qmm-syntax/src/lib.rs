@@ -1,6 +1,36 @@
+/// Emits a `tracing` event if the `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
 pub mod qmm;
 pub mod text;
 
+/// Items most applications need: the quest model, parsing and writing, and
+/// the text and formula types.
+///
+/// Everything reachable from the prelude is covered by semver. The parsers of
+/// the quest's parts are internal and only exposed with the
+/// `parser-internals` feature.
+pub mod prelude {
+    pub use crate::{
+        qmm::{
+            parse_qmm, parse_qmm_located, parse_qmm_with, patch_qmm, unwrap_container, write_qmm,
+            FormulaSource, Jump, JumpId, JumpParameterCondition, LocatedParsingError, Location,
+            LocationId, LocationType, Parameter, ParameterChange, ParsingError, Quest, QuestText,
+            TextSource,
+        },
+        text::{
+            expression::{EvalContext, Expression},
+            formatted_text::{FormattedText, TextElement, TextElementKind},
+            formula::{Formula, FormulaParseOptions},
+        },
+    };
+}
+
 #[cfg(test)]
 mod qmm_tests {
     use std::fs;
@@ -9,7 +39,7 @@ mod qmm_tests {
         qmm::*,
         text::{
             formatted_text::{FormattedText, TextElement, TextElementKind},
-            formula::Formula,
+            formula::{Formula, FormulaParseOptions},
         },
     };
 
@@ -82,6 +112,41 @@ mod qmm_tests {
         assert_eq!(params[0].name.len(), params[0].name.capacity());
     }
 
+    #[test]
+    pub fn parameter_display() {
+        let mut parameter = parse_qmm(&quest_data()).unwrap().parameters.remove(0);
+
+        assert_eq!(parameter.display_for(0).as_deref(), Some("Вы мертвы"));
+        assert_eq!(
+            parameter.display_for(20).as_deref(),
+            Some("Ваше здоровье: {[p1]*5}%")
+        );
+        assert_eq!(parameter.display_for(21), None);
+
+        parameter.formatted_range_lines = vec![
+            FormattedRangeLine {
+                from: 0,
+                to: 10,
+                value: "Мало: <>".to_string(),
+            },
+            FormattedRangeLine {
+                from: 5,
+                to: 20,
+                value: "Много: <>".to_string(),
+            },
+            FormattedRangeLine {
+                from: 40,
+                to: 30,
+                value: "Никогда".to_string(),
+            },
+        ];
+
+        assert_eq!(parameter.display_for(7).as_deref(), Some("Мало: 7"));
+        assert_eq!(parameter.display_for(15).as_deref(), Some("Много: 15"));
+        assert_eq!(parameter.display_for(35), None);
+        assert_eq!(parameter.display_for(-1), None);
+    }
+
     #[test]
     pub fn parse_string_replacements() {
         let data = quest_data();
@@ -232,6 +297,8 @@ mod qmm_tests {
             locations[0],
             Location {
                 do_pass_day: false,
+                x: 21,
+                y: 77,
                 id: LocationId(1),
                 max_visits: MaxVisits::Infinite,
                 ty: LocationType::Starting,
@@ -251,13 +318,13 @@ mod qmm_tests {
                             kind: TextElementKind::Text,
                             value: " и пройдя таможенные формальности, вы первым делом перевели в ближайшем банкомате ".to_string()
                         }, TextElement {
-                            kind: TextElementKind::Selection { text: "10.000".to_string() },
+                            kind: TextElementKind::Selection { text: FormattedText::parse("10.000") },
                             value: "<clr>10.000<clrEnd>".to_string()
                         }, TextElement {
                             kind: TextElementKind::Text,
                             value: " cr на свою карточку ".to_string()
                         }, TextElement {
-                            kind: TextElementKind::Selection { text: "Galactic Express".to_string() },
+                            kind: TextElementKind::Selection { text: FormattedText::parse("Galactic Express") },
                             value: "<clr>Galactic Express<clrEnd>".to_string()
                         }, TextElement {
                             kind: TextElementKind::Text,
@@ -292,6 +359,7 @@ mod qmm_tests {
             locations[0].parameter_changes[0],
             ParameterChange {
                 parameter_id: 1,
+                change: 0,
                 show_type: ParameterShowType::Hide,
                 change_type: ParameterChangeType::Sum,
                 formula: Formula::default(),
@@ -344,4 +412,530 @@ mod qmm_tests {
             }
         )
     }
+
+    #[test]
+    pub fn edit_jump() {
+        let mut jump = parse_qmm(&quest_data()).unwrap().jumps.remove(0);
+
+        jump.set_condition(JumpParameterCondition::range(1, 0..=5))
+            .unwrap();
+        jump.set_condition(JumpParameterCondition::range(1, 2..=5))
+            .unwrap();
+        jump.set_condition(JumpParameterCondition::range(2, 0..=1))
+            .unwrap();
+        assert_eq!(jump.parameters_conditions.len(), 2);
+        assert_eq!(jump.condition(1).unwrap().range_start, 2);
+
+        assert_eq!(
+            jump.set_condition(JumpParameterCondition::range(0, 0..=1)),
+            Err(JumpEditError::InvalidParameter(0))
+        );
+        assert_eq!(
+            jump.update_condition(1, |condition| condition.range_end = 1),
+            Err(JumpEditError::InvalidRange { start: 2, end: 1 })
+        );
+        assert_eq!(
+            jump.update_condition(1, |condition| condition.parameter_id = 2),
+            Err(JumpEditError::DuplicateCondition(2))
+        );
+        assert_eq!(
+            jump.update_condition(3, |_| ()),
+            Err(JumpEditError::MissingCondition(3))
+        );
+        jump.update_condition(1, |condition| {
+            condition.must_equal = true;
+            condition.must_equal_values = vec![3];
+        })
+        .unwrap();
+        assert_eq!(jump.condition(1).unwrap().must_equal_values, vec![3]);
+        assert_eq!(jump.remove_condition(2).unwrap().parameter_id, 2);
+        assert!(jump.condition(2).is_none());
+
+        jump.set_formula("[p1] > 2").unwrap();
+        assert_eq!(jump.formula.to_string(), "[p1] > 2");
+        assert!(jump.set_formula("[p1] >").is_err());
+        assert_eq!(jump.formula.to_string(), "[p1] > 2");
+        jump.set_formula("").unwrap();
+        assert!(jump.formula.is_empty());
+    }
+
+    #[test]
+    pub fn lookup_by_id() {
+        let data = quest_data();
+        let quest = parse_qmm(&data).unwrap();
+
+        for location in &quest.locations {
+            assert_eq!(quest.location(location.id), Some(location));
+        }
+
+        for jump in &quest.jumps {
+            assert_eq!(quest.jump(jump.id), Some(jump));
+        }
+
+        assert_eq!(quest.location(LocationId(u32::MAX)), None);
+        assert_eq!(quest.jump(JumpId(u32::MAX)), None);
+        assert!(quest
+            .jumps_from(LocationId(1))
+            .all(|jump| jump.from == LocationId(1)));
+        assert_eq!(
+            quest.jumps_from(LocationId(1)).next().map(|jump| jump.id),
+            Some(JumpId(2))
+        );
+    }
+
+    #[test]
+    pub fn texts() {
+        let data = quest_data();
+        let quest = parse_qmm(&data).unwrap();
+        let texts = quest.texts();
+
+        assert_eq!(texts[0].source, TextSource::TaskText);
+        assert_eq!(texts[0].text, quest.info.task_text.to_string());
+        assert!(texts.iter().all(|text| !text.text.trim().is_empty()));
+        assert!(texts
+            .iter()
+            .any(|text| text.source == TextSource::JumpText(JumpId(2)) && text.text == "Очнуться"));
+        assert_eq!(
+            TextSource::LocationText {
+                location: LocationId(17),
+                index: 1
+            }
+            .to_string(),
+            "location 17 text 1"
+        );
+
+        let text = |text: &str| QuestText {
+            source: TextSource::TaskText,
+            text: text.to_string(),
+        };
+
+        assert_eq!(text("Очнуться").word_count(), 1);
+        assert_eq!(text("  Идти к дому - санитара\n").word_count(), 4);
+        assert_eq!(text("").word_count(), 0);
+    }
+
+    #[test]
+    pub fn set_text() {
+        let mut quest = parse_qmm(&quest_data()).unwrap();
+        let texts = quest.texts();
+
+        for text in &texts {
+            assert!(quest.set_text(text.source, &format!("{}!", text.text)));
+        }
+
+        let changed = quest.texts();
+
+        assert_eq!(changed.len(), texts.len());
+        for (changed, text) in changed.iter().zip(&texts) {
+            assert_eq!(changed.source, text.source);
+            assert_eq!(changed.text, format!("{}!", text.text));
+        }
+
+        assert!(!quest.set_text(TextSource::JumpText(JumpId(100_000)), ""));
+    }
+
+    #[test]
+    pub fn formatted_texts_and_formulas() {
+        let quest = parse_qmm(&quest_data()).unwrap();
+        let texts: Vec<_> = quest.formatted_texts().collect();
+
+        assert_eq!(texts[0], (TextSource::TaskText, &quest.info.task_text));
+        assert_eq!(
+            texts.len(),
+            2 + quest
+                .locations
+                .iter()
+                .map(|location| location.texts.len())
+                .sum::<usize>()
+                + 2 * quest.jumps.len()
+        );
+        // The non-empty ones are the same as the plain texts
+        let plain: Vec<_> = quest
+            .texts()
+            .into_iter()
+            .filter(|text| {
+                matches!(
+                    text.source,
+                    TextSource::TaskText
+                        | TextSource::SuccessText
+                        | TextSource::LocationText { .. }
+                        | TextSource::JumpText(_)
+                        | TextSource::JumpDescription(_)
+                )
+            })
+            .map(|text| (text.source, text.text))
+            .collect();
+        let formatted: Vec<_> = texts
+            .iter()
+            .map(|(source, text)| (*source, text.to_string()))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect();
+        assert_eq!(formatted, plain);
+
+        let formulas: Vec<_> = quest.formulas().collect();
+        let jump = quest.jump(JumpId(2)).unwrap();
+
+        assert!(formulas.contains(&(FormulaSource::JumpFormula(JumpId(2)), &jump.formula)));
+        assert_eq!(
+            formulas
+                .iter()
+                .filter(|(source, _)| matches!(source, FormulaSource::JumpFormula(_)))
+                .count(),
+            quest.jumps.len()
+        );
+        assert_eq!(
+            FormulaSource::LocationChange {
+                location: LocationId(3),
+                parameter: 2
+            }
+            .to_string(),
+            "location 3 parameter 2 change"
+        );
+    }
+
+    #[test]
+    pub fn located_errors() {
+        let data = quest_data();
+
+        let err = parse_qmm_located(&data[..2]).unwrap_err();
+        assert_eq!(err.section, QuestSection::Header);
+        assert_eq!(err.error.kind(), "Incomplete");
+
+        let mut invalid = data.clone();
+        invalid.push(0);
+
+        let err = parse_qmm_located(&invalid).unwrap_err();
+        assert_eq!(
+            err,
+            LocatedParsingError {
+                error: ParsingError::ExpectedEnd,
+                section: QuestSection::End,
+                offset: data.len() as u64,
+            }
+        );
+
+        let quest = parse_qmm(&data).unwrap();
+        let err = parse_qmm_located(&data[..data.len() - 1]).unwrap_err();
+        assert_eq!(err.section, QuestSection::Jump(quest.jumps.len() - 1));
+    }
+
+    #[test]
+    pub fn quest_readers() {
+        use std::{
+            fs::File,
+            io::{BufReader, Cursor},
+        };
+
+        let data = quest_data();
+        let quest = format!("{:?}", parse_qmm(&data).unwrap());
+
+        let mut file = SeekReader::new(BufReader::new(File::open("test.qmm").unwrap())).unwrap();
+        assert_eq!(file.len(), data.len() as u64);
+        assert_eq!(format!("{:?}", QmmParser::parse(&mut file).unwrap()), quest);
+        assert_eq!(file.position(), data.len() as u64);
+
+        let mut owned = Cursor::new(data.clone());
+        assert_eq!(
+            format!("{:?}", QmmParser::parse(&mut owned).unwrap()),
+            quest
+        );
+
+        let mut truncated = SeekReader::new(Cursor::new(&data[..data.len() / 2])).unwrap();
+        assert_eq!(
+            QmmParser::parse(&mut truncated).unwrap_err(),
+            ParsingError::Incomplete
+        );
+    }
+
+    #[test]
+    pub fn unwrap_containers() {
+        let data = quest_data();
+        let xor = XorCodec {
+            key: vec![0x5A, 0x13, 0x77],
+        };
+        let codecs: Vec<Box<dyn ContainerCodec>> = vec![Box::new(xor.clone())];
+
+        // Plain quests are not touched
+        assert_eq!(unwrap_container(&data, &codecs).unwrap(), data);
+
+        let wrapped = xor.decode(&data).unwrap();
+        assert!(xor.detect(&wrapped));
+        assert_eq!(unwrap_container(&wrapped, &codecs).unwrap(), data);
+
+        // Unknown data is left for the parser to report
+        assert_eq!(unwrap_container(&wrapped, &[]).unwrap(), wrapped);
+    }
+
+    #[cfg(feature = "zlib")]
+    #[test]
+    pub fn unwrap_zlib_container() {
+        use std::io::Write;
+
+        use flate2::{write::ZlibEncoder, Compression};
+
+        let data = quest_data();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert!(ZlibCodec.detect(&compressed));
+        assert!(!ZlibCodec.detect(&data));
+        assert_eq!(
+            unwrap_container(&compressed, &default_codecs()).unwrap(),
+            data
+        );
+
+        let truncated = &compressed[..compressed.len() / 2];
+        assert!(matches!(
+            unwrap_container(truncated, &default_codecs()),
+            Err(ContainerError::Corrupted { codec: "zlib", .. })
+        ));
+    }
+
+    #[test]
+    pub fn neighborhood() {
+        let quest = parse_qmm(&quest_data()).unwrap();
+        let start = LocationId(1);
+
+        let only = quest.neighborhood(start, 0);
+        assert_eq!(only.locations.len(), 1);
+        assert!(only
+            .jumps
+            .iter()
+            .all(|jump| jump.from == start && jump.to == start));
+
+        let near = quest.neighborhood(start, 1);
+        let targets: Vec<LocationId> = quest.jumps_from(start).map(|jump| jump.to).collect();
+        assert!(targets.iter().all(|id| near.location(*id).is_some()));
+        assert!(near.jumps.iter().all(|jump| {
+            near.location(jump.from).is_some() && near.location(jump.to).is_some()
+        }));
+        assert_eq!(near.info.locations_count, near.locations.len() as u32);
+
+        let whole = quest.neighborhood(start, usize::MAX);
+        assert!(whole.locations.len() > near.locations.len());
+        assert!(whole.locations.len() <= quest.locations.len());
+
+        assert!(quest
+            .neighborhood(LocationId(u32::MAX), 3)
+            .locations
+            .is_empty());
+    }
+
+    #[test]
+    pub fn distances() {
+        let quest = parse_qmm(&quest_data()).unwrap();
+        let distances = quest.distances(LocationId(1));
+
+        assert_eq!(distances[&LocationId(1)], 0);
+
+        for jump in &quest.jumps {
+            if let Some(from) = distances.get(&jump.from) {
+                assert!(distances[&jump.to] <= from + 1);
+            }
+        }
+
+        assert!(quest
+            .locations
+            .iter()
+            .filter(|location| location.ty == LocationType::Success)
+            .all(|location| distances.contains_key(&location.id)));
+        assert!(quest.distances(LocationId(u32::MAX)).is_empty());
+    }
+
+    #[test]
+    pub fn add_and_remove_objects() {
+        let mut quest = parse_qmm(&quest_data()).unwrap();
+        let start = LocationId(1);
+        let location = quest.add_location(10, 20);
+
+        assert!(quest.locations.iter().all(|other| other.id.0 <= location.0));
+        assert_eq!(quest.info.locations_count, quest.locations.len() as u32);
+        assert_eq!(quest.location(location).unwrap().texts.len(), 1);
+
+        let jump = quest.add_jump(start, location).unwrap();
+        assert_eq!(quest.jump(jump).unwrap().to, location);
+        assert_eq!(quest.info.jumps_count, quest.jumps.len() as u32);
+        assert_eq!(quest.add_jump(start, LocationId(u32::MAX)), None);
+
+        let back = quest.add_jump(location, start).unwrap();
+        assert!(quest.remove_jump(back).is_some());
+        assert!(quest.jump(back).is_none());
+
+        quest.add_jump(location, start).unwrap();
+        let jumps = quest.jumps.len();
+        assert!(quest.remove_location(location).is_some());
+        assert_eq!(quest.jumps.len(), jumps - 2);
+        assert!(quest
+            .jumps
+            .iter()
+            .all(|jump| jump.from != location && jump.to != location));
+        assert_eq!(quest.info.locations_count, quest.locations.len() as u32);
+        assert_eq!(quest.info.jumps_count, quest.jumps.len() as u32);
+
+        #[cfg(feature = "zlib")]
+        parse_qmm(&write_qmm(&quest)).unwrap();
+    }
+
+    #[cfg(feature = "zlib")]
+    #[test]
+    pub fn write_round_trip() {
+        let quest = parse_qmm(&quest_data()).unwrap();
+        let data = write_qmm(&quest);
+        let written = parse_qmm(&data).unwrap();
+
+        assert_eq!(written.header, quest.header);
+        assert_eq!(written.parameters, quest.parameters);
+        assert_eq!(written.string_replacements, quest.string_replacements);
+        assert_eq!(written.info, quest.info);
+        assert_eq!(written.locations, quest.locations);
+        assert_eq!(written.jumps, quest.jumps);
+        assert_eq!(write_qmm(&written), data);
+    }
+
+    #[test]
+    pub fn patch_keeps_unchanged_sections() {
+        let data = quest_data();
+        let mut quest = parse_qmm(&data).unwrap();
+
+        assert_eq!(patch_qmm(&data, &quest).unwrap(), data);
+
+        let location = quest.locations[0].id;
+        let source = TextSource::LocationText { location, index: 1 };
+        let old_units = quest.locations[0].texts[0]
+            .to_string()
+            .encode_utf16()
+            .count();
+        assert!(quest.set_text(source, "Patched"));
+
+        let patched = patch_qmm(&data, &quest).unwrap();
+        let prefix = data
+            .iter()
+            .zip(&patched)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = data
+            .iter()
+            .rev()
+            .zip(patched.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        // Only the text differs, its line breaks are stored as CRLF
+        assert!(prefix + suffix + 4 + old_units * 4 >= data.len());
+        assert_eq!(parse_qmm(&patched).unwrap().locations, quest.locations);
+
+        let jump = quest.jumps[0].id;
+        quest.remove_jump(jump).unwrap();
+        quest.header.difficult += 1;
+
+        let written = parse_qmm(&patch_qmm(&data, &quest).unwrap()).unwrap();
+        assert_eq!(written.header.difficult, quest.header.difficult);
+        assert_eq!(written.jumps, quest.jumps);
+        assert_eq!(written.locations, quest.locations);
+    }
+
+    #[test]
+    pub fn describe_format_sections() {
+        let sections = describe_format(&quest_data()).unwrap();
+        let names: Vec<_> = sections.iter().map(|section| section.name).collect();
+
+        assert_eq!(
+            names,
+            [
+                "header",
+                "parameter",
+                "string_replacements",
+                "info",
+                "location",
+                "jump"
+            ]
+        );
+
+        let field = |section: usize, name: &str| {
+            sections[section]
+                .fields
+                .iter()
+                .position(|field| field.name == name)
+                .unwrap()
+        };
+
+        assert_eq!(sections[0].fields[0].name, "magic");
+        assert_eq!(sections[0].fields.last().unwrap().name, "parameters_count");
+        assert_eq!(sections[1].fields[3].ty, FieldType::Bytes(3));
+        assert!(field(4, "parameter_changes_count") < field(4, "parameter_changes[].media.image"));
+        assert!(field(4, "parameter_changes[].media.track") < field(4, "texts_count"));
+        assert!(field(5, "formula") < field(5, "media.image"));
+    }
+
+    #[test]
+    pub fn pack_bundle() {
+        let data = quest_data();
+        let bundle = pack(&data, Compression::Gzip).unwrap();
+
+        assert_eq!(&bundle[..5], b"QMMZ\x01");
+        assert!(bundle.len() < data.len());
+        assert_eq!(unwrap_container(&bundle, &default_codecs()).unwrap(), data);
+
+        let mut wrong_size = bundle.clone();
+        wrong_size[5] ^= 1;
+        assert!(matches!(
+            BundleCodec.decode(&wrong_size),
+            Err(ContainerError::Corrupted {
+                codec: "bundle",
+                ..
+            })
+        ));
+
+        let mut unknown = bundle.clone();
+        unknown[4] = 0xFF;
+        assert!(BundleCodec.decode(&unknown).is_err());
+        assert!(BundleCodec.decode(b"QMMZ").is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    pub fn pack_zstd_bundle() {
+        let data = quest_data();
+        let bundle = pack(&data, Compression::Zstd).unwrap();
+
+        assert_eq!(unwrap_container(&bundle, &default_codecs()).unwrap(), data);
+    }
+
+    #[test]
+    pub fn parse_lenient_formulas() {
+        let mut quest = parse_qmm(&quest_data()).unwrap();
+        quest.jumps[0].formula = Formula::parse("[p1] > 1.5").unwrap();
+
+        // Formulas are written with a decimal point, put a comma instead
+        let utf16 =
+            |text: &str| -> Vec<u8> { text.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+        let mut data = write_qmm(&quest);
+        let point = utf16("1.5");
+        let positions: Vec<usize> = data
+            .windows(point.len())
+            .enumerate()
+            .filter(|(_, window)| *window == point)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(positions.len(), 1);
+        data[positions[0]..positions[0] + point.len()].copy_from_slice(&utf16("1,5"));
+
+        assert!(matches!(
+            parse_qmm(&data),
+            Err(ParsingError::InvalidFormula { formula, .. }) if formula == "[p1] > 1,5"
+        ));
+
+        let options = FormulaParseOptions {
+            lenient_numbers: true,
+            ..Default::default()
+        };
+        let lenient = parse_qmm_with(&data, options).unwrap();
+
+        assert_eq!(
+            lenient.jumps[0].formula.expression(),
+            quest.jumps[0].formula.expression()
+        );
+        assert_eq!(lenient.jumps[1..], quest.jumps[1..]);
+    }
 }
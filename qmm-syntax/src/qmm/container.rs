@@ -0,0 +1,322 @@
+use std::{borrow::Cow, fmt::Display, io::Read};
+
+use super::Version;
+
+/// Maximum size of the unwrapped data, protects from decompression bombs.
+pub const MAX_UNWRAPPED_SIZE: usize = 64 * 1024 * 1024;
+/// Maximum number of nested containers.
+const MAX_LAYERS: usize = 4;
+/// Magic of the bundles written by [`pack`].
+const BUNDLE_MAGIC: [u8; 4] = *b"QMMZ";
+/// Magic, compression and the unwrapped size.
+const BUNDLE_HEADER_SIZE: usize = 9;
+
+/// Wrapper around the QMM data used by some quest packs and fan launchers.
+pub trait ContainerCodec {
+    /// Name of the container for the error messages.
+    fn name(&self) -> &'static str;
+
+    /// Whether the data is wrapped into this container.
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// Data inside the container.
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ContainerError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContainerError {
+    Corrupted {
+        codec: &'static str,
+        message: String,
+    },
+    TooLarge {
+        limit: usize,
+    },
+    TooManyLayers,
+    /// The crate is built without the feature needed by the container.
+    Unsupported {
+        codec: &'static str,
+    },
+}
+
+impl Display for ContainerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerError::Corrupted { codec, message } => {
+                f.write_fmt(format_args!("Corrupted {codec} container: {message}"))
+            }
+            ContainerError::TooLarge { limit } => f.write_fmt(format_args!(
+                "Container data is larger than the limit of {limit} bytes"
+            )),
+            ContainerError::TooManyLayers => {
+                f.write_fmt(format_args!("More than {MAX_LAYERS} nested containers"))
+            }
+            ContainerError::Unsupported { codec } => {
+                f.write_fmt(format_args!("{codec} containers are not supported"))
+            }
+        }
+    }
+}
+
+/// Reads the whole stream, but no more than [`MAX_UNWRAPPED_SIZE`].
+#[allow(dead_code)]
+fn read_limited(reader: impl Read, codec: &'static str) -> Result<Vec<u8>, ContainerError> {
+    let mut unwrapped = Vec::new();
+
+    reader
+        .take(MAX_UNWRAPPED_SIZE as u64 + 1)
+        .read_to_end(&mut unwrapped)
+        .map_err(|err| ContainerError::Corrupted {
+            codec,
+            message: err.to_string(),
+        })?;
+
+    if unwrapped.len() > MAX_UNWRAPPED_SIZE {
+        return Err(ContainerError::TooLarge {
+            limit: MAX_UNWRAPPED_SIZE,
+        });
+    }
+
+    Ok(unwrapped)
+}
+
+fn is_qmm(data: &[u8]) -> bool {
+    data.first_chunk::<4>()
+        .is_some_and(|magic| Version::try_from(magic).is_ok())
+}
+
+/// Unwraps the data until it starts with the QMM magic. The data that isn't
+/// wrapped into any of the containers is returned as is.
+pub fn unwrap_container<'d>(
+    data: &'d [u8],
+    codecs: &[Box<dyn ContainerCodec>],
+) -> Result<Cow<'d, [u8]>, ContainerError> {
+    let mut data = Cow::Borrowed(data);
+
+    for _ in 0..MAX_LAYERS {
+        if is_qmm(&data) {
+            return Ok(data);
+        }
+
+        let Some(codec) = codecs.iter().find(|codec| codec.detect(&data)) else {
+            return Ok(data);
+        };
+
+        data = Cow::Owned(codec.decode(&data)?);
+    }
+
+    if is_qmm(&data) {
+        Ok(data)
+    } else {
+        Err(ContainerError::TooManyLayers)
+    }
+}
+
+/// Containers which can be detected without any settings.
+pub fn default_codecs() -> Vec<Box<dyn ContainerCodec>> {
+    vec![
+        Box::new(BundleCodec),
+        #[cfg(feature = "zlib")]
+        Box::new(ZlibCodec),
+    ]
+}
+
+/// Data XORed with the repeating key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XorCodec {
+    pub key: Vec<u8>,
+}
+
+impl XorCodec {
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        data.iter()
+            .zip(self.key.iter().cycle())
+            .map(|(byte, key)| byte ^ key)
+            .collect()
+    }
+}
+
+impl ContainerCodec for XorCodec {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        !self.key.is_empty() && data.len() >= 4 && is_qmm(&self.apply(&data[..4]))
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        Ok(self.apply(data))
+    }
+}
+
+/// Data compressed into the zlib stream.
+#[cfg(feature = "zlib")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZlibCodec;
+
+#[cfg(feature = "zlib")]
+impl ContainerCodec for ZlibCodec {
+    fn name(&self) -> &'static str {
+        "zlib"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        let [cmf, flg, ..] = *data else {
+            return false;
+        };
+
+        // Deflate method with the valid header checksum
+        cmf & 0x0F == 8 && u16::from_be_bytes([cmf, flg]) % 31 == 0
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        read_limited(flate2::read::ZlibDecoder::new(data), self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compression {
+    /// Needs the `zlib` feature
+    Gzip,
+    /// Needs the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(value: Compression) -> Self {
+        match value {
+            Compression::Gzip => 1,
+            Compression::Zstd => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Compression::Gzip),
+            2 => Ok(Compression::Zstd),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Compresses the quest into a bundle read by [`BundleCodec`]: the `QMMZ`
+/// magic, the compression byte, the unwrapped size as `u32` and the
+/// compressed data.
+pub fn pack(data: &[u8], compression: Compression) -> Result<Vec<u8>, ContainerError> {
+    let size = u32::try_from(data.len()).map_err(|_| ContainerError::TooLarge {
+        limit: u32::MAX as usize,
+    })?;
+    let mut bundle = Vec::with_capacity(BUNDLE_HEADER_SIZE + data.len() / 2);
+
+    bundle.extend_from_slice(&BUNDLE_MAGIC);
+    bundle.push(compression.into());
+    bundle.extend_from_slice(&size.to_le_bytes());
+
+    match compression {
+        #[cfg(feature = "zlib")]
+        Compression::Gzip => {
+            use std::io::Write;
+
+            let mut encoder = flate2::write::GzEncoder::new(bundle, flate2::Compression::default());
+            let compressed = encoder.write_all(data).and_then(|_| encoder.finish());
+
+            compressed.map_err(|err| ContainerError::Corrupted {
+                codec: compression.name(),
+                message: err.to_string(),
+            })
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::stream::copy_encode(data, &mut bundle, 0)
+            .map(|_| bundle)
+            .map_err(|err| ContainerError::Corrupted {
+                codec: compression.name(),
+                message: err.to_string(),
+            }),
+        #[allow(unreachable_patterns)]
+        _ => Err(ContainerError::Unsupported {
+            codec: compression.name(),
+        }),
+    }
+}
+
+#[allow(unused_variables)]
+fn decompress(compression: Compression, compressed: &[u8]) -> Result<Vec<u8>, ContainerError> {
+    match compression {
+        #[cfg(feature = "zlib")]
+        Compression::Gzip => {
+            read_limited(flate2::read::GzDecoder::new(compressed), compression.name())
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let decoder = zstd::stream::read::Decoder::new(compressed).map_err(|err| {
+                ContainerError::Corrupted {
+                    codec: compression.name(),
+                    message: err.to_string(),
+                }
+            })?;
+
+            read_limited(decoder, compression.name())
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(ContainerError::Unsupported {
+            codec: compression.name(),
+        }),
+    }
+}
+
+/// Bundle written by [`pack`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BundleCodec;
+
+impl ContainerCodec for BundleCodec {
+    fn name(&self) -> &'static str {
+        "bundle"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(&BUNDLE_MAGIC)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        let corrupted = |message: &str| ContainerError::Corrupted {
+            codec: self.name(),
+            message: message.to_string(),
+        };
+
+        let Some((header, compressed)) = data.split_first_chunk::<BUNDLE_HEADER_SIZE>() else {
+            return Err(corrupted("incomplete header"));
+        };
+
+        let compression =
+            Compression::try_from(header[4]).map_err(|_| corrupted("unknown compression"))?;
+        let size = u32::from_le_bytes([header[5], header[6], header[7], header[8]]) as usize;
+
+        if size > MAX_UNWRAPPED_SIZE {
+            return Err(ContainerError::TooLarge {
+                limit: MAX_UNWRAPPED_SIZE,
+            });
+        }
+
+        let unwrapped = decompress(compression, compressed)?;
+
+        if unwrapped.len() != size {
+            return Err(corrupted("size mismatch"));
+        }
+
+        Ok(unwrapped)
+    }
+}
@@ -0,0 +1,165 @@
+//! Layout of the quest files, recorded from the marks the parsers leave on
+//! the reader, so the description can't drift from the implementation.
+
+use std::{fmt::Display, io::Cursor};
+
+use super::{ParsingError, QmmParser, QuestRead};
+
+/// Encoding of a field, all numbers are little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    I32,
+    F64,
+    Byte,
+    /// A byte which is either 0 or 1
+    Bool,
+    /// `i32` flag, then the `i32` length and the UTF-16 units if the flag
+    /// isn't zero
+    String,
+    /// Raw bytes the parser checks or skips
+    Bytes(usize),
+}
+
+impl FieldType {
+    /// Size in bytes, `None` for the strings.
+    pub fn size(self) -> Option<usize> {
+        match self {
+            FieldType::I32 => Some(4),
+            FieldType::F64 => Some(8),
+            FieldType::Byte | FieldType::Bool => Some(1),
+            FieldType::String => None,
+            FieldType::Bytes(size) => Some(size),
+        }
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FieldType::I32 => "i32",
+            FieldType::F64 => "f64",
+            FieldType::Byte => "u8",
+            FieldType::Bool => "bool",
+            FieldType::String => "string",
+            FieldType::Bytes(_) => "bytes",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFormat {
+    /// Path in the section, like `parameter_changes[].media.image`. `[]`
+    /// marks the elements of a list, which follow its count.
+    pub name: String,
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionFormat {
+    pub name: &'static str,
+    pub fields: Vec<FieldFormat>,
+}
+
+/// [`QuestRead`] which records the fields the parsers read, merged over all
+/// the instances of a section.
+pub struct FormatRecorder<R> {
+    reader: R,
+    groups: Vec<&'static str>,
+    sections: Vec<SectionFormat>,
+    /// Index of the last recorded field in the current section, the new
+    /// fields are inserted after it
+    last_field: Option<usize>,
+}
+
+impl<R: QuestRead> FormatRecorder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            groups: Vec::new(),
+            sections: Vec::new(),
+            last_field: None,
+        }
+    }
+
+    /// Sections in the file order.
+    pub fn into_sections(self) -> Vec<SectionFormat> {
+        self.sections
+    }
+}
+
+impl<R: QuestRead> QuestRead for FormatRecorder<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.reader.read_exact(buf)
+    }
+
+    fn position(&self) -> u64 {
+        self.reader.position()
+    }
+
+    fn seek(&mut self, position: u64) -> std::io::Result<()> {
+        self.reader.seek(position)
+    }
+
+    fn len(&self) -> u64 {
+        self.reader.len()
+    }
+
+    fn field(&mut self, name: &'static str, ty: FieldType) {
+        let Some((section, groups)) = self.groups.split_first() else {
+            return;
+        };
+        let name = groups
+            .iter()
+            .chain([&name])
+            .copied()
+            .collect::<Vec<_>>()
+            .join(".");
+        let Some(section) = self
+            .sections
+            .iter_mut()
+            .find(|candidate| candidate.name == *section)
+        else {
+            return;
+        };
+
+        let index = match section.fields.iter().position(|field| field.name == name) {
+            Some(index) => index,
+            None => {
+                let index = self.last_field.map_or(0, |last| last + 1);
+                section.fields.insert(index, FieldFormat { name, ty });
+
+                index
+            }
+        };
+
+        self.last_field = Some(index);
+    }
+
+    fn begin(&mut self, name: &'static str) {
+        if self.groups.is_empty() {
+            self.last_field = None;
+
+            if !self.sections.iter().any(|section| section.name == name) {
+                self.sections.push(SectionFormat {
+                    name,
+                    fields: Vec::new(),
+                });
+            }
+        }
+
+        self.groups.push(name);
+    }
+
+    fn end(&mut self) {
+        self.groups.pop();
+    }
+}
+
+/// Layout of the sections, recorded by parsing the sample quest. The fields
+/// of the lists which are empty in the sample are missing.
+pub fn describe_format(sample: &[u8]) -> Result<Vec<SectionFormat>, ParsingError> {
+    let mut recorder = FormatRecorder::new(Cursor::new(sample));
+    QmmParser::parse(&mut recorder)?;
+
+    Ok(recorder.into_sections())
+}
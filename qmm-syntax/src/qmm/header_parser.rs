@@ -1,14 +1,12 @@
-use std::io::{Cursor, Read};
-
 use super::{
-    CompletionCondition, Header, HeaderError, JumpsLimit, ParsingError, PlanetType, PlayerStatus,
-    PrimitiveParser, Race, Version,
+    CompletionCondition, FieldType, Header, HeaderError, JumpsLimit, ParsingError, PlanetType,
+    PlayerStatus, PrimitiveParser, QuestRead, Race, Version,
 };
 
 pub struct HeaderParser;
 
 impl HeaderParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Header, ParsingError> {
+    pub fn parse(cursor: &mut impl QuestRead) -> Result<Header, ParsingError> {
         let version = Self::parse_version(cursor)?;
         let giver_race = Self::parse_quest_giver_race(cursor)?;
         let completion_condition = Self::parse_completion_condition(cursor)?;
@@ -18,14 +16,14 @@ impl HeaderParser {
         let relation_change = Self::parse_relation_change(cursor)?;
 
         // Skip screen and grid sizes...
-        PrimitiveParser::parse_i32(cursor)?;
-        PrimitiveParser::parse_i32(cursor)?;
-        PrimitiveParser::parse_i32(cursor)?;
-        PrimitiveParser::parse_i32(cursor)?;
+        PrimitiveParser::parse_i32(cursor, "screen_width")?;
+        PrimitiveParser::parse_i32(cursor, "screen_height")?;
+        PrimitiveParser::parse_i32(cursor, "grid_width")?;
+        PrimitiveParser::parse_i32(cursor, "grid_height")?;
 
         let default_jumps_limit = Self::parse_jumps_limit(cursor)?;
-        let difficult = PrimitiveParser::parse_i32(cursor)? as u32;
-        let parameters_count = PrimitiveParser::parse_i32(cursor)? as usize;
+        let difficult = PrimitiveParser::parse_i32(cursor, "difficult")? as u32;
+        let parameters_count = PrimitiveParser::parse_i32(cursor, "parameters_count")? as usize;
 
         Ok(Header {
             version,
@@ -41,9 +39,10 @@ impl HeaderParser {
         })
     }
 
-    fn parse_version(cursor: &mut Cursor<&[u8]>) -> Result<Version, ParsingError> {
+    fn parse_version(cursor: &mut impl QuestRead) -> Result<Version, ParsingError> {
         let mut version_bytes = [0; 4];
 
+        cursor.field("magic", FieldType::Bytes(4));
         cursor
             .read_exact(&mut version_bytes)
             .map_err(|_| ParsingError::Incomplete)?;
@@ -69,26 +68,28 @@ impl HeaderParser {
                 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             ]
         {
-            cursor.set_position(old_pos);
+            cursor.seek(old_pos).map_err(|_| ParsingError::Incomplete)?;
+        } else {
+            cursor.field("qmm7_version", FieldType::Bytes(12));
         }
 
         Ok(version)
     }
 
-    fn parse_quest_giver_race(cursor: &mut Cursor<&[u8]>) -> Result<Race, ParsingError> {
-        Race::try_from(PrimitiveParser::parse_byte(cursor)?)
+    fn parse_quest_giver_race(cursor: &mut impl QuestRead) -> Result<Race, ParsingError> {
+        Race::try_from(PrimitiveParser::parse_byte(cursor, "giver_race")?)
             .map_err(|_| ParsingError::InvalidHeader(HeaderError::InvalidQuestGiverRace))
     }
 
     fn parse_completion_condition(
-        cursor: &mut Cursor<&[u8]>,
+        cursor: &mut impl QuestRead,
     ) -> Result<CompletionCondition, ParsingError> {
-        CompletionCondition::try_from(PrimitiveParser::parse_byte(cursor)?)
+        CompletionCondition::try_from(PrimitiveParser::parse_byte(cursor, "completion_condition")?)
             .map_err(|_| ParsingError::InvalidHeader(HeaderError::InvalidCompletionCondition))
     }
 
-    fn parse_quest_planet_type(cursor: &mut Cursor<&[u8]>) -> Result<PlanetType, ParsingError> {
-        let value = PrimitiveParser::parse_byte(cursor)?;
+    fn parse_quest_planet_type(cursor: &mut impl QuestRead) -> Result<PlanetType, ParsingError> {
+        let value = PrimitiveParser::parse_byte(cursor, "planet_type")?;
 
         match value {
             0x40 => Ok(PlanetType::Uninhabited),
@@ -98,19 +99,20 @@ impl HeaderParser {
         }
     }
 
-    fn parse_player_status(cursor: &mut Cursor<&[u8]>) -> Result<PlayerStatus, ParsingError> {
-        PlayerStatus::try_from(PrimitiveParser::parse_byte(cursor)?)
+    fn parse_player_status(cursor: &mut impl QuestRead) -> Result<PlayerStatus, ParsingError> {
+        PlayerStatus::try_from(PrimitiveParser::parse_byte(cursor, "player_status")?)
             .map_err(|_| ParsingError::InvalidHeader(HeaderError::InvalidPlayerStatus))
     }
 
-    fn parse_player_race(cursor: &mut Cursor<&[u8]>) -> Result<Race, ParsingError> {
-        Race::try_from(PrimitiveParser::parse_byte(cursor)?)
+    fn parse_player_race(cursor: &mut impl QuestRead) -> Result<Race, ParsingError> {
+        Race::try_from(PrimitiveParser::parse_byte(cursor, "player_race")?)
             .map_err(|_| ParsingError::InvalidHeader(HeaderError::InvalidPlayerRace))
     }
 
-    fn parse_relation_change(cursor: &mut Cursor<&[u8]>) -> Result<i8, ParsingError> {
+    fn parse_relation_change(cursor: &mut impl QuestRead) -> Result<i8, ParsingError> {
         let mut relation_change_bytes = [0; 4];
 
+        cursor.field("relation_change", FieldType::Bytes(4));
         cursor
             .read_exact(&mut relation_change_bytes)
             .map_err(|_| ParsingError::Incomplete)?;
@@ -126,8 +128,8 @@ impl HeaderParser {
         }
     }
 
-    fn parse_jumps_limit(cursor: &mut Cursor<&[u8]>) -> Result<JumpsLimit, ParsingError> {
-        let value = PrimitiveParser::parse_i32(cursor)? as u32;
+    fn parse_jumps_limit(cursor: &mut impl QuestRead) -> Result<JumpsLimit, ParsingError> {
+        let value = PrimitiveParser::parse_i32(cursor, "default_jumps_limit")? as u32;
 
         if value == 0x00 {
             Ok(JumpsLimit::Infinite)
@@ -1,17 +1,15 @@
-use std::io::Cursor;
-
 use crate::text::formatted_text::FormattedText;
 
-use super::{Info, ParsingError, PrimitiveParser, StringParser};
+use super::{Info, ParsingError, PrimitiveParser, QuestRead, StringParser};
 
 pub struct InfoParser;
 
 impl InfoParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Info, ParsingError> {
-        let locations_count = PrimitiveParser::parse_i32(cursor)? as u32;
-        let jumps_count = PrimitiveParser::parse_i32(cursor)? as u32;
-        let success_text = FormattedText::parse(&StringParser::parse(cursor)?);
-        let task_text = FormattedText::parse(&StringParser::parse(cursor)?);
+    pub fn parse(cursor: &mut impl QuestRead) -> Result<Info, ParsingError> {
+        let locations_count = PrimitiveParser::parse_i32(cursor, "locations_count")? as u32;
+        let jumps_count = PrimitiveParser::parse_i32(cursor, "jumps_count")? as u32;
+        let success_text = FormattedText::parse(&StringParser::parse(cursor, "success_text")?);
+        let task_text = FormattedText::parse(&StringParser::parse(cursor, "task_text")?);
 
         Ok(Info {
             locations_count,
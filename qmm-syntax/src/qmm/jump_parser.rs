@@ -1,58 +1,71 @@
-use std::io::Cursor;
-
-use crate::text::{formatted_text::FormattedText, formula::Formula};
+use crate::text::{
+    formatted_text::FormattedText,
+    formula::{Formula, FormulaParseOptions},
+};
 
 use super::{
     Jump, JumpId, JumpParameterConditionParser, LocationId, MaxVisits, MediaParser,
-    ParameterChangeParser, ParsingError, PrimitiveParser, StringParser,
+    ParameterChangeParser, ParsingError, PrimitiveParser, QuestRead, StringParser,
 };
 
 pub struct JumpParser;
 
 impl JumpParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Jump, ParsingError> {
-        let priority = PrimitiveParser::parse_f64(cursor)?;
-        let do_pass_day = PrimitiveParser::parse_i32(cursor)? > 0;
-        let id = JumpId(PrimitiveParser::parse_i32(cursor)? as u32);
-        let from = LocationId(PrimitiveParser::parse_i32(cursor)? as u32);
-        let to = LocationId(PrimitiveParser::parse_i32(cursor)? as u32);
-        let show_always = PrimitiveParser::parse_bool(cursor)?;
-        let max_visits = PrimitiveParser::parse_i32(cursor)? as u32;
+    pub fn parse(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+    ) -> Result<Jump, ParsingError> {
+        let priority = PrimitiveParser::parse_f64(cursor, "priority")?;
+        let do_pass_day = PrimitiveParser::parse_i32(cursor, "do_pass_day")? > 0;
+        let id = JumpId(PrimitiveParser::parse_i32(cursor, "id")? as u32);
+        let from = LocationId(PrimitiveParser::parse_i32(cursor, "from")? as u32);
+        let to = LocationId(PrimitiveParser::parse_i32(cursor, "to")? as u32);
+        let show_always = PrimitiveParser::parse_bool(cursor, "show_always")?;
+        let max_visits = PrimitiveParser::parse_i32(cursor, "max_visits")? as u32;
         let max_visits = match max_visits {
             0 => MaxVisits::Infinite,
             _ => MaxVisits::Limit(max_visits),
         };
-        let show_order = PrimitiveParser::parse_i32(cursor)? as u32;
-        let jump_parameters_conditions_count = PrimitiveParser::parse_i32(cursor)?;
+        let show_order = PrimitiveParser::parse_i32(cursor, "show_order")? as u32;
+        let jump_parameters_conditions_count =
+            PrimitiveParser::parse_i32(cursor, "parameters_conditions_count")?;
         let mut parameters_conditions =
             Vec::with_capacity(jump_parameters_conditions_count as usize);
         let mut jump_parameters_conditions_iter = 0;
 
         while jump_parameters_conditions_iter < jump_parameters_conditions_count {
+            cursor.begin("parameters_conditions[]");
             parameters_conditions.push(JumpParameterConditionParser::parse(cursor)?);
+            cursor.end();
 
             jump_parameters_conditions_iter += 1;
         }
 
-        let parameters_changes_count = PrimitiveParser::parse_i32(cursor)?;
+        let parameters_changes_count =
+            PrimitiveParser::parse_i32(cursor, "parameter_changes_count")?;
         let mut parameter_changes = Vec::with_capacity(parameters_changes_count as usize);
         let mut parameters_changes_iter = 0;
 
         while parameters_changes_iter < parameters_changes_count {
-            parameter_changes.push(ParameterChangeParser::parse(cursor)?);
+            cursor.begin("parameter_changes[]");
+            parameter_changes.push(ParameterChangeParser::parse(cursor, options)?);
+            cursor.end();
 
             parameters_changes_iter += 1;
         }
 
-        let formula_text = StringParser::parse(cursor)?;
-        let formula =
-            Formula::parse(&formula_text).map_err(|err| ParsingError::InvalidFormula {
+        let formula_text = StringParser::parse(cursor, "formula")?;
+        let formula = Formula::parse_with(&formula_text, options)
+            .map(|(formula, _)| formula)
+            .map_err(|err| ParsingError::InvalidFormula {
                 error: err,
                 formula: formula_text,
             })?;
-        let text = FormattedText::parse(&StringParser::parse(cursor)?);
-        let description = FormattedText::parse(&StringParser::parse(cursor)?);
+        let text = FormattedText::parse(&StringParser::parse(cursor, "text")?);
+        let description = FormattedText::parse(&StringParser::parse(cursor, "description")?);
+        cursor.begin("media");
         let media = MediaParser::parse(cursor)?;
+        cursor.end();
 
         Ok(Jump {
             priority,
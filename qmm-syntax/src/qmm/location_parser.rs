@@ -1,70 +1,82 @@
-use std::io::Cursor;
-
-use crate::text::{formatted_text::FormattedText, formula::Formula};
+use crate::text::{
+    formatted_text::FormattedText,
+    formula::{Formula, FormulaParseOptions},
+};
 
 use super::{
     Location, LocationError, LocationId, LocationSelectType, LocationType, MaxVisits, MediaParser,
-    ParameterChangeParser, ParsingError, PrimitiveParser, StringParser,
+    ParameterChangeParser, ParsingError, PrimitiveParser, QuestRead, StringParser,
 };
 
 pub struct LocationParser;
 
 impl LocationParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Location, ParsingError> {
-        let do_pass_day = PrimitiveParser::parse_i32(cursor)? > 0;
+    pub fn parse(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+    ) -> Result<Location, ParsingError> {
+        let do_pass_day = PrimitiveParser::parse_i32(cursor, "do_pass_day")? > 0;
 
-        // Skip coordinates
-        PrimitiveParser::parse_i32(cursor)?;
-        PrimitiveParser::parse_i32(cursor)?;
+        let x = PrimitiveParser::parse_i32(cursor, "x")?;
+        let y = PrimitiveParser::parse_i32(cursor, "y")?;
 
-        let id = LocationId(PrimitiveParser::parse_i32(cursor)? as u32);
-        let max_visits = PrimitiveParser::parse_i32(cursor)? as u32;
+        let id = LocationId(PrimitiveParser::parse_i32(cursor, "id")? as u32);
+        let max_visits = PrimitiveParser::parse_i32(cursor, "max_visits")? as u32;
         let max_visits = match max_visits {
             0 => MaxVisits::Infinite,
             _ => MaxVisits::Limit(max_visits),
         };
-        let ty = LocationType::try_from(PrimitiveParser::parse_byte(cursor)?)
+        let ty = LocationType::try_from(PrimitiveParser::parse_byte(cursor, "type")?)
             .map_err(|_| ParsingError::InvalidLocation(LocationError::InvalidLocationType))?;
 
-        let parameters_changes_count = PrimitiveParser::parse_i32(cursor)?;
+        let parameters_changes_count =
+            PrimitiveParser::parse_i32(cursor, "parameter_changes_count")?;
         let mut parameter_changes = Vec::with_capacity(parameters_changes_count as usize);
         let mut parameters_changes_iter = 0;
 
         while parameters_changes_iter < parameters_changes_count {
-            parameter_changes.push(ParameterChangeParser::parse(cursor)?);
+            cursor.begin("parameter_changes[]");
+            parameter_changes.push(ParameterChangeParser::parse(cursor, options)?);
+            cursor.end();
 
             parameters_changes_iter += 1;
         }
 
-        let location_texts_count = PrimitiveParser::parse_i32(cursor)?;
+        let location_texts_count = PrimitiveParser::parse_i32(cursor, "texts_count")?;
         let mut texts = Vec::with_capacity(location_texts_count as usize);
         let mut media = Vec::with_capacity(location_texts_count as usize);
         let mut location_texts_iter = 0;
 
         while location_texts_iter < location_texts_count {
-            texts.push(FormattedText::parse(&StringParser::parse(cursor)?));
+            cursor.begin("texts[]");
+            texts.push(FormattedText::parse(&StringParser::parse(cursor, "text")?));
+            cursor.begin("media");
             media.push(MediaParser::parse(cursor)?);
+            cursor.end();
+            cursor.end();
 
             location_texts_iter += 1;
         }
 
-        let select_type = PrimitiveParser::parse_bool(cursor)?;
-        let select_formula = StringParser::parse(cursor)?;
+        let select_type = PrimitiveParser::parse_bool(cursor, "select_by_formula")?;
+        let select_formula = StringParser::parse(cursor, "select_formula")?;
         let select_type = match select_type {
             false => LocationSelectType::ByOrder,
             true => {
-                let formula = Formula::parse(&select_formula).map_err(|err| {
-                    ParsingError::InvalidFormula {
+                let formula = Formula::parse_with(&select_formula, options)
+                    .map(|(formula, _)| formula)
+                    .map_err(|err| ParsingError::InvalidFormula {
                         error: err,
                         formula: select_formula,
-                    }
-                })?;
+                    })?;
                 LocationSelectType::ByFormula(formula)
             }
         };
 
         Ok(Location {
             do_pass_day,
+            x,
+            y,
             id,
             max_visits,
             ty,
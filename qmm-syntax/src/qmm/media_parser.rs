@@ -1,14 +1,12 @@
-use std::io::Cursor;
-
-use super::{Media, ParsingError, StringParser};
+use super::{Media, ParsingError, QuestRead, StringParser};
 
 pub struct MediaParser;
 
 impl MediaParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Media, ParsingError> {
-        let image = StringParser::parse(cursor)?;
-        let sound = StringParser::parse(cursor)?;
-        let track = StringParser::parse(cursor)?;
+    pub fn parse(cursor: &mut impl QuestRead) -> Result<Media, ParsingError> {
+        let image = StringParser::parse(cursor, "image")?;
+        let sound = StringParser::parse(cursor, "sound")?;
+        let track = StringParser::parse(cursor, "track")?;
 
         Ok(Media {
             image,
@@ -1,3 +1,5 @@
+mod container;
+mod format;
 mod header_parser;
 mod info_parser;
 mod jump_parameter_condition_parser;
@@ -8,12 +10,17 @@ mod parameter_change_parser;
 mod parameter_parser;
 mod parser;
 mod primitive_parser;
+mod reader;
 mod string_parser;
 mod string_replacements_parser;
+mod texts;
 mod types;
+mod writer;
 
 use std::io::Cursor;
 
+use crate::text::formula::FormulaParseOptions;
+
 use header_parser::HeaderParser;
 use info_parser::InfoParser;
 use jump_parameter_condition_parser::JumpParameterConditionParser;
@@ -27,8 +34,52 @@ use primitive_parser::PrimitiveParser;
 use string_parser::StringParser;
 use string_replacements_parser::StringReplacementsParser;
 
+#[cfg(feature = "zlib")]
+pub use container::ZlibCodec;
+pub use container::{
+    default_codecs, pack, unwrap_container, BundleCodec, Compression, ContainerCodec,
+    ContainerError, XorCodec, MAX_UNWRAPPED_SIZE,
+};
+pub use format::{describe_format, FieldFormat, FieldType, FormatRecorder, SectionFormat};
+pub use reader::{QuestRead, SeekReader};
+pub use texts::{FormulaSource, QuestText, TextSource};
 pub use types::*;
+pub use writer::QmmWriter;
+
+/// Parsers of the quest's parts. They follow the file layout and may change
+/// in any release, use [`parse_qmm`] or [`QmmParser`] instead.
+#[cfg(feature = "parser-internals")]
+pub mod internals {
+    pub use super::{
+        header_parser::HeaderParser, info_parser::InfoParser,
+        jump_parameter_condition_parser::JumpParameterConditionParser, jump_parser::JumpParser,
+        location_parser::LocationParser, media_parser::MediaParser,
+        parameter_change_parser::ParameterChangeParser, parameter_parser::ParameterParser,
+        primitive_parser::PrimitiveParser, string_parser::StringParser,
+        string_replacements_parser::StringReplacementsParser,
+    };
+}
 
 pub fn parse_qmm(data: &[u8]) -> Result<Quest, ParsingError> {
     QmmParser::parse(&mut Cursor::new(data))
 }
+
+/// Same as [`parse_qmm`], but parses the formulas with the options.
+pub fn parse_qmm_with(data: &[u8], options: FormulaParseOptions) -> Result<Quest, ParsingError> {
+    QmmParser::parse_with(&mut Cursor::new(data), options)
+}
+
+pub fn parse_qmm_located(data: &[u8]) -> Result<Quest, LocatedParsingError> {
+    QmmParser::parse_located(&mut Cursor::new(data))
+}
+
+/// Serializes the quest, [`parse_qmm`] reads it back.
+pub fn write_qmm(quest: &Quest) -> Vec<u8> {
+    QmmWriter::write(quest)
+}
+
+/// Serializes the quest parsed from `original`, copying the unchanged
+/// sections as they are. See [`QmmWriter::patch`].
+pub fn patch_qmm(original: &[u8], quest: &Quest) -> Result<Vec<u8>, ParsingError> {
+    QmmWriter::patch(original, quest)
+}
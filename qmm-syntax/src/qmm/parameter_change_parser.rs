@@ -1,38 +1,45 @@
-use std::io::Cursor;
-
-use crate::text::formula::Formula;
+use crate::text::formula::{Formula, FormulaParseOptions};
 
 use super::{
     MediaParser, ParameterChange, ParameterChangeError, ParameterChangeType, ParameterShowType,
-    ParsingError, PrimitiveParser, StringParser,
+    ParsingError, PrimitiveParser, QuestRead, StringParser,
 };
 
 pub struct ParameterChangeParser;
 
 impl ParameterChangeParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<ParameterChange, ParsingError> {
-        let parameter_id = PrimitiveParser::parse_i32(cursor)? as u32;
-        PrimitiveParser::parse_i32(cursor)?;
+    pub fn parse(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+    ) -> Result<ParameterChange, ParsingError> {
+        let parameter_id = PrimitiveParser::parse_i32(cursor, "parameter_id")? as u32;
+        let change = PrimitiveParser::parse_i32(cursor, "change")?;
 
         let show_type =
-            ParameterShowType::try_from(PrimitiveParser::parse_byte(cursor)?).map_err(|_| {
-                ParsingError::InvalidParameterChange(ParameterChangeError::InvalidShowType)
-            })?;
-        let change_type = ParameterChangeType::try_from(PrimitiveParser::parse_byte(cursor)?)
-            .map_err(|_| {
-                ParsingError::InvalidParameterChange(ParameterChangeError::InvalidChangeType)
-            })?;
-        let formula_text = StringParser::parse(cursor)?;
-        let formula =
-            Formula::parse(&formula_text).map_err(|err| ParsingError::InvalidFormula {
+            ParameterShowType::try_from(PrimitiveParser::parse_byte(cursor, "show_type")?)
+                .map_err(|_| {
+                    ParsingError::InvalidParameterChange(ParameterChangeError::InvalidShowType)
+                })?;
+        let change_type =
+            ParameterChangeType::try_from(PrimitiveParser::parse_byte(cursor, "change_type")?)
+                .map_err(|_| {
+                    ParsingError::InvalidParameterChange(ParameterChangeError::InvalidChangeType)
+                })?;
+        let formula_text = StringParser::parse(cursor, "formula")?;
+        let formula = Formula::parse_with(&formula_text, options)
+            .map(|(formula, _)| formula)
+            .map_err(|err| ParsingError::InvalidFormula {
                 formula: formula_text,
                 error: err,
             })?;
-        let critical_text = StringParser::parse(cursor)?;
+        let critical_text = StringParser::parse(cursor, "critical_text")?;
+        cursor.begin("media");
         let media = MediaParser::parse(cursor)?;
+        cursor.end();
 
         Ok(ParameterChange {
             parameter_id,
+            change,
             show_type,
             change_type,
             formula,
@@ -1,41 +1,41 @@
-use std::io::{Cursor, Seek};
-
 use super::{
-    CriticalValue, FormattedRangeLine, Parameter, ParameterError, ParameterType, ParsingError,
-    PrimitiveParser, StringParser,
+    CriticalValue, FieldType, FormattedRangeLine, Parameter, ParameterError, ParameterType,
+    ParsingError, PrimitiveParser, QuestRead, StringParser,
 };
 
-pub struct ParameterParser<'c, 'd> {
-    cursor: &'c mut Cursor<&'d [u8]>,
+pub struct ParameterParser<'c, R> {
+    cursor: &'c mut R,
 }
 
-impl<'c, 'd> ParameterParser<'c, 'd> {
-    pub fn new(cursor: &'c mut Cursor<&'d [u8]>) -> Self {
+impl<'c, R: QuestRead> ParameterParser<'c, R> {
+    pub fn new(cursor: &'c mut R) -> Self {
         Self { cursor }
     }
 
     pub fn parse(&mut self) -> Result<Parameter, ParsingError> {
-        let min_value = PrimitiveParser::parse_i32(self.cursor)?;
-        let max_value = PrimitiveParser::parse_i32(self.cursor)?;
+        let min_value = PrimitiveParser::parse_i32(self.cursor, "min_value")?;
+        let max_value = PrimitiveParser::parse_i32(self.cursor, "max_value")?;
         let ty = self.parse_type()?;
 
         // Skip zero bytes
+        self.cursor.field("padding", FieldType::Bytes(3));
         self.cursor
-            .seek(std::io::SeekFrom::Current(3))
+            .seek(self.cursor.position() + 3)
             .map_err(|_| ParsingError::Incomplete)?;
 
-        let show_when_zero = PrimitiveParser::parse_bool(self.cursor)?;
+        let show_when_zero = PrimitiveParser::parse_bool(self.cursor, "show_when_zero")?;
         let critical_value = self.parse_critical_value()?;
-        let is_active = PrimitiveParser::parse_bool(self.cursor)?;
-        let formatted_lines_count = PrimitiveParser::parse_i32(self.cursor)? as usize;
-        let is_money = PrimitiveParser::parse_bool(self.cursor)?;
-        let name = StringParser::parse(self.cursor)?;
+        let is_active = PrimitiveParser::parse_bool(self.cursor, "is_active")?;
+        let formatted_lines_count =
+            PrimitiveParser::parse_i32(self.cursor, "formatted_range_lines_count")? as usize;
+        let is_money = PrimitiveParser::parse_bool(self.cursor, "is_money")?;
+        let name = StringParser::parse(self.cursor, "name")?;
         let formatted_range_lines = self.parse_formatted_range_lines(formatted_lines_count)?;
-        let critical_text = StringParser::parse(self.cursor)?;
-        let image = StringParser::parse(self.cursor)?;
-        let sound = StringParser::parse(self.cursor)?;
-        let track = StringParser::parse(self.cursor)?;
-        let starting_value = StringParser::parse(self.cursor)?;
+        let critical_text = StringParser::parse(self.cursor, "critical_text")?;
+        let image = StringParser::parse(self.cursor, "image")?;
+        let sound = StringParser::parse(self.cursor, "sound")?;
+        let track = StringParser::parse(self.cursor, "track")?;
+        let starting_value = StringParser::parse(self.cursor, "starting_value")?;
 
         Ok(Parameter {
             min_value,
@@ -56,12 +56,12 @@ impl<'c, 'd> ParameterParser<'c, 'd> {
     }
 
     fn parse_type(&mut self) -> Result<ParameterType, ParsingError> {
-        ParameterType::try_from(PrimitiveParser::parse_byte(self.cursor)?)
+        ParameterType::try_from(PrimitiveParser::parse_byte(self.cursor, "type")?)
             .map_err(|_| ParsingError::InvalidParameter(ParameterError::InvalidType))
     }
 
     fn parse_critical_value(&mut self) -> Result<CriticalValue, ParsingError> {
-        match PrimitiveParser::parse_byte(self.cursor)? {
+        match PrimitiveParser::parse_byte(self.cursor, "critical_value")? {
             0x00 => Ok(CriticalValue::Max),
             0x01 => Ok(CriticalValue::Min),
             _ => Err(ParsingError::InvalidParameter(
@@ -78,11 +78,13 @@ impl<'c, 'd> ParameterParser<'c, 'd> {
         let mut iters = 0;
 
         while iters < count {
+            self.cursor.begin("formatted_range_lines[]");
             parsed.push(FormattedRangeLine {
-                from: PrimitiveParser::parse_i32(self.cursor)?,
-                to: PrimitiveParser::parse_i32(self.cursor)?,
-                value: StringParser::parse(self.cursor)?,
+                from: PrimitiveParser::parse_i32(self.cursor, "from")?,
+                to: PrimitiveParser::parse_i32(self.cursor, "to")?,
+                value: StringParser::parse(self.cursor, "value")?,
             });
+            self.cursor.end();
 
             iters += 1;
         }
@@ -1,6 +1,8 @@
-use std::io::Cursor;
+use std::ops::Range;
 
-use super::{ParsingError, Quest};
+use crate::text::formula::FormulaParseOptions;
+
+use super::{LocatedParsingError, ParsingError, Quest, QuestRead, QuestSection};
 
 use super::{
     HeaderParser, InfoParser, JumpParser, LocationParser, ParameterParser, StringReplacementsParser,
@@ -8,25 +10,149 @@ use super::{
 
 pub struct QmmParser;
 
+/// Byte ranges of the parsed sections, for copying the unchanged ones.
+#[derive(Debug, Default)]
+pub(super) struct QuestSpans {
+    pub header: Range<usize>,
+    pub parameters: Vec<Range<usize>>,
+    pub string_replacements: Range<usize>,
+    pub info: Range<usize>,
+    pub locations: Vec<Range<usize>>,
+    pub jumps: Vec<Range<usize>>,
+}
+
 impl QmmParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Quest, ParsingError> {
+    pub fn parse(cursor: &mut impl QuestRead) -> Result<Quest, ParsingError> {
+        Self::parse_with(cursor, FormulaParseOptions::default())
+    }
+
+    /// Same as [`QmmParser::parse`], but parses the formulas with the options.
+    pub fn parse_with(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+    ) -> Result<Quest, ParsingError> {
+        Self::located(cursor, options).map_err(|err| err.error)
+    }
+
+    /// Same as [`QmmParser::parse`], but the error tells where parsing stopped.
+    pub fn parse_located(cursor: &mut impl QuestRead) -> Result<Quest, LocatedParsingError> {
+        Self::located(cursor, FormulaParseOptions::default())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "parse_qmm", skip_all, fields(len = cursor.len())))]
+    fn located(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+    ) -> Result<Quest, LocatedParsingError> {
+        let mut section = QuestSection::Header;
+
+        Self::parse_sections(cursor, options, &mut section, &mut QuestSpans::default()).map_err(
+            |error| {
+                let err = LocatedParsingError {
+                    error,
+                    section,
+                    offset: cursor.position(),
+                };
+                trace_event!(warn, error = %err, "parsing failed");
+
+                err
+            },
+        )
+    }
+
+    /// Parses the quest along with the ranges of its sections.
+    pub(super) fn parse_spanned(
+        cursor: &mut impl QuestRead,
+    ) -> Result<(Quest, QuestSpans), ParsingError> {
+        let mut spans = QuestSpans::default();
+        let quest = Self::parse_sections(
+            cursor,
+            FormulaParseOptions::default(),
+            &mut QuestSection::Header,
+            &mut spans,
+        )?;
+
+        Ok((quest, spans))
+    }
+
+    fn parse_sections(
+        cursor: &mut impl QuestRead,
+        options: FormulaParseOptions,
+        section: &mut QuestSection,
+        spans: &mut QuestSpans,
+    ) -> Result<Quest, ParsingError> {
+        cursor.begin("header");
         let header = HeaderParser::parse(cursor)?;
+        cursor.end();
+        spans.header = 0..cursor.position() as usize;
+        trace_event!(
+            debug,
+            offset = cursor.position(),
+            version = ?header.version,
+            parameters = header.parameters_count,
+            "parsed header"
+        );
+
         let mut parameters = Vec::with_capacity(header.parameters_count);
         let mut parameters_iters = 0;
 
         while parameters_iters < header.parameters_count {
+            *section = QuestSection::Parameter(parameters_iters);
+            let offset = cursor.position();
+            cursor.begin("parameter");
             parameters.push(ParameterParser::new(cursor).parse()?);
+            cursor.end();
+            spans.parameters.push(span(offset, cursor));
 
             parameters_iters += 1;
         }
 
+        trace_event!(debug, offset = cursor.position(), "parsed parameters");
+
+        *section = QuestSection::StringReplacements;
+        let offset = cursor.position();
+        cursor.begin("string_replacements");
         let string_replacements = StringReplacementsParser::parse(cursor)?;
+        cursor.end();
+        spans.string_replacements = span(offset, cursor);
+        trace_event!(
+            debug,
+            offset = cursor.position(),
+            "parsed string replacements"
+        );
+
+        *section = QuestSection::Info;
+        let offset = cursor.position();
+        cursor.begin("info");
         let info = InfoParser::parse(cursor)?;
+        cursor.end();
+        spans.info = span(offset, cursor);
+        trace_event!(
+            debug,
+            offset = cursor.position(),
+            locations = info.locations_count,
+            jumps = info.jumps_count,
+            "parsed info"
+        );
+
         let mut locations = Vec::with_capacity(info.locations_count as usize);
         let mut locations_iter = 0;
 
         while locations_iter < info.locations_count {
-            locations.push(LocationParser::parse(cursor)?);
+            *section = QuestSection::Location(locations_iter as usize);
+            let offset = cursor.position();
+            cursor.begin("location");
+            let location = LocationParser::parse(cursor, options)?;
+            cursor.end();
+            spans.locations.push(span(offset, cursor));
+            trace_event!(
+                trace,
+                offset = offset,
+                id = location.id.0,
+                "parsed location"
+            );
+
+            locations.push(location);
             locations_iter += 1;
         }
 
@@ -34,11 +160,21 @@ impl QmmParser {
         let mut jumps_iter = 0;
 
         while jumps_iter < info.jumps_count {
-            jumps.push(JumpParser::parse(cursor)?);
+            *section = QuestSection::Jump(jumps_iter as usize);
+            let offset = cursor.position();
+            cursor.begin("jump");
+            let jump = JumpParser::parse(cursor, options)?;
+            cursor.end();
+            spans.jumps.push(span(offset, cursor));
+            trace_event!(trace, offset = offset, id = jump.id.0, "parsed jump");
+
+            jumps.push(jump);
             jumps_iter += 1;
         }
 
-        if (cursor.position() as usize) != cursor.get_ref().len() {
+        *section = QuestSection::End;
+
+        if cursor.position() != cursor.len() {
             return Err(ParsingError::ExpectedEnd);
         }
 
@@ -52,3 +188,7 @@ impl QmmParser {
         })
     }
 }
+
+fn span(start: u64, cursor: &impl QuestRead) -> Range<usize> {
+    start as usize..cursor.position() as usize
+}
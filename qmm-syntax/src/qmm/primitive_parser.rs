@@ -1,11 +1,13 @@
-use std::io::{Cursor, Read};
-
-use super::ParsingError;
+use super::{FieldType, ParsingError, QuestRead};
 
 pub struct PrimitiveParser;
 
 impl PrimitiveParser {
-    pub fn parse_i32(cursor: &mut Cursor<&[u8]>) -> Result<i32, ParsingError> {
+    pub fn parse_i32(
+        cursor: &mut impl QuestRead,
+        field: &'static str,
+    ) -> Result<i32, ParsingError> {
+        cursor.field(field, FieldType::I32);
         let mut bytes = [0; 4];
 
         cursor
@@ -15,7 +17,11 @@ impl PrimitiveParser {
         Ok(i32::from_le_bytes(bytes))
     }
 
-    pub fn parse_f64(cursor: &mut Cursor<&[u8]>) -> Result<f64, ParsingError> {
+    pub fn parse_f64(
+        cursor: &mut impl QuestRead,
+        field: &'static str,
+    ) -> Result<f64, ParsingError> {
+        cursor.field(field, FieldType::F64);
         let mut bytes = [0; 8];
 
         cursor
@@ -25,7 +31,11 @@ impl PrimitiveParser {
         Ok(f64::from_le_bytes(bytes))
     }
 
-    pub fn parse_bool(cursor: &mut Cursor<&[u8]>) -> Result<bool, ParsingError> {
+    pub fn parse_bool(
+        cursor: &mut impl QuestRead,
+        field: &'static str,
+    ) -> Result<bool, ParsingError> {
+        cursor.field(field, FieldType::Bool);
         let mut bytes = [0; 1];
 
         cursor
@@ -39,7 +49,11 @@ impl PrimitiveParser {
         }
     }
 
-    pub fn parse_byte(cursor: &mut Cursor<&[u8]>) -> Result<u8, ParsingError> {
+    pub fn parse_byte(
+        cursor: &mut impl QuestRead,
+        field: &'static str,
+    ) -> Result<u8, ParsingError> {
+        cursor.field(field, FieldType::Byte);
         let mut byte = [0; 1];
 
         cursor
@@ -0,0 +1,105 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use super::FieldType;
+
+/// Source of the quest data for the parsers.
+///
+/// Implemented for cursors over anything that derefs to bytes: slices,
+/// vectors and memory-mapped files (`Cursor::new(mmap)`), so huge packs can
+/// be parsed without copying them. Other readers, like files, are wrapped
+/// into [`SeekReader`].
+pub trait QuestRead {
+    /// Reads exactly `buf.len()` bytes.
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()>;
+
+    /// Offset from the start of the data.
+    fn position(&self) -> u64;
+
+    /// Moves to the offset from the start of the data.
+    fn seek(&mut self, position: u64) -> io::Result<()>;
+
+    /// Total length of the data.
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Called by the parsers before reading a field. Only
+    /// [`super::FormatRecorder`] cares about the marks.
+    fn field(&mut self, _name: &'static str, _ty: FieldType) {}
+
+    /// Starts a section or a nested structure, the following fields belong
+    /// to it until [`QuestRead::end`].
+    fn begin(&mut self, _name: &'static str) {}
+
+    fn end(&mut self) {}
+}
+
+impl<T: AsRef<[u8]>> QuestRead for Cursor<T> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        Read::read_exact(self, buf)
+    }
+
+    fn position(&self) -> u64 {
+        Cursor::position(self)
+    }
+
+    fn seek(&mut self, position: u64) -> io::Result<()> {
+        self.set_position(position);
+
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.get_ref().as_ref().len() as u64
+    }
+}
+
+/// [`QuestRead`] over any seekable reader, e.g. a buffered file.
+pub struct SeekReader<R> {
+    reader: R,
+    position: u64,
+    len: u64,
+}
+
+impl<R: Read + Seek> SeekReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let position = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(position))?;
+
+        Ok(Self {
+            reader,
+            position,
+            len,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> QuestRead for SeekReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(buf)?;
+        self.position += buf.len() as u64;
+
+        Ok(())
+    }
+
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn seek(&mut self, position: u64) -> io::Result<()> {
+        self.position = self.reader.seek(SeekFrom::Start(position))?;
+
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
@@ -1,11 +1,11 @@
-use std::io::{Cursor, Read};
-
-use super::ParsingError;
+use super::{FieldType, ParsingError, QuestRead};
 
 pub struct StringParser;
 
 impl StringParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<String, ParsingError> {
+    pub fn parse(cursor: &mut impl QuestRead, field: &'static str) -> Result<String, ParsingError> {
+        cursor.field(field, FieldType::String);
+
         let mut has_string_bytes = [0; 4];
 
         cursor
@@ -1,21 +1,19 @@
-use std::io::Cursor;
-
-use super::{ParsingError, StringParser, StringReplacements};
+use super::{ParsingError, QuestRead, StringParser, StringReplacements};
 
 pub struct StringReplacementsParser;
 
 impl StringReplacementsParser {
-    pub fn parse(cursor: &mut Cursor<&[u8]>) -> Result<StringReplacements, ParsingError> {
-        let to_star = StringParser::parse(cursor)?;
-        let to_planet = StringParser::parse(cursor)?;
+    pub fn parse(cursor: &mut impl QuestRead) -> Result<StringReplacements, ParsingError> {
+        let to_star = StringParser::parse(cursor, "to_star")?;
+        let to_planet = StringParser::parse(cursor, "to_planet")?;
 
         // Skip '<Date>' and '<Money>' strings
-        StringParser::parse(cursor)?;
-        StringParser::parse(cursor)?;
+        StringParser::parse(cursor, "date")?;
+        StringParser::parse(cursor, "money")?;
 
-        let from_planet = StringParser::parse(cursor)?;
-        let from_star = StringParser::parse(cursor)?;
-        let ranger = StringParser::parse(cursor)?;
+        let from_planet = StringParser::parse(cursor, "from_planet")?;
+        let from_star = StringParser::parse(cursor, "from_star")?;
+        let ranger = StringParser::parse(cursor, "ranger")?;
 
         Ok(StringReplacements {
             to_star,
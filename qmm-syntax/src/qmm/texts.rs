@@ -0,0 +1,368 @@
+use std::{fmt::Display, iter};
+
+use crate::text::{formatted_text::FormattedText, formula::Formula};
+
+use super::{JumpId, LocationId, LocationSelectType, Parameter, ParameterChangeType, Quest};
+
+/// Place of a text in the quest.
+///
+/// Parameters are identified by their one-based ids, text indices are
+/// one-based as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextSource {
+    TaskText,
+    SuccessText,
+    ParameterName(u32),
+    ParameterRangeLine {
+        parameter: u32,
+        index: usize,
+    },
+    ParameterCriticalText(u32),
+    LocationText {
+        location: LocationId,
+        index: usize,
+    },
+    LocationCriticalText {
+        location: LocationId,
+        parameter: u32,
+    },
+    JumpText(JumpId),
+    JumpDescription(JumpId),
+    JumpCriticalText {
+        jump: JumpId,
+        parameter: u32,
+    },
+}
+
+impl Display for TextSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextSource::TaskText => f.write_str("task text"),
+            TextSource::SuccessText => f.write_str("success text"),
+            TextSource::ParameterName(parameter) => write!(f, "parameter {parameter} name"),
+            TextSource::ParameterRangeLine { parameter, index } => {
+                write!(f, "parameter {parameter} range line {index}")
+            }
+            TextSource::ParameterCriticalText(parameter) => {
+                write!(f, "parameter {parameter} critical text")
+            }
+            TextSource::LocationText { location, index } => {
+                write!(f, "location {} text {index}", location.0)
+            }
+            TextSource::LocationCriticalText {
+                location,
+                parameter,
+            } => write!(
+                f,
+                "location {} parameter {parameter} critical text",
+                location.0
+            ),
+            TextSource::JumpText(jump) => write!(f, "jump {} text", jump.0),
+            TextSource::JumpDescription(jump) => write!(f, "jump {} description", jump.0),
+            TextSource::JumpCriticalText { jump, parameter } => {
+                write!(f, "jump {} parameter {parameter} critical text", jump.0)
+            }
+        }
+    }
+}
+
+/// Place of a formula in the quest, parameters are identified by their
+/// one-based ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormulaSource {
+    /// Selects the location's text
+    LocationSelection(LocationId),
+    LocationChange {
+        location: LocationId,
+        parameter: u32,
+    },
+    /// Condition of the jump
+    JumpFormula(JumpId),
+    JumpChange {
+        jump: JumpId,
+        parameter: u32,
+    },
+}
+
+impl Display for FormulaSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormulaSource::LocationSelection(location) => {
+                write!(f, "location {} text selection", location.0)
+            }
+            FormulaSource::LocationChange {
+                location,
+                parameter,
+            } => write!(f, "location {} parameter {parameter} change", location.0),
+            FormulaSource::JumpFormula(jump) => write!(f, "jump {} formula", jump.0),
+            FormulaSource::JumpChange { jump, parameter } => {
+                write!(f, "jump {} parameter {parameter} change", jump.0)
+            }
+        }
+    }
+}
+
+/// Non-empty text of the quest with its place.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QuestText {
+    pub source: TextSource,
+    pub text: String,
+}
+
+impl QuestText {
+    /// Number of words, a word is a run of characters between whitespaces
+    /// that has at least one letter or digit.
+    pub fn word_count(&self) -> usize {
+        self.text
+            .split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count()
+    }
+}
+
+impl Quest {
+    /// All non-empty texts of the quest, in file order.
+    pub fn texts(&self) -> Vec<QuestText> {
+        let mut texts = Vec::new();
+        let mut push = |source, text: String| {
+            if !text.trim().is_empty() {
+                texts.push(QuestText { source, text });
+            }
+        };
+
+        push(TextSource::TaskText, self.info.task_text.to_string());
+        push(TextSource::SuccessText, self.info.success_text.to_string());
+
+        for (parameter, id) in self.parameters.iter().zip(1..) {
+            push(TextSource::ParameterName(id), parameter.name.clone());
+
+            for (line, index) in parameter.formatted_range_lines.iter().zip(1..) {
+                push(
+                    TextSource::ParameterRangeLine {
+                        parameter: id,
+                        index,
+                    },
+                    line.value.clone(),
+                );
+            }
+
+            push(
+                TextSource::ParameterCriticalText(id),
+                parameter.critical_text.clone(),
+            );
+        }
+
+        for location in &self.locations {
+            for (text, index) in location.texts.iter().zip(1..) {
+                push(
+                    TextSource::LocationText {
+                        location: location.id,
+                        index,
+                    },
+                    text.to_string(),
+                );
+            }
+
+            for change in &location.parameter_changes {
+                push(
+                    TextSource::LocationCriticalText {
+                        location: location.id,
+                        parameter: change.parameter_id,
+                    },
+                    change.critical_text.clone(),
+                );
+            }
+        }
+
+        for jump in &self.jumps {
+            push(TextSource::JumpText(jump.id), jump.text.to_string());
+            push(
+                TextSource::JumpDescription(jump.id),
+                jump.description.to_string(),
+            );
+
+            for change in &jump.parameter_changes {
+                push(
+                    TextSource::JumpCriticalText {
+                        jump: jump.id,
+                        parameter: change.parameter_id,
+                    },
+                    change.critical_text.clone(),
+                );
+            }
+        }
+
+        texts
+    }
+
+    /// Texts of the quest kept formatted, the empty ones included, in file
+    /// order: the task and success texts, the location texts, the jump texts
+    /// and descriptions. The other texts are plain strings.
+    pub fn formatted_texts(&self) -> impl Iterator<Item = (TextSource, &FormattedText)> {
+        let info = [
+            (TextSource::TaskText, &self.info.task_text),
+            (TextSource::SuccessText, &self.info.success_text),
+        ];
+        let locations = self.locations.iter().flat_map(|location| {
+            location.texts.iter().zip(1..).map(|(text, index)| {
+                let source = TextSource::LocationText {
+                    location: location.id,
+                    index,
+                };
+
+                (source, text)
+            })
+        });
+        let jumps = self.jumps.iter().flat_map(|jump| {
+            [
+                (TextSource::JumpText(jump.id), &jump.text),
+                (TextSource::JumpDescription(jump.id), &jump.description),
+            ]
+        });
+
+        info.into_iter().chain(locations).chain(jumps)
+    }
+
+    /// Formulas of the quest in file order, the empty jump formulas
+    /// included. Only the formula changes have a change formula, and the
+    /// starting values of the parameters are plain strings.
+    pub fn formulas(&self) -> impl Iterator<Item = (FormulaSource, &Formula)> {
+        let locations = self.locations.iter().flat_map(|location| {
+            let selection = match &location.select_type {
+                LocationSelectType::ByFormula(formula) => {
+                    Some((FormulaSource::LocationSelection(location.id), formula))
+                }
+                LocationSelectType::ByOrder => None,
+            };
+            let changes = location
+                .parameter_changes
+                .iter()
+                .filter(|change| change.change_type == ParameterChangeType::Formula)
+                .map(|change| {
+                    let source = FormulaSource::LocationChange {
+                        location: location.id,
+                        parameter: change.parameter_id,
+                    };
+
+                    (source, &change.formula)
+                });
+
+            selection.into_iter().chain(changes)
+        });
+        let jumps = self.jumps.iter().flat_map(|jump| {
+            let changes = jump
+                .parameter_changes
+                .iter()
+                .filter(|change| change.change_type == ParameterChangeType::Formula)
+                .map(|change| {
+                    let source = FormulaSource::JumpChange {
+                        jump: jump.id,
+                        parameter: change.parameter_id,
+                    };
+
+                    (source, &change.formula)
+                });
+
+            iter::once((FormulaSource::JumpFormula(jump.id), &jump.formula)).chain(changes)
+        });
+
+        locations.chain(jumps)
+    }
+
+    fn parameter_mut(&mut self, id: u32) -> Option<&mut Parameter> {
+        self.parameters.get_mut((id as usize).checked_sub(1)?)
+    }
+
+    /// Replaces the text at the source, returns `false` if there is no such
+    /// text. The critical texts of the parameter changes are found by the
+    /// first change of the parameter.
+    pub fn set_text(&mut self, source: TextSource, text: &str) -> bool {
+        let target: Option<&mut String> = match source {
+            TextSource::TaskText => {
+                self.info.task_text = FormattedText::parse(text);
+                return true;
+            }
+            TextSource::SuccessText => {
+                self.info.success_text = FormattedText::parse(text);
+                return true;
+            }
+            TextSource::ParameterName(id) => {
+                self.parameter_mut(id).map(|parameter| &mut parameter.name)
+            }
+            TextSource::ParameterRangeLine {
+                parameter: id,
+                index,
+            } => self
+                .parameter_mut(id)
+                .and_then(|parameter| {
+                    parameter
+                        .formatted_range_lines
+                        .get_mut(index.checked_sub(1)?)
+                })
+                .map(|line| &mut line.value),
+            TextSource::ParameterCriticalText(id) => self
+                .parameter_mut(id)
+                .map(|parameter| &mut parameter.critical_text),
+            TextSource::LocationText { location, index } => {
+                let Some(target) = self
+                    .locations
+                    .iter_mut()
+                    .find(|candidate| candidate.id == location)
+                    .and_then(|location| location.texts.get_mut(index.checked_sub(1)?))
+                else {
+                    return false;
+                };
+
+                *target = FormattedText::parse(text);
+                return true;
+            }
+            TextSource::LocationCriticalText {
+                location,
+                parameter,
+            } => self
+                .locations
+                .iter_mut()
+                .find(|candidate| candidate.id == location)
+                .and_then(|location| {
+                    location
+                        .parameter_changes
+                        .iter_mut()
+                        .find(|change| change.parameter_id == parameter)
+                })
+                .map(|change| &mut change.critical_text),
+            TextSource::JumpText(id) | TextSource::JumpDescription(id) => {
+                let Some(jump) = self.jumps.iter_mut().find(|jump| jump.id == id) else {
+                    return false;
+                };
+
+                if matches!(source, TextSource::JumpText(_)) {
+                    jump.text = FormattedText::parse(text);
+                } else {
+                    jump.description = FormattedText::parse(text);
+                }
+
+                return true;
+            }
+            TextSource::JumpCriticalText { jump, parameter } => self
+                .jumps
+                .iter_mut()
+                .find(|candidate| candidate.id == jump)
+                .and_then(|jump| {
+                    jump.parameter_changes
+                        .iter_mut()
+                        .find(|change| change.parameter_id == parameter)
+                })
+                .map(|change| &mut change.critical_text),
+        };
+
+        match target {
+            Some(target) => {
+                *target = text.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use std::fmt::Display;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque},
+    fmt::Display,
+    ops::RangeInclusive,
+};
 
 use bitflags::bitflags;
 
@@ -8,6 +12,7 @@ use crate::text::{
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Version {
     Qmm6,
     Qmm7,
@@ -27,6 +32,7 @@ impl TryFrom<&[u8; 4]> for Version {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Race: u8 {
         const Malok = 0x01;
         const Peleng = 0x02;
@@ -45,6 +51,7 @@ impl TryFrom<u8> for Race {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompletionCondition {
     Immediately,
     AfterReturning,
@@ -63,6 +70,7 @@ impl TryFrom<u8> for CompletionCondition {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlanetType {
     Populated(Race),
     Uninhabited,
@@ -70,6 +78,7 @@ pub enum PlanetType {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct PlayerStatus: u8 {
         const Trader = 0x01;
         const Pirate = 0x02;
@@ -86,12 +95,14 @@ impl TryFrom<u8> for PlayerStatus {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JumpsLimit {
     Infinite,
     Limit(u32),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub version: Version,
     pub giver_race: Race,
@@ -106,6 +117,7 @@ pub struct Header {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParameterType {
     Ordinary,
     Fail,
@@ -128,12 +140,14 @@ impl TryFrom<u8> for ParameterType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CriticalValue {
     Min,
     Max,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FormattedRangeLine {
     pub from: i32,
     pub to: i32,
@@ -141,6 +155,7 @@ pub struct FormattedRangeLine {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Parameter {
     pub min_value: i32,
     pub max_value: i32,
@@ -158,7 +173,29 @@ pub struct Parameter {
     pub starting_value: String,
 }
 
+impl Parameter {
+    /// Range line shown for the value.
+    ///
+    /// Like in the original engine the lines are checked in order, so the
+    /// first one wins when they overlap, and a line with `from` greater than
+    /// `to` never matches. A value in a gap between the lines has no line and
+    /// the parameter isn't shown.
+    pub fn range_line(&self, value: i32) -> Option<&FormattedRangeLine> {
+        self.formatted_range_lines
+            .iter()
+            .find(|line| line.from <= value && value <= line.to)
+    }
+
+    /// Text of the range line for the value with `<>` replaced by the value.
+    /// The formulas and variables of the line are left to the player.
+    pub fn display_for(&self, value: i32) -> Option<String> {
+        self.range_line(value)
+            .map(|line| line.value.replace("<>", &value.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StringReplacements {
     pub to_star: String,
     pub to_planet: String,
@@ -168,6 +205,7 @@ pub struct StringReplacements {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Info {
     pub locations_count: u32,
     pub jumps_count: u32,
@@ -176,12 +214,14 @@ pub struct Info {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MaxVisits {
     Infinite,
     Limit(u32),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocationType {
     Ordinary,
     Starting,
@@ -208,6 +248,7 @@ impl TryFrom<u8> for LocationType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParameterShowType {
     Nothing,
     Show,
@@ -228,6 +269,7 @@ impl TryFrom<u8> for ParameterShowType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParameterChangeType {
     Value,
     Sum,
@@ -250,6 +292,7 @@ impl TryFrom<u8> for ParameterChangeType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Media {
     pub image: String,
     pub sound: String,
@@ -257,8 +300,10 @@ pub struct Media {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParameterChange {
     pub parameter_id: u32,
+    pub change: i32,
     pub show_type: ParameterShowType,
     pub change_type: ParameterChangeType,
     pub formula: Formula,
@@ -267,17 +312,23 @@ pub struct ParameterChange {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocationSelectType {
     ByOrder,
     ByFormula(Formula),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocationId(pub u32);
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub do_pass_day: bool,
+    /// Position in the editor, `y` grows downwards
+    pub x: i32,
+    pub y: i32,
     pub id: LocationId,
     pub max_visits: MaxVisits,
     pub ty: LocationType,
@@ -287,10 +338,12 @@ pub struct Location {
     pub select_type: LocationSelectType,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JumpId(pub u32);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JumpParameterCondition {
     pub parameter_id: u32,
     pub range_start: i32,
@@ -302,6 +355,7 @@ pub struct JumpParameterCondition {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Jump {
     pub priority: f64,
     pub do_pass_day: bool,
@@ -319,7 +373,156 @@ pub struct Jump {
     pub media: Media,
 }
 
+/// Reason a jump edit was rejected, the jump is left unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JumpEditError {
+    /// Parameter ids start at 1
+    InvalidParameter(u32),
+    InvalidRange {
+        start: i32,
+        end: i32,
+    },
+    /// The condition of the parameter can't be changed into the condition of
+    /// another one that already has a condition
+    DuplicateCondition(u32),
+    MissingCondition(u32),
+    InvalidFormula(String),
+}
+
+impl Display for JumpEditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JumpEditError::InvalidParameter(id) => write!(f, "Invalid parameter id {id}"),
+            JumpEditError::InvalidRange { start, end } => {
+                write!(f, "Range start {start} is greater than its end {end}")
+            }
+            JumpEditError::DuplicateCondition(id) => {
+                write!(f, "[p{id}] already has a condition")
+            }
+            JumpEditError::MissingCondition(id) => write!(f, "[p{id}] has no condition"),
+            JumpEditError::InvalidFormula(err) => write!(f, "Invalid formula: {err}"),
+        }
+    }
+}
+
+impl JumpParameterCondition {
+    /// Condition met when the parameter is within the range.
+    pub fn range(parameter_id: u32, range: RangeInclusive<i32>) -> Self {
+        Self {
+            parameter_id,
+            range_start: *range.start(),
+            range_end: *range.end(),
+            must_equal: false,
+            must_equal_values: Vec::new(),
+            must_mod: false,
+            must_mod_values: Vec::new(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), JumpEditError> {
+        if self.parameter_id == 0 {
+            return Err(JumpEditError::InvalidParameter(self.parameter_id));
+        }
+
+        if self.range_start > self.range_end {
+            return Err(JumpEditError::InvalidRange {
+                start: self.range_start,
+                end: self.range_end,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Mutators keeping at most one valid condition per parameter and a formula
+/// the player can evaluate.
+impl Jump {
+    pub fn condition(&self, parameter_id: u32) -> Option<&JumpParameterCondition> {
+        self.parameters_conditions
+            .iter()
+            .find(|condition| condition.parameter_id == parameter_id)
+    }
+
+    /// Adds the condition or replaces the one of the same parameter.
+    pub fn set_condition(
+        &mut self,
+        condition: JumpParameterCondition,
+    ) -> Result<(), JumpEditError> {
+        condition.validate()?;
+
+        match self
+            .parameters_conditions
+            .iter_mut()
+            .find(|existing| existing.parameter_id == condition.parameter_id)
+        {
+            Some(existing) => *existing = condition,
+            None => self.parameters_conditions.push(condition),
+        }
+
+        Ok(())
+    }
+
+    /// Changes the condition of the parameter in place.
+    pub fn update_condition(
+        &mut self,
+        parameter_id: u32,
+        update: impl FnOnce(&mut JumpParameterCondition),
+    ) -> Result<(), JumpEditError> {
+        let index = self
+            .parameters_conditions
+            .iter()
+            .position(|condition| condition.parameter_id == parameter_id)
+            .ok_or(JumpEditError::MissingCondition(parameter_id))?;
+        let mut condition = self.parameters_conditions[index].clone();
+
+        update(&mut condition);
+        condition.validate()?;
+
+        if condition.parameter_id != parameter_id
+            && self.condition(condition.parameter_id).is_some()
+        {
+            return Err(JumpEditError::DuplicateCondition(condition.parameter_id));
+        }
+
+        self.parameters_conditions[index] = condition;
+
+        Ok(())
+    }
+
+    pub fn remove_condition(&mut self, parameter_id: u32) -> Option<JumpParameterCondition> {
+        let index = self
+            .parameters_conditions
+            .iter()
+            .position(|condition| condition.parameter_id == parameter_id)?;
+
+        Some(self.parameters_conditions.remove(index))
+    }
+
+    /// Replaces the formula, an empty text removes it.
+    pub fn set_formula(&mut self, text: &str) -> Result<(), JumpEditError> {
+        let formula =
+            Formula::parse(text).map_err(|err| JumpEditError::InvalidFormula(err.to_string()))?;
+
+        if !formula.is_empty() {
+            formula
+                .expression()
+                .map_err(|err| JumpEditError::InvalidFormula(err.to_string()))?;
+        }
+
+        self.formula = formula;
+
+        Ok(())
+    }
+}
+
+/// Parsed quest.
+///
+/// `parameters`, `locations` and `jumps` are always stored in the order they
+/// appear in the file. Locations and jumps should be looked up by their ids
+/// via [`Quest::location`] and [`Quest::jump`] rather than by position.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Quest {
     pub header: Header,
     pub parameters: Vec<Parameter>,
@@ -329,6 +532,198 @@ pub struct Quest {
     pub jumps: Vec<Jump>,
 }
 
+impl Quest {
+    pub fn location(&self, id: LocationId) -> Option<&Location> {
+        self.locations.iter().find(|loc| loc.id == id)
+    }
+
+    pub fn jump(&self, id: JumpId) -> Option<&Jump> {
+        self.jumps.iter().find(|jump| jump.id == id)
+    }
+
+    /// Jumps leading out of the location, in file order.
+    pub fn jumps_from(&self, id: LocationId) -> impl Iterator<Item = &Jump> {
+        self.jumps.iter().filter(move |jump| jump.from == id)
+    }
+
+    /// Fewest jumps from the location to every location reachable from it,
+    /// ignoring the jump conditions.
+    pub fn distances(&self, from: LocationId) -> BTreeMap<LocationId, usize> {
+        let mut distances = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        if self.location(from).is_some() {
+            distances.insert(from, 0);
+            queue.push_back(from);
+        }
+
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[&id] + 1;
+
+            for jump in self.jumps_from(id) {
+                if let Entry::Vacant(entry) = distances.entry(jump.to) {
+                    entry.insert(distance);
+                    queue.push_back(jump.to);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Part of the quest with the locations at most `depth` jumps away from
+    /// the location in either direction and the jumps between them, for
+    /// looking at a large quest piece by piece. Empty if the location
+    /// doesn't exist.
+    pub fn neighborhood(&self, id: LocationId, depth: usize) -> Quest {
+        let mut included = BTreeSet::new();
+        let mut frontier = Vec::new();
+
+        if self.location(id).is_some() {
+            included.insert(id);
+            frontier.push(id);
+        }
+
+        for _ in 0..depth {
+            if frontier.is_empty() {
+                break;
+            }
+
+            let mut next = Vec::new();
+
+            for jump in &self.jumps {
+                for (from, to) in [(jump.from, jump.to), (jump.to, jump.from)] {
+                    if frontier.contains(&from) && included.insert(to) {
+                        next.push(to);
+                    }
+                }
+            }
+
+            frontier = next;
+        }
+
+        let locations: Vec<Location> = self
+            .locations
+            .iter()
+            .filter(|location| included.contains(&location.id))
+            .cloned()
+            .collect();
+        let jumps: Vec<Jump> = self
+            .jumps
+            .iter()
+            .filter(|jump| included.contains(&jump.from) && included.contains(&jump.to))
+            .cloned()
+            .collect();
+
+        Quest {
+            header: self.header.clone(),
+            parameters: self.parameters.clone(),
+            string_replacements: self.string_replacements.clone(),
+            info: Info {
+                locations_count: locations.len() as u32,
+                jumps_count: jumps.len() as u32,
+                ..self.info.clone()
+            },
+            locations,
+            jumps,
+        }
+    }
+}
+
+/// Mutators keeping the jumps between existing locations and the counts of
+/// the info up to date.
+impl Quest {
+    fn empty_media() -> Media {
+        Media {
+            image: String::new(),
+            sound: String::new(),
+            track: String::new(),
+        }
+    }
+
+    /// Adds an ordinary location with a single empty text at the editor
+    /// position.
+    pub fn add_location(&mut self, x: i32, y: i32) -> LocationId {
+        let id = LocationId(
+            self.locations
+                .iter()
+                .map(|location| location.id.0)
+                .max()
+                .unwrap_or(0)
+                + 1,
+        );
+
+        self.locations.push(Location {
+            do_pass_day: false,
+            x,
+            y,
+            id,
+            max_visits: MaxVisits::Infinite,
+            ty: LocationType::Ordinary,
+            parameter_changes: Vec::new(),
+            texts: vec![FormattedText::default()],
+            media: vec![Self::empty_media()],
+            select_type: LocationSelectType::ByOrder,
+        });
+        self.info.locations_count = self.locations.len() as u32;
+
+        id
+    }
+
+    /// Removes the location with the jumps leading out of it and into it.
+    pub fn remove_location(&mut self, id: LocationId) -> Option<Location> {
+        let index = self
+            .locations
+            .iter()
+            .position(|location| location.id == id)?;
+
+        self.jumps.retain(|jump| jump.from != id && jump.to != id);
+        self.info.jumps_count = self.jumps.len() as u32;
+        self.info.locations_count = self.locations.len() as u32 - 1;
+
+        Some(self.locations.remove(index))
+    }
+
+    /// Adds an unconditional jump with an empty text, `None` if any of the
+    /// locations doesn't exist.
+    pub fn add_jump(&mut self, from: LocationId, to: LocationId) -> Option<JumpId> {
+        self.location(from)?;
+        self.location(to)?;
+
+        let id = JumpId(self.jumps.iter().map(|jump| jump.id.0).max().unwrap_or(0) + 1);
+
+        self.jumps.push(Jump {
+            priority: 1.0,
+            do_pass_day: false,
+            id,
+            from,
+            to,
+            show_always: false,
+            max_visits: MaxVisits::Infinite,
+            // The original editor's default
+            show_order: 5,
+            parameters_conditions: Vec::new(),
+            parameter_changes: Vec::new(),
+            formula: Formula::default(),
+            text: FormattedText::default(),
+            description: FormattedText::default(),
+            media: Self::empty_media(),
+        });
+        self.info.jumps_count = self.jumps.len() as u32;
+
+        Some(id)
+    }
+
+    pub fn remove_jump(&mut self, id: JumpId) -> Option<Jump> {
+        let index = self.jumps.iter().position(|jump| jump.id == id)?;
+        let jump = self.jumps.remove(index);
+
+        self.info.jumps_count = self.jumps.len() as u32;
+
+        Some(jump)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ParsingError {
     InvalidHeader(HeaderError),
@@ -363,6 +758,68 @@ impl Display for ParsingError {
     }
 }
 
+impl ParsingError {
+    /// Name of the error variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParsingError::InvalidHeader(_) => "InvalidHeader",
+            ParsingError::InvalidParameter(_) => "InvalidParameter",
+            ParsingError::InvalidLocation(_) => "InvalidLocation",
+            ParsingError::InvalidParameterChange(_) => "InvalidParameterChange",
+            ParsingError::InvalidBool => "InvalidBool",
+            ParsingError::InvalidString => "InvalidString",
+            ParsingError::Incomplete => "Incomplete",
+            ParsingError::ExpectedEnd => "ExpectedEnd",
+            ParsingError::InvalidFormula { .. } => "InvalidFormula",
+        }
+    }
+}
+
+/// Part of the quest file, the index is zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuestSection {
+    Header,
+    Parameter(usize),
+    StringReplacements,
+    Info,
+    Location(usize),
+    Jump(usize),
+    /// Trailing data after the last jump
+    End,
+}
+
+impl Display for QuestSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestSection::Header => f.write_str("header"),
+            QuestSection::Parameter(index) => f.write_fmt(format_args!("parameter #{index}")),
+            QuestSection::StringReplacements => f.write_str("string replacements"),
+            QuestSection::Info => f.write_str("info"),
+            QuestSection::Location(index) => f.write_fmt(format_args!("location #{index}")),
+            QuestSection::Jump(index) => f.write_fmt(format_args!("jump #{index}")),
+            QuestSection::End => f.write_str("end"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LocatedParsingError {
+    pub error: ParsingError,
+    /// Section that was being parsed
+    pub section: QuestSection,
+    /// Position in the data where parsing stopped
+    pub offset: u64,
+}
+
+impl Display for LocatedParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} (in {} at byte {})",
+            self.error, self.section, self.offset
+        ))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HeaderError {
     InvalidMagic,
@@ -0,0 +1,394 @@
+use std::{io::Cursor, ops::Range};
+
+use super::{
+    parser::QuestSpans, CompletionCondition, CriticalValue, Header, Jump, JumpParameterCondition,
+    JumpsLimit, Location, LocationSelectType, LocationType, MaxVisits, Media, Parameter,
+    ParameterChange, ParameterChangeType, ParameterShowType, ParameterType, ParsingError,
+    PlanetType, QmmParser, Quest, StringReplacements, Version,
+};
+
+/// Serializes quests into the format read by [`super::QmmParser`].
+///
+/// The counts of the header and the info are taken from the quest's vectors,
+/// so the edited quests stay consistent. The editor's screen and grid sizes
+/// aren't parsed and are written with the usual values.
+pub struct QmmWriter {
+    data: Vec<u8>,
+}
+
+impl QmmWriter {
+    pub fn write(quest: &Quest) -> Vec<u8> {
+        let mut writer = Self { data: Vec::new() };
+
+        writer.header(&quest.header, quest.parameters.len());
+
+        for parameter in &quest.parameters {
+            writer.parameter(parameter);
+        }
+
+        writer.string_replacements(&quest.string_replacements);
+        writer.i32(quest.locations.len() as i32);
+        writer.i32(quest.jumps.len() as i32);
+        writer.string(&quest.info.success_text.to_string());
+        writer.string(&quest.info.task_text.to_string());
+
+        for location in &quest.locations {
+            writer.location(location);
+        }
+
+        for jump in &quest.jumps {
+            writer.jump(jump);
+        }
+
+        writer.data
+    }
+
+    /// Writes the quest over the file it was parsed from, copying the
+    /// sections whose objects didn't change byte for byte.
+    ///
+    /// Parameters are matched by position, locations and jumps by id. The
+    /// copied sections keep whatever the parser skips and [`QmmWriter::write`]
+    /// normalizes, like the editor's screen sizes and the padding bytes, so
+    /// only the edited objects differ from the original file.
+    pub fn patch(original: &[u8], quest: &Quest) -> Result<Vec<u8>, ParsingError> {
+        let (old, spans) = QmmParser::parse_spanned(&mut Cursor::new(original))?;
+        let mut writer = Self {
+            data: Vec::with_capacity(original.len()),
+        };
+
+        writer.patch_header(original, &old, &spans, quest);
+
+        for (i, parameter) in quest.parameters.iter().enumerate() {
+            let old = old.parameters.get(i).zip(spans.parameters.get(i));
+            writer.copy_or_write(original, old, parameter, Self::parameter);
+        }
+
+        writer.copy_or_write(
+            original,
+            Some((&old.string_replacements, &spans.string_replacements)),
+            &quest.string_replacements,
+            Self::string_replacements,
+        );
+
+        let info_start = writer.data.len();
+
+        if (&old.info.success_text, &old.info.task_text)
+            == (&quest.info.success_text, &quest.info.task_text)
+        {
+            writer.copy(original, &spans.info);
+        } else {
+            writer.i32(0);
+            writer.i32(0);
+            writer.string(&quest.info.success_text.to_string());
+            writer.string(&quest.info.task_text.to_string());
+        }
+
+        // The counts are taken from the vectors like in `write`
+        writer.set_i32(info_start, quest.locations.len() as i32);
+        writer.set_i32(info_start + 4, quest.jumps.len() as i32);
+
+        for location in &quest.locations {
+            let old = old
+                .locations
+                .iter()
+                .zip(&spans.locations)
+                .find(|(old, _)| old.id == location.id);
+            writer.copy_or_write(original, old, location, Self::location);
+        }
+
+        for jump in &quest.jumps {
+            let old = old
+                .jumps
+                .iter()
+                .zip(&spans.jumps)
+                .find(|(old, _)| old.id == jump.id);
+            writer.copy_or_write(original, old, jump, Self::jump);
+        }
+
+        Ok(writer.data)
+    }
+
+    fn patch_header(&mut self, original: &[u8], old: &Quest, spans: &QuestSpans, quest: &Quest) {
+        let header = &original[spans.header.clone()];
+        let unchanged = Header {
+            parameters_count: old.header.parameters_count,
+            ..quest.header.clone()
+        } == old.header;
+
+        if unchanged {
+            self.data.extend_from_slice(header);
+        } else {
+            self.header(&quest.header, 0);
+
+            // Both end with the screen and grid sizes, the jumps limit, the
+            // difficulty and the parameters count
+            let sizes = header.len() - 28..header.len() - 12;
+            let start = self.data.len() - 28;
+            self.data[start..start + 16].copy_from_slice(&header[sizes]);
+        }
+
+        let count_start = self.data.len() - 4;
+        self.set_i32(count_start, quest.parameters.len() as i32);
+    }
+
+    fn copy(&mut self, original: &[u8], span: &Range<usize>) {
+        self.data.extend_from_slice(&original[span.clone()]);
+    }
+
+    fn copy_or_write<T: PartialEq>(
+        &mut self,
+        original: &[u8],
+        old: Option<(&T, &Range<usize>)>,
+        new: &T,
+        write: fn(&mut Self, &T),
+    ) {
+        match old {
+            Some((old, span)) if old == new => self.copy(original, span),
+            _ => write(self, new),
+        }
+    }
+
+    fn set_i32(&mut self, offset: usize, value: i32) {
+        self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn byte(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    fn string(&mut self, value: &str) {
+        if value.is_empty() {
+            self.i32(0);
+            return;
+        }
+
+        let units: Vec<u16> = value.encode_utf16().collect();
+
+        self.i32(1);
+        self.i32(units.len() as i32);
+
+        for unit in units {
+            self.data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    fn max_visits(&mut self, value: &MaxVisits) {
+        match value {
+            MaxVisits::Infinite => self.i32(0),
+            MaxVisits::Limit(limit) => self.i32(*limit as i32),
+        }
+    }
+
+    fn media(&mut self, media: &Media) {
+        self.string(&media.image);
+        self.string(&media.sound);
+        self.string(&media.track);
+    }
+
+    fn header(&mut self, header: &Header, parameters_count: usize) {
+        self.data.extend_from_slice(match header.version {
+            Version::Qmm6 => &[0xD6, 0x35, 0x3A, 0x42],
+            // Followed by the major and minor versions and an empty
+            // changelog
+            Version::Qmm7 => &[
+                0xD7, 0x35, 0x3A, 0x42, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00,
+            ],
+        });
+        self.byte(header.giver_race.bits());
+        self.byte(match header.completion_condition {
+            CompletionCondition::AfterReturning => 0x00,
+            CompletionCondition::Immediately => 0x01,
+        });
+        self.byte(match header.quest_planet_type {
+            PlanetType::Populated(race) => race.bits(),
+            PlanetType::Uninhabited => 0x40,
+        });
+        self.byte(header.player_status.bits());
+        self.byte(header.player_race.bits());
+
+        // The parser negates the value marked by the high bytes
+        let relation = header.relation_change;
+        self.data.extend_from_slice(&if relation < 0 {
+            [relation.unsigned_abs(), 0xFF, 0xFF, 0xFF]
+        } else {
+            [relation as u8, 0x00, 0x00, 0x00]
+        });
+
+        // Screen and grid sizes
+        for size in [1280, 753, 30, 24] {
+            self.i32(size);
+        }
+
+        match header.default_jumps_limit {
+            JumpsLimit::Infinite => self.i32(0),
+            JumpsLimit::Limit(limit) => self.i32(limit as i32),
+        }
+
+        self.i32(header.difficult as i32);
+        self.i32(parameters_count as i32);
+    }
+
+    fn parameter(&mut self, parameter: &Parameter) {
+        self.i32(parameter.min_value);
+        self.i32(parameter.max_value);
+        self.byte(match parameter.ty {
+            ParameterType::Ordinary => 0x00,
+            ParameterType::Fail => 0x01,
+            ParameterType::Win => 0x02,
+            ParameterType::Death => 0x03,
+        });
+        self.data.extend_from_slice(&[0x00; 3]);
+        self.bool(parameter.show_when_zero);
+        self.byte(match parameter.critical_value {
+            CriticalValue::Max => 0x00,
+            CriticalValue::Min => 0x01,
+        });
+        self.bool(parameter.is_active);
+        self.i32(parameter.formatted_range_lines.len() as i32);
+        self.bool(parameter.is_money);
+        self.string(&parameter.name);
+
+        for line in &parameter.formatted_range_lines {
+            self.i32(line.from);
+            self.i32(line.to);
+            self.string(&line.value);
+        }
+
+        self.string(&parameter.critical_text);
+        self.string(&parameter.image);
+        self.string(&parameter.sound);
+        self.string(&parameter.track);
+        self.string(&parameter.starting_value);
+    }
+
+    fn string_replacements(&mut self, replacements: &StringReplacements) {
+        self.string(&replacements.to_star);
+        self.string(&replacements.to_planet);
+        self.string("<Date>");
+        self.string("<Money>");
+        self.string(&replacements.from_planet);
+        self.string(&replacements.from_star);
+        self.string(&replacements.ranger);
+    }
+
+    fn parameter_change(&mut self, change: &ParameterChange) {
+        self.i32(change.parameter_id as i32);
+        self.i32(change.change);
+        self.byte(match change.show_type {
+            ParameterShowType::Nothing => 0x00,
+            ParameterShowType::Show => 0x01,
+            ParameterShowType::Hide => 0x02,
+        });
+        self.byte(match change.change_type {
+            ParameterChangeType::Value => 0x00,
+            ParameterChangeType::Sum => 0x01,
+            ParameterChangeType::Percentage => 0x02,
+            ParameterChangeType::Formula => 0x03,
+        });
+        self.string(&change.formula.to_string());
+        self.string(&change.critical_text);
+        self.media(&change.media);
+    }
+
+    fn location(&mut self, location: &Location) {
+        self.i32(location.do_pass_day as i32);
+        self.i32(location.x);
+        self.i32(location.y);
+        self.i32(location.id.0 as i32);
+        self.max_visits(&location.max_visits);
+        self.byte(match location.ty {
+            LocationType::Ordinary => 0x00,
+            LocationType::Starting => 0x01,
+            LocationType::Empty => 0x02,
+            LocationType::Success => 0x03,
+            LocationType::Fail => 0x04,
+            LocationType::Death => 0x05,
+        });
+        self.i32(location.parameter_changes.len() as i32);
+
+        for change in &location.parameter_changes {
+            self.parameter_change(change);
+        }
+
+        self.i32(location.texts.len() as i32);
+
+        for (i, text) in location.texts.iter().enumerate() {
+            self.string(&text.to_string());
+            // Texts and media are parsed in pairs
+            match location.media.get(i) {
+                Some(media) => self.media(media),
+                None => self.data.extend_from_slice(&[0x00; 12]),
+            }
+        }
+
+        match &location.select_type {
+            LocationSelectType::ByOrder => {
+                self.bool(false);
+                self.string("");
+            }
+            LocationSelectType::ByFormula(formula) => {
+                self.bool(true);
+                self.string(&formula.to_string());
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &JumpParameterCondition) {
+        self.i32(condition.parameter_id as i32);
+        self.i32(condition.range_start);
+        self.i32(condition.range_end);
+        self.i32(condition.must_equal_values.len() as i32);
+        self.bool(condition.must_equal);
+
+        for value in &condition.must_equal_values {
+            self.i32(*value);
+        }
+
+        self.i32(condition.must_mod_values.len() as i32);
+        self.bool(condition.must_mod);
+
+        for value in &condition.must_mod_values {
+            self.i32(*value);
+        }
+    }
+
+    fn jump(&mut self, jump: &Jump) {
+        self.f64(jump.priority);
+        self.i32(jump.do_pass_day as i32);
+        self.i32(jump.id.0 as i32);
+        self.i32(jump.from.0 as i32);
+        self.i32(jump.to.0 as i32);
+        self.bool(jump.show_always);
+        self.max_visits(&jump.max_visits);
+        self.i32(jump.show_order as i32);
+        self.i32(jump.parameters_conditions.len() as i32);
+
+        for condition in &jump.parameters_conditions {
+            self.condition(condition);
+        }
+
+        self.i32(jump.parameter_changes.len() as i32);
+
+        for change in &jump.parameter_changes {
+            self.parameter_change(change);
+        }
+
+        self.string(&jump.formula.to_string());
+        self.string(&jump.text.to_string());
+        self.string(&jump.description.to_string());
+        self.media(&jump.media);
+    }
+}
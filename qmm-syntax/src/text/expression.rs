@@ -0,0 +1,1061 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use super::formula::{FormulaToken, FormulaTokenKind, ParseLimits, ToRangeValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BinaryOperator {
+    Add,
+    Substract,
+    Multiply,
+    Divide,
+    DivideWithRemain,
+    Modulo,
+    In,
+    And,
+    Or,
+    Greater,
+    GreaterOrEqual,
+    Lesser,
+    LesserOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl BinaryOperator {
+    fn from_token(kind: &FormulaTokenKind) -> Option<BinaryOperator> {
+        match kind {
+            FormulaTokenKind::Add => Some(BinaryOperator::Add),
+            FormulaTokenKind::Substract => Some(BinaryOperator::Substract),
+            FormulaTokenKind::Multiply => Some(BinaryOperator::Multiply),
+            FormulaTokenKind::Divide => Some(BinaryOperator::Divide),
+            FormulaTokenKind::DivideWithRemain => Some(BinaryOperator::DivideWithRemain),
+            FormulaTokenKind::Modulo => Some(BinaryOperator::Modulo),
+            FormulaTokenKind::In => Some(BinaryOperator::In),
+            FormulaTokenKind::And => Some(BinaryOperator::And),
+            FormulaTokenKind::Or => Some(BinaryOperator::Or),
+            FormulaTokenKind::Greater => Some(BinaryOperator::Greater),
+            FormulaTokenKind::GreaterOrEqual => Some(BinaryOperator::GreaterOrEqual),
+            FormulaTokenKind::Lesser => Some(BinaryOperator::Lesser),
+            FormulaTokenKind::LesserOrEqual => Some(BinaryOperator::LesserOrEqual),
+            FormulaTokenKind::Equal => Some(BinaryOperator::Equal),
+            FormulaTokenKind::NotEqual => Some(BinaryOperator::NotEqual),
+            _ => None,
+        }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::In
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterOrEqual
+            | BinaryOperator::Lesser
+            | BinaryOperator::LesserOrEqual
+            | BinaryOperator::Equal
+            | BinaryOperator::NotEqual => 3,
+            BinaryOperator::Add | BinaryOperator::Substract => 4,
+            BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::DivideWithRemain
+            | BinaryOperator::Modulo => 5,
+        }
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Substract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::DivideWithRemain => "div",
+            BinaryOperator::Modulo => "mod",
+            BinaryOperator::In => "in",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Lesser => "<",
+            BinaryOperator::LesserOrEqual => "<=",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "<>",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Integer(i32),
+    Double(f64),
+    /// `[p1]`, one-based
+    Parameter(usize),
+    /// `[1..5;7]`
+    Range(Vec<RangeInclusive<ToRangeValue>>),
+    /// `[p1] to 10`
+    ToRange {
+        start: ToRangeValue,
+        end: ToRangeValue,
+    },
+    Negate(Box<Expression>),
+    Binary {
+        operator: BinaryOperator,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_range_value = |value: &ToRangeValue| match value {
+            ToRangeValue::Parameter { index } => format!("[p{index}]"),
+            ToRangeValue::Integer { value } => value.to_string(),
+        };
+        // Parameters inside the range brackets are written without their own
+        let range_bound = |value: &ToRangeValue| match value {
+            ToRangeValue::Parameter { index } => format!("p{index}"),
+            ToRangeValue::Integer { value } => value.to_string(),
+        };
+
+        match self {
+            Expression::Integer(value) => write!(f, "{value}"),
+            Expression::Double(value) => write!(f, "{value}"),
+            Expression::Parameter(index) => write!(f, "[p{index}]"),
+            Expression::Range(ranges) => {
+                let ranges: Vec<String> = ranges
+                    .iter()
+                    .map(|range| match (range.start(), range.end()) {
+                        (start, end) if start == end => range_bound(start),
+                        (ToRangeValue::Integer { value: i32::MIN }, end) => {
+                            format!("..{}", range_bound(end))
+                        }
+                        (start, ToRangeValue::Integer { value: i32::MAX }) => {
+                            format!("{}..", range_bound(start))
+                        }
+                        (start, end) => format!("{}..{}", range_bound(start), range_bound(end)),
+                    })
+                    .collect();
+
+                write!(f, "[{}]", ranges.join(";"))
+            }
+            Expression::ToRange { start, end } => {
+                write!(f, "{} to {}", to_range_value(start), to_range_value(end))
+            }
+            Expression::Negate(operand) => match **operand {
+                Expression::Binary { .. } => write!(f, "-({operand})"),
+                _ => write!(f, "-{operand}"),
+            },
+            Expression::Binary { operator, lhs, rhs } => {
+                let precedence = operator.precedence();
+                let child_precedence = |expression: &Expression| match expression {
+                    Expression::Binary { operator, .. } => operator.precedence(),
+                    _ => u8::MAX,
+                };
+
+                if child_precedence(lhs) < precedence {
+                    write!(f, "({lhs})")?;
+                } else {
+                    write!(f, "{lhs}")?;
+                }
+
+                write!(f, " {operator} ")?;
+
+                if child_precedence(rhs) <= precedence {
+                    write!(f, "({rhs})")
+                } else {
+                    write!(f, "{rhs}")
+                }
+            }
+        }
+    }
+}
+
+/// Value of a subexpression recorded by [`Expression::explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainStep {
+    /// Nesting level in the expression tree, the whole expression is at 0
+    pub depth: usize,
+    pub expression: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExpressionErrorKind {
+    Empty,
+    ExpectedOperand,
+    ExpectedOperator,
+    UnclosedParenthesis,
+    TooDeep { limit: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ExpressionError {
+    /// Index of the offending token
+    pub token: usize,
+    pub kind: ExpressionErrorKind,
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = self.token;
+
+        match self.kind {
+            ExpressionErrorKind::Empty => f.write_str("Empty expression"),
+            ExpressionErrorKind::ExpectedOperand => {
+                f.write_fmt(format_args!("Expected operand at token {token}"))
+            }
+            ExpressionErrorKind::ExpectedOperator => {
+                f.write_fmt(format_args!("Expected operator at token {token}"))
+            }
+            ExpressionErrorKind::UnclosedParenthesis => {
+                f.write_fmt(format_args!("Unclosed parenthesis at token {token}"))
+            }
+            ExpressionErrorKind::TooDeep { limit } => f.write_fmt(format_args!(
+                "Expression at token {token} is nested deeper than the limit of {limit}"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EvalError {
+    UnknownParameter(usize),
+    DivisionByZero,
+    /// The context has no operations left, see [`EvalContext::count_operation`]
+    OperationLimit,
+    /// A value is picked from a range with more values than
+    /// [`EvalContext::max_range_size`]
+    RangeTooLarge(u64),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::UnknownParameter(index) => {
+                f.write_fmt(format_args!("Unknown parameter `[p{index}]`"))
+            }
+            EvalError::DivisionByZero => f.write_str("Division by zero"),
+            EvalError::OperationLimit => f.write_str("Too many operations"),
+            EvalError::RangeTooLarge(size) => {
+                f.write_fmt(format_args!("Range of {size} values is too large"))
+            }
+        }
+    }
+}
+
+/// Source of parameter values and randomness for [`Expression::eval`].
+pub trait EvalContext {
+    /// Value of the one-based parameter `[p{index}]`.
+    fn parameter(&self, index: usize) -> Option<i32>;
+
+    /// Uniformly distributed integer from the range.
+    fn random(&mut self, range: RangeInclusive<i32>) -> i32;
+
+    /// Whether division by zero is an error. Otherwise the division
+    /// saturates to the `i32` bounds and the modulo gives zero.
+    fn strict_math(&self) -> bool {
+        true
+    }
+
+    /// Called for every evaluated subexpression, `false` stops the
+    /// evaluation with [`EvalError::OperationLimit`]. Lets the context limit
+    /// the work of the formulas.
+    fn count_operation(&mut self) -> bool {
+        true
+    }
+
+    /// Largest number of values a random value may be picked from, e.g.
+    /// `[1..1000]` has 1000. The ranges on the right of `in` aren't limited.
+    fn max_range_size(&self) -> Option<u64> {
+        None
+    }
+}
+
+enum Value {
+    Number(f64),
+    Ranges(Vec<RangeInclusive<i32>>),
+}
+
+/// Precedence climbing over the tokens. Keeps the depth of the built tree and
+/// the recursion within the limit, both grow with long chains of operators
+/// and nested parentheses.
+struct ExpressionParser<'t> {
+    tokens: &'t [FormulaToken],
+    pos: usize,
+    max_depth: usize,
+}
+
+impl ExpressionParser<'_> {
+    fn check_depth(&self, depth: usize) -> Result<(), ExpressionError> {
+        if depth > self.max_depth {
+            return Err(ExpressionError {
+                token: self.pos.saturating_sub(1),
+                kind: ExpressionErrorKind::TooDeep {
+                    limit: self.max_depth,
+                },
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Expression with the depth of its tree, `nesting` is the recursion level.
+    fn parse_binary(
+        &mut self,
+        min_precedence: u8,
+        nesting: usize,
+    ) -> Result<(Expression, usize), ExpressionError> {
+        self.check_depth(nesting)?;
+
+        let (mut lhs, mut depth) = self.parse_operand(nesting)?;
+
+        while let Some(token) = self.tokens.get(self.pos) {
+            let Some(operator) = BinaryOperator::from_token(&token.kind) else {
+                break;
+            };
+
+            let precedence = operator.precedence();
+
+            if precedence <= min_precedence {
+                break;
+            }
+
+            self.pos += 1;
+
+            let (rhs, rhs_depth) = self.parse_binary(precedence, nesting + 1)?;
+
+            depth = depth.max(rhs_depth) + 1;
+            self.check_depth(depth)?;
+
+            lhs = Expression::Binary {
+                operator,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+
+        Ok((lhs, depth))
+    }
+
+    fn parse_operand(&mut self, nesting: usize) -> Result<(Expression, usize), ExpressionError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(ExpressionError {
+                token: self.pos,
+                kind: ExpressionErrorKind::ExpectedOperand,
+            });
+        };
+
+        self.pos += 1;
+
+        match &token.kind {
+            FormulaTokenKind::Integer { value } => Ok((Expression::Integer(*value), 0)),
+            FormulaTokenKind::Double { value } => Ok((Expression::Double(*value), 0)),
+            FormulaTokenKind::Parameter { value } => Ok((Expression::Parameter(*value), 0)),
+            FormulaTokenKind::Range { value } => Ok((Expression::Range(value.clone()), 0)),
+            FormulaTokenKind::ToRange { start, end } => Ok((
+                Expression::ToRange {
+                    start: *start,
+                    end: *end,
+                },
+                0,
+            )),
+            FormulaTokenKind::Substract => {
+                self.check_depth(nesting + 1)?;
+
+                let (operand, depth) = self.parse_operand(nesting + 1)?;
+
+                Ok((Expression::Negate(Box::new(operand)), depth + 1))
+            }
+            FormulaTokenKind::OpenParenthesis => {
+                let open = self.pos - 1;
+                let inner = self.parse_binary(0, nesting + 1)?;
+
+                if !matches!(
+                    self.tokens.get(self.pos).map(|token| &token.kind),
+                    Some(FormulaTokenKind::CloseParenthesis)
+                ) {
+                    return Err(ExpressionError {
+                        token: open,
+                        kind: ExpressionErrorKind::UnclosedParenthesis,
+                    });
+                }
+
+                self.pos += 1;
+
+                Ok(inner)
+            }
+            _ => Err(ExpressionError {
+                token: self.pos - 1,
+                kind: ExpressionErrorKind::ExpectedOperand,
+            }),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(tokens: &[FormulaToken]) -> Result<Expression, ExpressionError> {
+        Self::parse_with(tokens, ParseLimits::default())
+    }
+
+    /// Same as [`Expression::parse`], but with the custom nesting limit.
+    pub fn parse_with(
+        tokens: &[FormulaToken],
+        limits: ParseLimits,
+    ) -> Result<Expression, ExpressionError> {
+        if tokens.is_empty() {
+            return Err(ExpressionError {
+                token: 0,
+                kind: ExpressionErrorKind::Empty,
+            });
+        }
+
+        let mut parser = ExpressionParser {
+            tokens,
+            pos: 0,
+            max_depth: limits.max_depth,
+        };
+        let (expression, _) = parser.parse_binary(0, 0)?;
+
+        if parser.pos != tokens.len() {
+            return Err(ExpressionError {
+                token: parser.pos,
+                kind: ExpressionErrorKind::ExpectedOperator,
+            });
+        }
+
+        Ok(expression)
+    }
+
+    /// Whether evaluating the expression may consume randomness, so its result
+    /// can't be reused even if parameters stay the same.
+    pub fn is_random(&self) -> bool {
+        match self {
+            Expression::Integer(_) | Expression::Double(_) | Expression::Parameter(_) => false,
+            Expression::Range(_) | Expression::ToRange { .. } => true,
+            Expression::Negate(operand) => operand.is_random(),
+            Expression::Binary {
+                operator: BinaryOperator::In,
+                lhs,
+                rhs,
+            } => {
+                lhs.is_random()
+                    || !matches!(**rhs, Expression::Range(_) | Expression::ToRange { .. })
+                        && rhs.is_random()
+            }
+            Expression::Binary { lhs, rhs, .. } => lhs.is_random() || rhs.is_random(),
+        }
+    }
+
+    /// Evaluates the expression to a number.
+    ///
+    /// Like in the original engine there is no separate boolean type: the
+    /// comparisons, `in`, `and` and `or` give `1` or `0`, so their results
+    /// can be added or multiplied, e.g. `([p1] > 5) * 10`. `and` and `or`
+    /// take any number, see [`Expression::is_true`].
+    pub fn eval(&self, ctx: &mut impl EvalContext) -> Result<f64, EvalError> {
+        self.eval_traced(ctx, &mut None, 0)
+    }
+
+    /// Whether the value counts as true for `and` and `or`: any number but
+    /// zero, including the fractions like `0.5`. `NaN` is false.
+    pub fn is_true(value: f64) -> bool {
+        value != 0.0 && !value.is_nan()
+    }
+
+    /// Evaluates the expression, recording the value of every subexpression
+    /// in the tree order: a node goes before its operands.
+    pub fn explain(
+        &self,
+        ctx: &mut impl EvalContext,
+    ) -> Result<(f64, Vec<ExplainStep>), EvalError> {
+        let mut steps = Some(Vec::new());
+        let value = self.eval_traced(ctx, &mut steps, 0)?;
+
+        Ok((value, steps.unwrap_or_default()))
+    }
+
+    fn eval_traced(
+        &self,
+        ctx: &mut impl EvalContext,
+        steps: &mut Option<Vec<ExplainStep>>,
+        depth: usize,
+    ) -> Result<f64, EvalError> {
+        let index = steps.as_mut().map(|steps| {
+            steps.push(ExplainStep {
+                depth,
+                expression: self.to_string(),
+                value: 0.0,
+            });
+
+            steps.len() - 1
+        });
+
+        let value = self.eval_value(ctx, steps, depth + 1)?;
+        let value = Self::to_number(value, ctx)?;
+
+        if let (Some(steps), Some(index)) = (steps.as_mut(), index) {
+            steps[index].value = value;
+        }
+
+        Ok(value)
+    }
+
+    fn eval_value(
+        &self,
+        ctx: &mut impl EvalContext,
+        steps: &mut Option<Vec<ExplainStep>>,
+        depth: usize,
+    ) -> Result<Value, EvalError> {
+        if !ctx.count_operation() {
+            return Err(EvalError::OperationLimit);
+        }
+
+        match self {
+            Expression::Integer(value) => Ok(Value::Number(*value as f64)),
+            Expression::Double(value) => Ok(Value::Number(*value)),
+            Expression::Parameter(index) => Ok(Value::Number(Self::parameter(*index, ctx)? as f64)),
+            Expression::Range(ranges) => {
+                let mut values = Vec::with_capacity(ranges.len());
+
+                for range in ranges {
+                    let start = Self::to_range_value(*range.start(), ctx)?;
+                    let end = Self::to_range_value(*range.end(), ctx)?;
+
+                    values.push(Self::range(start, end));
+                }
+
+                Ok(Value::Ranges(values))
+            }
+            Expression::ToRange { start, end } => {
+                let start = Self::to_range_value(*start, ctx)?;
+                let end = Self::to_range_value(*end, ctx)?;
+
+                Ok(Value::Ranges(vec![Self::range(start, end)]))
+            }
+            Expression::Negate(operand) => {
+                Ok(Value::Number(-operand.eval_traced(ctx, steps, depth)?))
+            }
+            Expression::Binary { operator, lhs, rhs } => {
+                if *operator == BinaryOperator::In {
+                    let lhs = lhs.eval_traced(ctx, steps, depth)?;
+
+                    return match rhs.eval_value(ctx, steps, depth)? {
+                        Value::Number(rhs) => Ok(Value::Number(Self::from_bool(lhs == rhs))),
+                        Value::Ranges(ranges) => {
+                            let lhs = lhs.round();
+
+                            Ok(Value::Number(Self::from_bool(ranges.iter().any(|range| {
+                                (*range.start() as f64..=*range.end() as f64).contains(&lhs)
+                            }))))
+                        }
+                    };
+                }
+
+                let lhs = lhs.eval_traced(ctx, steps, depth)?;
+                let rhs = rhs.eval_traced(ctx, steps, depth)?;
+
+                Ok(Value::Number(Self::eval_binary(
+                    *operator,
+                    lhs,
+                    rhs,
+                    ctx.strict_math(),
+                )?))
+            }
+        }
+    }
+
+    fn eval_binary(
+        operator: BinaryOperator,
+        lhs: f64,
+        rhs: f64,
+        strict_math: bool,
+    ) -> Result<f64, EvalError> {
+        if rhs == 0.0 && !strict_math {
+            match operator {
+                BinaryOperator::Divide | BinaryOperator::DivideWithRemain => {
+                    return Ok(Self::saturate(lhs))
+                }
+                BinaryOperator::Modulo => return Ok(0.0),
+                _ => (),
+            }
+        }
+
+        let result = match operator {
+            BinaryOperator::Add => lhs + rhs,
+            BinaryOperator::Substract => lhs - rhs,
+            BinaryOperator::Multiply => lhs * rhs,
+            BinaryOperator::Divide => {
+                if rhs == 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+
+                lhs / rhs
+            }
+            BinaryOperator::DivideWithRemain => {
+                if rhs == 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+
+                (lhs / rhs).trunc()
+            }
+            BinaryOperator::Modulo => {
+                if rhs == 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+
+                lhs % rhs
+            }
+            BinaryOperator::And => Self::from_bool(Self::is_true(lhs) && Self::is_true(rhs)),
+            BinaryOperator::Or => Self::from_bool(Self::is_true(lhs) || Self::is_true(rhs)),
+            BinaryOperator::Greater => Self::from_bool(lhs > rhs),
+            BinaryOperator::GreaterOrEqual => Self::from_bool(lhs >= rhs),
+            BinaryOperator::Lesser => Self::from_bool(lhs < rhs),
+            BinaryOperator::LesserOrEqual => Self::from_bool(lhs <= rhs),
+            BinaryOperator::Equal => Self::from_bool(lhs == rhs),
+            BinaryOperator::NotEqual => Self::from_bool(lhs != rhs),
+            BinaryOperator::In => unreachable!(),
+        };
+
+        Ok(result)
+    }
+
+    fn to_number(value: Value, ctx: &mut impl EvalContext) -> Result<f64, EvalError> {
+        match value {
+            Value::Number(value) => Ok(value),
+            Value::Ranges(ranges) => {
+                let total: i64 = ranges
+                    .iter()
+                    .map(|range| (*range.end() as i64 - *range.start() as i64).max(-1) + 1)
+                    .sum();
+
+                if total <= 0 {
+                    return Ok(0.0);
+                }
+
+                if let Some(max) = ctx.max_range_size() {
+                    if total as u64 > max {
+                        return Err(EvalError::RangeTooLarge(total as u64));
+                    }
+                }
+
+                let mut pick = Self::pick(total as u64, ctx) as i64;
+
+                for range in &ranges {
+                    let len = (*range.end() as i64 - *range.start() as i64).max(-1) + 1;
+
+                    if pick < len {
+                        return Ok((*range.start() as i64 + pick) as f64);
+                    }
+
+                    pick -= len;
+                }
+
+                Ok(0.0)
+            }
+        }
+    }
+
+    /// Uniformly distributed index below `len`. The ranges wider than `i32`
+    /// are picked from two draws, the rare values past the last whole
+    /// multiple of `len` are drawn again.
+    fn pick(len: u64, ctx: &mut impl EvalContext) -> u64 {
+        if len <= 1 << 31 {
+            return ctx.random(0..=(len - 1) as i32) as u64;
+        }
+
+        let limit = u64::MAX - (u64::MAX % len + 1) % len;
+
+        loop {
+            let high = ctx.random(i32::MIN..=i32::MAX) as u32 as u64;
+            let low = ctx.random(i32::MIN..=i32::MAX) as u32 as u64;
+            let bits = high << 32 | low;
+
+            if bits <= limit {
+                return bits % len;
+            }
+        }
+    }
+
+    fn to_range_value(value: ToRangeValue, ctx: &impl EvalContext) -> Result<i32, EvalError> {
+        match value {
+            ToRangeValue::Integer { value } => Ok(value),
+            ToRangeValue::Parameter { index } => Self::parameter(index, ctx),
+        }
+    }
+
+    /// Range between the bounds in any order.
+    fn range(start: i32, end: i32) -> RangeInclusive<i32> {
+        start.min(end)..=start.max(end)
+    }
+
+    fn parameter(index: usize, ctx: &impl EvalContext) -> Result<i32, EvalError> {
+        ctx.parameter(index)
+            .ok_or(EvalError::UnknownParameter(index))
+    }
+
+    /// Result of dividing the value by zero when the math isn't strict.
+    fn saturate(value: f64) -> f64 {
+        if value > 0.0 {
+            i32::MAX as f64
+        } else if value < 0.0 {
+            i32::MIN as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn from_bool(value: bool) -> f64 {
+        if value {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use crate::text::formula::{Formula, ParseLimits};
+
+    use super::{EvalContext, EvalError, Expression, ExpressionError, ExpressionErrorKind};
+
+    struct TestContext {
+        parameters: Vec<i32>,
+        strict_math: bool,
+    }
+
+    impl EvalContext for TestContext {
+        fn parameter(&self, index: usize) -> Option<i32> {
+            self.parameters.get(index.checked_sub(1)?).copied()
+        }
+
+        fn random(&mut self, range: RangeInclusive<i32>) -> i32 {
+            *range.start()
+        }
+
+        fn strict_math(&self) -> bool {
+            self.strict_math
+        }
+    }
+
+    fn eval(text: &str, parameters: &[i32]) -> Result<f64, EvalError> {
+        Expression::parse(&Formula::parse(text).unwrap().tokens)
+            .unwrap()
+            .eval(&mut TestContext {
+                parameters: parameters.to_vec(),
+                strict_math: true,
+            })
+    }
+
+    #[test]
+    pub fn eval_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3", &[]), Ok(7.0));
+        assert_eq!(eval("(1 + 2) * 3", &[]), Ok(9.0));
+        assert_eq!(eval("7 div 2", &[]), Ok(3.0));
+        assert_eq!(eval("7 mod 2", &[]), Ok(1.0));
+        assert_eq!(eval("7 / 2", &[]), Ok(3.5));
+        assert_eq!(eval("1 / 0", &[]), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("5-3", &[]), Ok(2.0));
+        assert_eq!(eval("[p1]-[p2]", &[5, 7]), Ok(-2.0));
+        assert_eq!(eval("2*(-3)", &[]), Ok(-6.0));
+    }
+
+    #[test]
+    pub fn eval_saturating_division() {
+        let eval = |text| {
+            Expression::parse(&Formula::parse(text).unwrap().tokens)
+                .unwrap()
+                .eval(&mut TestContext {
+                    parameters: Vec::new(),
+                    strict_math: false,
+                })
+        };
+
+        assert_eq!(eval("5 / 0"), Ok(i32::MAX as f64));
+        assert_eq!(eval("(0 - 5) div 0"), Ok(i32::MIN as f64));
+        assert_eq!(eval("0 / 0"), Ok(0.0));
+        assert_eq!(eval("5 mod 0"), Ok(0.0));
+    }
+
+    #[test]
+    pub fn display() {
+        let display = |text| {
+            Expression::parse(&Formula::parse(text).unwrap().tokens)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(display("1 + 2 * 3"), "1 + 2 * 3");
+        assert_eq!(display("(1 + 2) * 3"), "(1 + 2) * 3");
+        assert_eq!(display("8 - (2 - 1)"), "8 - (2 - 1)");
+        assert_eq!(display("[p1] in [1..3;5]"), "[p1] in [1..3;5]");
+        assert_eq!(
+            display("[p1] in [..0;10..1;20..]"),
+            "[p1] in [..0;1..10;20..]"
+        );
+        assert_eq!(display("[p1] in [p2..p3;p4]"), "[p1] in [p2..p3;p4]");
+        assert_eq!(display("-([p1] mod 2)"), "-([p1] mod 2)");
+    }
+
+    #[test]
+    pub fn explain() {
+        let expression =
+            Expression::parse(&Formula::parse("([p1] + 2) * 3").unwrap().tokens).unwrap();
+        let (value, steps) = expression
+            .explain(&mut TestContext {
+                parameters: vec![4],
+                strict_math: true,
+            })
+            .unwrap();
+        let steps: Vec<(usize, &str, f64)> = steps
+            .iter()
+            .map(|step| (step.depth, step.expression.as_str(), step.value))
+            .collect();
+
+        assert_eq!(value, 18.0);
+        assert_eq!(
+            steps,
+            vec![
+                (0, "([p1] + 2) * 3", 18.0),
+                (1, "[p1] + 2", 6.0),
+                (2, "[p1]", 4.0),
+                (2, "2", 2.0),
+                (1, "3", 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn eval_parameters() {
+        assert_eq!(eval("[p1] * 5", &[4]), Ok(20.0));
+        assert_eq!(eval("[p2] + [p1]", &[4, 6]), Ok(10.0));
+        assert_eq!(eval("[p3]", &[4]), Err(EvalError::UnknownParameter(3)));
+    }
+
+    #[test]
+    pub fn eval_logic() {
+        assert_eq!(eval("(([p1] div 2) mod 2)=0", &[4]), Ok(1.0));
+        assert_eq!(eval("(([p1] div 2) mod 2)=0", &[6]), Ok(0.0));
+        assert_eq!(eval("(([p1] div 2) mod 2)=0", &[8]), Ok(1.0));
+        assert_eq!(eval("[p1] > 1 and [p1] < 3", &[2]), Ok(1.0));
+        assert_eq!(eval("[p1] > 1 and [p1] < 3", &[3]), Ok(0.0));
+        assert_eq!(eval("[p1] = 1 or [p1] <> 3", &[2]), Ok(1.0));
+    }
+
+    #[test]
+    pub fn eval_boolean_arithmetic() {
+        for (text, values, result) in [
+            ("([p1] > 5) * 10", [6], 10.0),
+            ("([p1] > 5) * 10", [5], 0.0),
+            ("([p1] > 1) + ([p1] > 2) + ([p1] > 3)", [3], 2.0),
+            ("1 - ([p1] = 2)", [2], 0.0),
+            ("([p1] in [1..3]) * [p1]", [2], 2.0),
+            ("([p1] > 1) = 1", [2], 1.0),
+            ("([p1] and 1) + ([p1] or 0)", [7], 2.0),
+            ("-[p1] and 1", [3], 1.0),
+            ("[p1] and 1", [0], 0.0),
+            ("(1 / 2) and 1", [0], 1.0),
+            ("0 or (0.1 * [p1])", [0], 0.0),
+        ] {
+            assert_eq!(eval(text, &values), Ok(result), "{text}");
+        }
+
+        assert!(!Expression::is_true(f64::NAN));
+        assert!(Expression::is_true(-0.5));
+        assert!(!Expression::is_true(-0.0));
+    }
+
+    #[test]
+    pub fn eval_equality() {
+        for (text, values, result) in [
+            ("[p1] = 2", [2], 1.0),
+            ("[p1] == 2", [2], 1.0),
+            ("[p1] = 2", [3], 0.0),
+            ("[p1] == 2", [3], 0.0),
+            ("[p1] = 2 and [p1] == 2", [2], 1.0),
+        ] {
+            assert_eq!(eval(text, &values), Ok(result), "{text}");
+        }
+    }
+
+    #[test]
+    pub fn eval_ranges() {
+        assert_eq!(eval("[p1] in [1..3;5]", &[5]), Ok(1.0));
+        assert_eq!(eval("[p1] in [1..3;5]", &[4]), Ok(0.0));
+        assert_eq!(eval("[p1] in 1 to [p2]", &[4, 6]), Ok(1.0));
+        assert_eq!(eval("[20]", &[]), Ok(20.0));
+        assert_eq!(eval("[5..10]", &[]), Ok(5.0));
+        assert_eq!(eval("[p1] in [p2..p3]", &[5, 1, 7]), Ok(1.0));
+        assert_eq!(eval("[p1] in [p2..p3]", &[5, 7, 1]), Ok(1.0));
+        assert_eq!(eval("[p1] in [p2..p3]", &[9, 1, 7]), Ok(0.0));
+        assert_eq!(eval("[p2..10]", &[0, 3]), Ok(3.0));
+        assert_eq!(
+            eval("[p1] in [1..p4]", &[1]),
+            Err(EvalError::UnknownParameter(4))
+        );
+    }
+
+    #[test]
+    pub fn randomness() {
+        let expression = |text| Expression::parse(&Formula::parse(text).unwrap().tokens).unwrap();
+
+        assert!(!expression("[p1] + 1").is_random());
+        assert!(!expression("[p1] in [1..3]").is_random());
+        assert!(expression("[1..3]").is_random());
+        assert!(expression("[p1] + (0 to 3)").is_random());
+    }
+
+    #[test]
+    pub fn parse_errors() {
+        let parse = |text| Expression::parse(&Formula::parse(text).unwrap().tokens);
+
+        assert_eq!(
+            parse(""),
+            Err(ExpressionError {
+                token: 0,
+                kind: ExpressionErrorKind::Empty
+            })
+        );
+        assert_eq!(
+            parse("(1 + 2"),
+            Err(ExpressionError {
+                token: 0,
+                kind: ExpressionErrorKind::UnclosedParenthesis
+            })
+        );
+        assert_eq!(
+            parse("1 +"),
+            Err(ExpressionError {
+                token: 2,
+                kind: ExpressionErrorKind::ExpectedOperand
+            })
+        );
+        assert_eq!(
+            parse("1 2"),
+            Err(ExpressionError {
+                token: 1,
+                kind: ExpressionErrorKind::ExpectedOperator
+            })
+        );
+    }
+
+    #[test]
+    pub fn depth_limit() {
+        let limits = ParseLimits {
+            max_depth: 8,
+            ..Default::default()
+        };
+        let parse = |text: &str| {
+            Expression::parse_with(&Formula::parse(text).unwrap().tokens, limits)
+                .map_err(|err| err.kind)
+        };
+        let too_deep = Err(ExpressionErrorKind::TooDeep { limit: 8 });
+
+        assert!(parse(&format!("{}1{}", "(".repeat(8), ")".repeat(8))).is_ok());
+        assert_eq!(
+            parse(&format!("{}1{}", "(".repeat(9), ")".repeat(9))),
+            too_deep
+        );
+        assert!(parse(&["1"; 9].join(" + ")).is_ok());
+        assert_eq!(parse(&["1"; 10].join(" + ")), too_deep);
+        assert_eq!(parse(&format!("{}1", "- ".repeat(9))), too_deep);
+
+        // The default limit still fits any realistic formula
+        let long = ["[p1]"; 200].join(" + ");
+        assert!(Expression::parse(&Formula::parse(&long).unwrap().tokens).is_ok());
+        assert_eq!(
+            Expression::parse(&Formula::parse(&["1"; 300].join(" + ")).unwrap().tokens)
+                .map_err(|err| err.kind),
+            Err(ExpressionErrorKind::TooDeep { limit: 256 })
+        );
+    }
+
+    #[test]
+    pub fn eval_limits() {
+        struct LimitedContext {
+            operations_left: usize,
+        }
+
+        impl EvalContext for LimitedContext {
+            fn parameter(&self, _: usize) -> Option<i32> {
+                Some(i32::MAX)
+            }
+
+            fn random(&mut self, range: RangeInclusive<i32>) -> i32 {
+                *range.start()
+            }
+
+            fn count_operation(&mut self) -> bool {
+                let left = self.operations_left > 0;
+                self.operations_left = self.operations_left.saturating_sub(1);
+
+                left
+            }
+
+            fn max_range_size(&self) -> Option<u64> {
+                Some(1000)
+            }
+        }
+
+        let eval = |text: &str, operations_left| {
+            Expression::parse(&Formula::parse(text).unwrap().tokens)
+                .unwrap()
+                .eval(&mut LimitedContext { operations_left })
+        };
+
+        // Three operations: the sum and its operands
+        assert_eq!(eval("1 + 2", 3), Ok(3.0));
+        assert_eq!(eval("1 + 2", 2), Err(EvalError::OperationLimit));
+        assert_eq!(eval("[1..1000]", 10), Ok(1.0));
+        assert_eq!(eval("5 in [1000..1]", 10), Ok(1.0));
+        assert_eq!(eval("[1..1001]", 10), Err(EvalError::RangeTooLarge(1001)));
+        assert_eq!(
+            eval("0 to [p1]", 10),
+            Err(EvalError::RangeTooLarge(i32::MAX as u64 + 1))
+        );
+        // Only the picked values are limited
+        assert_eq!(eval("[p1] in [5..]", 10), Ok(1.0));
+        assert_eq!(eval("[p1] in [..0]", 10), Ok(0.0));
+        assert_eq!(eval("5 in 0 to [p1]", 10), Ok(1.0));
+    }
+
+    #[test]
+    pub fn eval_wide_ranges() {
+        struct ScriptedContext {
+            draws: Vec<i32>,
+        }
+
+        impl EvalContext for ScriptedContext {
+            fn parameter(&self, _: usize) -> Option<i32> {
+                None
+            }
+
+            fn random(&mut self, range: RangeInclusive<i32>) -> i32 {
+                let value = self.draws.remove(0);
+                assert!(range.contains(&value));
+
+                value
+            }
+        }
+
+        let eval = |text: &str, draws: &[i32]| {
+            Expression::parse(&Formula::parse(text).unwrap().tokens)
+                .unwrap()
+                .eval(&mut ScriptedContext {
+                    draws: draws.to_vec(),
+                })
+        };
+
+        // 4000000001 values, the pick reaches past `i32::MAX` of them
+        let range = "[-2000000000..2000000000]";
+        assert_eq!(eval(range, &[0, 0]), Ok(-2000000000.0));
+        assert_eq!(eval(range, &[0, 4000000000u32 as i32]), Ok(2000000000.0));
+        // Wraps past the end of the range
+        assert_eq!(eval(range, &[0, -1]), Ok(-1705032706.0));
+        // Narrower ranges keep a single draw
+        assert_eq!(eval("[1..10]", &[7]), Ok(8.0));
+    }
+}
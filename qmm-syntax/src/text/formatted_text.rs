@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::digit_match;
+use super::utils::digit_match;
 
 static VARIABLES: [&str; 8] = [
     "<ToStar>",
@@ -17,6 +17,7 @@ static CLR_BEGIN_TAG: &str = "<clr>";
 static CLR_END_TAG: &str = "<clrEnd>";
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextElementKind {
     Text,
     /// `<ToStar>`, `<ToPlanet>`
@@ -31,9 +32,10 @@ pub enum TextElementKind {
     CurrentParameter,
     /// `\n`, `\r\n`
     NewLine,
-    /// `<clr>Foo<clrEnd>`,
+    /// `<clr>Foo <Ranger><clrEnd>`, the selected text keeps its variables
+    /// and formulas
     Selection {
-        text: String,
+        text: FormattedText,
     },
     /// `[p1]`
     Parameter {
@@ -42,12 +44,14 @@ pub enum TextElementKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextElement {
     pub kind: TextElementKind,
     pub value: String,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FormattedText {
     pub elements: Vec<TextElement>,
 }
@@ -62,7 +66,129 @@ impl Display for FormattedText {
     }
 }
 
+impl From<&str> for FormattedText {
+    fn from(value: &str) -> Self {
+        FormattedText::parse(value)
+    }
+}
+
 impl FormattedText {
+    /// Appends the plain text, line breaks become new line elements and tags
+    /// aren't parsed. The text is merged into the last text element.
+    pub fn push_text(&mut self, text: &str) -> &mut Self {
+        for (idx, line) in text.split('\n').enumerate() {
+            if idx > 0 {
+                self.elements.push(TextElement {
+                    kind: TextElementKind::NewLine,
+                    value: "\n".to_string(),
+                });
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+
+            match self.elements.last_mut() {
+                Some(last) if last.kind == TextElementKind::Text => last.value.push_str(line),
+                _ => self.elements.push(TextElement {
+                    kind: TextElementKind::Text,
+                    value: line.to_string(),
+                }),
+            }
+        }
+
+        self
+    }
+
+    /// Appends the variable by its name without the brackets, e.g. `Ranger`.
+    pub fn push_var(&mut self, name: &str) -> &mut Self {
+        self.elements.push(TextElement {
+            kind: TextElementKind::Variable {
+                name: name.to_string(),
+            },
+            value: format!("<{name}>"),
+        });
+
+        self
+    }
+
+    /// Appends the elements of the other text.
+    pub fn concat(mut self, other: FormattedText) -> Self {
+        self.elements.extend(other.elements);
+        self
+    }
+
+    /// Merges adjacent text elements, trims trailing whitespace on every line
+    /// and at the end and keeps no more than two line breaks in a row, so the
+    /// texts which differ only in the editor formatting look the same.
+    pub fn normalize(self) -> Self {
+        fn trim_end(elements: &mut Vec<TextElement>) {
+            while let Some(last) = elements.last_mut() {
+                if last.kind != TextElementKind::Text {
+                    return;
+                }
+
+                last.value.truncate(last.value.trim_end().len());
+
+                if !last.value.is_empty() {
+                    return;
+                }
+
+                elements.pop();
+            }
+        }
+
+        let mut elements: Vec<TextElement> = Vec::with_capacity(self.elements.len());
+        let mut new_lines = 0;
+
+        for el in self.elements {
+            match el.kind {
+                TextElementKind::NewLine => {
+                    trim_end(&mut elements);
+                    new_lines += 1;
+
+                    if new_lines <= 2 {
+                        elements.push(el);
+                    }
+                }
+                TextElementKind::Text => {
+                    // Lines of whitespace are trimmed and don't break the line
+                    // breaks sequence
+                    if !el.value.trim_end().is_empty() {
+                        new_lines = 0;
+                    }
+
+                    match elements.last_mut() {
+                        Some(last) if last.kind == TextElementKind::Text => {
+                            last.value.push_str(&el.value)
+                        }
+                        _ => elements.push(el),
+                    }
+                }
+                _ => {
+                    new_lines = 0;
+                    elements.push(el);
+                }
+            }
+        }
+
+        trim_end(&mut elements);
+
+        while elements
+            .last()
+            .is_some_and(|last| last.kind == TextElementKind::NewLine)
+        {
+            elements.pop();
+            trim_end(&mut elements);
+        }
+
+        FormattedText { elements }
+    }
+
+    /// Splits the text into elements, malformed tags are kept as plain text.
+    ///
+    /// Tags are delimited by ASCII characters, so the elements never split
+    /// multi-byte characters.
     pub fn parse(text: &str) -> FormattedText {
         let mut elements = Vec::new();
 
@@ -84,79 +210,40 @@ impl FormattedText {
                 let text_bytes = buffer[last_el_pos..pos].to_vec();
                 elements.push(TextElement {
                     kind: TextElementKind::Text,
-                    value: String::from_utf8(text_bytes).unwrap(),
+                    value: String::from_utf8_lossy(&text_bytes).into_owned(),
                 });
             }
         }
 
         while pos < buffer.len() {
-            let ch = buffer[pos];
-
-            match ch {
-                b'<' => {
-                    if let Some(el) = Self::try_parse_variable(buffer, pos)
-                        .or_else(|| Self::try_parse_current_parameter(buffer, pos))
-                        .or_else(|| Self::try_parse_text_selection(buffer, pos))
-                    {
-                        push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
-
-                        pos += el.value.len();
-                        last_el_pos = pos;
-                        elements.push(el);
-                    }
-                }
-                b'{' => {
-                    if let Some(el) = Self::try_parse_formula(buffer, pos) {
-                        push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
-
-                        pos += el.value.len();
-                        last_el_pos = pos;
-                        elements.push(el);
-                    }
-                }
-                b'\n' => {
-                    let el = TextElement {
-                        kind: TextElementKind::NewLine,
-                        value: "\n".to_string(),
-                    };
-
-                    push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
-
-                    pos += el.value.len();
-                    last_el_pos = pos;
-                    elements.push(el);
-                }
-                b'\r' => {
-                    if !matches!(buffer.get(pos + 1), Some(b'\n')) {
-                        continue;
-                    }
-
-                    let el = TextElement {
-                        kind: TextElementKind::NewLine,
-                        value: "\r\n".to_string(),
-                    };
-
-                    push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
-
-                    pos += el.value.len();
-                    last_el_pos = pos;
-                    elements.push(el);
-
-                    continue;
-                }
-                b'[' => {
-                    if let Some(el) = Self::try_parse_parameter(buffer, pos) {
-                        push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
+            let el = match buffer[pos] {
+                b'<' => Self::try_parse_variable(buffer, pos)
+                    .or_else(|| Self::try_parse_current_parameter(buffer, pos))
+                    .or_else(|| Self::try_parse_text_selection(buffer, pos)),
+                b'{' => Self::try_parse_formula(buffer, pos),
+                b'\n' => Some(TextElement {
+                    kind: TextElementKind::NewLine,
+                    value: "\n".to_string(),
+                }),
+                // A lone `\r` stays in the text
+                b'\r' if matches!(buffer.get(pos + 1), Some(b'\n')) => Some(TextElement {
+                    kind: TextElementKind::NewLine,
+                    value: "\r\n".to_string(),
+                }),
+                b'[' => Self::try_parse_parameter(buffer, pos),
+                _ => None,
+            };
 
-                        pos += el.value.len();
-                        last_el_pos = pos;
-                        elements.push(el);
-                    }
-                }
-                _ => (),
+            let Some(el) = el else {
+                pos += 1;
+                continue;
             };
 
-            pos += 1;
+            push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
+
+            pos += el.value.len();
+            last_el_pos = pos;
+            elements.push(el);
         }
 
         push_text_from_prev_el(last_el_pos, pos, buffer, &mut elements);
@@ -164,7 +251,7 @@ impl FormattedText {
         FormattedText { elements }
     }
 
-    pub fn try_parse_parameter(buffer: &[u8], start: usize) -> Option<TextElement> {
+    fn try_parse_parameter(buffer: &[u8], start: usize) -> Option<TextElement> {
         if !matches!(buffer.get(start + 1), Some(b'p')) {
             return None;
         }
@@ -173,9 +260,7 @@ impl FormattedText {
         let mut pos = number_start;
 
         while pos < buffer.len() {
-            let Some(ch) = buffer.get(pos) else {
-                return None;
-            };
+            let ch = buffer.get(pos)?;
 
             match ch {
                 b']' => {
@@ -184,7 +269,7 @@ impl FormattedText {
                     }
 
                     let index_bytes = buffer[number_start..pos].to_vec();
-                    let index_string = String::from_utf8(index_bytes).unwrap();
+                    let index_string = String::from_utf8(index_bytes).ok()?;
 
                     let Ok(index) = index_string.parse::<usize>() else {
                         return None;
@@ -194,7 +279,7 @@ impl FormattedText {
 
                     return Some(TextElement {
                         kind: TextElementKind::Parameter { index },
-                        value: String::from_utf8(value_bytes).unwrap(),
+                        value: String::from_utf8(value_bytes).ok()?,
                     });
                 }
                 digit_match!() => (),
@@ -207,7 +292,7 @@ impl FormattedText {
         None
     }
 
-    pub fn try_parse_formula(buffer: &[u8], start: usize) -> Option<TextElement> {
+    fn try_parse_formula(buffer: &[u8], start: usize) -> Option<TextElement> {
         let mut pos = start;
         let text_start = start + 1;
 
@@ -216,13 +301,13 @@ impl FormattedText {
 
             if ch == b'}' {
                 let text_bytes = buffer[text_start..pos].to_vec();
-                let text = String::from_utf8(text_bytes).unwrap();
+                let text = String::from_utf8(text_bytes).ok()?;
 
                 let var_bytes = buffer[start..=pos].to_vec();
 
                 return Some(TextElement {
                     kind: TextElementKind::Formula { text },
-                    value: String::from_utf8(var_bytes).unwrap(),
+                    value: String::from_utf8(var_bytes).ok()?,
                 });
             }
 
@@ -232,11 +317,9 @@ impl FormattedText {
         None
     }
 
-    pub fn try_parse_current_parameter(buffer: &[u8], start: usize) -> Option<TextElement> {
+    fn try_parse_current_parameter(buffer: &[u8], start: usize) -> Option<TextElement> {
         let ch = buffer[start];
-        let Some(next_ch) = buffer.get(start + 1).copied() else {
-            return None;
-        };
+        let next_ch = buffer.get(start + 1).copied()?;
 
         if matches!([ch, next_ch], [b'<', b'>']) {
             Some(TextElement {
@@ -248,49 +331,43 @@ impl FormattedText {
         }
     }
 
-    pub fn try_parse_text_selection(buffer: &[u8], start: usize) -> Option<TextElement> {
-        let Some(begin_tag_end) = Self::try_parse_text_selection_begin_tag_end(buffer, start) else {
-            return None;
-        };
+    fn try_parse_text_selection(buffer: &[u8], start: usize) -> Option<TextElement> {
+        let begin_tag_end = Self::try_parse_text_selection_begin_tag_end(buffer, start)?;
 
         let text_start = begin_tag_end + 1;
         let mut pos = text_start;
 
+        // The first end tag closes the selection, so the tags inside it are
+        // kept as is
         while pos < buffer.len() {
-            let ch = buffer[pos];
-
-            if ch == b'<' {
-                let Some(end_tag_end) = Self::try_parse_text_selection_end_tag_end(buffer, pos) else {
-                    return None;
-                };
-
-                let text = if text_start == pos {
-                    String::new()
-                } else {
-                    let text_bytes = buffer[text_start..pos].to_vec();
-                    String::from_utf8(text_bytes).unwrap()
-                };
+            let end_tag_end = match buffer[pos] {
+                b'<' => Self::try_parse_text_selection_end_tag_end(buffer, pos),
+                _ => None,
+            };
 
-                let tag_bytes = buffer[start..=end_tag_end].to_vec();
+            let Some(end_tag_end) = end_tag_end else {
+                pos += 1;
+                continue;
+            };
 
-                return Some(TextElement {
-                    kind: TextElementKind::Selection { text },
-                    value: String::from_utf8(tag_bytes).unwrap(),
-                });
-            }
+            let text = std::str::from_utf8(&buffer[text_start..pos]).ok()?;
+            let tag_bytes = buffer[start..=end_tag_end].to_vec();
 
-            pos += 1;
+            return Some(TextElement {
+                kind: TextElementKind::Selection {
+                    text: FormattedText::parse(text),
+                },
+                value: String::from_utf8(tag_bytes).ok()?,
+            });
         }
 
         None
     }
 
-    pub fn try_parse_text_selection_begin_tag_end(buffer: &[u8], start: usize) -> Option<usize> {
+    fn try_parse_text_selection_begin_tag_end(buffer: &[u8], start: usize) -> Option<usize> {
         let begin_tag_end = start + CLR_BEGIN_TAG.len() - 1;
 
-        let Some(clr_begin) = buffer.get(start..=begin_tag_end) else {
-            return None;
-        };
+        let clr_begin = buffer.get(start..=begin_tag_end)?;
 
         if clr_begin == CLR_BEGIN_TAG.as_bytes() {
             Some(begin_tag_end)
@@ -299,12 +376,10 @@ impl FormattedText {
         }
     }
 
-    pub fn try_parse_text_selection_end_tag_end(buffer: &[u8], start: usize) -> Option<usize> {
+    fn try_parse_text_selection_end_tag_end(buffer: &[u8], start: usize) -> Option<usize> {
         let end_tag_end = start + CLR_END_TAG.len() - 1;
 
-        let Some(clr_begin) = buffer.get(start..=end_tag_end) else {
-            return None;
-        };
+        let clr_begin = buffer.get(start..=end_tag_end)?;
 
         if clr_begin == CLR_END_TAG.as_bytes() {
             Some(end_tag_end)
@@ -313,7 +388,7 @@ impl FormattedText {
         }
     }
 
-    pub fn try_parse_variable(buffer: &[u8], start: usize) -> Option<TextElement> {
+    fn try_parse_variable(buffer: &[u8], start: usize) -> Option<TextElement> {
         let mut pos = start;
         let mut rel_pos = 0;
         let name_start = start + 1;
@@ -335,13 +410,13 @@ impl FormattedText {
 
             if ch == b'>' {
                 let name_bytes = buffer[name_start..pos].to_vec();
-                let name = String::from_utf8(name_bytes).unwrap();
+                let name = String::from_utf8(name_bytes).ok()?;
 
                 let var_bytes = buffer[start..=pos].to_vec();
 
                 return Some(TextElement {
                     kind: TextElementKind::Variable { name },
-                    value: String::from_utf8(var_bytes).unwrap(),
+                    value: String::from_utf8(var_bytes).ok()?,
                 });
             }
 
@@ -355,6 +430,8 @@ impl FormattedText {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use crate::text::formatted_text::{TextElement, TextElementKind};
 
     use super::FormattedText;
@@ -586,7 +663,7 @@ mod tests {
             FormattedText {
                 elements: vec![TextElement {
                     kind: TextElementKind::Selection {
-                        text: String::new()
+                        text: FormattedText::default()
                     },
                     value: "<clr><clrEnd>".to_string()
                 }]
@@ -598,7 +675,7 @@ mod tests {
             FormattedText {
                 elements: vec![TextElement {
                     kind: TextElementKind::Selection {
-                        text: "lorem".to_string()
+                        text: FormattedText::parse("lorem")
                     },
                     value: "<clr>lorem<clrEnd>".to_string()
                 }]
@@ -606,6 +683,47 @@ mod tests {
         )
     }
 
+    #[test]
+    pub fn parse_nested_text_selection() {
+        let parsed = FormattedText::parse("<clr><Ranger>: {[p1]+1}\n<clr>cr<clrEnd>!");
+
+        assert_eq!(
+            parsed,
+            FormattedText {
+                elements: vec![
+                    TextElement {
+                        kind: TextElementKind::Selection {
+                            text: FormattedText::parse("<Ranger>: {[p1]+1}\n<clr>cr"),
+                        },
+                        value: "<clr><Ranger>: {[p1]+1}\n<clr>cr<clrEnd>".to_string()
+                    },
+                    TextElement {
+                        kind: TextElementKind::Text,
+                        value: "!".to_string()
+                    }
+                ]
+            }
+        );
+
+        let TextElementKind::Selection { text } = &parsed.elements[0].kind else {
+            unreachable!()
+        };
+        assert_eq!(
+            text.elements.iter().map(|el| &el.kind).collect::<Vec<_>>(),
+            [
+                &TextElementKind::Variable {
+                    name: "Ranger".to_string()
+                },
+                &TextElementKind::Text,
+                &TextElementKind::Formula {
+                    text: "[p1]+1".to_string()
+                },
+                &TextElementKind::NewLine,
+                &TextElementKind::Text,
+            ]
+        );
+    }
+
     #[test]
     pub fn parse_invalid_text_selection() {
         assert_eq!(
@@ -674,4 +792,106 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    pub fn parse_multibyte_text() {
+        assert_eq!(
+            FormattedText::parse("Ж\rЖ<clr>Жук<clrEnd>{Ж}"),
+            FormattedText {
+                elements: vec![
+                    TextElement {
+                        kind: TextElementKind::Text,
+                        value: "Ж\rЖ".to_string()
+                    },
+                    TextElement {
+                        kind: TextElementKind::Selection {
+                            text: FormattedText::parse("Жук")
+                        },
+                        value: "<clr>Жук<clrEnd>".to_string()
+                    },
+                    TextElement {
+                        kind: TextElementKind::Formula {
+                            text: "Ж".to_string()
+                        },
+                        value: "{Ж}".to_string()
+                    }
+                ]
+            }
+        )
+    }
+
+    #[test]
+    pub fn build_text() {
+        let mut text = FormattedText::from("> ");
+        text.push_text("Привет, ")
+            .push_var("Ranger")
+            .push_text("!\n\n<>");
+
+        assert_eq!(
+            text.clone().concat(FormattedText::parse("<Money>")),
+            FormattedText::parse("> Привет, <Ranger>!\n\n").concat(FormattedText {
+                elements: vec![
+                    TextElement {
+                        kind: TextElementKind::Text,
+                        value: "<>".to_string(),
+                    },
+                    TextElement {
+                        kind: TextElementKind::Variable {
+                            name: "Money".to_string(),
+                        },
+                        value: "<Money>".to_string(),
+                    },
+                ]
+            })
+        );
+        assert_eq!(text.to_string(), "> Привет, <Ranger>!\n\n<>");
+    }
+
+    #[test]
+    pub fn normalize() {
+        let text = FormattedText {
+            elements: vec![
+                TextElement {
+                    kind: TextElementKind::Text,
+                    value: "Привет, ".to_string(),
+                },
+                TextElement {
+                    kind: TextElementKind::Text,
+                    value: "мир  ".to_string(),
+                },
+            ],
+        }
+        .concat(FormattedText::parse("\r\n  \n\n\n<Ranger> \n\n  \n"));
+
+        assert_eq!(
+            text.normalize(),
+            FormattedText::parse("Привет, мир\r\n\n<Ranger>")
+        );
+        assert_eq!(
+            FormattedText::parse("  a\n \nb").normalize(),
+            FormattedText::parse("  a\n\nb")
+        );
+        assert_eq!(
+            FormattedText::parse(" \n\n").normalize(),
+            FormattedText::default()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn display_roundtrip(text in any::<String>()) {
+            prop_assert_eq!(FormattedText::parse(&text).to_string(), text);
+        }
+
+        #[test]
+        fn display_roundtrip_tags(text in "([\\[\\]<>{}p0-9\r\nЖ ]|<clr>|<clrEnd>|<Ranger>){0,16}") {
+            prop_assert_eq!(FormattedText::parse(&text).to_string(), text);
+        }
+
+        #[test]
+        fn normalize_idempotent(text in "([a <>\r\n]|<Ranger>){0,16}") {
+            let normalized = FormattedText::parse(&text).normalize();
+            prop_assert_eq!(normalized.clone().normalize(), normalized);
+        }
+    }
 }
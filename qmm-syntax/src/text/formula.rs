@@ -1,14 +1,24 @@
-use std::{fmt::Display, ops::RangeInclusive};
+use std::{collections::BTreeSet, fmt::Display, ops::RangeInclusive};
 
-use crate::digit_match;
+use super::utils::digit_match;
+
+use super::expression::{Expression, ExpressionError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ToRangeValue {
     Parameter { index: usize },
     Integer { value: i32 },
 }
 
+impl From<i32> for ToRangeValue {
+    fn from(value: i32) -> Self {
+        ToRangeValue::Integer { value }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FormulaTokenKind {
     OpenParenthesis,
     CloseParenthesis,
@@ -25,9 +35,10 @@ pub enum FormulaTokenKind {
     GreaterOrEqual,
     Lesser,
     LesserOrEqual,
+    /// Both `=` and `==`. The original engine has no assignments in
+    /// formulas, so `=` compares like in the conditions of the quests
     Equal,
     NotEqual,
-    Assignment,
     Integer {
         value: i32,
     },
@@ -38,7 +49,7 @@ pub enum FormulaTokenKind {
         value: usize,
     },
     Range {
-        value: Vec<RangeInclusive<i32>>,
+        value: Vec<RangeInclusive<ToRangeValue>>,
     },
     ToRange {
         start: ToRangeValue,
@@ -46,13 +57,30 @@ pub enum FormulaTokenKind {
     },
 }
 
+impl FormulaTokenKind {
+    /// Whether the token ends an operand, so a following `-` is binary.
+    fn is_operand(&self) -> bool {
+        matches!(
+            self,
+            FormulaTokenKind::CloseParenthesis
+                | FormulaTokenKind::Integer { .. }
+                | FormulaTokenKind::Double { .. }
+                | FormulaTokenKind::Parameter { .. }
+                | FormulaTokenKind::Range { .. }
+                | FormulaTokenKind::ToRange { .. }
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FormulaToken {
     pub kind: FormulaTokenKind,
     pub value: String,
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Formula {
     pub tokens: Vec<FormulaToken>,
 }
@@ -66,6 +94,75 @@ pub enum FormulaErrorKind {
     ExpectedInteger,
     ExpectedDouble,
     UnexpectedEOF,
+    TooLong {
+        limit: usize,
+    },
+}
+
+/// Limits protecting the formula and expression parsers from broken or
+/// hostile quests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum length of a formula text in bytes.
+    pub max_length: usize,
+    /// Maximum nesting of parentheses and operators in an expression.
+    pub max_depth: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_length: 16 * 1024,
+            max_depth: 256,
+        }
+    }
+}
+
+/// Options of the formula parser.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormulaParseOptions {
+    /// Accept numbers with a decimal comma (`1,5`) and an exponent (`1e3`),
+    /// every such number is reported as a [`FormulaLint`].
+    pub lenient_numbers: bool,
+    pub limits: ParseLimits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FormulaLintKind {
+    DecimalComma,
+    Exponent,
+    /// Range without one of the bounds: `[..5]`, `[5..]`.
+    OpenRange,
+    /// Range with the start greater than the end: `[10..1]`.
+    ReversedRange,
+}
+
+/// Non-canonical, but accepted form found in a formula.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FormulaLint {
+    pub position: usize,
+    pub kind: FormulaLintKind,
+}
+
+impl Display for FormulaLint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pos = self.position;
+
+        match self.kind {
+            FormulaLintKind::DecimalComma => f.write_fmt(format_args!(
+                "Number at position {pos} uses a decimal comma instead of a point"
+            )),
+            FormulaLintKind::Exponent => f.write_fmt(format_args!(
+                "Number at position {pos} uses the scientific notation"
+            )),
+            FormulaLintKind::OpenRange => f.write_fmt(format_args!(
+                "Range at position {pos} has no start or end bound"
+            )),
+            FormulaLintKind::ReversedRange => f.write_fmt(format_args!(
+                "Range at position {pos} has the start greater than the end"
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -100,13 +197,94 @@ impl Display for FormulaError {
             FormulaErrorKind::UnexpectedEOF => {
                 f.write_fmt(format_args!("Unexpected end of formula at position {pos}"))
             }
+            FormulaErrorKind::TooLong { limit } => f.write_fmt(format_args!(
+                "Formula is longer than the limit of {limit} bytes"
+            )),
         }
     }
 }
 
+/// Formula text rebuilt from the tokens, separated by single spaces.
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut previous: Option<&FormulaTokenKind> = None;
+
+        for token in &self.tokens {
+            let glued = matches!(previous, None | Some(FormulaTokenKind::OpenParenthesis))
+                || token.kind == FormulaTokenKind::CloseParenthesis;
+
+            if !glued {
+                f.write_str(" ")?;
+            }
+
+            f.write_str(&token.value)?;
+            previous = Some(&token.kind);
+        }
+
+        Ok(())
+    }
+}
+
 impl Formula {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// One-based indices of all parameters referenced by the formula.
+    pub fn parameters(&self) -> BTreeSet<usize> {
+        let mut parameters = BTreeSet::new();
+
+        for token in &self.tokens {
+            match &token.kind {
+                FormulaTokenKind::Parameter { value } => {
+                    parameters.insert(*value);
+                }
+                FormulaTokenKind::ToRange { start, end } => {
+                    for value in [start, end] {
+                        if let ToRangeValue::Parameter { index } = value {
+                            parameters.insert(*index);
+                        }
+                    }
+                }
+                FormulaTokenKind::Range { value } => {
+                    for value in value.iter().flat_map(|range| [range.start(), range.end()]) {
+                        if let ToRangeValue::Parameter { index } = value {
+                            parameters.insert(*index);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        parameters
+    }
+
+    pub fn expression(&self) -> Result<Expression, ExpressionError> {
+        Expression::parse(&self.tokens)
+    }
+
     pub fn parse(text: &str) -> Result<Formula, FormulaError> {
+        Self::parse_with(text, FormulaParseOptions::default()).map(|(formula, _)| formula)
+    }
+
+    /// Same as [`Formula::parse`], but also returns the lints of the accepted
+    /// non-canonical forms.
+    pub fn parse_with(
+        text: &str,
+        options: FormulaParseOptions,
+    ) -> Result<(Formula, Vec<FormulaLint>), FormulaError> {
         let mut formula = Formula { tokens: Vec::new() };
+        let mut lints = Vec::new();
+
+        if text.len() > options.limits.max_length {
+            return Err(FormulaError {
+                position: options.limits.max_length,
+                kind: FormulaErrorKind::TooLong {
+                    limit: options.limits.max_length,
+                },
+            });
+        }
         let buffer = text.as_bytes();
         let mut pos = 0;
 
@@ -143,9 +321,21 @@ impl Formula {
                     kind: FormulaTokenKind::CloseParenthesis,
                     value: ")".to_string(),
                 }),
-                b'-' => match Self::try_parse_number(buffer, pos) {
+                // After an operand it's always a subtraction: `5-3`, `[p1]-2`
+                b'-' if formula
+                    .tokens
+                    .last()
+                    .is_some_and(|token| token.kind.is_operand()) =>
+                {
+                    formula.tokens.push(FormulaToken {
+                        kind: FormulaTokenKind::Substract,
+                        value: "-".to_string(),
+                    })
+                }
+                b'-' => match Self::parse_number(buffer, pos, options.lenient_numbers) {
                     Some(Err(err)) => return Err(err),
                     Some(Ok(token)) => {
+                        Self::lint_number(&token, pos, &mut lints);
                         pos += token.value.len() - 1;
                         formula.tokens.push(token);
                     }
@@ -273,77 +463,146 @@ impl Formula {
                         pos += 1;
                     } else {
                         formula.tokens.push(FormulaToken {
-                            kind: FormulaTokenKind::Assignment,
+                            kind: FormulaTokenKind::Equal,
                             value: "=".to_string(),
                         })
                     }
                 }
                 digit_match!() => {
-                    let Some(token) = Self::try_parse_to_range(buffer, pos).or_else(|| Self::try_parse_number(buffer, pos)) else {
-                        return Err(FormulaError { position: pos, kind: FormulaErrorKind::ExpectedInteger });
+                    let Some(token) = Self::try_parse_to_range(buffer, pos)
+                        .or_else(|| Self::parse_number(buffer, pos, options.lenient_numbers))
+                    else {
+                        return Err(FormulaError {
+                            position: pos,
+                            kind: FormulaErrorKind::ExpectedInteger,
+                        });
                     };
 
                     let token = token?;
 
+                    Self::lint_number(&token, pos, &mut lints);
                     pos += token.value.len() - 1;
                     formula.tokens.push(token);
                 }
                 b'[' => {
-                    let Some(token) = Self::try_parse_range(buffer, pos).or_else(|| Self::try_parse_to_range(buffer, pos).or_else(|| Self::try_parse_parameter(buffer, pos))) else {
+                    let Some(token) = Self::try_parse_range(buffer, pos).or_else(|| {
+                        Self::try_parse_to_range(buffer, pos)
+                            .or_else(|| Self::try_parse_parameter(buffer, pos))
+                    }) else {
                         unexpected!('[')
                     };
 
                     let token = token?;
 
+                    Self::lint_range(&token, pos, &mut lints);
                     pos += token.value.len() - 1;
                     formula.tokens.push(token);
                 }
                 b' ' => (),
-                _ => {
-                    unexpected!(ch as char);
-                }
+                _ => return Err(Self::unexpected(buffer, pos, None)),
             }
 
             pos += 1;
         }
 
-        Ok(formula)
+        Ok((formula, lints))
     }
 
-    pub fn try_parse_to_range(
+    fn lint_range(token: &FormulaToken, pos: usize, lints: &mut Vec<FormulaLint>) {
+        if !matches!(token.kind, FormulaTokenKind::Range { .. }) {
+            return;
+        }
+
+        let inner = &token.value[1..token.value.len() - 1];
+
+        for (start, end) in inner.split(';').filter_map(|range| range.split_once("..")) {
+            let kind = match (start.parse::<i32>(), end.parse::<i32>()) {
+                (Ok(start), Ok(end)) if start > end => FormulaLintKind::ReversedRange,
+                _ if start.is_empty() || end.is_empty() => FormulaLintKind::OpenRange,
+                _ => continue,
+            };
+
+            lints.push(FormulaLint {
+                position: pos,
+                kind,
+            });
+        }
+    }
+
+    fn lint_number(token: &FormulaToken, pos: usize, lints: &mut Vec<FormulaLint>) {
+        if !matches!(token.kind, FormulaTokenKind::Double { .. }) {
+            return;
+        }
+
+        if token.value.contains(',') {
+            lints.push(FormulaLint {
+                position: pos,
+                kind: FormulaLintKind::DecimalComma,
+            });
+        }
+
+        if token.value.contains(['e', 'E']) {
+            lints.push(FormulaLint {
+                position: pos,
+                kind: FormulaLintKind::Exponent,
+            });
+        }
+    }
+
+    /// Character starting at the position, the text may contain multi-byte
+    /// characters.
+    fn char_at(buffer: &[u8], pos: usize) -> char {
+        let end = buffer.len().min(pos + 4);
+
+        buffer
+            .get(pos..end)
+            .and_then(|bytes| String::from_utf8_lossy(bytes).chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+
+    /// Error for the character at the position, or for the end of the text.
+    fn unexpected(buffer: &[u8], pos: usize, expected: Option<&str>) -> FormulaError {
+        if pos >= buffer.len() {
+            return FormulaError {
+                position: pos,
+                kind: FormulaErrorKind::UnexpectedEOF,
+            };
+        }
+
+        FormulaError {
+            position: pos,
+            kind: FormulaErrorKind::UnexpectedToken {
+                found: Self::char_at(buffer, pos),
+                expected: expected.map(str::to_string),
+            },
+        }
+    }
+
+    fn try_parse_to_range(
         buffer: &[u8],
         start: usize,
     ) -> Option<Result<FormulaToken, FormulaError>> {
         let mut pos = start;
-        let start_range;
-        let end_range;
 
-        match Self::try_parse_parameter(buffer, pos).or_else(|| Self::try_parse_number(buffer, pos))
+        let start_range = match Self::try_parse_parameter(buffer, pos)
+            .or_else(|| Self::try_parse_number(buffer, pos))
         {
             Some(Ok(FormulaToken {
                 kind: FormulaTokenKind::Parameter { value: index },
                 value,
             })) => {
                 pos += value.len();
-                start_range = ToRangeValue::Parameter { index }
+                ToRangeValue::Parameter { index }
             }
             Some(Ok(FormulaToken {
                 kind: FormulaTokenKind::Integer { value: int },
                 value,
             })) => {
                 pos += value.len();
-                start_range = ToRangeValue::Integer { value: int }
+                ToRangeValue::Integer { value: int }
             }
             Some(Err(err)) => return Some(Err(err)),
-            None => {
-                return Some(Err(FormulaError {
-                    position: pos,
-                    kind: FormulaErrorKind::UnexpectedToken {
-                        found: buffer[pos] as char,
-                        expected: None,
-                    },
-                }))
-            }
+            None => return Some(Err(Self::unexpected(buffer, pos, None))),
             _ => return None,
         };
 
@@ -361,7 +620,7 @@ impl Formula {
             return None;
         }
 
-        if !matches!(buffer.get(pos..=pos + 1), Some([b't', b'o'])) {
+        if !Self::try_parse_word("to", buffer, pos) {
             return None;
         }
 
@@ -384,41 +643,25 @@ impl Formula {
             }));
         }
 
-        match Self::try_parse_parameter(buffer, pos).or_else(|| Self::try_parse_number(buffer, pos))
+        let end_range = match Self::try_parse_parameter(buffer, pos)
+            .or_else(|| Self::try_parse_number(buffer, pos))
         {
             Some(Ok(FormulaToken {
                 kind: FormulaTokenKind::Parameter { value: index },
                 value,
             })) => {
                 pos += value.len();
-                end_range = ToRangeValue::Parameter { index }
+                ToRangeValue::Parameter { index }
             }
             Some(Ok(FormulaToken {
                 kind: FormulaTokenKind::Integer { value: int },
                 value,
             })) => {
                 pos += value.len();
-                end_range = ToRangeValue::Integer { value: int }
+                ToRangeValue::Integer { value: int }
             }
             Some(Err(err)) => return Some(Err(err)),
-            None => {
-                return Some(Err(FormulaError {
-                    position: pos,
-                    kind: FormulaErrorKind::UnexpectedToken {
-                        found: buffer[pos] as char,
-                        expected: None,
-                    },
-                }))
-            }
-            _ => {
-                return Some(Err(FormulaError {
-                    position: pos,
-                    kind: FormulaErrorKind::UnexpectedToken {
-                        found: buffer[pos] as char,
-                        expected: None,
-                    },
-                }))
-            }
+            _ => return Some(Err(Self::unexpected(buffer, pos, None))),
         };
 
         let value_bytes = buffer[start..=pos - 1].to_vec();
@@ -428,14 +671,11 @@ impl Formula {
                 start: start_range,
                 end: end_range,
             },
-            value: String::from_utf8(value_bytes).unwrap(),
+            value: String::from_utf8_lossy(&value_bytes).into_owned(),
         }))
     }
 
-    pub fn try_parse_range(
-        buffer: &[u8],
-        start: usize,
-    ) -> Option<Result<FormulaToken, FormulaError>> {
+    fn try_parse_range(buffer: &[u8], start: usize) -> Option<Result<FormulaToken, FormulaError>> {
         let mut pos = start;
 
         if !matches!(buffer[pos], b'[') {
@@ -454,20 +694,14 @@ impl Formula {
         }
 
         while pos < buffer.len() {
-            let start_range = match Self::try_parse_number(buffer, pos) {
+            let open_start = matches!(buffer.get(pos..=pos + 1), Some([b'.', b'.']));
+            let start_range = match Self::try_parse_range_bound(buffer, pos) {
+                // Open start: `[..5]`
+                None if open_start => ToRangeValue::Integer { value: i32::MIN },
                 Some(Err(err)) => return Some(Err(err)),
-                Some(Ok(token)) => {
-                    pos += token.value.len();
-
-                    match token.kind {
-                        FormulaTokenKind::Integer { value } => value,
-                        _ => {
-                            return Some(Err(FormulaError {
-                                position: pos - token.value.len(),
-                                kind: FormulaErrorKind::ExpectedInteger,
-                            }))
-                        }
-                    }
+                Some(Ok((value, len))) => {
+                    pos += len;
+                    value
                 }
                 None => {
                     return None;
@@ -477,20 +711,15 @@ impl Formula {
             if matches!(buffer.get(pos..=pos + 1), Some([b'.', b'.'])) {
                 pos += 2;
 
-                let end_range = match Self::try_parse_number(buffer, pos) {
+                let end_range = match Self::try_parse_range_bound(buffer, pos) {
+                    // Open end: `[5..]`, but not both ends at once
+                    None if !open_start && matches!(buffer.get(pos), Some(b']' | b';')) => {
+                        ToRangeValue::Integer { value: i32::MAX }
+                    }
                     Some(Err(err)) => return Some(Err(err)),
-                    Some(Ok(token)) => {
-                        pos += token.value.len();
-
-                        match token.kind {
-                            FormulaTokenKind::Integer { value } => value,
-                            _ => {
-                                return Some(Err(FormulaError {
-                                    position: pos - token.value.len(),
-                                    kind: FormulaErrorKind::ExpectedInteger,
-                                }));
-                            }
-                        }
+                    Some(Ok((value, len))) => {
+                        pos += len;
+                        value
                     }
                     None => {
                         return Some(Err(FormulaError {
@@ -500,7 +729,14 @@ impl Formula {
                     }
                 };
 
-                ranges.push(start_range..=end_range);
+                ranges.push(match (start_range, end_range) {
+                    // Reversed bounds are swapped: `[10..1]` is `[1..10]`
+                    (
+                        ToRangeValue::Integer { value: start },
+                        ToRangeValue::Integer { value: end },
+                    ) if start > end => end_range..=start_range,
+                    _ => start_range..=end_range,
+                });
 
                 match buffer.get(pos) {
                     Some(b']') => break,
@@ -509,31 +745,39 @@ impl Formula {
                         continue;
                     }
                     _ => {
-                        return Some(Err(FormulaError {
-                            position: pos,
-                            kind: FormulaErrorKind::UnexpectedToken {
-                                found: buffer[pos] as char,
-                                expected: Some(";".to_string()),
-                            },
-                        }));
+                        return Some(Err(Self::unexpected(buffer, pos, Some(";"))));
                     }
                 }
             }
 
+            // A lone parameter is a parameter token: `[p1]`
+            if matches!(start_range, ToRangeValue::Parameter { .. })
+                && ranges.is_empty()
+                && matches!(buffer.get(pos), Some(b']'))
+            {
+                return None;
+            }
+
             let Some(ch) = buffer.get(pos) else {
-                return Some(Err(FormulaError { position: pos, kind: FormulaErrorKind::UnexpectedEOF }));
+                return Some(Err(FormulaError {
+                    position: pos,
+                    kind: FormulaErrorKind::UnexpectedEOF,
+                }));
             };
 
             match ch {
                 b';' => {
                     ranges.push(start_range..=start_range);
                 }
-                b']' => break,
+                b']' => {
+                    ranges.push(start_range..=start_range);
+                    break;
+                }
                 _ => {
                     return Some(Err(FormulaError {
                         position: pos,
                         kind: FormulaErrorKind::UnexpectedToken {
-                            found: *ch as char,
+                            found: Self::char_at(buffer, pos),
                             expected: Some("; or ]".to_string()),
                         },
                     }))
@@ -544,17 +788,11 @@ impl Formula {
         }
 
         if !matches!(buffer.get(pos), Some(b']')) {
-            return Some(Err(FormulaError {
-                position: pos,
-                kind: FormulaErrorKind::UnexpectedToken {
-                    found: buffer[pos] as char,
-                    expected: Some("]".to_string()),
-                },
-            }));
+            return Some(Err(Self::unexpected(buffer, pos, Some("]"))));
         }
 
         let string_bytes = buffer[start..=pos].to_vec();
-        let string = String::from_utf8(string_bytes).unwrap();
+        let string = String::from_utf8_lossy(&string_bytes).into_owned();
 
         Some(Ok(FormulaToken {
             kind: FormulaTokenKind::Range { value: ranges },
@@ -562,7 +800,48 @@ impl Formula {
         }))
     }
 
-    pub fn try_parse_parameter(
+    /// Bound of a range inside the brackets, an integer or a parameter:
+    /// `5`, `p1`. Returns the bound with its length in bytes.
+    fn try_parse_range_bound(
+        buffer: &[u8],
+        start: usize,
+    ) -> Option<Result<(ToRangeValue, usize), FormulaError>> {
+        let (pos, is_parameter) = match buffer.get(start) {
+            Some(b'p') => (start + 1, true),
+            _ => (start, false),
+        };
+
+        let token = match Self::try_parse_number(buffer, pos) {
+            Some(Err(err)) => return Some(Err(err)),
+            Some(Ok(token)) => token,
+            None if is_parameter => {
+                return Some(Err(FormulaError {
+                    position: pos,
+                    kind: FormulaErrorKind::ExpectedInteger,
+                }))
+            }
+            None => return None,
+        };
+
+        let value = match token.kind {
+            FormulaTokenKind::Integer { value } if is_parameter && value >= 0 => {
+                ToRangeValue::Parameter {
+                    index: value as usize,
+                }
+            }
+            FormulaTokenKind::Integer { value } if !is_parameter => ToRangeValue::Integer { value },
+            _ => {
+                return Some(Err(FormulaError {
+                    position: pos,
+                    kind: FormulaErrorKind::ExpectedInteger,
+                }))
+            }
+        };
+
+        Some(Ok((value, pos - start + token.value.len())))
+    }
+
+    fn try_parse_parameter(
         buffer: &[u8],
         start: usize,
     ) -> Option<Result<FormulaToken, FormulaError>> {
@@ -598,17 +877,11 @@ impl Formula {
         };
 
         if !matches!(buffer.get(pos), Some(b']')) {
-            return Some(Err(FormulaError {
-                position: pos,
-                kind: FormulaErrorKind::UnexpectedToken {
-                    found: buffer[pos] as char,
-                    expected: Some("]".to_string()),
-                },
-            }));
+            return Some(Err(Self::unexpected(buffer, pos, Some("]"))));
         }
 
         let string_bytes = buffer[start..=pos].to_vec();
-        let string = String::from_utf8(string_bytes).unwrap();
+        let string = String::from_utf8_lossy(&string_bytes).into_owned();
 
         Some(Ok(FormulaToken {
             kind: FormulaTokenKind::Parameter {
@@ -618,18 +891,48 @@ impl Formula {
         }))
     }
 
-    pub fn try_parse_number(
+    fn try_parse_number(buffer: &[u8], start: usize) -> Option<Result<FormulaToken, FormulaError>> {
+        Self::parse_number(buffer, start, false)
+    }
+
+    /// Number token, with `lenient` also accepts a decimal comma and an
+    /// exponent: `1,5`, `2.5e-3`.
+    fn parse_number(
         buffer: &[u8],
         start: usize,
+        lenient: bool,
     ) -> Option<Result<FormulaToken, FormulaError>> {
         let mut pos = start;
         let mut is_double = false;
+        let mut has_exponent = false;
 
         while pos < buffer.len() {
             let ch = buffer[pos];
 
             match ch {
                 digit_match!() => {}
+                b',' if lenient
+                    && !is_double
+                    && buffer[pos - 1].is_ascii_digit()
+                    && matches!(buffer.get(pos + 1), Some(digit_match!())) =>
+                {
+                    is_double = true;
+                }
+                b'e' | b'E' if lenient && !has_exponent && buffer[pos - 1].is_ascii_digit() => {
+                    let digits = match buffer.get(pos + 1) {
+                        Some(b'+' | b'-') => pos + 2,
+                        _ => pos + 1,
+                    };
+
+                    if !matches!(buffer.get(digits), Some(digit_match!())) {
+                        break;
+                    }
+
+                    is_double = true;
+                    has_exponent = true;
+                    pos = digits;
+                }
+                b'.' if has_exponent => break,
                 b'.' => {
                     if matches!(buffer.get(pos + 1), Some(b'.')) {
                         break;
@@ -659,10 +962,10 @@ impl Formula {
         }
 
         let number_bytes = buffer[start..pos].to_vec();
-        let number_string = String::from_utf8(number_bytes).unwrap();
+        let number_string = String::from_utf8_lossy(&number_bytes).into_owned();
 
         if is_double {
-            let Ok(number) = number_string.parse::<f64>() else {
+            let Ok(number) = number_string.replace(',', ".").parse::<f64>() else {
                 return None;
             };
 
@@ -682,15 +985,21 @@ impl Formula {
         }
     }
 
-    pub fn try_parse_word(word: &str, buffer: &[u8], start: usize) -> bool {
+    /// Whether the word is at the position. Keywords must not be followed by
+    /// a letter, so `div` doesn't match the start of `divide`.
+    fn try_parse_word(word: &str, buffer: &[u8], start: usize) -> bool {
         let mut pos = start;
         let mut relative = 0;
+        let is_keyword = word
+            .bytes()
+            .last()
+            .is_some_and(|ch| ch.is_ascii_alphabetic());
 
         while pos < buffer.len() {
             let ch = buffer[pos];
 
             if relative == word.len() {
-                return true;
+                return !is_keyword || !Self::char_at(buffer, pos).is_alphabetic();
             }
 
             let Some(target_ch) = word.as_bytes().get(relative).copied() else {
@@ -711,7 +1020,33 @@ impl Formula {
 
 #[cfg(test)]
 mod tests {
-    use crate::text::formula::{Formula, FormulaToken, FormulaTokenKind, ToRangeValue};
+    use std::{collections::BTreeSet, ops::RangeInclusive};
+
+    use proptest::prelude::*;
+
+    use crate::text::formula::{
+        Formula, FormulaError, FormulaErrorKind, FormulaLint, FormulaLintKind, FormulaParseOptions,
+        FormulaToken, FormulaTokenKind, ParseLimits, ToRangeValue,
+    };
+
+    fn range(start: i32, end: i32) -> RangeInclusive<ToRangeValue> {
+        start.into()..=end.into()
+    }
+
+    #[test]
+    pub fn parameters() {
+        assert_eq!(Formula::parse("").unwrap().parameters(), BTreeSet::new());
+        assert_eq!(
+            Formula::parse("[p1] + [p3] * [p1] - [1..2]")
+                .unwrap()
+                .parameters(),
+            BTreeSet::from([1, 3])
+        );
+        assert_eq!(
+            Formula::parse("[p2] to [p4]").unwrap().parameters(),
+            BTreeSet::from([2, 4])
+        );
+    }
 
     #[test]
     pub fn parse_open_parenthesis() {
@@ -935,12 +1270,12 @@ mod tests {
     }
 
     #[test]
-    pub fn parse_assignment() {
+    pub fn parse_single_equal() {
         assert_eq!(
             Formula::parse("=").unwrap(),
             Formula {
                 tokens: vec![FormulaToken {
-                    kind: FormulaTokenKind::Assignment,
+                    kind: FormulaTokenKind::Equal,
                     value: "=".to_string()
                 }]
             }
@@ -1018,7 +1353,9 @@ mod tests {
             Formula::parse("[0..1]").unwrap(),
             Formula {
                 tokens: vec![FormulaToken {
-                    kind: FormulaTokenKind::Range { value: vec![0..=1] },
+                    kind: FormulaTokenKind::Range {
+                        value: vec![range(0, 1)]
+                    },
                     value: "[0..1]".to_string()
                 }]
             }
@@ -1029,12 +1366,107 @@ mod tests {
             Formula {
                 tokens: vec![FormulaToken {
                     kind: FormulaTokenKind::Range {
-                        value: vec![0..=1, 2..=2, 3..=4]
+                        value: vec![range(0, 1), range(2, 2), range(3, 4)]
                     },
                     value: "[0..1;2;3..4]".to_string()
                 }]
             }
-        )
+        );
+
+        assert_eq!(
+            Formula::parse("[20]").unwrap(),
+            Formula {
+                tokens: vec![FormulaToken {
+                    kind: FormulaTokenKind::Range {
+                        value: vec![range(20, 20)]
+                    },
+                    value: "[20]".to_string()
+                }]
+            }
+        );
+
+        assert_eq!(
+            Formula::parse("[0..1;5]").unwrap(),
+            Formula {
+                tokens: vec![FormulaToken {
+                    kind: FormulaTokenKind::Range {
+                        value: vec![range(0, 1), range(5, 5)]
+                    },
+                    value: "[0..1;5]".to_string()
+                }]
+            }
+        );
+
+        assert_eq!(
+            Formula::parse("[..5;10..1;20..]").unwrap(),
+            Formula {
+                tokens: vec![FormulaToken {
+                    kind: FormulaTokenKind::Range {
+                        value: vec![range(i32::MIN, 5), range(1, 10), range(20, i32::MAX)]
+                    },
+                    value: "[..5;10..1;20..]".to_string()
+                }]
+            }
+        );
+        assert!(Formula::parse("[..]").is_err());
+    }
+
+    #[test]
+    pub fn parse_parameter_range() {
+        assert_eq!(
+            Formula::parse("[p1..p2;5..p3;p4..]").unwrap(),
+            Formula {
+                tokens: vec![FormulaToken {
+                    kind: FormulaTokenKind::Range {
+                        value: vec![
+                            ToRangeValue::Parameter { index: 1 }..=ToRangeValue::Parameter {
+                                index: 2
+                            },
+                            5.into()..=ToRangeValue::Parameter { index: 3 },
+                            ToRangeValue::Parameter { index: 4 }..=i32::MAX.into(),
+                        ]
+                    },
+                    value: "[p1..p2;5..p3;p4..]".to_string()
+                }]
+            }
+        );
+        assert_eq!(
+            Formula::parse("[p1] in [p2..p3]").unwrap().parameters(),
+            BTreeSet::from([1, 2, 3])
+        );
+
+        // A lone parameter is still a parameter
+        assert_eq!(
+            Formula::parse("[p1]").unwrap().tokens[0].kind,
+            FormulaTokenKind::Parameter { value: 1 }
+        );
+        assert!(Formula::parse("[p..2]").is_err());
+        assert!(Formula::parse("[1..p]").is_err());
+    }
+
+    #[test]
+    pub fn range_lints() {
+        let (_, lints) =
+            Formula::parse_with("[p1] in [..5;10..1] or 2 in [0..2;3..]", Default::default())
+                .unwrap();
+
+        assert_eq!(
+            lints,
+            vec![
+                FormulaLint {
+                    position: 8,
+                    kind: FormulaLintKind::OpenRange
+                },
+                FormulaLint {
+                    position: 8,
+                    kind: FormulaLintKind::ReversedRange
+                },
+                FormulaLint {
+                    position: 28,
+                    kind: FormulaLintKind::OpenRange
+                },
+            ]
+        );
     }
 
     #[test]
@@ -1135,7 +1567,7 @@ mod tests {
                         value: ")".to_string()
                     },
                     FormulaToken {
-                        kind: FormulaTokenKind::Assignment,
+                        kind: FormulaTokenKind::Equal,
                         value: "=".to_string()
                     },
                     FormulaToken {
@@ -1234,4 +1666,267 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    pub fn display() {
+        let display = |text| Formula::parse(text).unwrap().to_string();
+
+        assert_eq!(display("[p1]*5+( [p2] mod 2)"), "[p1] * 5 + ([p2] mod 2)");
+        assert_eq!(display("[p1] in [1..3;5]"), "[p1] in [1..3;5]");
+        assert_eq!(display(""), "");
+    }
+
+    #[test]
+    pub fn unary_minus() {
+        let kinds = |text| {
+            Formula::parse(text)
+                .unwrap()
+                .tokens
+                .into_iter()
+                .map(|token| token.kind)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds("5-3"),
+            vec![
+                FormulaTokenKind::Integer { value: 5 },
+                FormulaTokenKind::Substract,
+                FormulaTokenKind::Integer { value: 3 },
+            ]
+        );
+        assert_eq!(
+            kinds("(-3)"),
+            vec![
+                FormulaTokenKind::OpenParenthesis,
+                FormulaTokenKind::Integer { value: -3 },
+                FormulaTokenKind::CloseParenthesis,
+            ]
+        );
+        assert_eq!(
+            kinds("[p1]-[p2]"),
+            vec![
+                FormulaTokenKind::Parameter { value: 1 },
+                FormulaTokenKind::Substract,
+                FormulaTokenKind::Parameter { value: 2 },
+            ]
+        );
+        assert_eq!(
+            kinds("(1)-2"),
+            vec![
+                FormulaTokenKind::OpenParenthesis,
+                FormulaTokenKind::Integer { value: 1 },
+                FormulaTokenKind::CloseParenthesis,
+                FormulaTokenKind::Substract,
+                FormulaTokenKind::Integer { value: 2 },
+            ]
+        );
+        assert_eq!(
+            kinds("2*-3"),
+            vec![
+                FormulaTokenKind::Integer { value: 2 },
+                FormulaTokenKind::Multiply,
+                FormulaTokenKind::Integer { value: -3 },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn multibyte_errors() {
+        assert_eq!(
+            Formula::parse("[p1] + Ж").unwrap_err(),
+            FormulaError {
+                position: 7,
+                kind: FormulaErrorKind::UnexpectedToken {
+                    found: 'Ж',
+                    expected: None,
+                },
+            }
+        );
+        assert_eq!(
+            Formula::parse("[").unwrap_err().kind,
+            FormulaErrorKind::UnexpectedEOF
+        );
+    }
+
+    #[test]
+    pub fn length_limit() {
+        let options = FormulaParseOptions {
+            limits: ParseLimits {
+                max_length: 8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(Formula::parse_with("1 + 2", options).is_ok());
+        assert_eq!(
+            Formula::parse_with("[p1] + [p2]", options).unwrap_err(),
+            FormulaError {
+                position: 8,
+                kind: FormulaErrorKind::TooLong { limit: 8 },
+            }
+        );
+        assert_eq!(
+            Formula::parse(&"1+".repeat(16 * 1024)).unwrap_err().kind,
+            FormulaErrorKind::TooLong { limit: 16 * 1024 }
+        );
+    }
+
+    #[test]
+    pub fn keyword_boundaries() {
+        let unexpected = |position, found, expected: &str| FormulaError {
+            position,
+            kind: FormulaErrorKind::UnexpectedToken {
+                found,
+                expected: Some(expected.to_string()),
+            },
+        };
+
+        assert_eq!(
+            Formula::parse("7 divide 2").unwrap_err(),
+            unexpected(2, 'd', "div")
+        );
+        assert_eq!(
+            Formula::parse("[p1] > 1 order").unwrap_err(),
+            unexpected(9, 'o', "or")
+        );
+        assert_eq!(
+            Formula::parse("[p1] modulo 2").unwrap_err(),
+            unexpected(5, 'm', "mod")
+        );
+        assert_eq!(
+            Formula::parse("1 android").unwrap_err(),
+            unexpected(2, 'a', "and")
+        );
+        assert_eq!(
+            Formula::parse("[p1] inside").unwrap_err(),
+            unexpected(5, 'i', "in")
+        );
+        assert_eq!(
+            Formula::parse("[p1] orЖ").unwrap_err(),
+            unexpected(5, 'o', "or")
+        );
+        assert!(Formula::parse("1 tomato 5").is_err());
+
+        // Non-letters are fine right after a keyword
+        assert!(Formula::parse("[p1] in[1..3]").is_ok());
+        assert!(Formula::parse("(7 div(2))").is_ok());
+        assert!(Formula::parse("7 mod-2").is_ok());
+        assert_eq!(
+            Formula::parse("1 to5").unwrap().tokens[0].kind,
+            FormulaTokenKind::ToRange {
+                start: ToRangeValue::Integer { value: 1 },
+                end: ToRangeValue::Integer { value: 5 },
+            }
+        );
+    }
+
+    #[test]
+    pub fn lenient_numbers() {
+        let options = FormulaParseOptions {
+            lenient_numbers: true,
+            ..Default::default()
+        };
+
+        let (formula, lints) = Formula::parse_with("1,5 + 2e3 - -2.5E-1", options).unwrap();
+        assert_eq!(
+            formula.tokens,
+            vec![
+                FormulaToken {
+                    kind: FormulaTokenKind::Double { value: 1.5 },
+                    value: "1,5".to_string()
+                },
+                FormulaToken {
+                    kind: FormulaTokenKind::Add,
+                    value: "+".to_string()
+                },
+                FormulaToken {
+                    kind: FormulaTokenKind::Double { value: 2000.0 },
+                    value: "2e3".to_string()
+                },
+                FormulaToken {
+                    kind: FormulaTokenKind::Substract,
+                    value: "-".to_string()
+                },
+                FormulaToken {
+                    kind: FormulaTokenKind::Double { value: -0.25 },
+                    value: "-2.5E-1".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            lints,
+            vec![
+                FormulaLint {
+                    position: 0,
+                    kind: FormulaLintKind::DecimalComma
+                },
+                FormulaLint {
+                    position: 6,
+                    kind: FormulaLintKind::Exponent
+                },
+                FormulaLint {
+                    position: 12,
+                    kind: FormulaLintKind::Exponent
+                },
+            ]
+        );
+
+        let (formula, lints) = Formula::parse_with("[1..2;3] + 1.5", options).unwrap();
+        assert_eq!(formula, Formula::parse("[1..2;3] + 1.5").unwrap());
+        assert!(lints.is_empty());
+
+        assert!(Formula::parse("1,5").is_err());
+        assert!(Formula::parse("1e3").is_err());
+        assert!(Formula::parse_with("1e", options).is_err());
+    }
+
+    fn operand() -> impl Strategy<Value = String> {
+        prop_oneof![
+            (0..10000i32).prop_map(|value| value.to_string()),
+            (1..30usize).prop_map(|index| format!("[p{index}]")),
+            (-100..100i32, 0..100i32)
+                .prop_map(|(start, len)| format!("[{start}..{}]", start + len)),
+            (1..30usize, 0..100i32).prop_map(|(index, end)| format!("[p{index}] to {end}")),
+        ]
+    }
+
+    fn formula() -> impl Strategy<Value = String> {
+        let operators = prop::sample::select(vec![
+            "+", "-", "*", "/", "div", "mod", "and", "or", ">", ">=", "<", "<=", "=", "<>", "in",
+        ]);
+
+        operand().prop_recursive(4, 32, 2, move |inner| {
+            prop_oneof![
+                (inner.clone(), operators.clone(), inner.clone())
+                    .prop_map(|(left, operator, right)| format!("{left} {operator} {right}")),
+                inner.prop_map(|formula| format!("({formula})")),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn display_roundtrip(text in formula()) {
+            let formula = Formula::parse(&text).unwrap();
+
+            prop_assert_eq!(Formula::parse(&formula.to_string()).unwrap(), formula);
+        }
+
+        #[test]
+        fn parse_arbitrary_text(text in any::<String>()) {
+            let _ = Formula::parse(&text);
+        }
+
+        #[test]
+        fn parse_arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = Formula::parse(&String::from_utf8_lossy(&bytes));
+        }
+
+        #[test]
+        fn parse_formula_like_text(text in "[-+*/()\\[\\]p0-9.; <>=divmodantorЖ]{0,24}") {
+            let _ = Formula::parse(&text);
+        }
+    }
 }
@@ -0,0 +1,334 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use super::{
+    expression::{BinaryOperator, Expression},
+    formula::ToRangeValue,
+};
+
+/// Closed range of the values an expression may take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Interval {
+    /// Any value, used where nothing better is known.
+    pub const FULL: Interval = Interval {
+        min: f64::NEG_INFINITY,
+        max: f64::INFINITY,
+    };
+
+    const FALSE: Interval = Interval::point(0.0);
+    const TRUE: Interval = Interval::point(1.0);
+    const BOOL: Interval = Interval { min: 0.0, max: 1.0 };
+
+    pub fn new(min: f64, max: f64) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    pub const fn point(value: f64) -> Self {
+        Self {
+            min: value,
+            max: value,
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+
+    /// Smallest interval containing both.
+    pub fn union(&self, other: &Interval) -> Interval {
+        Interval {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn intersects(&self, other: &Interval) -> bool {
+        self.min <= other.max && other.min <= self.max
+    }
+
+    /// Values of the expression after it's rounded to an integer, as the
+    /// player does with the formula results.
+    pub fn round(&self) -> Interval {
+        Interval {
+            min: self.min.round(),
+            max: self.max.round(),
+        }
+    }
+
+    /// Hull of the values, NaN of `0 * inf` and `inf - inf` gives the full
+    /// interval.
+    fn hull(values: &[f64]) -> Interval {
+        if values.iter().any(|value| value.is_nan()) {
+            return Interval::FULL;
+        }
+
+        Interval {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    fn from_bool(always: bool, never: bool) -> Interval {
+        match (always, never) {
+            (true, _) => Interval::TRUE,
+            (_, true) => Interval::FALSE,
+            _ => Interval::BOOL,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        !self.contains(0.0)
+    }
+
+    fn is_false(&self) -> bool {
+        *self == Interval::FALSE
+    }
+}
+
+impl From<RangeInclusive<i32>> for Interval {
+    fn from(value: RangeInclusive<i32>) -> Self {
+        Interval::new(*value.start() as f64, *value.end() as f64)
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}..{}", self.min, self.max)
+        }
+    }
+}
+
+impl Expression {
+    /// Values the expression may take when the parameter `[p{n}]` is within
+    /// `parameters[n - 1]`, unknown parameters may have any value.
+    ///
+    /// The result is sound but not exact: every value the evaluation can
+    /// give is inside, some values inside may be impossible. Evaluation
+    /// errors aren't values, so the division by an interval containing zero
+    /// gives the full interval.
+    pub fn eval_interval(&self, parameters: &[Interval]) -> Interval {
+        match self {
+            Expression::Integer(value) => Interval::point(*value as f64),
+            Expression::Double(value) => Interval::point(*value),
+            Expression::Parameter(index) => Self::parameter_interval(*index, parameters),
+            Expression::Range(_) | Expression::ToRange { .. } => {
+                Self::ranges_interval(self, parameters).unwrap_or(Interval::FULL)
+            }
+            Expression::Negate(operand) => {
+                let operand = operand.eval_interval(parameters);
+
+                Interval::new(-operand.max, -operand.min)
+            }
+            Expression::Binary {
+                operator: BinaryOperator::In,
+                lhs,
+                rhs,
+            } => {
+                let lhs = lhs.eval_interval(parameters);
+
+                match Self::ranges_interval(rhs, parameters) {
+                    // Picking a random number, then comparing
+                    None => Self::eval_binary_interval(
+                        BinaryOperator::Equal,
+                        lhs,
+                        rhs.eval_interval(parameters),
+                    ),
+                    Some(range) => {
+                        let lhs = lhs.round();
+
+                        Interval::from_bool(
+                            lhs.min == lhs.max && Self::range_always_contains(rhs, lhs.min),
+                            !lhs.intersects(&range),
+                        )
+                    }
+                }
+            }
+            Expression::Binary { operator, lhs, rhs } => Self::eval_binary_interval(
+                *operator,
+                lhs.eval_interval(parameters),
+                rhs.eval_interval(parameters),
+            ),
+        }
+    }
+
+    fn eval_binary_interval(operator: BinaryOperator, lhs: Interval, rhs: Interval) -> Interval {
+        let divisor_has_zero = rhs.contains(0.0);
+
+        match operator {
+            BinaryOperator::Add => Interval::hull(&[lhs.min + rhs.min, lhs.max + rhs.max]),
+            BinaryOperator::Substract => Interval::hull(&[lhs.min - rhs.max, lhs.max - rhs.min]),
+            BinaryOperator::Multiply => Interval::hull(&[
+                lhs.min * rhs.min,
+                lhs.min * rhs.max,
+                lhs.max * rhs.min,
+                lhs.max * rhs.max,
+            ]),
+            BinaryOperator::Divide | BinaryOperator::DivideWithRemain if divisor_has_zero => {
+                Interval::FULL
+            }
+            BinaryOperator::Divide => Interval::hull(&[
+                lhs.min / rhs.min,
+                lhs.min / rhs.max,
+                lhs.max / rhs.min,
+                lhs.max / rhs.max,
+            ]),
+            BinaryOperator::DivideWithRemain => {
+                let quotient = Self::eval_binary_interval(BinaryOperator::Divide, lhs, rhs);
+
+                Interval::new(quotient.min.trunc(), quotient.max.trunc())
+            }
+            BinaryOperator::Modulo => {
+                // The remainder takes the sign of the dividend and is smaller
+                // than both operands by the absolute value
+                let divisor = rhs.min.abs().max(rhs.max.abs());
+                let min = if lhs.min >= 0.0 {
+                    0.0
+                } else {
+                    lhs.min.max(-divisor)
+                };
+                let max = if lhs.max <= 0.0 {
+                    0.0
+                } else {
+                    lhs.max.min(divisor)
+                };
+
+                Interval::new(min, max)
+            }
+            BinaryOperator::And => Interval::from_bool(
+                lhs.is_true() && rhs.is_true(),
+                lhs.is_false() || rhs.is_false(),
+            ),
+            BinaryOperator::Or => Interval::from_bool(
+                lhs.is_true() || rhs.is_true(),
+                lhs.is_false() && rhs.is_false(),
+            ),
+            BinaryOperator::Greater => Interval::from_bool(lhs.min > rhs.max, lhs.max <= rhs.min),
+            BinaryOperator::GreaterOrEqual => {
+                Interval::from_bool(lhs.min >= rhs.max, lhs.max < rhs.min)
+            }
+            BinaryOperator::Lesser => Interval::from_bool(lhs.max < rhs.min, lhs.min >= rhs.max),
+            BinaryOperator::LesserOrEqual => {
+                Interval::from_bool(lhs.max <= rhs.min, lhs.min > rhs.max)
+            }
+            BinaryOperator::Equal => {
+                Interval::from_bool(lhs.min == lhs.max && lhs == rhs, !lhs.intersects(&rhs))
+            }
+            BinaryOperator::NotEqual => {
+                Interval::from_bool(!lhs.intersects(&rhs), lhs.min == lhs.max && lhs == rhs)
+            }
+            BinaryOperator::In => unreachable!(),
+        }
+    }
+
+    fn parameter_interval(index: usize, parameters: &[Interval]) -> Interval {
+        index
+            .checked_sub(1)
+            .and_then(|index| parameters.get(index))
+            .copied()
+            .unwrap_or(Interval::FULL)
+    }
+
+    fn bound_interval(value: ToRangeValue, parameters: &[Interval]) -> Interval {
+        match value {
+            ToRangeValue::Integer { value } => Interval::point(value as f64),
+            ToRangeValue::Parameter { index } => Self::parameter_interval(index, parameters),
+        }
+    }
+
+    /// Hull of the numbers a range or a `to` range may pick, `None` for the
+    /// other expressions.
+    fn ranges_interval(expression: &Expression, parameters: &[Interval]) -> Option<Interval> {
+        let bounds: Vec<(ToRangeValue, ToRangeValue)> = match expression {
+            Expression::Range(ranges) => ranges
+                .iter()
+                .map(|range| (*range.start(), *range.end()))
+                .collect(),
+            Expression::ToRange { start, end } => vec![(*start, *end)],
+            _ => return None,
+        };
+
+        bounds
+            .into_iter()
+            .map(|(start, end)| {
+                Self::bound_interval(start, parameters)
+                    .union(&Self::bound_interval(end, parameters))
+            })
+            .reduce(|lhs, rhs| lhs.union(&rhs))
+    }
+
+    /// Whether the constant ranges contain the value whatever is picked.
+    fn range_always_contains(expression: &Expression, value: f64) -> bool {
+        let contains = |start: &ToRangeValue, end: &ToRangeValue| match (start, end) {
+            (ToRangeValue::Integer { value: start }, ToRangeValue::Integer { value: end }) => {
+                Interval::new(*start as f64, *end as f64).contains(value)
+            }
+            _ => false,
+        };
+
+        match expression {
+            Expression::Range(ranges) => ranges
+                .iter()
+                .any(|range| contains(range.start(), range.end())),
+            Expression::ToRange { start, end } => contains(start, end),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text::formula::Formula;
+
+    use super::Interval;
+
+    fn eval(text: &str, parameters: &[Interval]) -> Interval {
+        Formula::parse(text)
+            .unwrap()
+            .expression()
+            .unwrap()
+            .eval_interval(parameters)
+    }
+
+    #[test]
+    pub fn arithmetic() {
+        let p = [Interval::from(0..=10), Interval::from(-2..=3)];
+
+        assert_eq!(eval("[p1] + 5", &p), Interval::from(5..=15));
+        assert_eq!(eval("[p1] - [p2]", &p), Interval::from(-3..=12));
+        assert_eq!(eval("[p1] * [p2]", &p), Interval::from(-20..=30));
+        assert_eq!(eval("-[p1]", &p), Interval::from(-10..=0));
+        assert_eq!(eval("[p1] / 2", &p), Interval::new(0.0, 5.0));
+        assert_eq!(eval("[p1] div 3", &p), Interval::from(0..=3));
+        assert_eq!(eval("[p1] mod 4", &p), Interval::from(0..=4));
+        assert_eq!(eval("[p1] / [p2]", &p), Interval::FULL);
+        assert_eq!(eval("[p3] + 1", &p), Interval::FULL);
+        assert_eq!(eval("[2..5;p1]", &p), Interval::from(0..=10));
+    }
+
+    #[test]
+    pub fn conditions() {
+        let p = [Interval::from(0..=10)];
+
+        assert_eq!(eval("[p1] > 10", &p), Interval::point(0.0));
+        assert_eq!(eval("[p1] >= 0", &p), Interval::point(1.0));
+        assert_eq!(eval("[p1] > 5", &p), Interval::new(0.0, 1.0));
+        assert_eq!(eval("[p1] = 11", &p), Interval::point(0.0));
+        assert_eq!(eval("[p1] <> 11", &p), Interval::point(1.0));
+        assert_eq!(eval("[p1] > 5 and [p1] < 3", &p), Interval::new(0.0, 1.0));
+        assert_eq!(eval("[p1] > 10 and [p1] < 3", &p), Interval::point(0.0));
+        assert_eq!(eval("[p1] > 10 or [p1] >= 0", &p), Interval::point(1.0));
+        assert_eq!(eval("[p1] in [11..20]", &p), Interval::point(0.0));
+        assert_eq!(eval("5 in [1..10]", &p), Interval::point(1.0));
+        assert_eq!(eval("[p1] in [5..20]", &p), Interval::new(0.0, 1.0));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod expression;
 pub mod formatted_text;
 pub mod formula;
+pub mod interval;
+pub mod mojibake;
+#[cfg(feature = "ratatui")]
+pub mod terminal;
+pub mod transliteration;
 mod utils;
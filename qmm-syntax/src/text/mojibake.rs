@@ -0,0 +1,182 @@
+use std::fmt::Display;
+
+/// Way a text was garbled by reading its bytes in the wrong encoding, as
+/// happens to the quests converted by old tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mojibake {
+    /// CP1251 read as Latin-1 or CP1252: `Ïðèâåò` for `Привет`
+    Cp1251AsLatin1,
+    /// UTF-8 read as CP1251: `РџСЂРёРІРµС‚` for `Привет`
+    Utf8AsCp1251,
+}
+
+impl Display for Mojibake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Mojibake::Cp1251AsLatin1 => "CP1251 read as Latin-1",
+            Mojibake::Utf8AsCp1251 => "UTF-8 read as CP1251",
+        })
+    }
+}
+
+/// Characters of the CP1251 bytes from `0x80`, the unassigned `0x98` is kept
+/// as the control character.
+const CP1251: [char; 128] = [
+    '\u{0402}', '\u{0403}', '\u{201A}', '\u{0453}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{20AC}', '\u{2030}', '\u{0409}', '\u{2039}', '\u{040A}', '\u{040C}', '\u{040B}', '\u{040F}',
+    '\u{0452}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{0098}', '\u{2122}', '\u{0459}', '\u{203A}', '\u{045A}', '\u{045C}', '\u{045B}', '\u{045F}',
+    '\u{00A0}', '\u{040E}', '\u{045E}', '\u{0408}', '\u{00A4}', '\u{0490}', '\u{00A6}', '\u{00A7}',
+    '\u{0401}', '\u{00A9}', '\u{0404}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{0407}',
+    '\u{00B0}', '\u{00B1}', '\u{0406}', '\u{0456}', '\u{0491}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{0451}', '\u{2116}', '\u{0454}', '\u{00BB}', '\u{0458}', '\u{0405}', '\u{0455}', '\u{0457}',
+    'А', 'Б', 'В', 'Г', 'Д', 'Е', 'Ж', 'З', 'И', 'Й', 'К', 'Л', 'М', 'Н', 'О', 'П', //
+    'Р', 'С', 'Т', 'У', 'Ф', 'Х', 'Ц', 'Ч', 'Ш', 'Щ', 'Ъ', 'Ы', 'Ь', 'Э', 'Ю', 'Я', //
+    'а', 'б', 'в', 'г', 'д', 'е', 'ж', 'з', 'и', 'й', 'к', 'л', 'м', 'н', 'о', 'п', //
+    'р', 'с', 'т', 'у', 'ф', 'х', 'ц', 'ч', 'ш', 'щ', 'ъ', 'ы', 'ь', 'э', 'ю', 'я', //
+];
+
+/// Characters CP1252 has instead of the Latin-1 control characters.
+const CP1252: [(u8, char); 27] = [
+    (0x80, '\u{20AC}'),
+    (0x82, '\u{201A}'),
+    (0x83, '\u{0192}'),
+    (0x84, '\u{201E}'),
+    (0x85, '\u{2026}'),
+    (0x86, '\u{2020}'),
+    (0x87, '\u{2021}'),
+    (0x88, '\u{02C6}'),
+    (0x89, '\u{2030}'),
+    (0x8A, '\u{0160}'),
+    (0x8B, '\u{2039}'),
+    (0x8C, '\u{0152}'),
+    (0x8E, '\u{017D}'),
+    (0x91, '\u{2018}'),
+    (0x92, '\u{2019}'),
+    (0x93, '\u{201C}'),
+    (0x94, '\u{201D}'),
+    (0x95, '\u{2022}'),
+    (0x96, '\u{2013}'),
+    (0x97, '\u{2014}'),
+    (0x98, '\u{02DC}'),
+    (0x99, '\u{2122}'),
+    (0x9A, '\u{0161}'),
+    (0x9B, '\u{203A}'),
+    (0x9C, '\u{0153}'),
+    (0x9E, '\u{017E}'),
+    (0x9F, '\u{0178}'),
+];
+
+fn cp1251_char(byte: u8) -> char {
+    match byte {
+        0..=0x7F => byte as char,
+        _ => CP1251[byte as usize - 0x80],
+    }
+}
+
+fn cp1251_byte(char: char) -> Option<u8> {
+    if char.is_ascii() {
+        return Some(char as u8);
+    }
+
+    CP1251
+        .iter()
+        .position(|c| *c == char)
+        .map(|index| (index + 0x80) as u8)
+}
+
+fn latin1_byte(char: char) -> Option<u8> {
+    u8::try_from(char as u32).ok().or_else(|| {
+        CP1252
+            .iter()
+            .find(|(_, c)| *c == char)
+            .map(|(byte, _)| *byte)
+    })
+}
+
+/// Whether more words are made of the Latin-1 letters, which the Cyrillic
+/// ones turn into, than the words mixing them with the ASCII letters, like
+/// the French and German ones.
+fn looks_like_cp1251_as_latin1(text: &str) -> bool {
+    let mut garbled = 0;
+    let mut latin = 0;
+
+    for word in text.split(|char: char| char.is_whitespace() || char.is_ascii_punctuation()) {
+        let high = word
+            .chars()
+            .filter(|char| ('\u{C0}'..='\u{FF}').contains(char))
+            .count();
+
+        if word.chars().any(|char| char.is_ascii_alphabetic()) {
+            if high > 0 {
+                latin += 1;
+            }
+        } else if high >= 2 {
+            garbled += 1;
+        }
+    }
+
+    garbled > latin
+}
+
+/// Text with the garbled characters decoded back and the way they were
+/// garbled, `None` if the text doesn't look garbled.
+///
+/// A text is repaired only if all of it can be, so the texts mixing the
+/// garbled and the right characters are left alone.
+pub fn repair(text: &str) -> Option<(String, Mojibake)> {
+    if text.is_ascii() {
+        return None;
+    }
+
+    if let Some(bytes) = text.chars().map(cp1251_byte).collect::<Option<Vec<u8>>>() {
+        // CP1251 Cyrillic is almost never valid UTF-8 by chance
+        if let Ok(repaired) = String::from_utf8(bytes) {
+            return Some((repaired, Mojibake::Utf8AsCp1251));
+        }
+    }
+
+    if looks_like_cp1251_as_latin1(text) {
+        let bytes = text.chars().map(latin1_byte).collect::<Option<Vec<u8>>>()?;
+
+        return Some((
+            bytes.into_iter().map(cp1251_char).collect(),
+            Mojibake::Cp1251AsLatin1,
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{repair, Mojibake};
+
+    #[test]
+    pub fn repair_mojibake() {
+        let text = "Привет, мир! Ещё «№1» — <clr>Далее<clrEnd>";
+
+        assert_eq!(
+            repair("Ïðèâåò, ìèð! Åù¸ «¹1» — <clr>Äàëåå<clrEnd>"),
+            Some((text.to_string(), Mojibake::Cp1251AsLatin1))
+        );
+        // The control characters of Latin-1 instead of the CP1252 dash
+        assert_eq!(
+            repair("Ïðèâåò, ìèð! Åù¸ «¹1» \u{97} <clr>Äàëåå<clrEnd>"),
+            Some((text.to_string(), Mojibake::Cp1251AsLatin1))
+        );
+        assert_eq!(
+            repair("РџСЂРёРІРµС‚, РјРёСЂ! Р•С‰С‘ В«в„–1В» вЂ” <clr>Р”Р°Р»РµРµ<clrEnd>"),
+            Some((text.to_string(), Mojibake::Utf8AsCp1251))
+        );
+        assert_eq!(
+            repair("Äàëåå"),
+            Some(("Далее".to_string(), Mojibake::Cp1251AsLatin1))
+        );
+
+        assert_eq!(repair(text), None);
+        assert_eq!(repair("Next"), None);
+        assert_eq!(repair("Ça a été très réussi, Müller."), None);
+        assert_eq!(repair("Ïðèâåò, Привет"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+};
+
+use super::formatted_text::{FormattedText, TextElement, TextElementKind};
+
+/// Styles of the text elements in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStyles {
+    pub text: Style,
+    /// `<Ranger>`, `<Money>` and other variables, patched over the selection
+    /// style inside the selections
+    pub variable: Style,
+    /// `<clr>Foo<clrEnd>`
+    pub selection: Style,
+}
+
+impl Default for TextStyles {
+    fn default() -> Self {
+        let highlight = Style::default()
+            .fg(Color::LightBlue)
+            .add_modifier(Modifier::BOLD);
+
+        Self {
+            text: Style::default(),
+            variable: highlight,
+            selection: highlight,
+        }
+    }
+}
+
+/// Converts the text into lines of styled spans. The variables and formulas
+/// are expected to be already substituted, their values are shown as is.
+pub fn to_ratatui_text(text: &FormattedText, styles: &TextStyles) -> Text<'static> {
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+
+    push_elements(&text.elements, styles.text, styles, &mut lines, &mut spans);
+    lines.push(Spans::from(spans));
+
+    Text::from(lines)
+}
+
+fn push_elements(
+    elements: &[TextElement],
+    style: Style,
+    styles: &TextStyles,
+    lines: &mut Vec<Spans<'static>>,
+    spans: &mut Vec<Span<'static>>,
+) {
+    for el in elements {
+        match &el.kind {
+            TextElementKind::NewLine => lines.push(Spans::from(std::mem::take(spans))),
+            TextElementKind::Selection { text } => push_elements(
+                &text.elements,
+                style.patch(styles.selection),
+                styles,
+                lines,
+                spans,
+            ),
+            TextElementKind::Variable { .. } => {
+                spans.push(Span::styled(el.value.clone(), style.patch(styles.variable)))
+            }
+            _ => spans.push(Span::styled(el.value.clone(), style)),
+        }
+    }
+}
+
+impl From<&FormattedText> for Text<'static> {
+    fn from(value: &FormattedText) -> Self {
+        to_ratatui_text(value, &TextStyles::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        style::{Color, Style},
+        text::{Span, Spans, Text},
+    };
+
+    use super::{to_ratatui_text, TextStyles};
+    use crate::text::formatted_text::FormattedText;
+
+    #[test]
+    pub fn convert_text() {
+        let styles = TextStyles {
+            text: Style::default(),
+            variable: Style::default().fg(Color::Red),
+            selection: Style::default().bg(Color::Blue),
+        };
+        let text = FormattedText::parse("Привет, <Ranger>!\n<clr>Ждём <Ranger><clrEnd>\n");
+
+        assert_eq!(
+            to_ratatui_text(&text, &styles),
+            Text::from(vec![
+                Spans::from(vec![
+                    Span::raw("Привет, "),
+                    Span::styled("<Ranger>", Style::default().fg(Color::Red)),
+                    Span::raw("!"),
+                ]),
+                Spans::from(vec![
+                    Span::styled("Ждём ", Style::default().bg(Color::Blue)),
+                    Span::styled("<Ranger>", Style::default().fg(Color::Red).bg(Color::Blue)),
+                ]),
+                Spans::default(),
+            ])
+        );
+    }
+}
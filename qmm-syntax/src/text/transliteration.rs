@@ -0,0 +1,102 @@
+/// Latin letters of the lowercase Cyrillic ones, following the BGN/PCGN
+/// romanization without the diacritics and the marks of the hard and soft
+/// signs.
+fn latin(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        // Ukrainian and Belarusian
+        'є' => "ye",
+        'і' => "i",
+        'ї' => "yi",
+        'ґ' => "g",
+        'ў' => "w",
+        _ => return None,
+    })
+}
+
+/// Text with the Cyrillic letters written in Latin ones, e.g. for the
+/// terminals and fonts without Cyrillic. The rest of the text, the tags and
+/// the formulas included, is kept.
+///
+/// An uppercase letter next to another one is written in uppercase
+/// entirely, so `ЩИТ` becomes `SHCHIT` and `Щит` becomes `Shchit`.
+pub fn transliterate(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+
+    for (i, ch) in chars.iter().enumerate() {
+        let lower = ch.to_lowercase().next().unwrap_or(*ch);
+        let Some(latin) = latin(lower) else {
+            result.push(*ch);
+            continue;
+        };
+
+        if lower == *ch {
+            result.push_str(latin);
+            continue;
+        }
+
+        let upper_around = [i.checked_sub(1), Some(i + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| chars.get(i))
+            .any(|ch| ch.is_uppercase());
+
+        if upper_around {
+            result.push_str(&latin.to_uppercase());
+        } else {
+            let mut latin = latin.chars();
+            result.extend(latin.next().map(|first| first.to_ascii_uppercase()));
+            result.extend(latin);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::transliterate;
+
+    #[test]
+    pub fn transliterate_cyrillic() {
+        assert_eq!(
+            transliterate("Щит и ёж, <clr>Съешь<clrEnd> {[p1]+1}"),
+            "Shchit i yozh, <clr>Sesh<clrEnd> {[p1]+1}"
+        );
+        assert_eq!(transliterate("ЩИТ Юг ЮГ"), "SHCHIT Yug YUG");
+        assert_eq!(transliterate("Київ, Ґанок"), "Kiyiv, Ganok");
+        assert_eq!(transliterate("Next, Müller"), "Next, Müller");
+    }
+}